@@ -0,0 +1,131 @@
+//! Text encodings for `--encoding`. Files are searched as UTF-8 unless they
+//! start with a byte-order mark, which the searcher honors by itself. Windows
+//! tools also write UTF-16 without one; with `--encoding auto` such files are
+//! recognized from their first bytes instead of being skipped as binary.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use grep_searcher::{Encoding, Searcher, SearcherBuilder};
+
+/// Bytes read from the start of a file to guess whether it is UTF-16.
+const SNIFF_LEN: usize = 1024;
+
+/// How the bytes of searched files are decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextEncoding {
+    /// UTF-8, or UTF-16 going by a byte-order mark or the first bytes of a file.
+    Auto,
+    /// The raw bytes, byte-order marks included.
+    None,
+    /// Every file in this encoding, unless it starts with a byte-order mark.
+    Label(Encoding),
+}
+
+impl TextEncoding {
+    /// Parses `auto`, `none` or a WHATWG encoding label such as `utf-16le`,
+    /// `latin1` or `shift_jis`.
+    ///
+    /// # Errors
+    ///
+    /// If `value` is none of these.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "auto" => Ok(TextEncoding::Auto),
+            "none" => Ok(TextEncoding::None),
+            label => Encoding::new(label)
+                .map(TextEncoding::Label)
+                .map_err(|e: grep_searcher::ConfigError| e.to_string()),
+        }
+    }
+
+    /// Sets up `builder` to decode sources in this encoding.
+    pub fn configure(&self, builder: &mut SearcherBuilder) {
+        match self {
+            TextEncoding::Auto => builder.encoding(None).bom_sniffing(true),
+            TextEncoding::None => builder.encoding(None).bom_sniffing(false),
+            TextEncoding::Label(encoding) => builder.encoding(Some(encoding.clone())),
+        };
+    }
+
+    /// A searcher of its own for the file at `path`, made from `builder`, if the
+    /// file needs an encoding other than what `configure` set up.
+    #[must_use]
+    pub fn searcher_for(&self, builder: &SearcherBuilder, path: &Path) -> Option<Searcher> {
+        let encoding: Encoding = self.override_for(path)?;
+        Some(builder.clone().encoding(Some(encoding)).build())
+    }
+
+    /// The encoding the file at `path` needs other than what `configure` set up:
+    /// UTF-16 without a byte-order mark, found with `Auto`.
+    #[must_use]
+    pub fn override_for(&self, path: &Path) -> Option<Encoding> {
+        if *self != TextEncoding::Auto {
+            return None;
+        }
+        let mut head: Vec<u8> = Vec::with_capacity(SNIFF_LEN);
+        File::open(path)
+            .ok()?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut head)
+            .ok()?;
+        Encoding::new(sniff_utf16(&head)?).ok()
+    }
+}
+
+/// Guesses from the start of a file that it is UTF-16 without a byte-order
+/// mark: text that is mostly ASCII leaves every other byte zero.
+fn sniff_utf16(head: &[u8]) -> Option<&'static str> {
+    let boms: [&[u8]; 3] = [b"\xEF\xBB\xBF", b"\xFF\xFE", b"\xFE\xFF"];
+    if boms.iter().any(|bom: &&[u8]| head.starts_with(bom)) {
+        return None;
+    }
+    let pairs: Vec<&[u8]> = head.chunks_exact(2).collect();
+    if pairs.len() < 2 {
+        return None;
+    }
+    let mostly = |count: usize| count * 10 >= pairs.len() * 9;
+    let little: usize = pairs
+        .iter()
+        .filter(|pair: &&&[u8]| pair[0] != 0 && pair[1] == 0)
+        .count();
+    let big: usize = pairs
+        .iter()
+        .filter(|pair: &&&[u8]| pair[0] == 0 && pair[1] != 0)
+        .count();
+    if mostly(little) {
+        Some("utf-16le")
+    } else if mostly(big) {
+        Some("utf-16be")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_utf16_without_bom() {
+        let text: &str = "PS C:\\> Get-Credential\r\n";
+        let little: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let big: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(sniff_utf16(&little), Some("utf-16le"));
+        assert_eq!(sniff_utf16(&big), Some("utf-16be"));
+        assert_eq!(sniff_utf16(text.as_bytes()), None);
+        assert_eq!(sniff_utf16(b"\xFF\xFEa\x00b\x00"), None);
+        assert_eq!(
+            sniff_utf16(b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00"),
+            None
+        );
+
+        assert_eq!(TextEncoding::parse("AUTO"), Ok(TextEncoding::Auto));
+        assert!(matches!(
+            TextEncoding::parse("latin1"),
+            Ok(TextEncoding::Label(_))
+        ));
+        assert!(TextEncoding::parse("klingon").is_err());
+    }
+}
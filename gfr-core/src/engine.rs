@@ -0,0 +1,642 @@
+//! The regex engines a pattern can run on. Patterns use Rust's `regex` crate by
+//! default, which never backtracks but has no lookaround or backreferences.
+//! Rules imported from other tools often need those, so a pattern can ask for
+//! the `backtrack` engine instead: fancy-regex, which adds lookahead,
+//! lookbehind, backreferences and atomic groups to Rust's syntax. It is not
+//! PCRE, so constructs only PCRE has, such as recursion, don't compile. Rules
+//! that ask for `pcre2` get it too, as that is what most of them need PCRE for.
+//!
+//! Patterns made of literal `strings` skip regexes altogether and run on
+//! Aho-Corasick, as do the `keywords` that decide which lines a pattern is run
+//! on at all.
+//!
+//! Matchers find matches within one line unless built with
+//! `PatternMatcher::spanning`; those report no line terminator, which makes the
+//! searcher hand them whole files so that matches can cross line breaks.
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use grep_matcher::{Captures, Match, Matcher};
+use grep_regex::{RegexCaptures, RegexMatcher, RegexMatcherBuilder};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    /// Rust's regex engine: linear time, no lookaround or backreferences.
+    #[default]
+    Rust,
+    /// fancy-regex, a backtracking engine: Rust's syntax plus lookaround,
+    /// backreferences and atomic groups. Slower, and gives up on matches that backtrack too much.
+    /// Also accepted as `pcre2`, though it is not PCRE2.
+    #[serde(alias = "pcre2")]
+    #[cfg_attr(feature = "clap", value(alias = "pcre2"))]
+    Backtrack,
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Engine::Rust => "rust",
+            Engine::Backtrack => "backtrack",
+        })
+    }
+}
+
+/// An error from compiling or running a regex.
+#[derive(Debug)]
+pub struct EngineError(String);
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<grep_matcher::NoError> for EngineError {
+    fn from(never: grep_matcher::NoError) -> Self {
+        EngineError(never.to_string())
+    }
+}
+
+/// A compiled pattern on either engine, or a set of literal strings.
+#[derive(Debug, Clone)]
+pub enum PatternMatcher {
+    Rust(RegexMatcher),
+    Backtrack(BacktrackingMatcher),
+    Literal(AhoCorasick),
+    /// Matches where any of these matchers does, reporting the leftmost match.
+    /// It has no capture groups besides the whole match.
+    Any(Vec<PatternMatcher>),
+    Gated(GatedMatcher),
+}
+
+impl PatternMatcher {
+    /// Compiles `regex` for `engine`. Flags are expected inline, e.g. `(?i:...)`.
+    ///
+    /// # Errors
+    ///
+    /// If `regex` doesn't compile on `engine`.
+    pub fn new(regex: &str, engine: Engine) -> Result<Self, EngineError> {
+        Self::build(regex, engine, Some(b'\n'))
+    }
+
+    /// Compiles `regex` for `engine` so that its matches may span lines.
+    ///
+    /// # Errors
+    ///
+    /// If `regex` doesn't compile on `engine`.
+    pub fn spanning(regex: &str, engine: Engine) -> Result<Self, EngineError> {
+        Self::build(regex, engine, None)
+    }
+
+    fn build(
+        regex: &str,
+        engine: Engine,
+        line_terminator: Option<u8>,
+    ) -> Result<Self, EngineError> {
+        match engine {
+            Engine::Rust => RegexMatcherBuilder::new()
+                .line_terminator(line_terminator)
+                .build(regex)
+                .map(PatternMatcher::Rust)
+                .map_err(|e: grep_regex::Error| EngineError(e.to_string())),
+            Engine::Backtrack => fancy_regex::Regex::new(regex)
+                .map(|regex: fancy_regex::Regex| {
+                    PatternMatcher::Backtrack(BacktrackingMatcher {
+                        regex,
+                        by_line: line_terminator.is_some(),
+                    })
+                })
+                .map_err(|e: fancy_regex::Error| EngineError(e.to_string())),
+        }
+    }
+
+    /// Matches any of `strings` literally, the longest one where several start at
+    /// the same place. `ignore_case` only folds ASCII letters.
+    ///
+    /// # Errors
+    ///
+    /// If a string is empty or holds a line break.
+    pub fn literal(strings: &[String], ignore_case: bool) -> Result<Self, EngineError> {
+        if strings.iter().any(String::is_empty) {
+            return Err(EngineError("strings must not be empty".to_string()));
+        }
+        if let Some(spanning) = strings.iter().find(|s: &&String| s.contains('\n')) {
+            return Err(EngineError(format!(
+                "\"{}\" contains a line break, but matches are found within one line",
+                spanning.escape_debug()
+            )));
+        }
+        AhoCorasick::builder()
+            .match_kind(MatchKind::LeftmostLongest)
+            .ascii_case_insensitive(ignore_case)
+            .build(strings)
+            .map(PatternMatcher::Literal)
+            .map_err(|e: aho_corasick::BuildError| EngineError(e.to_string()))
+    }
+
+    /// Runs each matcher only on lines holding one of its keywords, which are
+    /// matched ignoring ASCII case. Every matcher needs at least one keyword.
+    ///
+    /// # Errors
+    ///
+    /// If a matcher has no keywords, or a keyword is empty or holds a line break.
+    pub fn gated(gates: Vec<(Vec<String>, PatternMatcher)>) -> Result<Self, EngineError> {
+        let mut keywords: Vec<String> = Vec::new();
+        let mut owners: Vec<usize> = Vec::new();
+        let mut matchers: Vec<PatternMatcher> = Vec::new();
+        for (i, (words, matcher)) in gates.into_iter().enumerate() {
+            if words.is_empty() {
+                return Err(EngineError("keywords must not be empty".to_string()));
+            }
+            if let Some(word) = words
+                .iter()
+                .find(|w: &&String| w.is_empty() || w.contains('\n'))
+            {
+                return Err(EngineError(format!(
+                    "keyword \"{}\" must be non-empty and within one line",
+                    word.escape_debug()
+                )));
+            }
+            owners.extend(std::iter::repeat_n(i, words.len()));
+            keywords.extend(words);
+            matchers.push(matcher);
+        }
+        let keywords: AhoCorasick = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .build(&keywords)
+            .map_err(|e: aho_corasick::BuildError| EngineError(e.to_string()))?;
+        Ok(PatternMatcher::Gated(GatedMatcher {
+            keywords,
+            owners,
+            matchers,
+        }))
+    }
+
+    /// One matcher for all of `matchers`.
+    #[must_use]
+    pub fn any(mut matchers: Vec<PatternMatcher>) -> Self {
+        if matchers.len() == 1 {
+            matchers.remove(0)
+        } else {
+            PatternMatcher::Any(matchers)
+        }
+    }
+}
+
+/// Capture groups of a match of a `PatternMatcher`.
+#[derive(Debug, Clone)]
+pub enum PatternCaptures {
+    Rust(RegexCaptures),
+    /// The groups of any other matcher, indexed like a regex's.
+    Groups(Vec<Option<Match>>),
+}
+
+impl Captures for PatternCaptures {
+    fn len(&self) -> usize {
+        match self {
+            PatternCaptures::Rust(caps) => caps.len(),
+            PatternCaptures::Groups(groups) => groups.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> Option<Match> {
+        match self {
+            PatternCaptures::Rust(caps) => caps.get(i),
+            PatternCaptures::Groups(groups) => groups.get(i).copied().flatten(),
+        }
+    }
+}
+
+impl Matcher for PatternMatcher {
+    type Captures = PatternCaptures;
+    type Error = EngineError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, EngineError> {
+        match self {
+            PatternMatcher::Rust(m) => m.find_at(haystack, at).map_err(EngineError::from),
+            PatternMatcher::Backtrack(m) => m.find_at(haystack, at),
+            PatternMatcher::Literal(automaton) => Ok(automaton
+                .find(aho_corasick::Input::new(haystack).span(at..haystack.len()))
+                .map(|m: aho_corasick::Match| Match::new(m.start(), m.end()))),
+            PatternMatcher::Any(matchers) => leftmost(matchers.iter(), haystack, at),
+            PatternMatcher::Gated(m) => m.find_at(haystack, at),
+        }
+    }
+
+    fn new_captures(&self) -> Result<PatternCaptures, EngineError> {
+        Ok(match self {
+            PatternMatcher::Rust(m) => PatternCaptures::Rust(m.new_captures()?),
+            PatternMatcher::Backtrack(m) => {
+                PatternCaptures::Groups(vec![None; m.regex.captures_len()])
+            }
+            PatternMatcher::Gated(m) if m.matchers.len() == 1 => m.matchers[0].new_captures()?,
+            PatternMatcher::Literal(_) | PatternMatcher::Any(_) | PatternMatcher::Gated(_) => {
+                PatternCaptures::Groups(vec![None])
+            }
+        })
+    }
+
+    fn capture_count(&self) -> usize {
+        match self {
+            PatternMatcher::Rust(m) => m.capture_count(),
+            PatternMatcher::Backtrack(m) => m.regex.captures_len(),
+            PatternMatcher::Gated(m) if m.matchers.len() == 1 => m.matchers[0].capture_count(),
+            PatternMatcher::Literal(_) | PatternMatcher::Any(_) | PatternMatcher::Gated(_) => 1,
+        }
+    }
+
+    fn capture_index(&self, name: &str) -> Option<usize> {
+        match self {
+            PatternMatcher::Rust(m) => m.capture_index(name),
+            PatternMatcher::Backtrack(m) => m
+                .regex
+                .capture_names()
+                .position(|group: Option<&str>| group == Some(name)),
+            PatternMatcher::Gated(m) if m.matchers.len() == 1 => m.matchers[0].capture_index(name),
+            PatternMatcher::Literal(_) | PatternMatcher::Any(_) | PatternMatcher::Gated(_) => None,
+        }
+    }
+
+    fn captures_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut PatternCaptures,
+    ) -> Result<bool, EngineError> {
+        match (self, caps) {
+            // A single gated matcher has the groups of its pattern, found at the
+            // match the keywords let through.
+            (PatternMatcher::Gated(m), caps) if m.matchers.len() == 1 => {
+                match m.find_at(haystack, at)? {
+                    Some(found) => m.matchers[0].captures_at(haystack, found.start(), caps),
+                    None => Ok(false),
+                }
+            }
+            (PatternMatcher::Rust(m), PatternCaptures::Rust(caps)) => {
+                Ok(m.captures_at(haystack, at, caps)?)
+            }
+            (PatternMatcher::Backtrack(m), PatternCaptures::Groups(groups)) => {
+                m.captures_at(haystack, at, groups)
+            }
+            (
+                PatternMatcher::Literal(_) | PatternMatcher::Any(_) | PatternMatcher::Gated(_),
+                PatternCaptures::Groups(groups),
+            ) => {
+                let found: Option<Match> = self.find_at(haystack, at)?;
+                groups.fill(None);
+                if let Some(whole) = groups.first_mut() {
+                    *whole = found;
+                }
+                Ok(found.is_some())
+            }
+            _ => Err(EngineError(
+                "captures belong to a matcher of another engine".to_string(),
+            )),
+        }
+    }
+
+    fn line_terminator(&self) -> Option<grep_matcher::LineTerminator> {
+        match self {
+            PatternMatcher::Rust(m) => m.line_terminator(),
+            PatternMatcher::Backtrack(m) => {
+                m.by_line.then(|| grep_matcher::LineTerminator::byte(b'\n'))
+            }
+            // No string holds a line break.
+            PatternMatcher::Literal(_) => Some(grep_matcher::LineTerminator::byte(b'\n')),
+            PatternMatcher::Any(matchers)
+            | PatternMatcher::Gated(GatedMatcher { matchers, .. }) => {
+                let first: Option<grep_matcher::LineTerminator> =
+                    matchers.first()?.line_terminator();
+                matchers
+                    .iter()
+                    .all(|m: &PatternMatcher| m.line_terminator() == first)
+                    .then_some(first)
+                    .flatten()
+            }
+        }
+    }
+}
+
+/// The leftmost match of any of `matchers` from `at`; of matches starting at the
+/// same place, the longest.
+fn leftmost<'a>(
+    matchers: impl Iterator<Item = &'a PatternMatcher>,
+    haystack: &[u8],
+    at: usize,
+) -> Result<Option<Match>, EngineError> {
+    let mut found: Vec<Match> = Vec::new();
+    for matcher in matchers {
+        found.extend(matcher.find_at(haystack, at)?);
+    }
+    Ok(leftmost_of(found.into_iter()))
+}
+
+/// The leftmost of `matches`, the longest of those starting at the same place.
+fn leftmost_of(matches: impl Iterator<Item = Match>) -> Option<Match> {
+    matches.min_by_key(|m: &Match| (m.start(), Reverse(m.end())))
+}
+
+/// Matchers that only run on lines holding one of their keywords. Finding the
+/// keywords is one pass of an automaton, so lines without any cost no regex.
+/// Matchers whose matches span lines run on the whole haystack if it holds one
+/// of their keywords anywhere.
+#[derive(Debug, Clone)]
+pub struct GatedMatcher {
+    keywords: AhoCorasick,
+    /// The index into `matchers` each keyword belongs to.
+    owners: Vec<usize>,
+    matchers: Vec<PatternMatcher>,
+}
+
+impl GatedMatcher {
+    fn spans(matcher: &PatternMatcher) -> bool {
+        matcher.line_terminator().is_none()
+    }
+
+    /// Which matchers have a keyword in `haystack[start..end]`.
+    fn opened(&self, haystack: &[u8], start: usize, end: usize) -> Vec<bool> {
+        let mut open: Vec<bool> = vec![false; self.matchers.len()];
+        for found in self
+            .keywords
+            .find_overlapping_iter(aho_corasick::Input::new(&haystack[..end]).span(start..end))
+        {
+            open[self.owners[found.pattern().as_usize()]] = true;
+        }
+        open
+    }
+
+    /// The matchers that are `open` and do or don't span lines.
+    fn running<'a>(
+        &'a self,
+        open: &'a [bool],
+        spanning: bool,
+    ) -> impl Iterator<Item = &'a PatternMatcher> {
+        self.matchers
+            .iter()
+            .zip(open)
+            .filter(move |(matcher, open): &(&PatternMatcher, &bool)| {
+                **open && Self::spans(matcher) == spanning
+            })
+            .map(|(matcher, _): (&PatternMatcher, &bool)| matcher)
+    }
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, EngineError> {
+        let spanning: Option<Match> = if self.matchers.iter().any(Self::spans) {
+            let open: Vec<bool> = self.opened(haystack, 0, haystack.len());
+            leftmost(self.running(&open, true), haystack, at)?
+        } else {
+            None
+        };
+        let in_line: Option<Match> = if self.matchers.iter().all(Self::spans) {
+            None
+        } else {
+            self.find_in_lines(haystack, at)?
+        };
+        Ok(leftmost_of(spanning.into_iter().chain(in_line)))
+    }
+
+    fn find_in_lines(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, EngineError> {
+        let line_start = |pos: usize| {
+            haystack[..pos]
+                .iter()
+                .rposition(|b: &u8| *b == b'\n')
+                .map_or(0, |i: usize| i + 1)
+        };
+        // Keywords earlier on the line of `at` still let its matchers run there.
+        let mut from: usize = line_start(at.min(haystack.len()));
+        while from < haystack.len() {
+            let Some(keyword) = self
+                .keywords
+                .find(aho_corasick::Input::new(haystack).span(from..haystack.len()))
+            else {
+                return Ok(None);
+            };
+            let start: usize = line_start(keyword.start());
+            let end: usize = haystack[keyword.end()..]
+                .iter()
+                .position(|b: &u8| *b == b'\n')
+                .map_or(haystack.len(), |i: usize| keyword.end() + i);
+            let open: Vec<bool> = self.opened(haystack, start, end);
+            let line: &[u8] = &haystack[..end];
+            if let Some(m) = leftmost(self.running(&open, false), line, at.max(start))? {
+                return Ok(Some(m));
+            }
+            from = end + 1;
+        }
+        Ok(None)
+    }
+}
+
+/// The `backtrack` engine. It matches `str`s, so bytes that aren't UTF-8 split the
+/// haystack into pieces that are searched separately, as do line breaks unless
+/// its matches may span lines.
+#[derive(Debug, Clone)]
+pub struct BacktrackingMatcher {
+    regex: fancy_regex::Regex,
+    by_line: bool,
+}
+
+impl BacktrackingMatcher {
+    /// The pieces of `haystack` that end at or after `at`, with where they start.
+    fn pieces<'h>(&self, haystack: &'h [u8], at: usize) -> impl Iterator<Item = (usize, &'h str)> {
+        let by_line: bool = self.by_line;
+        let mut start: usize = 0;
+        haystack
+            .utf8_chunks()
+            .flat_map(move |chunk: std::str::Utf8Chunk<'h>| {
+                let chunk_start: usize = start;
+                start += chunk.valid().len() + chunk.invalid().len();
+                let mut line_start: usize = chunk_start;
+                chunk
+                    .valid()
+                    .split(move |c: char| by_line && c == '\n')
+                    .map(move |piece: &str| {
+                        let found: (usize, &str) = (line_start, piece);
+                        line_start += piece.len() + 1;
+                        found
+                    })
+            })
+            .filter(move |(start, valid): &(usize, &str)| start + valid.len() >= at)
+    }
+
+    /// Where in `valid`, which starts at `start`, a search from `at` begins.
+    fn position(valid: &str, start: usize, at: usize) -> usize {
+        let mut pos: usize = at.saturating_sub(start);
+        while !valid.is_char_boundary(pos) {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, EngineError> {
+        for (start, valid) in self.pieces(haystack, at) {
+            let found: Option<fancy_regex::Match<'_>> = self
+                .regex
+                .find_from_pos(valid, Self::position(valid, start, at))
+                .map_err(|e: fancy_regex::Error| EngineError(e.to_string()))?;
+            if let Some(m) = found {
+                return Ok(Some(Match::new(start + m.start(), start + m.end())));
+            }
+        }
+        Ok(None)
+    }
+
+    fn captures_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        groups: &mut [Option<Match>],
+    ) -> Result<bool, EngineError> {
+        for (start, valid) in self.pieces(haystack, at) {
+            let found: Option<fancy_regex::Captures<'_>> = self
+                .regex
+                .captures_from_pos(valid, Self::position(valid, start, at))
+                .map_err(|e: fancy_regex::Error| EngineError(e.to_string()))?;
+            if let Some(caps) = found {
+                for (i, group) in groups.iter_mut().enumerate() {
+                    *group = caps.get(i).map(|m: fancy_regex::Match<'_>| {
+                        Match::new(start + m.start(), start + m.end())
+                    });
+                }
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcre2_is_an_alias_of_backtrack() {
+        assert_eq!(
+            serde_json::from_str::<Engine>(r#""pcre2""#).unwrap(),
+            Engine::Backtrack
+        );
+        assert_eq!(Engine::Backtrack.to_string(), "backtrack");
+    }
+
+    #[test]
+    fn test_backtrack_engine_matches_lookaround_and_bytes() {
+        let lookbehind: &str = r"(?<=password=)(?<secret>\w+)(?!\w*\.example)";
+        assert!(PatternMatcher::new(lookbehind, Engine::Rust).is_err());
+        let matcher: PatternMatcher = PatternMatcher::new(lookbehind, Engine::Backtrack).unwrap();
+
+        let haystack: &[u8] = b"user=a \xff password=hunter2 ";
+        assert_eq!(matcher.find(haystack).unwrap(), Some(Match::new(18, 25)));
+        assert_eq!(
+            matcher.find_at(haystack, 18).unwrap(),
+            Some(Match::new(18, 25))
+        );
+        assert_eq!(matcher.find_at(haystack, 19).unwrap(), None);
+
+        let mut caps: PatternCaptures = matcher.new_captures().unwrap();
+        assert!(matcher.captures(haystack, &mut caps).unwrap());
+        let secret: usize = matcher.capture_index("secret").unwrap();
+        assert_eq!(caps.get(secret), Some(Match::new(18, 25)));
+
+        let backreference: PatternMatcher =
+            PatternMatcher::new(r"(['\x22])[a-z]+\1", Engine::Backtrack).unwrap();
+        assert!(backreference.is_match(b"key = 'abc'").unwrap());
+        assert!(!backreference.is_match(b"key = 'abc\"").unwrap());
+
+        // Unless built to span lines, matches and lookaround stay within a line.
+        let line_end: PatternMatcher =
+            PatternMatcher::new(r"(?<=a\n)b|c$", Engine::Backtrack).unwrap();
+        assert_eq!(line_end.find(b"a\nb c\nd").unwrap(), Some(Match::new(4, 5)));
+        let spanning: PatternMatcher =
+            PatternMatcher::spanning(r"(?<=a\n)b", Engine::Backtrack).unwrap();
+        assert_eq!(spanning.find(b"a\nb c\nd").unwrap(), Some(Match::new(2, 3)));
+    }
+
+    #[test]
+    fn test_literal_matcher_and_any() {
+        let strings: Vec<String> = vec!["db.internal".to_string(), "db.internal.corp".to_string()];
+        let literal: PatternMatcher = PatternMatcher::literal(&strings, true).unwrap();
+        assert_eq!(
+            literal.find(b"host=DB.Internal.corp").unwrap(),
+            Some(Match::new(5, 21))
+        );
+        assert!(!literal.is_match(b"dbxinternal").unwrap());
+        assert_eq!(literal.find_at(b"db.internal", 1).unwrap(), None);
+        assert!(PatternMatcher::literal(&[String::new()], false).is_err());
+        assert!(PatternMatcher::literal(&["a\nb".to_string()], false).is_err());
+
+        let any: PatternMatcher = PatternMatcher::any(vec![
+            PatternMatcher::new("intern", Engine::Rust).unwrap(),
+            literal,
+        ]);
+        assert_eq!(
+            any.find(b"see db.internal").unwrap(),
+            Some(Match::new(4, 15))
+        );
+        let mut caps: PatternCaptures = any.new_captures().unwrap();
+        assert!(any.captures_at(b"x internal", 0, &mut caps).unwrap());
+        assert_eq!(caps.get(0), Some(Match::new(2, 8)));
+        assert!(any.line_terminator().is_some());
+        let spanning: PatternMatcher = PatternMatcher::any(vec![
+            PatternMatcher::spanning("a", Engine::Backtrack).unwrap(),
+            PatternMatcher::new("b", Engine::Rust).unwrap(),
+        ]);
+        assert!(spanning.line_terminator().is_none());
+    }
+
+    #[test]
+    fn test_gated_matcher_runs_on_keyword_lines() {
+        let word = |regex: &str| PatternMatcher::new(regex, Engine::Rust).unwrap();
+        let gated: PatternMatcher = PatternMatcher::gated(vec![
+            (vec!["token".to_string()], word(r"(?P<value>[0-9]{4})")),
+            (
+                vec!["key".to_string(), "secret".to_string()],
+                word("[a-f]{6}"),
+            ),
+        ])
+        .unwrap();
+        let haystack: &[u8] = b"1234 abcdef\nTOKEN=5678\nsecret 9999 fedcba\n";
+        assert_eq!(gated.find(haystack).unwrap(), Some(Match::new(18, 22)));
+        assert_eq!(
+            gated.find_at(haystack, 22).unwrap(),
+            Some(Match::new(35, 41))
+        );
+        // The keyword is before `at`, but on the same line.
+        assert_eq!(
+            gated.find_at(haystack, 30).unwrap(),
+            Some(Match::new(35, 41))
+        );
+        assert_eq!(gated.find(b"1234 abcdef").unwrap(), None);
+
+        let single: PatternMatcher = PatternMatcher::gated(vec![(
+            vec!["token".to_string()],
+            word(r"(?P<value>[0-9]{4})"),
+        )])
+        .unwrap();
+        let mut caps: PatternCaptures = single.new_captures().unwrap();
+        assert!(single.captures(haystack, &mut caps).unwrap());
+        let value: usize = single.capture_index("value").unwrap();
+        assert_eq!(caps.get(value), Some(Match::new(18, 22)));
+
+        // A multiline member runs on the whole haystack once its keyword is anywhere.
+        let block: PatternMatcher = PatternMatcher::gated(vec![(
+            vec!["private".to_string()],
+            PatternMatcher::spanning(r"BEGIN\n[0-9]+\nEND", Engine::Rust).unwrap(),
+        )])
+        .unwrap();
+        assert_eq!(
+            block.find(b"BEGIN\n12\nEND\n# private key").unwrap(),
+            Some(Match::new(0, 12))
+        );
+        assert_eq!(block.find(b"BEGIN\n12\nEND\n").unwrap(), None);
+
+        assert!(PatternMatcher::gated(vec![(Vec::new(), word("a"))]).is_err());
+        assert!(PatternMatcher::gated(vec![(vec![String::new()], word("a"))]).is_err());
+    }
+}
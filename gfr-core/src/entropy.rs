@@ -0,0 +1,81 @@
+//! Shannon entropy of matched text, so generic token patterns can drop hits that
+//! don't look random, like hashes of "placeholder" or `xxxxxxxx` filler.
+
+use grep_matcher::{Captures, Matcher};
+
+use crate::engine::{PatternCaptures, PatternMatcher};
+
+/// The entropy a pattern's matches need, from `min_entropy` or `--min-entropy`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntropyRule {
+    /// Minimum Shannon entropy in bits per character.
+    pub min: f64,
+    /// Named capture group to measure instead of the whole match.
+    pub group: Option<String>,
+}
+
+impl EntropyRule {
+    /// Whether `text`, a match of `matcher`, is random enough to report.
+    /// Falls back to the whole match when the group didn't take part in it.
+    #[must_use]
+    pub fn passes(&self, matcher: &PatternMatcher, text: &[u8]) -> bool {
+        let measured: &[u8] = self
+            .group
+            .as_deref()
+            .and_then(|group: &str| group_text(matcher, text, group))
+            .unwrap_or(text);
+        shannon(&String::from_utf8_lossy(measured)) >= self.min
+    }
+}
+
+/// Re-runs `matcher` on its own match to pull out a named group.
+fn group_text<'a>(matcher: &PatternMatcher, text: &'a [u8], group: &str) -> Option<&'a [u8]> {
+    let index: usize = matcher.capture_index(group)?;
+    let mut caps: PatternCaptures = matcher.new_captures().ok()?;
+    if !matcher.captures(text, &mut caps).ok()? {
+        return None;
+    }
+    caps.get(index).map(|m: grep_matcher::Match| &text[m])
+}
+
+/// Shannon entropy of `text` in bits per character: 0 for a repeated character,
+/// about 4 for random hex and about 6 for random base64.
+#[allow(clippy::cast_precision_loss)] // Character counts are far below 2^52.
+#[must_use]
+pub fn shannon(text: &str) -> f64 {
+    let mut counts: std::collections::HashMap<char, usize> = std::collections::HashMap::new();
+    for c in text.chars() {
+        *counts.entry(c).or_default() += 1;
+    }
+    let total: f64 = counts.values().sum::<usize>() as f64;
+    counts
+        .values()
+        .map(|&count: &usize| {
+            let p: f64 = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Engine;
+
+    #[test]
+    fn test_entropy_rule() {
+        assert!(shannon("").abs() < f64::EPSILON);
+        assert!(shannon("aaaa").abs() < f64::EPSILON);
+        assert!((shannon("abcd") - 2.0).abs() < 1e-9);
+
+        let rule: EntropyRule = EntropyRule {
+            min: 3.5,
+            group: Some("secret".to_string()),
+        };
+        let matcher: PatternMatcher =
+            PatternMatcher::new(r"token=(?P<secret>[a-z0-9]{16})", Engine::Rust).unwrap();
+        assert!(rule.passes(&matcher, b"token=k3j9x0q2m8v7z1w4"));
+        assert!(!rule.passes(&matcher, b"token=placeholderxxxxx"));
+        assert!(!rule.passes(&matcher, b"token=aaaaaaaaaaaaaaaa"));
+    }
+}
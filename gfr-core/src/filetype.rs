@@ -0,0 +1,343 @@
+//! Named file types, such as `rust`, `docker` or `make`, with the globs ripgrep
+//! gives them. A pattern's `file_types` may name one instead of an extension,
+//! a file name or a file name glob, and `gfr search --type` selects the files of
+//! a walk by them.
+//!
+//! Languages, such as `javascript` or `shell`, name the built-in type of their
+//! files, for a pattern's `languages` and `gfr search --lang`.
+//!
+//! Files without an extension can also be typed by their first line: the
+//! interpreter of a `#!` line, or an `<?php` or `<?xml` opening.
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::SplitWhitespace;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use anyhow::{anyhow, Result};
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use ignore::types::{FileTypeDef, Types, TypesBuilder};
+
+/// How much of a file [`detect`] reads to find its first line.
+const SNIFF_LEN: u64 = 256;
+
+/// File types by the interpreter a `#!` line names, version numbers stripped.
+const INTERPRETERS: [(&str, &[&str]); 15] = [
+    ("sh", &["sh"]),
+    ("bash", &["sh", "bash"]),
+    ("dash", &["sh", "dash"]),
+    ("zsh", &["sh", "zsh"]),
+    ("ksh", &["sh", "ksh"]),
+    ("fish", &["fish"]),
+    ("python", &["py", "python"]),
+    ("node", &["js", "node"]),
+    ("deno", &["ts", "js", "deno"]),
+    ("ruby", &["rb", "ruby"]),
+    ("perl", &["pl", "perl"]),
+    ("php", &["php"]),
+    ("lua", &["lua"]),
+    ("pwsh", &["ps1", "ps", "powershell"]),
+    ("Rscript", &["r"]),
+];
+
+/// Languages by the names authors know them by, where that isn't the name of
+/// their built-in type already, like `rust` or `python` are.
+const LANGUAGES: [(&str, &str); 14] = [
+    ("javascript", "js"),
+    ("golang", "go"),
+    ("c++", "cpp"),
+    ("c#", "csharp"),
+    ("f#", "fsharp"),
+    ("objective-c", "objc"),
+    ("objective-c++", "objcpp"),
+    ("shell", "sh"),
+    ("bash", "sh"),
+    ("powershell", "ps"),
+    ("terraform", "tf"),
+    ("dockerfile", "docker"),
+    ("makefile", "make"),
+    ("visualbasic", "vb"),
+];
+
+/// A built-in type: its globs, and those globs compiled.
+#[derive(Debug)]
+struct Builtin {
+    globs: Vec<String>,
+    set: GlobSet,
+}
+
+/// The built-in types by name.
+fn builtins() -> &'static HashMap<String, Builtin> {
+    static BUILTINS: OnceLock<HashMap<String, Builtin>> = OnceLock::new();
+    BUILTINS.get_or_init(|| {
+        TypesBuilder::new()
+            .add_defaults()
+            .definitions()
+            .into_iter()
+            .filter_map(|def: FileTypeDef| {
+                let mut builder: GlobSetBuilder = GlobSetBuilder::new();
+                for glob in def.globs() {
+                    builder.add(Glob::new(glob).ok()?);
+                }
+                let builtin: Builtin = Builtin {
+                    globs: def.globs().to_vec(),
+                    set: builder.build().ok()?,
+                };
+                Some((def.name().to_string(), builtin))
+            })
+            .collect()
+    })
+}
+
+/// Whether `file_type` is a file name glob, e.g. `*.config.js`, rather than an
+/// extension, a file name or a type name.
+fn is_glob(file_type: &str) -> bool {
+    file_type.contains(['*', '?', '[', '{'])
+}
+
+/// Whether `file_type` may be a whole file name, e.g. `Dockerfile` or `go.mod`:
+/// it has a capital letter or a dot, which bare extensions like `js` don't.
+fn is_file_name(file_type: &str) -> bool {
+    file_type.contains('.') || file_type.contains(|c: char| c.is_ascii_uppercase())
+}
+
+/// Whether the file name `name` matches `glob`, compiled the first time it
+/// comes up. Invalid globs match nothing.
+fn matches_glob(glob: &str, name: &str) -> bool {
+    static GLOBS: OnceLock<Mutex<HashMap<String, Option<GlobMatcher>>>> = OnceLock::new();
+    let mut globs = GLOBS
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    globs
+        .entry(glob.to_string())
+        .or_insert_with(|| Glob::new(glob).ok().map(|g: Glob| g.compile_matcher()))
+        .as_ref()
+        .is_some_and(|matcher: &GlobMatcher| matcher.is_match(name))
+}
+
+/// Whether the file at `path` is of `file_type`, which is one of:
+///
+/// - a file name glob, e.g. `*.config.js` or `docker-compose*.yml`;
+/// - a built-in type, e.g. `docker`;
+/// - an extension, e.g. `ts` for `app.ts` or `d.ts` for `index.d.ts`;
+/// - a whole file name with a capital letter or a dot, e.g. `Dockerfile`,
+///   `go.mod` or `.env`.
+///
+/// Names that are several of these, like `js`, match either way.
+#[must_use]
+pub fn is_of_type(path: &Path, file_type: &str) -> bool {
+    let Some(name) = path.file_name().and_then(OsStr::to_str) else {
+        return false;
+    };
+    if is_glob(file_type) {
+        return matches_glob(file_type, name);
+    }
+    is_file_name(file_type) && name == file_type
+        || name.ends_with(&format!(".{file_type}"))
+        || builtins()
+            .get(file_type)
+            .is_some_and(|builtin: &Builtin| builtin.set.is_match(name))
+}
+
+/// The built-in type of the files of `language`, e.g. `js` for `javascript` or
+/// `JavaScript`, or `None` if it isn't a language gfr knows. The name of a
+/// built-in type is a language too, so `rust` and `ts` stand for themselves.
+#[must_use]
+pub fn language_type(language: &str) -> Option<&'static str> {
+    let language: String = language.to_lowercase();
+    if let Some((_, file_type)) = LANGUAGES
+        .iter()
+        .find(|(name, _): &&(&str, &str)| *name == language)
+    {
+        return Some(file_type);
+    }
+    builtins()
+        .get_key_value(&language)
+        .map(|(name, _): (&String, &Builtin)| name.as_str())
+}
+
+/// Like [`language_type`], but an unknown language is an error.
+///
+/// # Errors
+///
+/// If `language` isn't a language gfr knows.
+pub fn require_language_type(language: &str) -> Result<&'static str> {
+    language_type(language)
+        .ok_or_else(|| anyhow!("Unknown language '{language}'; see `gfr search --type-list`"))
+}
+
+/// The file types the first line of a file gives away: those of the interpreter
+/// of a `#!` line, e.g. `sh` and `bash` for `#!/usr/bin/env bash`, or `php` or
+/// `xml` for files opening with `<?php` or `<?xml`.
+#[must_use]
+pub fn sniff(head: &[u8]) -> &'static [&'static str] {
+    if head.starts_with(b"<?php") {
+        return &["php"];
+    }
+    if head.starts_with(b"<?xml") {
+        return &["xml"];
+    }
+    let Some(shebang) = head.strip_prefix(b"#!") else {
+        return &[];
+    };
+    let line: &[u8] = shebang
+        .split(|b: &u8| *b == b'\n')
+        .next()
+        .unwrap_or_default();
+    let line: String = String::from_utf8_lossy(line).into_owned();
+    let mut words: SplitWhitespace<'_> = line.split_whitespace();
+    let mut program: &str = words.next().unwrap_or_default();
+    if program.rsplit('/').next() == Some("env") {
+        // `env -S python3 -u` and `env PATH=... node` name the program later on.
+        program = words
+            .find(|word: &&str| !word.starts_with('-') && !word.contains('='))
+            .unwrap_or_default();
+    }
+    let name: &str = program
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    INTERPRETERS
+        .iter()
+        .find(|(interpreter, _): &&(&str, &[&str])| *interpreter == name)
+        .map_or(&[], |(_, types): &(&str, &[&str])| *types)
+}
+
+/// The file types the first line of the file at `path` gives away, see
+/// [`sniff`]. Files with an extension are left to their names, and files that
+/// can't be read have no types.
+#[must_use]
+pub fn detect(path: &Path) -> &'static [&'static str] {
+    if path.extension().is_some() {
+        return &[];
+    }
+    let mut head: Vec<u8> = Vec::new();
+    match File::open(path).and_then(|file: File| file.take(SNIFF_LEN).read_to_end(&mut head)) {
+        Ok(_) => sniff(&head),
+        Err(_) => &[],
+    }
+}
+
+/// The file name globs `file_type` stands for, see [`is_of_type`]: a glob as
+/// it is, or else its extension, the file name it may be, and the globs of the
+/// built-in type of that name, if there is one.
+#[must_use]
+pub fn globs(file_type: &str) -> Vec<String> {
+    if is_glob(file_type) {
+        return vec![file_type.to_string()];
+    }
+    let mut globs: Vec<String> = vec![format!("*.{file_type}")];
+    if is_file_name(file_type) {
+        globs.push(file_type.to_string());
+    }
+    if let Some(builtin) = builtins().get(file_type) {
+        globs.extend(builtin.globs.iter().cloned());
+    }
+    globs
+}
+
+/// The built-in types, with `additions` such as `tf:*.tf` on top, sorted by name.
+///
+/// # Errors
+///
+/// If an addition isn't of the form `NAME:GLOB`.
+pub fn definitions(additions: &[String]) -> Result<Vec<FileTypeDef>> {
+    Ok(types_builder(additions)?.definitions())
+}
+
+/// Selects the files of a walk by type: only those of the `selected` types, if
+/// there are any, and none of the `negated` ones. `additions` such as `tf:*.tf`
+/// define types of their own or add globs to built-in ones.
+///
+/// # Errors
+///
+/// If an addition is malformed, or a type is neither built in nor added.
+pub fn walk_types(additions: &[String], selected: &[String], negated: &[String]) -> Result<Types> {
+    let mut builder: TypesBuilder = types_builder(additions)?;
+    for name in selected {
+        builder.select(name);
+    }
+    for name in negated {
+        builder.negate(name);
+    }
+    Ok(builder.build()?)
+}
+
+fn types_builder(additions: &[String]) -> Result<TypesBuilder> {
+    let mut builder: TypesBuilder = TypesBuilder::new();
+    builder.add_defaults();
+    for def in additions {
+        builder.add_def(def)?;
+    }
+    Ok(builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_types_and_extensions() {
+        assert!(is_of_type(Path::new("app.ts"), "ts"));
+        assert!(is_of_type(Path::new("types/index.d.ts"), "d.ts"));
+        assert!(is_of_type(Path::new("build/Dockerfile"), "docker"));
+        assert!(is_of_type(Path::new("Makefile"), "make"));
+        assert!(is_of_type(Path::new("app.mjs"), "js"));
+        assert!(!is_of_type(Path::new("src/js"), "js"));
+        assert!(globs("make").contains(&"[Mm]akefile".to_string()));
+        assert_eq!(globs("xyz"), ["*.xyz"]);
+        assert_eq!(globs("go.mod"), ["*.go.mod", "go.mod"]);
+        assert_eq!(globs("*.config.js"), ["*.config.js"]);
+        assert!(is_of_type(Path::new("build/Dockerfile"), "Dockerfile"));
+        assert!(is_of_type(Path::new("web/vite.config.js"), "*.config.js"));
+        assert!(!is_of_type(Path::new("web/app.js"), "*.config.js"));
+        assert!(is_of_type(
+            Path::new("docker-compose.prod.yml"),
+            "docker-compose*.yml"
+        ));
+        assert!(!is_of_type(Path::new("Dockerfile.dev"), "Dockerfile"));
+        assert!(is_of_type(Path::new("app/.env"), ".env"));
+
+        let types: Types = walk_types(&["tf:*.tf".to_string()], &["tf".to_string()], &[]).unwrap();
+        assert!(types.matched("main.tf", false).is_whitelist());
+        assert!(types.matched("main.rs", false).is_ignore());
+        assert!(walk_types(&[], &["no-such-type".to_string()], &[]).is_err());
+        assert!(definitions(&["tf".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_language_types() {
+        assert_eq!(language_type("javascript"), Some("js"));
+        assert_eq!(language_type("TypeScript"), Some("typescript"));
+        assert_eq!(language_type("ts"), Some("ts"));
+        assert_eq!(language_type("Shell"), Some("sh"));
+        assert_eq!(language_type("no-such-language"), None);
+        assert!(require_language_type("no-such-language").is_err());
+        for (language, file_type) in LANGUAGES {
+            assert!(
+                builtins().contains_key(file_type),
+                "{language}: {file_type}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sniff_first_lines() {
+        assert_eq!(sniff(b"#!/bin/sh\nset -e\n"), ["sh"]);
+        assert_eq!(sniff(b"#!/usr/bin/env bash\n"), ["sh", "bash"]);
+        assert_eq!(sniff(b"#! /usr/bin/python3.11 -u\n"), ["py", "python"]);
+        assert_eq!(
+            sniff(b"#!/usr/bin/env -S node --no-warnings\n"),
+            ["js", "node"]
+        );
+        assert_eq!(sniff(b"<?php echo 1;"), ["php"]);
+        assert!(sniff(b"#!/usr/bin/env\n").is_empty());
+        assert!(sniff(b"#!/opt/bin/unknown\n").is_empty());
+        assert!(sniff(b"plain text\n").is_empty());
+    }
+}
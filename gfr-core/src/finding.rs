@@ -0,0 +1,404 @@
+//! Findings: matches attributed to the pattern that produced them, with where
+//! they are and what they matched, and the `Sink` that collects them.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+use base64::prelude::{Engine as _, BASE64_STANDARD};
+use grep_matcher::{Captures, Matcher};
+use grep_searcher::{Searcher, Sink, SinkMatch};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::engine::{PatternCaptures, PatternMatcher};
+use crate::pattern::Severity;
+use crate::verification::Verification;
+use crate::{suppress, winpath};
+
+/// Shown in place of a file name for findings in piped input.
+pub const STDIN_LABEL: &str = "<stdin>";
+
+/// A single match attributed to the pattern that produced it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Finding {
+    /// Stable identifier of the finding, independent of its line number.
+    pub fingerprint: String,
+    /// Name of the pattern that produced the match.
+    pub pattern: String,
+    /// Severity of the pattern, if it declares one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// Path of the file containing the match, or `None` when searching stdin.
+    pub path: Option<String>,
+    /// 1-based line number of the matching line.
+    pub line_number: Option<u64>,
+    /// 1-based byte column of the start of the match within its line.
+    pub column: u64,
+    /// Absolute byte offset of the start of the match.
+    pub byte_offset: u64,
+    /// The text matched by the pattern.
+    pub matched: String,
+    /// The match rewritten with `--replace`, if given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// The full line containing the match, without its line terminator.
+    pub line: String,
+    /// Confidence in the pattern derived from `gfr triage` verdicts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Original bytes of any text field above that isn't valid UTF-8.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw: Option<RawFields>,
+    /// Whether `--verify` found the matched credential to be live.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Verification>,
+    /// Status set with `gfr findings mark`, if the finding was marked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<FindingStatus>,
+    /// Notes attached to the finding by plugins.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, Value>,
+}
+
+/// Base64-encoded original bytes of finding fields that were decoded lossily.
+/// Paths use the platform's encoding (WTF-8 on Windows).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawFields {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matched: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<String>,
+}
+
+impl RawFields {
+    /// Keeps the bytes of each field that `String::from_utf8_lossy` would alter.
+    #[must_use]
+    pub fn of(path: Option<&Path>, matched: &[u8], line: &[u8]) -> Option<Self> {
+        let raw = |bytes: &[u8]| {
+            std::str::from_utf8(bytes)
+                .is_err()
+                .then(|| BASE64_STANDARD.encode(bytes))
+        };
+        let fields: RawFields = RawFields {
+            path: path.and_then(|p: &Path| raw(p.as_os_str().as_encoded_bytes())),
+            matched: raw(matched),
+            line: raw(line),
+        };
+        (fields != RawFields::default()).then_some(fields)
+    }
+}
+
+/// Identifies a finding by pattern, file and matched text, so it survives code
+/// moving around within the file. The pattern name is kept readable as a prefix.
+#[must_use]
+pub fn finding_fingerprint(pattern: &str, path: Option<&str>, matched: &str) -> String {
+    let digest: String =
+        sha256_hex(format!("{pattern}\0{}\0{matched}", path.unwrap_or(STDIN_LABEL)).as_bytes());
+    format!("{pattern}:{}", &digest[..16])
+}
+
+/// A pattern's name and its own matcher.
+pub type NamedMatcher = (String, PatternMatcher);
+
+/// A `Sink` that attributes every match to its pattern and collects the
+/// resulting findings for one file.
+pub struct FindingSink<'a> {
+    matchers: Vec<&'a (String, PatternMatcher)>,
+    path: Option<&'a Path>,
+    /// The `--replace` template, applied with each pattern's own capture groups.
+    replacement: Option<&'a [u8]>,
+    /// Honor `gfr:ignore` comments.
+    inline_ignores: bool,
+    findings: Vec<Finding>,
+}
+
+impl<'a> FindingSink<'a> {
+    /// A sink attributing matches to `matchers`, for the file at `path` or stdin.
+    #[must_use]
+    pub fn new(
+        matchers: Vec<&'a (String, PatternMatcher)>,
+        path: Option<&'a Path>,
+        replacement: Option<&'a [u8]>,
+        inline_ignores: bool,
+    ) -> Self {
+        Self {
+            matchers,
+            path,
+            replacement,
+            inline_ignores,
+            findings: Vec::new(),
+        }
+    }
+
+    /// The findings collected so far.
+    #[must_use]
+    pub fn into_findings(self) -> Vec<Finding> {
+        self.findings
+    }
+}
+
+impl FindingSink<'_> {
+    /// Finds the matches of `matcher` in `text` and records them as findings.
+    /// `text` starts `offset` bytes into the source, on line `line_number`, and
+    /// `previous` is the line before it.
+    fn collect(
+        &mut self,
+        (name, matcher): &(String, PatternMatcher),
+        text: &[u8],
+        offset: u64,
+        line_number: Option<u64>,
+        previous: Option<&[u8]>,
+    ) -> io::Result<()> {
+        let path: Option<String> = self.path.map(winpath::display);
+        let mut found: Vec<Finding> = Vec::new();
+        let mut caps: PatternCaptures = matcher.new_captures().map_err(io::Error::other)?;
+        matcher
+            .captures_iter(text, &mut caps, |caps: &PatternCaptures| {
+                let Some(m) = caps.get(0) else {
+                    return true;
+                };
+                // The lines the match is on; usually one.
+                let start: usize = text[..m.start()]
+                    .iter()
+                    .rposition(|b: &u8| *b == b'\n')
+                    .map_or(0, |i: usize| i + 1);
+                let end: usize = text[m.end()..]
+                    .iter()
+                    .position(|b: &u8| *b == b'\n')
+                    .map_or(text.len(), |i: usize| m.end() + i);
+                let line: &[u8] = &text[start..end];
+                let first_line: &[u8] = line.split(|b: &u8| *b == b'\n').next().unwrap_or(line);
+                let previous: Option<&[u8]> = if start == 0 {
+                    previous
+                } else {
+                    text[..start - 1].rsplit(|b: &u8| *b == b'\n').next()
+                };
+                if self.inline_ignores && suppress::is_suppressed(Some(name), first_line, previous)
+                {
+                    return true;
+                }
+                let replacement: Option<String> = self.replacement.map(|template: &[u8]| {
+                    let mut dst: Vec<u8> = Vec::new();
+                    caps.interpolate(
+                        |group: &str| matcher.capture_index(group),
+                        text,
+                        template,
+                        &mut dst,
+                    );
+                    String::from_utf8_lossy(&dst).into_owned()
+                });
+                let lines_before: usize = text[..start].split(|b: &u8| *b == b'\n').count() - 1;
+                let hit: String = String::from_utf8_lossy(&text[m]).into_owned();
+                found.push(Finding {
+                    fingerprint: finding_fingerprint(name, path.as_deref(), &hit),
+                    pattern: name.clone(),
+                    severity: None,
+                    path: path.clone(),
+                    line_number: line_number.map(|n: u64| n + lines_before as u64),
+                    column: (m.start() - start) as u64 + 1,
+                    byte_offset: offset + m.start() as u64,
+                    matched: hit,
+                    replacement,
+                    line: String::from_utf8_lossy(line).into_owned(),
+                    confidence: None,
+                    raw: RawFields::of(self.path, &text[m], line),
+                    verification: None,
+                    status: None,
+                    annotations: BTreeMap::new(),
+                });
+                true
+            })
+            .map_err(io::Error::other)?;
+        self.findings.extend(found);
+        Ok(())
+    }
+}
+
+impl Sink for FindingSink<'_> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> io::Result<bool> {
+        let (spanning, by_line): (Vec<&NamedMatcher>, Vec<&NamedMatcher>) = self
+            .matchers
+            .iter()
+            .partition(|(_, m): &&&NamedMatcher| m.line_terminator().is_none());
+        let before: Option<&[u8]> =
+            suppress::previous_line(mat.buffer(), mat.bytes_range_in_buffer().start);
+
+        // Matches of multiline patterns may cross the lines of the block.
+        for named in spanning {
+            self.collect(
+                named,
+                trim_line_terminator(mat.bytes()),
+                mat.absolute_byte_offset(),
+                mat.line_number(),
+                before,
+            )?;
+        }
+        let mut line_number: Option<u64> = mat.line_number();
+        let mut line_offset: u64 = mat.absolute_byte_offset();
+        let mut previous: Option<&[u8]> = before;
+        for line in mat.lines() {
+            let trimmed: &[u8] = trim_line_terminator(line);
+            for named in &by_line {
+                self.collect(named, trimmed, line_offset, line_number, previous)?;
+            }
+            line_number = line_number.map(|n: u64| n + 1);
+            line_offset += line.len() as u64;
+            previous = Some(trimmed);
+        }
+        Ok(true)
+    }
+}
+
+/// Where a recorded finding stands, set with `gfr findings mark`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum FindingStatus {
+    /// Not triaged yet, or reopened.
+    Open,
+    /// Not a real issue; suppressed in later scans.
+    FalsePositive,
+    /// A real issue whose risk is accepted; suppressed in later scans.
+    Accepted,
+    /// Believed fixed; reported as a regression if it shows up again.
+    Fixed,
+}
+
+impl FindingStatus {
+    /// Whether later scans leave findings with this status out.
+    #[must_use]
+    pub fn suppresses(self) -> bool {
+        matches!(self, FindingStatus::FalsePositive | FindingStatus::Accepted)
+    }
+
+    /// The status written as `value` by `Display`; anything else is open.
+    #[must_use]
+    pub fn parse(value: &str) -> FindingStatus {
+        match value {
+            "false-positive" => FindingStatus::FalsePositive,
+            "accepted" => FindingStatus::Accepted,
+            "fixed" => FindingStatus::Fixed,
+            _ => FindingStatus::Open,
+        }
+    }
+}
+
+impl std::fmt::Display for FindingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FindingStatus::Open => "open",
+            FindingStatus::FalsePositive => "false-positive",
+            FindingStatus::Accepted => "accepted",
+            FindingStatus::Fixed => "fixed",
+        })
+    }
+}
+
+/// Strips a trailing `\n` or `\r\n` from a line.
+#[must_use]
+pub fn trim_line_terminator(line: &[u8]) -> &[u8] {
+    let line: &[u8] = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Returns the hex-encoded SHA-256 digest of `data`.
+#[must_use]
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_searcher::Searcher;
+
+    use super::*;
+    use crate::engine::Engine;
+    use crate::pattern::Pattern;
+
+    #[test]
+    fn test_finding_sink_attributes_matches() {
+        let key: Pattern = Pattern {
+            name: "key".to_string(),
+            regex: Some("key".to_string()),
+            ..Pattern::default()
+        };
+        let token: Pattern = Pattern {
+            name: "token".to_string(),
+            regex: Some("TOKEN".to_string()),
+            ignore_case: true,
+            ..Pattern::default()
+        };
+        let matchers: Vec<(String, PatternMatcher)> = [key, token]
+            .iter()
+            .map(|p: &Pattern| (p.name.clone(), p.build_matcher().unwrap()))
+            .collect();
+        let combined: PatternMatcher = PatternMatcher::new("(?i)key|token", Engine::Rust).unwrap();
+
+        let mut sink: FindingSink = FindingSink::new(matchers.iter().collect(), None, None, true);
+        Searcher::new()
+            .search_slice(&combined, b"nothing\napi key and token\n", &mut sink)
+            .unwrap();
+
+        let findings: Vec<Finding> = sink.into_findings();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].pattern, "key");
+        assert_eq!(findings[0].line_number, Some(2));
+        assert_eq!(findings[0].column, 5);
+        assert_eq!(findings[0].byte_offset, 12);
+        assert_eq!(findings[1].pattern, "token");
+        assert_eq!(findings[1].matched, "token");
+        assert_eq!(findings[1].line, "api key and token");
+    }
+
+    #[test]
+    fn test_finding_sink_applies_replacement_per_pattern() {
+        let url: Pattern = Pattern {
+            name: "url".to_string(),
+            regex: Some(r"https?://(?P<host>[a-z.]+)/(\w+)".to_string()),
+            ..Pattern::default()
+        };
+        let matchers: Vec<(String, PatternMatcher)> =
+            vec![(url.name.clone(), url.build_matcher().unwrap())];
+
+        let mut sink: FindingSink = FindingSink::new(
+            matchers.iter().collect(),
+            None,
+            Some(b"${host} [$2]".as_slice()),
+            true,
+        );
+        Searcher::new()
+            .search_slice(
+                &matchers[0].1,
+                b"see https://example.com/login now\n",
+                &mut sink,
+            )
+            .unwrap();
+
+        let findings: Vec<Finding> = sink.into_findings();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].matched, "https://example.com/login");
+        assert_eq!(
+            findings[0].replacement.as_deref(),
+            Some("example.com [login]")
+        );
+    }
+
+    #[test]
+    fn test_raw_fields_only_for_invalid_utf8() {
+        assert_eq!(
+            RawFields::of(Some(Path::new("a.txt")), b"key", b"key = 1"),
+            None
+        );
+
+        let raw: RawFields = RawFields::of(None, b"key", b"key = \xff").unwrap();
+        assert_eq!(raw.path, None);
+        assert_eq!(raw.matched, None);
+        assert_eq!(raw.line.as_deref(), Some("a2V5ID0g/w=="));
+    }
+}
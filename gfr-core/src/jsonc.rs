@@ -0,0 +1,121 @@
+//! Comments and trailing commas in hand-written pattern files. They are blanked
+//! out rather than removed, so `serde_json` still reports errors at the line and
+//! column they are at in the file.
+
+/// `source` with `//` and `/* */` comments and commas before a closing `}` or `]`
+/// replaced by spaces. Strings are left alone; line breaks are kept.
+#[must_use]
+pub fn strip(source: &str) -> String {
+    blank_trailing_commas(&blank_comments(source))
+}
+
+/// Replaces every byte of `c` with a space, or keeps it if it is a line break.
+fn blank(out: &mut String, c: char) {
+    if c == '\n' || c == '\r' {
+        out.push(c);
+    } else {
+        out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+    }
+}
+
+fn blank_comments(source: &str) -> String {
+    let mut out: String = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string: bool = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                blank(&mut out, c);
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    blank(&mut out, next);
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                blank(&mut out, c);
+                blank(&mut out, chars.next().unwrap_or('*'));
+                let mut previous: char = ' ';
+                for next in chars.by_ref() {
+                    blank(&mut out, next);
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn blank_trailing_commas(source: &str) -> String {
+    let mut out: String = String::with_capacity(source.len());
+    let mut in_string: bool = false;
+    let mut escaped: bool = false;
+    for (i, c) in source.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c == ',' && source[i + 1..].trim_start().starts_with(['}', ']']) {
+            out.push(' ');
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_keeps_strings_and_positions() {
+        let source: &str = concat!(
+            "{\n",
+            "  // AWS access key IDs\n",
+            "  \"pattern\": \"AKIA[0-9A-Z]{16}\", /* 20 chars */\n",
+            "  \"description\": \"not // a comment, nor /* this */\",\n",
+            "  \"tags\": [\"secrets\", \"aws\",],\n",
+            "  \"escaped\": \"a \\\" // quote\",\n",
+            "}\n",
+        );
+        let stripped: String = strip(source);
+        assert_eq!(stripped.len(), source.len());
+        assert_eq!(stripped.lines().count(), source.lines().count());
+
+        let json: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(json["pattern"], "AKIA[0-9A-Z]{16}");
+        assert_eq!(json["description"], "not // a comment, nor /* this */");
+        assert_eq!(json["tags"], serde_json::json!(["secrets", "aws"]));
+        assert_eq!(json["escaped"], "a \" // quote");
+
+        let error: serde_json::Error =
+            serde_json::from_str::<serde_json::Value>(&strip("{\n  /* é */ \"a\" 1\n}"))
+                .unwrap_err();
+        assert_eq!((error.line(), error.column()), (2, 16));
+    }
+}
@@ -0,0 +1,56 @@
+//! # gfr-core
+//! The pattern engine behind [gfr](https://github.com/Kr1shna4garwal/gfr), for
+//! embedding its searches in other tools instead of running the binary and
+//! reading its output.
+//!
+//! Patterns are loaded from gfr's config directory by name ([`load_pattern`]),
+//! from any file ([`load_pattern_file`]) or built in code as a [`Pattern`].
+//! [`SearchBuilder`] searches files with them and returns [`Finding`]s:
+//!
+//! ```no_run
+//! use gfr_core::SearchBuilder;
+//!
+//! let findings = SearchBuilder::new()
+//!     .pattern("rce")
+//!     .tags("secrets and not noisy")
+//!     .path("./src")
+//!     .run()?;
+//! println!("{} findings", findings.len());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The lower-level pieces the `gfr` binary is built from are public too: the
+//! matchers of [`engine`], [`pattern::combined_matcher`] and the
+//! [`finding::FindingSink`] that attributes matches to patterns.
+
+#![deny(clippy::all)]
+#![deny(clippy::pedantic)]
+#![allow(clippy::module_name_repetitions)]
+
+pub mod encoding;
+pub mod engine;
+pub mod entropy;
+pub mod filetype;
+pub mod finding;
+pub mod jsonc;
+pub mod load;
+pub mod migrate;
+pub mod ngram;
+pub mod pattern;
+pub mod search;
+pub mod suppress;
+pub mod tags;
+pub mod validators;
+pub mod verification;
+pub mod winpath;
+
+pub use engine::Engine;
+pub use finding::Finding;
+pub use load::{load_pattern, load_pattern_file};
+pub use pattern::{Pattern, Severity};
+pub use search::{Search, SearchBuilder};
+
+/// Runs the examples of the README with the other doctests.
+#[cfg(doctest)]
+#[doc = include_str!("../README.md")]
+struct ReadmeDoctests;
@@ -0,0 +1,327 @@
+//! Finding and loading local patterns. Patterns live in gfr's config directory,
+//! saved ones at the top level and installed ones in a directory per namespace,
+//! next to the files gfr keeps about them.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::migrate;
+use crate::pattern::Pattern;
+
+/// Name of gfr's directory in the user's config directory.
+pub const CONFIG_DIR: &str = "gfr";
+/// Where `gfr install` records the patterns it installed.
+pub const INSTALLED_MANIFEST_FILE: &str = "installed.json";
+/// Settings such as registries and the license policy.
+pub const USER_CONFIG_FILE: &str = "config.json";
+/// Bundle definitions from the indexes patterns were installed from.
+pub const BUNDLES_FILE: &str = "bundles.json";
+/// Verdicts recorded with `gfr triage`.
+pub const TRIAGE_FILE: &str = "triage.json";
+/// Short names for installed patterns, defined with `gfr alias`.
+pub const ALIASES_FILE: &str = "aliases.json";
+/// Directory of the collections installed from indexes.
+pub const COLLECTIONS_DIR: &str = "collections";
+/// Files in the pattern directory that are not patterns themselves.
+pub const RESERVED_FILES: [&str; 5] = [
+    INSTALLED_MANIFEST_FILE,
+    USER_CONFIG_FILE,
+    TRIAGE_FILE,
+    BUNDLES_FILE,
+    ALIASES_FILE,
+];
+/// Extensions of local pattern files, in the order a name is looked up by.
+pub const PATTERN_EXTENSIONS: [&str; 3] = ["json", "yaml", "yml"];
+
+/// Alias names mapped to the pattern names they stand for.
+pub type Aliases = BTreeMap<String, String>;
+
+/// Formats a local pattern file can be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum PatternFormat {
+    Json,
+    Yaml,
+}
+
+impl PatternFormat {
+    /// The format of the pattern file at `path`: YAML for `.yaml` and `.yml`,
+    /// JSON otherwise.
+    #[must_use]
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(std::ffi::OsStr::to_str) {
+            Some(e) if e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml") => {
+                Self::Yaml
+            }
+            _ => Self::Json,
+        }
+    }
+
+    /// The extension files of this format are written with.
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Yaml => "yaml",
+        }
+    }
+}
+
+/// The directory local patterns and gfr's own files live in, e.g.
+/// `~/.config/gfr`.
+///
+/// # Errors
+///
+/// If the platform has no config directory.
+pub fn get_pattern_dir() -> Result<PathBuf> {
+    dirs::config_dir()
+        .ok_or_else(|| anyhow!("Could not determine user's config directory."))
+        .map(|dir: PathBuf| dir.join(CONFIG_DIR))
+}
+
+/// Returns the names of all pattern files in `pattern_dir`, skipping gfr's own files.
+/// Patterns installed from an index live in a directory per namespace and are
+/// named `namespace/name`; saved ones sit at the top level under their own name.
+///
+/// # Errors
+///
+/// If the directory can't be read.
+pub fn list_pattern_names(pattern_dir: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = file_stems(pattern_dir, &PATTERN_EXTENSIONS)?
+        .into_iter()
+        .filter(|name: &String| !RESERVED_FILES.contains(&format!("{name}.json").as_str()))
+        .collect();
+    for entry in fs::read_dir(pattern_dir)?.filter_map(Result::ok) {
+        let Some(namespace) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if entry.path().is_dir() && namespace != COLLECTIONS_DIR {
+            for name in file_stems(&entry.path(), &PATTERN_EXTENSIONS)? {
+                names.push(format!("{namespace}/{name}"));
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Names of the files directly in `dir` with one of `extensions`, without the
+/// extension. A name shared by files of several extensions is listed once.
+///
+/// # Errors
+///
+/// If `dir` can't be read.
+pub fn file_stems(dir: &Path, extensions: &[&str]) -> Result<Vec<String>> {
+    let mut stems: Vec<String> = Vec::new();
+    for entry in fs::read_dir(dir)?.filter_map(Result::ok) {
+        let path: PathBuf = entry.path();
+        if path.is_file()
+            && path
+                .extension()
+                .and_then(std::ffi::OsStr::to_str)
+                .is_some_and(|e: &str| extensions.contains(&e))
+        {
+            if let Some(stem) = path.file_stem().and_then(std::ffi::OsStr::to_str) {
+                if !stems.iter().any(|s: &String| s == stem) {
+                    stems.push(stem.to_string());
+                }
+            }
+        }
+    }
+    Ok(stems)
+}
+
+/// The file of the pattern `name` in `dir`, trying each pattern file extension.
+#[must_use]
+pub fn pattern_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    PATTERN_EXTENSIONS
+        .iter()
+        .map(|extension: &&str| dir.join(format!("{name}.{extension}")))
+        .find(|path: &PathBuf| path.is_file())
+}
+
+/// Finds the full name of an installed pattern from its full or short name. A saved
+/// pattern wins over installed ones of the same short name; a short name shared by
+/// several namespaces is ambiguous.
+///
+/// # Errors
+///
+/// If the short name is ambiguous, or the directory can't be read.
+pub fn resolve_pattern_name(pattern_dir: &Path, name: &str) -> Result<Option<String>> {
+    if pattern_file(pattern_dir, name).is_some() {
+        return Ok(Some(name.to_string()));
+    }
+    if name.contains('/') || !pattern_dir.exists() {
+        return Ok(None);
+    }
+    let mut candidates: Vec<String> = list_pattern_names(pattern_dir)?
+        .into_iter()
+        .filter(|full: &String| {
+            full.split_once('/')
+                .is_some_and(|(_, short): (&str, &str)| short == name)
+        })
+        .collect();
+    match candidates.len() {
+        0 => Ok(None),
+        1 => Ok(candidates.pop()),
+        _ => {
+            candidates.sort();
+            Err(anyhow!(
+                "'{name}' is ambiguous; it is installed as {}. Use the full name.",
+                candidates.join(" and ")
+            ))
+        }
+    }
+}
+
+/// Loads an installed pattern by name or alias. An alias loads the pattern it
+/// stands for, under that pattern's own name.
+///
+/// # Errors
+///
+/// If no pattern or alias has this name, or its file doesn't load.
+pub fn load_pattern(name: &str) -> Result<Pattern> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let mut resolved: Option<String> = resolve_pattern_name(&pattern_dir, name)?;
+    if resolved.is_none() {
+        if let Some(target) = load_aliases()?.get(name) {
+            resolved = resolve_pattern_name(&pattern_dir, target)?;
+        }
+    }
+    let Some((resolved, path)) = resolved.and_then(|resolved: String| {
+        pattern_file(&pattern_dir, &resolved).map(|path: PathBuf| (resolved, path))
+    }) else {
+        return Err(anyhow!(
+            "Pattern file not found: {}",
+            pattern_dir.join(format!("{name}.json")).display()
+        ));
+    };
+    let mut pattern: Pattern = load_pattern_file(&path)?;
+    pattern.name = resolved;
+    Ok(pattern)
+}
+
+/// Loads a pattern from any file, naming it after the file. `.yaml` and `.yml`
+/// files are read as YAML, everything else as JSON with comments and trailing
+/// commas allowed. Files of older schema versions are upgraded on the way.
+///
+/// # Errors
+///
+/// If the file can't be read, doesn't parse, or isn't a valid pattern.
+pub fn load_pattern_file(pattern_file: &Path) -> Result<Pattern> {
+    let name: &str = pattern_file
+        .file_stem()
+        .and_then(std::ffi::OsStr::to_str)
+        .ok_or_else(|| anyhow!("Can't name a pattern after {}", pattern_file.display()))?;
+    let source: String = fs::read_to_string(pattern_file)
+        .with_context(|| format!("Failed to open pattern file: {}", pattern_file.display()))?;
+    let mut json: serde_json::Value = migrate::read_value(pattern_file, &source)?;
+    migrate::upgrade(&mut json)
+        .with_context(|| format!("Can't load {}", pattern_file.display()))?;
+    let mut pattern: Pattern = serde_json::from_value(json)
+        .with_context(|| format!("Invalid pattern file: {}", pattern_file.display()))?;
+    pattern.name = name.to_string();
+    Ok(pattern)
+}
+
+/// The aliases defined with `gfr alias`.
+///
+/// # Errors
+///
+/// If the aliases file can't be read or parsed.
+pub fn load_aliases() -> Result<Aliases> {
+    let path: PathBuf = get_pattern_dir()?.join(ALIASES_FILE);
+    if !path.exists() {
+        return Ok(Aliases::new());
+    }
+    let file: File = File::open(&path)?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse aliases from: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_pattern_file_names_pattern_after_file() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("internal-token.json");
+        fs::write(
+            &path,
+            r#"{"pattern": "itk_[0-9a-f]{32}", "tags": ["secrets"]}"#,
+        )
+        .unwrap();
+
+        let pattern: Pattern = load_pattern_file(&path).unwrap();
+        assert_eq!(pattern.name, "internal-token");
+        assert_eq!(pattern.regex.as_deref(), Some("itk_[0-9a-f]{32}"));
+        assert!(load_pattern_file(&dir.path().join("missing.json")).is_err());
+
+        let yaml: PathBuf = dir.path().join("jwt.yml");
+        fs::write(
+            &yaml,
+            "# tokens in headers\npatterns:\n  - eyJ[\\w-]+\\.eyJ[\\w-]+\n  - 'Bearer \\S+'\nignore_case: true\n",
+        )
+        .unwrap();
+        let pattern: Pattern = load_pattern_file(&yaml).unwrap();
+        assert_eq!(pattern.name, "jwt");
+        assert_eq!(
+            pattern.regex_list.unwrap(),
+            [r"eyJ[\w-]+\.eyJ[\w-]+", r"Bearer \S+"]
+        );
+        assert!(pattern.ignore_case);
+    }
+
+    #[test]
+    fn test_resolve_pattern_name_across_namespaces() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let pattern_dir: &Path = dir.path();
+        for file in [
+            "team/xss.json",
+            "Kr1shna4garwal/xss.json",
+            "Kr1shna4garwal/jwt.json",
+        ] {
+            let path: PathBuf = pattern_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "{}").unwrap();
+        }
+        fs::write(pattern_dir.join("mine.json"), "{}").unwrap();
+        fs::write(pattern_dir.join("mine.yaml"), "{}").unwrap();
+        fs::write(pattern_dir.join("notes.yml"), "{}").unwrap();
+        fs::write(pattern_dir.join("README.md"), "").unwrap();
+        fs::write(pattern_dir.join(INSTALLED_MANIFEST_FILE), "{}").unwrap();
+
+        let mut names: Vec<String> = list_pattern_names(pattern_dir).unwrap();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "Kr1shna4garwal/jwt",
+                "Kr1shna4garwal/xss",
+                "mine",
+                "notes",
+                "team/xss"
+            ]
+        );
+        let resolve = |name: &str| resolve_pattern_name(pattern_dir, name);
+        assert_eq!(
+            resolve("jwt").unwrap().as_deref(),
+            Some("Kr1shna4garwal/jwt")
+        );
+        assert_eq!(resolve("team/xss").unwrap().as_deref(), Some("team/xss"));
+        assert_eq!(resolve("mine").unwrap().as_deref(), Some("mine"));
+        assert_eq!(
+            pattern_file(pattern_dir, "mine"),
+            Some(pattern_dir.join("mine.json"))
+        );
+        assert_eq!(
+            pattern_file(pattern_dir, "notes"),
+            Some(pattern_dir.join("notes.yml"))
+        );
+        assert_eq!(resolve("sqli").unwrap(), None);
+        assert!(resolve("xss").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Pattern schema versions. Pattern files record the `schema_version` of the
+//! format they were written in; older files are upgraded one version at a time
+//! when they are loaded.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::jsonc;
+use crate::load::PatternFormat;
+
+/// Schema version of the pattern files this gfr writes.
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// Upgrades from each version to the next; the one at index `i` turns version
+/// `i + 1` into `i + 2`. Bumping `SCHEMA_VERSION` means adding one here.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[from_gf_flags];
+
+/// Version 1 is every pattern file from before `schema_version` existed. Files
+/// copied from gf carry its grep `flags`, e.g. `-HnriE`; of those only `i` means
+/// something to gfr, as `ignore_case`. The others are how gfr always searches.
+fn from_gf_flags(fields: &mut Map<String, Value>) {
+    let Some(flags) = fields.remove("flags") else {
+        return;
+    };
+    let ignore_case: bool = flags.as_str().is_some_and(|flags: &str| {
+        flags
+            .split_whitespace()
+            .filter(|flag: &&str| !flag.starts_with("--"))
+            .any(|flag: &str| flag.starts_with('-') && flag.contains('i'))
+    });
+    if ignore_case {
+        fields.entry("ignore_case").or_insert(Value::Bool(true));
+    }
+}
+
+/// The schema version `json` was written in. Files without one are version 1.
+fn version_of(json: &Value) -> Result<u64> {
+    match json.get("schema_version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .filter(|version: &u64| *version >= 1)
+            .ok_or_else(|| anyhow!("'schema_version' must be a positive integer")),
+    }
+}
+
+/// Upgrades the fields of the pattern `json` to the current schema version in
+/// place and returns the version it was written in. The `schema_version` field
+/// itself is left as it is; `gfr migrate` sets it when writing the file back.
+///
+/// # Errors
+///
+/// If `json` isn't an object, or was written for a newer schema version.
+pub fn upgrade(json: &mut Value) -> Result<u64> {
+    let written: u64 = version_of(json)?;
+    if written > SCHEMA_VERSION {
+        return Err(anyhow!(
+            "Written for pattern schema version {written}, but this gfr only knows up to {SCHEMA_VERSION}. Update gfr to use it."
+        ));
+    }
+    let fields: &mut Map<String, Value> = json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("A pattern file must hold a JSON object."))?;
+    for migration in MIGRATIONS.iter().skip(usize::try_from(written - 1)?) {
+        migration(fields);
+    }
+    Ok(written)
+}
+
+/// The contents of a pattern file as JSON: YAML for `.yaml` and `.yml` files,
+/// JSON with comments and trailing commas allowed otherwise.
+///
+/// # Errors
+///
+/// If `source` doesn't parse as JSON or YAML.
+pub fn read_value(path: &Path, source: &str) -> Result<Value> {
+    Ok(match PatternFormat::of(path) {
+        PatternFormat::Json => serde_json::from_str(&jsonc::strip(source))
+            .with_context(|| format!("Failed to parse JSON from: {}", path.display()))?,
+        PatternFormat::Yaml => serde_yaml::from_str(source)
+            .with_context(|| format!("Failed to parse YAML from: {}", path.display()))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_pattern_versions() {
+        assert_eq!(u64::try_from(MIGRATIONS.len()).unwrap() + 1, SCHEMA_VERSION);
+
+        let mut gf: Value =
+            serde_json::json!({"flags": "-HnriE --include=*.js", "pattern": "secret"});
+        assert_eq!(upgrade(&mut gf).unwrap(), 1);
+        assert_eq!(
+            gf,
+            serde_json::json!({"pattern": "secret", "ignore_case": true})
+        );
+
+        let mut case_sensitive: Value = serde_json::json!({"flags": "-HnrE --ignore-case"});
+        upgrade(&mut case_sensitive).unwrap();
+        assert!(case_sensitive.get("ignore_case").is_none());
+
+        let mut current: Value = serde_json::json!({"schema_version": 2, "pattern": "a"});
+        assert_eq!(upgrade(&mut current).unwrap(), SCHEMA_VERSION);
+        assert_eq!(
+            current,
+            serde_json::json!({"schema_version": 2, "pattern": "a"})
+        );
+
+        assert!(upgrade(&mut serde_json::json!({"schema_version": 3})).is_err());
+        assert!(upgrade(&mut serde_json::json!({"schema_version": 0})).is_err());
+        assert!(upgrade(&mut serde_json::json!(["a"])).is_err());
+    }
+}
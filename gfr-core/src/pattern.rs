@@ -0,0 +1,769 @@
+//! The pattern model: what a pattern file holds, and how its regexes, strings
+//! and flags become a matcher, alone or combined with other patterns.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{self, Engine, PatternMatcher};
+use crate::entropy::EntropyRule;
+use crate::filetype;
+use crate::validators::Validator;
+use crate::verification::VerificationSpec;
+
+/// How serious a pattern's findings are. Patterns without one count as `info`.
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name: &str = match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Examples a pattern must and must not match.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PatternTests {
+    /// Inputs the pattern has to match.
+    #[serde(rename = "match", default)]
+    pub should_match: Vec<String>,
+    /// Inputs the pattern must not match.
+    #[serde(default)]
+    pub no_match: Vec<String>,
+}
+
+/// A search pattern, as loaded from a JSON or YAML pattern file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[allow(clippy::struct_excessive_bools)] // Each bool is an independent field of the file format.
+pub struct Pattern {
+    /// Name of the pattern, taken from its file name when loaded.
+    #[serde(skip)]
+    pub name: String,
+    /// JSON Schema reference (optional, for validation support).
+    #[serde(rename = "$schema")]
+    pub schema: Option<String>,
+    /// Version of the pattern file format. Files without one are version 1 and
+    /// are upgraded to the current version when loaded.
+    pub schema_version: Option<u64>,
+    /// Semantic version of the pattern file.
+    #[serde(default = "default_version")]
+    pub version: String,
+    /// Author of the pattern.
+    pub author: Option<String>,
+    /// SPDX license identifier the pattern is distributed under.
+    pub license: Option<String>,
+    /// A brief explanation of the pattern.
+    pub description: Option<String>,
+    /// A list of tags for categorization.
+    pub tags: Option<Vec<String>>,
+    /// How serious a match is.
+    pub severity: Option<Severity>,
+    /// A single regular expression. Use this, `regex_list` or `strings`.
+    #[serde(rename = "pattern")]
+    pub regex: Option<String>,
+    /// A list of regular expressions. These will be combined into a single pattern.
+    #[serde(rename = "patterns")]
+    pub regex_list: Option<Vec<String>>,
+    /// Literal strings, matched with Aho-Corasick instead of a regex.
+    pub strings: Option<Vec<String>>,
+    /// Words a line must contain, ignoring case, for the pattern to be run on it.
+    pub keywords: Option<Vec<String>>,
+    /// A list of file extensions to specifically include in the search.
+    pub file_types: Option<Vec<String>>,
+    /// File types never to search, even those `file_types` includes, e.g.
+    /// `min.js` or `lock` for generated files.
+    pub exclude_file_types: Option<Vec<String>>,
+    /// Languages to search the files of, e.g. `javascript` or `shell`, on top
+    /// of `file_types`, see [`filetype::language_type`].
+    pub languages: Option<Vec<String>>,
+    /// Regexes one of which the path of a file, relative to the searched
+    /// directory and with `/` separators, must match, e.g. `^(config|deploy)/`.
+    pub paths: Option<Vec<String>>,
+    /// Regexes of relative paths never to search, e.g. `(^|/)testdata/`.
+    pub paths_exclude: Option<Vec<String>>,
+    /// If true, the search will be case-insensitive.
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// If true, matches may span lines: `.` matches line breaks and `^`/`$` match
+    /// at the start and end of every line.
+    #[serde(default)]
+    pub multiline: bool,
+    /// If true, matches only count where they aren't next to word characters.
+    #[serde(default)]
+    pub word: bool,
+    /// If false, `\w`, `\d`, `\b` and case folding only know ASCII.
+    #[serde(default = "default_unicode")]
+    pub unicode: bool,
+    /// Regex engine the pattern runs on; `rust` unless it needs `backtrack`.
+    pub engine: Option<Engine>,
+    /// Example inputs checked by `gfr test`.
+    pub tests: Option<PatternTests>,
+    /// Matches whose Shannon entropy (bits per character) is below this are dropped.
+    pub min_entropy: Option<f64>,
+    /// Named capture group whose text `min_entropy` is measured on, instead of the whole match.
+    pub entropy_group: Option<String>,
+    /// Built-in checks every match has to pass, e.g. `luhn` for card numbers.
+    pub validators: Option<Vec<Validator>>,
+    /// The request `--verify` sends to find out whether a match is a live credential.
+    pub verification: Option<VerificationSpec>,
+    /// WebAssembly modules every finding of this pattern is passed through, which
+    /// can drop, rewrite or annotate it. Relative paths are in the pattern directory.
+    pub plugins: Option<Vec<String>>,
+    /// Retired by its maintainers; searches with it print a warning.
+    #[serde(default)]
+    pub deprecated: bool,
+    /// The pattern that replaces this one.
+    pub superseded_by: Option<String>,
+}
+
+impl Default for Pattern {
+    /// An unnamed pattern that matches nothing until one of `regex`, `regex_list`
+    /// or `strings` is set.
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            schema: None,
+            schema_version: None,
+            version: default_version(),
+            author: None,
+            license: None,
+            description: None,
+            tags: None,
+            severity: None,
+            regex: None,
+            regex_list: None,
+            strings: None,
+            keywords: None,
+            file_types: None,
+            exclude_file_types: None,
+            languages: None,
+            paths: None,
+            paths_exclude: None,
+            ignore_case: false,
+            multiline: false,
+            word: false,
+            unicode: default_unicode(),
+            engine: None,
+            tests: None,
+            min_entropy: None,
+            entropy_group: None,
+            validators: None,
+            verification: None,
+            plugins: None,
+            deprecated: false,
+            superseded_by: None,
+        }
+    }
+}
+
+impl Pattern {
+    /// Combines `regex` and `regex_list` fields into a single regex string.
+    /// The patterns are joined with `|` to create a single regex; `strings` are
+    /// escaped and joined the same way.
+    ///
+    /// # Errors
+    ///
+    /// Unless exactly one of `regex`, a non-empty `regex_list` or non-empty `strings` is set.
+    pub fn get_raw_pattern(&self) -> Result<String> {
+        match (&self.regex, &self.regex_list, &self.strings) {
+            (Some(p), None, None) => Ok(p.clone()),
+            (None, Some(ps), None) if !ps.is_empty() => Ok(format!("(?:{})", ps.join("|"))),
+            (None, None, Some(strings)) if !strings.is_empty() => Ok(format!(
+                "(?:{})",
+                strings
+                    .iter()
+                    .map(|s: &String| regex_syntax::escape(s))
+                    .collect::<Vec<String>>()
+                    .join("|")
+            )),
+            _ => Err(anyhow!(
+                "Pattern file must contain exactly one of a 'pattern' key, or a non-empty 'patterns' or 'strings' key."
+            )),
+        }
+    }
+
+    /// The literal strings of this pattern, if it is made of them. Strings that
+    /// must be whole words, or whose case is folded beyond ASCII, are matched as a
+    /// regex instead.
+    #[must_use]
+    pub fn literals(&self) -> Option<&[String]> {
+        let folds_unicode = |strings: &[String]| {
+            self.ignore_case && self.unicode && !strings.iter().all(|s: &String| s.is_ascii())
+        };
+        match (&self.regex, &self.regex_list, &self.strings) {
+            (None, None, Some(strings))
+                if !strings.is_empty() && !self.word && !folds_unicode(strings) =>
+            {
+                Some(strings)
+            }
+            _ => None,
+        }
+    }
+
+    /// Builds a matcher for this pattern alone, honoring its own flags, engine
+    /// and keywords.
+    ///
+    /// # Errors
+    ///
+    /// If the pattern has no regex or strings, or they or its path regexes
+    /// don't compile, or it names a language gfr doesn't know.
+    pub fn build_matcher(&self) -> Result<PatternMatcher> {
+        for language in self.languages.iter().flatten() {
+            filetype::require_language_type(language)?;
+        }
+        for regex in self.paths.iter().chain(&self.paths_exclude).flatten() {
+            fancy_regex::Regex::new(regex)
+                .map_err(|e: fancy_regex::Error| anyhow!("Invalid path regex '{regex}': {e}"))?;
+        }
+        let matcher: PatternMatcher = self.ungated_matcher()?;
+        match self.gate() {
+            Some(keywords) => Ok(PatternMatcher::gated(vec![(keywords.to_vec(), matcher)])?),
+            None => Ok(matcher),
+        }
+    }
+
+    /// The `keywords` of this pattern, if it has any.
+    #[must_use]
+    pub fn gate(&self) -> Option<&[String]> {
+        self.keywords
+            .as_deref()
+            .filter(|keywords: &&[String]| !keywords.is_empty())
+    }
+
+    /// A matcher for this pattern that runs on every line, whatever its keywords.
+    fn ungated_matcher(&self) -> Result<PatternMatcher> {
+        if let Some(strings) = self.literals() {
+            return Ok(PatternMatcher::literal(strings, self.ignore_case)?);
+        }
+        let engine: Engine = self.engine.unwrap_or_default();
+        let regex: String = self.scoped_regex(engine)?;
+        compiler(self.multiline)(&regex, engine).map_err(|e: engine::EngineError| {
+            let backtrack: Result<String> = self.scoped_regex(Engine::Backtrack);
+            if engine == Engine::Rust
+                && backtrack.is_ok_and(|regex: String| PatternMatcher::new(&regex, Engine::Backtrack).is_ok())
+            {
+                anyhow!("{e}\nThe backtrack engine can run it: set \"engine\": \"backtrack\" in the pattern, or search with --engine backtrack.")
+            } else {
+                e.into()
+            }
+        })
+    }
+
+    /// The regex of this pattern in a group carrying its own flags, so it can be
+    /// joined with other patterns without their flags leaking into it. Word
+    /// boundaries are written in the syntax of `engine`.
+    fn scoped_regex(&self, engine: Engine) -> Result<String> {
+        let mut flags: String = String::new();
+        if self.ignore_case {
+            flags.push('i');
+        }
+        if self.multiline {
+            flags.push_str("sm");
+        }
+        if !self.unicode {
+            if engine == Engine::Backtrack {
+                return Err(anyhow!(
+                    "'unicode: false' is not supported on the backtrack engine."
+                ));
+            }
+            flags.push_str("-u");
+        }
+        let raw: String = self.get_raw_pattern()?;
+        Ok(match (self.word, engine) {
+            (false, _) => format!("(?{flags}:{raw})"),
+            (true, Engine::Rust) => format!(r"(?{flags}:\b{{start-half}}(?:{raw})\b{{end-half}})"),
+            (true, Engine::Backtrack) => format!(r"(?{flags}:(?<!\w)(?:{raw})(?!\w))"),
+        })
+    }
+
+    /// Whether `text`, a match of `matcher` built from this pattern, passes its
+    /// `min_entropy` and `validators`.
+    #[must_use]
+    pub fn accepts(&self, matcher: &PatternMatcher, text: &[u8]) -> bool {
+        let rule: Option<EntropyRule> = self.min_entropy.map(|min: f64| EntropyRule {
+            min,
+            group: self.entropy_group.clone(),
+        });
+        rule.is_none_or(|rule: EntropyRule| rule.passes(matcher, text))
+            && self
+                .validators
+                .iter()
+                .flatten()
+                .all(|validator: &Validator| validator.accepts(text))
+    }
+
+    /// Whether this pattern searches the file at `path`, relative to the searched
+    /// directory: it is one of the pattern's `file_types` or of its `languages`,
+    /// or it has neither, and
+    /// none of its `exclude_file_types`, and its `paths` and `paths_exclude`
+    /// allow it. Each file type is an extension, the name of a built-in file
+    /// type, a file name or a glob, see [`filetype::is_of_type`].
+    #[must_use]
+    pub fn covers(&self, path: &Path) -> bool {
+        !self.excludes(path) && self.includes(path) && self.in_paths(path)
+    }
+
+    /// Whether the relative `path` matches one of this pattern's `paths`, or it
+    /// has none, and none of its `paths_exclude`.
+    fn in_paths(&self, path: &Path) -> bool {
+        if self.paths.is_none() && self.paths_exclude.is_none() {
+            return true;
+        }
+        let path: String = path.to_string_lossy().replace('\\', "/");
+        self.paths
+            .as_ref()
+            .filter(|regexes: &&Vec<String>| !regexes.is_empty())
+            .is_none_or(|regexes: &Vec<String>| {
+                regexes.iter().any(|r: &String| path_matches(r, &path))
+            })
+            && !self
+                .paths_exclude
+                .iter()
+                .flatten()
+                .any(|r: &String| path_matches(r, &path))
+    }
+
+    /// Whether `path` is one of this pattern's `exclude_file_types`.
+    fn excludes(&self, path: &Path) -> bool {
+        self.exclude_file_types
+            .iter()
+            .flatten()
+            .any(|t: &String| filetype::is_of_type(path, t))
+    }
+
+    /// Whether `path` is one of this pattern's `file_types` or of its
+    /// `languages`, or it has neither.
+    fn includes(&self, path: &Path) -> bool {
+        let mut types = self.type_names().peekable();
+        types.peek().is_none() || types.any(|t: &str| filetype::is_of_type(path, t))
+    }
+
+    /// The file types this pattern searches: its `file_types`, and the type of
+    /// each of its `languages`. Empty if it searches every file.
+    #[must_use]
+    pub fn included_types(&self) -> Vec<String> {
+        self.type_names().map(ToString::to_string).collect()
+    }
+
+    fn type_names(&self) -> impl Iterator<Item = &str> {
+        self.file_types.iter().flatten().map(String::as_str).chain(
+            self.languages
+                .iter()
+                .flatten()
+                .filter_map(|l: &String| filetype::language_type(l)),
+        )
+    }
+}
+
+/// Whether the relative path `path` matches `regex`, compiled the first time
+/// it comes up. Invalid regexes, which [`Pattern::build_matcher`] rejects,
+/// match nothing.
+fn path_matches(regex: &str, path: &str) -> bool {
+    static REGEXES: OnceLock<Mutex<HashMap<String, Option<fancy_regex::Regex>>>> = OnceLock::new();
+    let mut regexes = REGEXES
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    regexes
+        .entry(regex.to_string())
+        .or_insert_with(|| fancy_regex::Regex::new(regex).ok())
+        .as_ref()
+        .is_some_and(|regex: &fancy_regex::Regex| regex.is_match(path).unwrap_or(false))
+}
+
+/// The indices of the `patterns` covering the file at `path`, found in a walk
+/// of `root`. With `detect_types`, a file without an extension is also covered
+/// by the patterns that include a type its first line gives away,
+/// see [`filetype::detect`].
+#[must_use]
+pub fn covering(patterns: &[Pattern], root: &Path, path: &Path, detect_types: bool) -> Vec<usize> {
+    let relative: &Path = path.strip_prefix(root).unwrap_or(path);
+    let mut detected: Option<&[&str]> = None;
+    (0..patterns.len())
+        .filter(|i: &usize| {
+            let p: &Pattern = &patterns[*i];
+            p.covers(relative)
+                || detect_types
+                    && !p.excludes(relative)
+                    && p.in_paths(relative)
+                    && p.type_names().any(|t: &str| {
+                        detected
+                            .get_or_insert_with(|| filetype::detect(path))
+                            .contains(&t)
+                    })
+        })
+        .collect()
+}
+
+/// A matcher for any of `patterns`. Their regexes are joined into one, on
+/// `backtrack` if any of the patterns needs it; their literal strings go into one
+/// automaton per case sensitivity. Patterns with keywords keep matchers of
+/// their own behind one automaton of all the keywords.
+///
+/// # Errors
+///
+/// If any of `patterns` doesn't build a matcher.
+pub fn combined_matcher(patterns: &[&Pattern]) -> Result<PatternMatcher> {
+    let (gated, ungated): (Vec<&Pattern>, Vec<&Pattern>) = patterns
+        .iter()
+        .partition(|p: &&&Pattern| p.gate().is_some());
+    let (literal, regex): (Vec<&Pattern>, Vec<&Pattern>) = ungated
+        .into_iter()
+        .partition(|p: &&Pattern| p.literals().is_some());
+    let mut matchers: Vec<PatternMatcher> = Vec::new();
+    // Patterns whose matches may span lines can't share a regex with the rest,
+    // and ASCII-only ones can't share one with patterns that need backtracking.
+    for (multiline, unicode) in [(false, true), (false, false), (true, true), (true, false)] {
+        let group: Vec<&Pattern> = regex
+            .iter()
+            .copied()
+            .filter(|p: &&Pattern| p.multiline == multiline && p.unicode == unicode)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        let engine: Engine = combined_engine(group.iter().copied());
+        let regexes: Vec<String> = group
+            .iter()
+            .map(|p: &&Pattern| p.scoped_regex(engine))
+            .collect::<Result<_>>()?;
+        matchers.push(compiler(multiline)(&regexes.join("|"), engine)?);
+    }
+    for ignore_case in [false, true] {
+        let strings: Vec<String> = literal
+            .iter()
+            .filter(|p: &&&Pattern| p.ignore_case == ignore_case)
+            .flat_map(|p: &&Pattern| p.literals().unwrap_or_default().iter().cloned())
+            .collect();
+        if !strings.is_empty() {
+            matchers.push(PatternMatcher::literal(&strings, ignore_case)?);
+        }
+    }
+    if !gated.is_empty() {
+        let keyed: Vec<(Vec<String>, PatternMatcher)> = gated
+            .iter()
+            .map(|p: &&Pattern| Ok((p.gate().unwrap_or_default().to_vec(), p.ungated_matcher()?)))
+            .collect::<Result<_>>()?;
+        matchers.push(PatternMatcher::gated(keyed)?);
+    }
+    Ok(PatternMatcher::any(matchers))
+}
+
+/// How regexes are compiled: to match within one line, or across lines for
+/// `multiline` patterns.
+fn compiler(multiline: bool) -> fn(&str, Engine) -> Result<PatternMatcher, engine::EngineError> {
+    if multiline {
+        PatternMatcher::spanning
+    } else {
+        PatternMatcher::new
+    }
+}
+
+/// The engine that can run all of `patterns` together.
+fn combined_engine<'a>(mut patterns: impl Iterator<Item = &'a Pattern>) -> Engine {
+    if patterns.any(|p: &Pattern| p.engine == Some(Engine::Backtrack)) {
+        Engine::Backtrack
+    } else {
+        Engine::Rust
+    }
+}
+
+/// Default version for a new pattern.
+#[must_use]
+pub fn default_version() -> String {
+    "1.0.0".to_string()
+}
+
+/// Patterns are Unicode-aware unless they opt out.
+fn default_unicode() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_matcher::Matcher;
+
+    use super::*;
+
+    #[test]
+    fn test_get_raw_pattern_logic() {
+        let p1: Pattern = Pattern {
+            regex: Some("abc".to_string()),
+            ..Pattern::default()
+        };
+        assert_eq!(p1.get_raw_pattern().unwrap(), "abc");
+
+        let p2: Pattern = Pattern {
+            regex_list: Some(vec!["a".to_string(), "b".to_string()]),
+            ..Pattern::default()
+        };
+        assert_eq!(p2.get_raw_pattern().unwrap(), "(?:a|b)");
+
+        let p3: Pattern = Pattern {
+            regex: None,
+            regex_list: Some(vec![]),
+            ..Pattern::default()
+        };
+        assert!(p3.get_raw_pattern().is_err());
+
+        let p4: Pattern = Pattern {
+            regex: None,
+            regex_list: None,
+            ..Pattern::default()
+        };
+        assert!(p4.get_raw_pattern().is_err());
+    }
+
+    #[test]
+    fn test_combined_matcher_keeps_flags_per_pattern() {
+        let key: Pattern = Pattern {
+            regex: Some("key".to_string()),
+            file_types: Some(vec!["js".to_string(), "d.ts".to_string()]),
+            ..Pattern::default()
+        };
+        let token: Pattern = Pattern {
+            regex_list: Some(vec!["token".to_string(), "a.b".to_string()]),
+            regex: None,
+            ignore_case: true,
+            multiline: true,
+            ..Pattern::default()
+        };
+        assert_eq!(
+            token.scoped_regex(Engine::Rust).unwrap(),
+            "(?ism:(?:token|a.b))"
+        );
+        let matcher: PatternMatcher = combined_matcher(&[&key, &token]).unwrap();
+        assert!(matcher.is_match(b"TOKEN").unwrap());
+        // The multiline pattern matches across lines; the other doesn't.
+        assert!(matcher.line_terminator().is_none());
+        assert_eq!(
+            matcher.find(b"a\nb ke\ny").unwrap(),
+            Some(grep_matcher::Match::new(0, 3))
+        );
+        assert_eq!(matcher.find(b"ke\ny").unwrap(), None);
+        assert!(matcher.is_match(b"key").unwrap());
+        assert!(!matcher.is_match(b"KEY").unwrap());
+
+        let hosts: Pattern = Pattern {
+            strings: Some(vec![
+                "db.internal".to_string(),
+                "db.internal.corp".to_string(),
+            ]),
+            ignore_case: true,
+            ..Pattern::default()
+        };
+        assert_eq!(
+            hosts.get_raw_pattern().unwrap(),
+            r"(?:db\.internal|db\.internal\.corp)"
+        );
+        let matcher: PatternMatcher = combined_matcher(&[&key, &token, &hosts]).unwrap();
+        assert!(matcher.is_match(b"DB.INTERNAL").unwrap());
+        assert!(!matcher.is_match(b"dbxinternal").unwrap());
+        assert_eq!(
+            matcher.find(b"key=db.Internal.corp").unwrap(),
+            Some(grep_matcher::Match::new(0, 3))
+        );
+        assert_eq!(
+            matcher.find(b"at db.Internal.corp").unwrap(),
+            Some(grep_matcher::Match::new(3, 19))
+        );
+
+        let gated: Pattern = Pattern {
+            regex: Some("[0-9]{4}".to_string()),
+            keywords: Some(vec!["pin".to_string()]),
+            ..Pattern::default()
+        };
+        let matcher: PatternMatcher = combined_matcher(&[&key, &gated]).unwrap();
+        assert!(!matcher.is_match(b"code 1234").unwrap());
+        assert!(matcher.is_match(b"PIN 1234").unwrap());
+        assert!(matcher.is_match(b"key 1234").unwrap());
+        assert!(!gated
+            .build_matcher()
+            .unwrap()
+            .is_match(b"key 1234")
+            .unwrap());
+
+        let words: Pattern = Pattern {
+            strings: Some(vec!["key".to_string(), "token".to_string()]),
+            word: true,
+            ..Pattern::default()
+        };
+        let lookahead: Pattern = Pattern {
+            regex: Some("x(?=y)".to_string()),
+            engine: Some(Engine::Backtrack),
+            ..Pattern::default()
+        };
+        for matcher in [
+            words.build_matcher().unwrap(),
+            combined_matcher(&[&words, &lookahead]).unwrap(),
+        ] {
+            assert!(matcher.is_match(b"the key=1").unwrap());
+            assert!(matcher.is_match(b"(token)").unwrap());
+            assert!(!matcher.is_match(b"monkey tokenizer").unwrap());
+        }
+
+        assert!(key.covers(Path::new("src/app.js")));
+        assert!(key.covers(Path::new("types/index.d.ts")));
+        assert!(!key.covers(Path::new("src/app.ts")));
+        assert!(!key.covers(Path::new("src/js")));
+        assert!(token.covers(Path::new("README")));
+    }
+
+    #[test]
+    fn test_unicode_can_be_turned_off_per_pattern() {
+        let name: Pattern = Pattern {
+            regex: Some(r"user=\w+".to_string()),
+            ..Pattern::default()
+        };
+        let ascii: Pattern = Pattern {
+            unicode: false,
+            ..name.clone()
+        };
+        let zoe: &[u8] = "user=zoë".as_bytes();
+        let found = |p: &Pattern| p.build_matcher().unwrap().find(zoe).unwrap();
+        assert_eq!(found(&name), Some(grep_matcher::Match::new(0, 9)));
+        assert_eq!(found(&ascii), Some(grep_matcher::Match::new(0, 7)));
+        assert_eq!(ascii.scoped_regex(Engine::Rust).unwrap(), r"(?-u:user=\w+)");
+        assert!(ascii.scoped_regex(Engine::Backtrack).is_err());
+        let lookahead: Pattern = Pattern {
+            regex: Some(r"id=(?=\d)".to_string()),
+            engine: Some(Engine::Backtrack),
+            ..Pattern::default()
+        };
+        let matcher: PatternMatcher = combined_matcher(&[&ascii, &lookahead]).unwrap();
+        assert_eq!(
+            matcher.find(zoe).unwrap(),
+            Some(grep_matcher::Match::new(0, 7))
+        );
+        assert!(matcher.is_match(b"id=7").unwrap());
+
+        let folded: Pattern = Pattern {
+            strings: Some(vec!["STRASSE".to_string(), "ÉTÉ".to_string()]),
+            ignore_case: true,
+            ..Pattern::default()
+        };
+        assert!(folded.literals().is_none());
+        assert!(folded
+            .build_matcher()
+            .unwrap()
+            .is_match("été".as_bytes())
+            .unwrap());
+        let ascii_folded: Pattern = Pattern {
+            unicode: false,
+            ..folded
+        };
+        assert!(ascii_folded.literals().is_some());
+        assert!(!ascii_folded
+            .build_matcher()
+            .unwrap()
+            .is_match("été".as_bytes())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_covers_file_type_names_and_detected_types() {
+        let docker: Pattern = Pattern {
+            file_types: Some(vec!["docker".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(docker.covers(Path::new("deploy/Dockerfile")));
+        assert!(!docker.covers(Path::new("deploy/run.sh")));
+
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let script: std::path::PathBuf = dir.path().join("deploy");
+        std::fs::write(&script, "#!/bin/bash\nexit 0\n").unwrap();
+        let shell: Pattern = Pattern {
+            file_types: Some(vec!["sh".to_string()]),
+            ..Pattern::default()
+        };
+        let patterns: [Pattern; 2] = [docker, shell];
+        assert!(covering(&patterns, dir.path(), &script, false).is_empty());
+        assert_eq!(covering(&patterns, dir.path(), &script, true), [1]);
+
+        let generated: Pattern = Pattern {
+            file_types: Some(vec!["js".to_string()]),
+            exclude_file_types: Some(vec!["min.js".to_string(), "*.bundle.js".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(generated.covers(Path::new("src/app.js")));
+        assert!(!generated.covers(Path::new("dist/app.min.js")));
+        assert!(!generated.covers(Path::new("dist/vendor.bundle.js")));
+        let everything_but: Pattern = Pattern {
+            exclude_file_types: Some(vec!["lock".to_string(), "map".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(everything_but.covers(Path::new("README")));
+        assert!(!everything_but.covers(Path::new("Cargo.lock")));
+        assert!(!everything_but.covers(Path::new("dist/app.js.map")));
+
+        let web: Pattern = Pattern {
+            file_types: Some(vec!["html".to_string()]),
+            languages: Some(vec!["JavaScript".to_string(), "typescript".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(web.covers(Path::new("src/app.mjs")));
+        assert!(web.covers(Path::new("src/App.tsx")));
+        assert!(web.covers(Path::new("index.html")));
+        assert!(!web.covers(Path::new("src/main.rs")));
+        assert_eq!(web.included_types(), ["html", "js", "typescript"]);
+        let unknown: Pattern = Pattern {
+            regex: Some("key".to_string()),
+            languages: Some(vec!["klingon".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(unknown.build_matcher().is_err());
+    }
+
+    #[test]
+    fn test_covers_paths() {
+        let deploy: Pattern = Pattern {
+            paths: Some(vec!["^(config|deploy)/".to_string()]),
+            paths_exclude: Some(vec!["(^|/)testdata/".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(deploy.covers(Path::new("config/app.yml")));
+        assert!(deploy.covers(Path::new("deploy/prod/env")));
+        assert!(!deploy.covers(Path::new("src/config/app.yml")));
+        assert!(!deploy.covers(Path::new("deploy/testdata/env")));
+        let root: &Path = Path::new("/srv/app");
+        assert_eq!(
+            covering(
+                std::slice::from_ref(&deploy),
+                root,
+                &root.join("config/app.yml"),
+                false
+            ),
+            [0]
+        );
+
+        let invalid: Pattern = Pattern {
+            regex: Some("key".to_string()),
+            paths_exclude: Some(vec!["(".to_string()]),
+            ..Pattern::default()
+        };
+        assert!(invalid.build_matcher().is_err());
+    }
+}
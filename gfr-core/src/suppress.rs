@@ -0,0 +1,118 @@
+//! Inline suppression comments.
+//!
+//! `gfr:ignore` on a matching line, or `gfr:ignore-next-line` on the line above
+//! it, hides the match. Either may name the patterns it applies to, separated by
+//! spaces or commas (e.g. `// gfr:ignore aws-keys, jwt`); without names it
+//! applies to all of them.
+
+const IGNORE: &[u8] = b"gfr:ignore";
+const NEXT_LINE: &[u8] = b"-next-line";
+
+/// Whether a match of `pattern` on `line` is suppressed by a comment on the line
+/// itself or on `previous`. A `None` pattern is only suppressed by unnamed comments.
+#[must_use]
+pub fn is_suppressed(pattern: Option<&str>, line: &[u8], previous: Option<&[u8]>) -> bool {
+    let applies = |names: Vec<&[u8]>| {
+        names.is_empty() || pattern.is_some_and(|p: &str| names.contains(&p.as_bytes()))
+    };
+    directive(line, false).is_some_and(applies)
+        || previous
+            .and_then(|prev: &[u8]| directive(prev, true))
+            .is_some_and(applies)
+}
+
+/// Finds a `gfr:ignore` (or, with `next_line`, `gfr:ignore-next-line`) comment in
+/// `line` and returns the pattern names it lists.
+fn directive(line: &[u8], next_line: bool) -> Option<Vec<&[u8]>> {
+    let mut rest: &[u8] = line;
+    while let Some(at) = find(rest, IGNORE) {
+        rest = &rest[at + IGNORE.len()..];
+        let is_next_line: bool = rest.starts_with(NEXT_LINE);
+        if is_next_line {
+            rest = &rest[NEXT_LINE.len()..];
+        }
+        // Skip things like `gfr:ignored`, which aren't directives.
+        if rest.first().is_some_and(|b: &u8| is_name_byte(*b)) {
+            continue;
+        }
+        if is_next_line == next_line {
+            return Some(names(rest));
+        }
+    }
+    None
+}
+
+/// Reads the pattern names following a directive, stopping at the first token
+/// that can't be a name, such as a closing `*/` or `-->`.
+fn names(rest: &[u8]) -> Vec<&[u8]> {
+    rest.split(|b: &u8| b.is_ascii_whitespace() || *b == b',')
+        .filter(|token: &&[u8]| !token.is_empty())
+        .take_while(|token: &&[u8]| token.iter().all(|b: &u8| is_name_byte(*b)))
+        .collect()
+}
+
+fn is_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-' || b == b'_'
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|w: &[u8]| w == needle)
+}
+
+/// Returns the line before the one starting at `start` in `buffer`, without its
+/// terminator, if the buffer still holds it.
+#[must_use]
+pub fn previous_line(buffer: &[u8], start: usize) -> Option<&[u8]> {
+    let before: &[u8] = buffer[..start].strip_suffix(b"\n")?;
+    let before: &[u8] = before.strip_suffix(b"\r").unwrap_or(before);
+    let line_start: usize = before
+        .iter()
+        .rposition(|b: &u8| *b == b'\n')
+        .map_or(0, |i: usize| i + 1);
+    Some(&before[line_start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_suppression() {
+        let key: &[u8] = b"const k = 'AKIA1234'; // gfr:ignore";
+        assert!(is_suppressed(Some("aws-keys"), key, None));
+        assert!(is_suppressed(None, key, None));
+
+        let named: &[u8] = b"k = 'AKIA1234' /* gfr:ignore aws-keys, jwt */";
+        assert!(is_suppressed(Some("jwt"), named, None));
+        assert!(!is_suppressed(Some("xss"), named, None));
+        assert!(!is_suppressed(None, named, None));
+
+        let above: &[u8] = b"# gfr:ignore-next-line aws-keys";
+        assert!(is_suppressed(
+            Some("aws-keys"),
+            b"k = 'AKIA1234'",
+            Some(above)
+        ));
+        // A next-line comment doesn't cover its own line, and vice versa.
+        assert!(!is_suppressed(Some("aws-keys"), above, None));
+        assert!(!is_suppressed(
+            Some("aws-keys"),
+            b"k",
+            Some(b"// gfr:ignore")
+        ));
+        assert!(!is_suppressed(
+            Some("aws-keys"),
+            b"gfr:ignored = true",
+            None
+        ));
+
+        let buffer: &[u8] = b"one\r\n// gfr:ignore-next-line\r\nthree\n";
+        assert_eq!(
+            previous_line(buffer, 30),
+            Some(&b"// gfr:ignore-next-line"[..])
+        );
+        assert_eq!(previous_line(buffer, 0), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Tag expressions for `--tags`: tags combined with `and`, `or`, `not` and
+//! parentheses, e.g. `web and (xss or sqli) and not noisy`. A comma works as
+//! `and`, so plain lists like `web,security` keep meaning "all of these".
+
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+/// A parsed `--tags` expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagExpr {
+    Tag(String),
+    Not(Box<TagExpr>),
+    And(Box<TagExpr>, Box<TagExpr>),
+    Or(Box<TagExpr>, Box<TagExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+type Tokens = Peekable<IntoIter<Token>>;
+
+impl TagExpr {
+    /// Parses an expression; `not` binds tightest, then `and`, then `or`.
+    ///
+    /// # Errors
+    ///
+    /// If `text` holds no tag, or a misplaced operator or parenthesis.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut tokens: Tokens = tokenize(text).into_iter().peekable();
+        if tokens.peek().is_none() {
+            return Err("expected at least one tag".to_string());
+        }
+        let expr: Self = parse_or(&mut tokens)?;
+        match tokens.next() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {} in tag expression", describe(&token))),
+        }
+    }
+
+    /// Whether a pattern with `tags` is selected.
+    #[must_use]
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            Self::Tag(tag) => tags.contains(tag),
+            Self::Not(inner) => !inner.matches(tags),
+            Self::And(left, right) => left.matches(tags) && right.matches(tags),
+            Self::Or(left, right) => left.matches(tags) || right.matches(tags),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut word: String = String::new();
+    let end_word = |word: &mut String, tokens: &mut Vec<Token>| {
+        if word.is_empty() {
+            return;
+        }
+        tokens.push(match word.as_str() {
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            _ => Token::Word(word.clone()),
+        });
+        word.clear();
+    };
+    for c in text.chars() {
+        match c {
+            '(' | ')' | ',' => {
+                end_word(&mut word, &mut tokens);
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::And,
+                });
+            }
+            c if c.is_whitespace() => end_word(&mut word, &mut tokens),
+            c => word.push(c),
+        }
+    }
+    end_word(&mut word, &mut tokens);
+    tokens
+}
+
+fn parse_or(tokens: &mut Tokens) -> Result<TagExpr, String> {
+    let mut expr: TagExpr = parse_and(tokens)?;
+    while tokens.next_if_eq(&Token::Or).is_some() {
+        expr = TagExpr::Or(Box::new(expr), Box::new(parse_and(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_and(tokens: &mut Tokens) -> Result<TagExpr, String> {
+    let mut expr: TagExpr = parse_not(tokens)?;
+    while tokens.next_if_eq(&Token::And).is_some() {
+        expr = TagExpr::And(Box::new(expr), Box::new(parse_not(tokens)?));
+    }
+    Ok(expr)
+}
+
+fn parse_not(tokens: &mut Tokens) -> Result<TagExpr, String> {
+    match tokens.next() {
+        Some(Token::Not) => Ok(TagExpr::Not(Box::new(parse_not(tokens)?))),
+        Some(Token::Word(tag)) => Ok(TagExpr::Tag(tag)),
+        Some(Token::Open) => {
+            let expr: TagExpr = parse_or(tokens)?;
+            match tokens.next() {
+                Some(Token::Close) => Ok(expr),
+                _ => Err("missing ')' in tag expression".to_string()),
+            }
+        }
+        Some(token) => Err(format!("expected a tag, found {}", describe(&token))),
+        None => Err("tag expression ends early".to_string()),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => format!("'{word}'"),
+        Token::And => "'and'".to_string(),
+        Token::Or => "'or'".to_string(),
+        Token::Not => "'not'".to_string(),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_expressions() {
+        let tags = |list: &[&str]| -> Vec<String> {
+            list.iter().map(|t: &&str| (*t).to_string()).collect()
+        };
+        let expr: TagExpr = TagExpr::parse("web and (xss or sqli) and not noisy").unwrap();
+        assert!(expr.matches(&tags(&["web", "sqli"])));
+        assert!(!expr.matches(&tags(&["web", "xss", "noisy"])));
+        assert!(!expr.matches(&tags(&["xss"])));
+
+        let list: TagExpr = TagExpr::parse("web,security").unwrap();
+        assert!(list.matches(&tags(&["security", "web", "api"])));
+        assert!(!list.matches(&tags(&["web"])));
+
+        // `and` binds tighter than `or`.
+        let mixed: TagExpr = TagExpr::parse("a or b and c").unwrap();
+        assert!(mixed.matches(&tags(&["a"])));
+        assert!(!mixed.matches(&tags(&["b"])));
+
+        assert!(TagExpr::parse("").is_err());
+        assert!(TagExpr::parse("web and").is_err());
+        assert!(TagExpr::parse("(web or xss").is_err());
+        assert!(TagExpr::parse("web xss").is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! The `verification` field of a pattern: the request that tells whether a
+//! match is a live credential, and the outcomes of sending it.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// How to check a pattern's matches against the service they belong to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct VerificationSpec {
+    /// HTTP method of the request.
+    #[serde(default = "default_method")]
+    pub method: String,
+    /// Request URL; `{{match}}` is replaced with the URL-encoded match.
+    pub url: String,
+    /// Request headers; `{{match}}` is replaced with the match.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Request body; `{{match}}` is replaced with the match.
+    pub body: Option<String>,
+    /// Statuses meaning the credential works.
+    #[serde(default = "default_success_status")]
+    pub success_status: Vec<u16>,
+    /// Text a successful response body must also contain.
+    pub success_body: Option<String>,
+    /// Statuses meaning the credential was rejected.
+    #[serde(default = "default_failure_status")]
+    pub failure_status: Vec<u16>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+fn default_success_status() -> Vec<u16> {
+    vec![200]
+}
+
+fn default_failure_status() -> Vec<u16> {
+    vec![401, 403]
+}
+
+/// Outcome of checking a finding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verification {
+    /// The service accepted the credential.
+    Verified,
+    /// The service rejected the credential.
+    Unverified,
+    /// The check failed or its response was inconclusive.
+    Unknown,
+}
+
+impl std::fmt::Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Verification::Verified => "verified",
+            Verification::Unverified => "unverified",
+            Verification::Unknown => "unknown",
+        })
+    }
+}
@@ -0,0 +1,148 @@
+//! Windows path forms: verbatim (`\\?\`) long paths and UNC network shares.
+//!
+//! The string helpers work on any platform so they can be tested everywhere;
+//! callers only apply them on Windows.
+
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+const VERBATIM_PREFIX: &str = r"\\?\";
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+/// Set on files and directories that are symlinks, junctions, or placeholders
+/// (e.g. cloud or HSM files that are only fetched when read).
+#[cfg(windows)]
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+/// Whether `path` names a UNC share, in plain (`\\server\share`) or verbatim form.
+#[must_use]
+pub fn is_unc(path: &str) -> bool {
+    path.starts_with(VERBATIM_UNC_PREFIX)
+        || (path.starts_with(r"\\")
+            && !path.starts_with(VERBATIM_PREFIX)
+            && !path.starts_with(r"\\.\"))
+}
+
+/// Rewrites a verbatim path back into the form users type, for output.
+/// `\\?\C:\src` becomes `C:\src` and `\\?\UNC\server\share` becomes `\\server\share`.
+#[must_use]
+pub fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    if let Some(rest) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+        Cow::Owned(format!(r"\\{rest}"))
+    } else if let Some(rest) = path
+        .strip_prefix(VERBATIM_PREFIX)
+        .filter(|rest: &&str| has_drive_prefix(rest))
+    {
+        Cow::Borrowed(rest)
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Rewrites an absolute path into verbatim form, which lifts the 260 character
+/// `MAX_PATH` limit for everything that opens files below it. Verbatim paths skip
+/// normalization, so separators are made uniform here. Returns `None` for
+/// relative or device paths.
+#[cfg_attr(not(windows), allow(dead_code))] // Only called on Windows, tested everywhere.
+#[must_use]
+pub fn to_verbatim(path: &str) -> Option<String> {
+    if path.starts_with(VERBATIM_PREFIX) {
+        return Some(path.to_string());
+    }
+    let path: String = path.replace('/', r"\");
+    if is_unc(&path) {
+        Some(format!("{VERBATIM_UNC_PREFIX}{}", &path[2..]))
+    } else if has_drive_prefix(&path) && path[2..].starts_with('\\') {
+        Some(format!("{VERBATIM_PREFIX}{path}"))
+    } else {
+        None
+    }
+}
+
+fn has_drive_prefix(path: &str) -> bool {
+    let bytes: &[u8] = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Rewrites an absolute search root (`C:\src`, `\\server\share`) into verbatim form,
+/// so every path the walker derives from it is usable past `MAX_PATH`.
+/// Relative roots are left alone to keep reported paths relative.
+#[cfg(windows)]
+pub fn search_root(path: &Path) -> PathBuf {
+    if !path.is_absolute() {
+        return path.to_path_buf();
+    }
+    // `absolute` resolves `.` and `..`, which verbatim paths would take literally.
+    std::path::absolute(path)
+        .ok()
+        .and_then(|abs: PathBuf| to_verbatim(abs.to_str()?))
+        .map_or_else(|| path.to_path_buf(), PathBuf::from)
+}
+
+#[cfg(not(windows))]
+#[must_use]
+pub fn search_root(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Formats a path for output, hiding the verbatim prefix added by `search_root`.
+#[must_use]
+pub fn display(path: &Path) -> String {
+    let shown: String = path.display().to_string();
+    if cfg!(windows) {
+        strip_verbatim(&shown).into_owned()
+    } else {
+        shown
+    }
+}
+
+/// Whether the walker entry is a reparse point: a symlink, junction, or a
+/// placeholder whose contents may be recalled from remote storage when read.
+/// Always false outside Windows.
+#[cfg(windows)]
+pub fn is_reparse_point(entry: &ignore::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    entry
+        .metadata()
+        .is_ok_and(|m: std::fs::Metadata| m.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0)
+}
+
+#[cfg(not(windows))]
+#[must_use]
+pub fn is_reparse_point(_entry: &ignore::DirEntry) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbatim_round_trip() {
+        assert_eq!(
+            to_verbatim(r"C:\src\app").as_deref(),
+            Some(r"\\?\C:\src\app")
+        );
+        assert_eq!(
+            to_verbatim("C:/src/app").as_deref(),
+            Some(r"\\?\C:\src\app")
+        );
+        assert_eq!(
+            to_verbatim(r"\\fileserver\share\dept").as_deref(),
+            Some(r"\\?\UNC\fileserver\share\dept")
+        );
+        assert_eq!(to_verbatim(r"src\app"), None);
+        assert_eq!(to_verbatim(r"C:relative"), None);
+        assert_eq!(to_verbatim(r"\\.\pipe\x"), None);
+
+        assert_eq!(strip_verbatim(r"\\?\C:\src\app"), r"C:\src\app");
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\fileserver\share\dept"),
+            r"\\fileserver\share\dept"
+        );
+        assert_eq!(strip_verbatim(r"\\?\Volume{1234}\x"), r"\\?\Volume{1234}\x");
+
+        assert!(is_unc(r"\\fileserver\share"));
+        assert!(is_unc(r"\\?\UNC\fileserver\share"));
+        assert!(!is_unc(r"\\?\C:\src"));
+    }
+}
@@ -0,0 +1,119 @@
+//! `gfr alias`: short names for installed patterns, so `gfr search keys` can mean
+//! a longer pattern name without renaming its file.
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+
+use crate::{get_pattern_dir, list_pattern_names, resolve_pattern_name, Styles};
+pub(crate) use gfr_core::load::load_aliases;
+use gfr_core::load::{Aliases, ALIASES_FILE};
+
+#[derive(Args, Debug)]
+pub(crate) struct AliasArgs {
+    /// The alias to define or remove. Lists all aliases when omitted.
+    alias: Option<String>,
+    /// The installed pattern the alias stands for.
+    pattern: Option<String>,
+    /// Remove the alias instead of defining it.
+    #[arg(long, requires = "alias", conflicts_with = "pattern")]
+    remove: bool,
+}
+
+fn save_aliases(aliases: &Aliases) -> Result<()> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    fs::create_dir_all(&pattern_dir)?;
+    let file: File = File::create(pattern_dir.join(ALIASES_FILE))?;
+    serde_json::to_writer_pretty(file, aliases)?;
+    Ok(())
+}
+
+/// Runs `gfr alias`.
+pub(crate) fn run_alias(args: &AliasArgs, styles: &Styles) -> Result<()> {
+    let mut aliases: Aliases = load_aliases()?;
+    let Some(alias) = &args.alias else {
+        if aliases.is_empty() {
+            println!("  {}", "No aliases defined.".style(styles.dim));
+        }
+        for (alias, pattern) in &aliases {
+            println!("  {} -> {}", alias.style(styles.highlight), pattern);
+        }
+        return Ok(());
+    };
+
+    if args.remove {
+        if aliases.remove(alias).is_none() {
+            return Err(anyhow!("No alias named '{alias}'."));
+        }
+        save_aliases(&aliases)?;
+        println!(
+            "{} Removed alias '{}'",
+            "✓".style(styles.success),
+            alias.style(styles.highlight)
+        );
+        return Ok(());
+    }
+
+    let pattern: &str = args.pattern.as_deref().ok_or_else(|| {
+        anyhow!("Give the pattern '{alias}' should stand for, or --remove to drop it.")
+    })?;
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    // Aliases point at full names, so installing a namespace later can't make them ambiguous.
+    let pattern: String =
+        resolve_pattern_name(&pattern_dir, pattern)?.unwrap_or_else(|| pattern.to_string());
+    let installed: Vec<String> = if pattern_dir.exists() {
+        list_pattern_names(&pattern_dir)?
+    } else {
+        Vec::new()
+    };
+    check_alias(alias, &pattern, &installed)?;
+    aliases.insert(alias.clone(), pattern.clone());
+    save_aliases(&aliases)?;
+    println!(
+        "{} '{}' now stands for '{}'",
+        "✓".style(styles.success),
+        alias.style(styles.highlight),
+        pattern.style(styles.highlight)
+    );
+    Ok(())
+}
+
+/// An alias has to point at an installed pattern and must not hide one, by its
+/// full or short name.
+fn check_alias(alias: &str, pattern: &str, installed: &[String]) -> Result<()> {
+    let hides: bool = installed.iter().any(|name: &String| {
+        name == alias
+            || name
+                .rsplit_once('/')
+                .is_some_and(|(_, short): (&str, &str)| short == alias)
+    });
+    if hides {
+        return Err(anyhow!(
+            "'{alias}' is already the name of an installed pattern."
+        ));
+    }
+    if !installed.iter().any(|name: &String| name == pattern) {
+        return Err(anyhow!("Pattern '{pattern}' is not installed."));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_alias() {
+        let installed: Vec<String> = vec![
+            "cloud-credentials-v2".to_string(),
+            "Kr1shna4garwal/jwt".to_string(),
+        ];
+        assert!(check_alias("keys", "cloud-credentials-v2", &installed).is_ok());
+        assert!(check_alias("keys", "Kr1shna4garwal/jwt", &installed).is_ok());
+        assert!(check_alias("jwt", "cloud-credentials-v2", &installed).is_err());
+        assert!(check_alias("keys", "keys", &installed).is_err());
+    }
+}
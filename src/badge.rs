@@ -0,0 +1,169 @@
+//! Status badges for `gfr search --badge`, so CI can publish the latest scan result.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::Severity;
+
+/// Approximate width of one character in the badge font, in pixels.
+const CHAR_WIDTH: usize = 7;
+/// Horizontal padding around each half of the badge.
+const PADDING: usize = 10;
+
+/// A two-part badge: a fixed label and a message summarizing the scan.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Badge {
+    label: String,
+    message: String,
+    /// Shields.io color name and the matching SVG fill.
+    color: (&'static str, &'static str),
+}
+
+impl Badge {
+    /// Summarizes per-pattern match counts. When the matching patterns declare
+    /// severities, the message counts findings per severity, worst first;
+    /// otherwise it names the patterns.
+    pub(crate) fn from_counts(
+        counts: &HashMap<String, usize>,
+        severities: &HashMap<String, Severity>,
+    ) -> Self {
+        let mut hits: Vec<(&str, usize)> = counts
+            .iter()
+            .filter(|(_, n): &(&String, &usize)| **n > 0)
+            .map(|(name, n): (&String, &usize)| (name.as_str(), *n))
+            .collect();
+        let total: usize = hits.iter().map(|(_, n): &(&str, usize)| n).sum();
+        hits.sort_by(|a: &(&str, usize), b: &(&str, usize)| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let rated: bool = hits
+            .iter()
+            .any(|(name, _): &(&str, usize)| severities.contains_key(*name));
+        let worst: Option<Severity> = hits
+            .iter()
+            .filter_map(|(name, _): &(&str, usize)| severities.get(*name).copied())
+            .max();
+
+        let message: String = if rated {
+            let mut per_severity: HashMap<Severity, usize> = HashMap::new();
+            for (name, n) in &hits {
+                *per_severity
+                    .entry(severities.get(*name).copied().unwrap_or_default())
+                    .or_default() += n;
+            }
+            [
+                Severity::Critical,
+                Severity::High,
+                Severity::Medium,
+                Severity::Low,
+                Severity::Info,
+            ]
+            .into_iter()
+            .filter_map(|s: Severity| {
+                let n: usize = per_severity.get(&s).copied().unwrap_or_default();
+                // The two most serious levels are always shown, even at zero.
+                (n > 0 || s >= Severity::High).then(|| format!("{n} {s}"))
+            })
+            .collect::<Vec<String>>()
+            .join(" / ")
+        } else {
+            match hits.as_slice() {
+                [] => "clean".to_string(),
+                // A single unattributed count comes from a search that didn't label matches.
+                [("", n)] => format!("{n} findings"),
+                [(name, n)] => format!("{name}: {n}"),
+                _ => format!("{total} findings in {} patterns", hits.len()),
+            }
+        };
+        let color: (&str, &str) = match (total, worst) {
+            (0, _) => ("brightgreen", "#4c1"),
+            (_, Some(Severity::Medium)) => ("orange", "#fe7d37"),
+            (_, Some(Severity::Low | Severity::Info)) => ("yellow", "#dfb317"),
+            _ => ("red", "#e05d44"),
+        };
+        Self {
+            label: "gfr".to_string(),
+            message,
+            color,
+        }
+    }
+
+    /// A shields.io endpoint document (<https://shields.io/badges/endpoint-badge>).
+    pub(crate) fn to_endpoint_json(&self) -> Value {
+        json!({
+            "schemaVersion": 1,
+            "label": self.label,
+            "message": self.message,
+            "color": self.color.0,
+        })
+    }
+
+    /// A self-contained SVG in the flat shields.io style.
+    pub(crate) fn to_svg(&self) -> String {
+        let label_width: usize = self.label.chars().count() * CHAR_WIDTH + PADDING;
+        let message_width: usize = self.message.chars().count() * CHAR_WIDTH + PADDING;
+        let width: usize = label_width + message_width;
+        let color: &str = self.color.1;
+        let label: String = escape_xml(&self.label);
+        let message: String = escape_xml(&self.message);
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}"><title>{label}: {message}</title><linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient><clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g><g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{}" y="14">{label}</text><text x="{}" y="14">{message}</text></g></svg>
+"##,
+            label_width / 2,
+            label_width + message_width / 2,
+        )
+    }
+}
+
+/// Writes `badge` to `path`: endpoint JSON for `.json` files, SVG otherwise.
+pub(crate) fn write(path: &Path, badge: &Badge) -> Result<()> {
+    let contents: String = if path
+        .extension()
+        .is_some_and(|e: &std::ffi::OsStr| e.eq_ignore_ascii_case("json"))
+    {
+        serde_json::to_string_pretty(&badge.to_endpoint_json())?
+    } else {
+        badge.to_svg()
+    };
+    fs::write(path, contents)
+        .with_context(|| format!("Failed to write badge to {}", path.display()))
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_badge_message_from_counts() {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut severities: HashMap<String, Severity> = HashMap::new();
+        assert_eq!(Badge::from_counts(&counts, &severities).message, "clean");
+
+        counts.insert("aws-keys".to_string(), 3);
+        counts.insert("jwt".to_string(), 0);
+        let badge: Badge = Badge::from_counts(&counts, &severities);
+        assert_eq!(badge.message, "aws-keys: 3");
+        assert_eq!(badge.to_endpoint_json()["color"], "red");
+
+        counts.insert("xss".to_string(), 2);
+        assert_eq!(
+            Badge::from_counts(&counts, &severities).message,
+            "5 findings in 2 patterns"
+        );
+
+        severities.insert("xss".to_string(), Severity::Medium);
+        let badge: Badge = Badge::from_counts(&counts, &severities);
+        assert_eq!(badge.message, "0 critical / 0 high / 2 medium / 3 info");
+        assert_eq!(badge.to_endpoint_json()["color"], "orange");
+    }
+}
@@ -0,0 +1,81 @@
+//! Baselines of accepted findings, so scans of legacy code only report new ones.
+//!
+//! A baseline stores finding fingerprints, which ignore line numbers, so existing
+//! findings stay recognized when the code around them moves.
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// The on-disk baseline written by `--write-baseline` and read by `--baseline`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Baseline {
+    fingerprints: BTreeSet<String>,
+}
+
+impl Baseline {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let file: File = File::open(path)
+            .with_context(|| format!("Failed to open baseline {}", path.display()))?;
+        serde_json::from_reader(file)
+            .with_context(|| format!("Failed to parse baseline {}", path.display()))
+    }
+
+    /// Whether the finding was already known when the baseline was written.
+    pub(crate) fn contains(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+}
+
+/// Collects fingerprints from all walker threads for `--write-baseline`.
+#[derive(Debug, Default)]
+pub(crate) struct BaselineWriter {
+    fingerprints: Mutex<BTreeSet<String>>,
+}
+
+impl BaselineWriter {
+    pub(crate) fn record(&self, fingerprint: &str) {
+        if let Ok(mut fingerprints) = self.fingerprints.lock() {
+            fingerprints.insert(fingerprint.to_string());
+        }
+    }
+
+    /// Writes everything recorded to `path`, returning how many findings it holds.
+    pub(crate) fn save(self, path: &Path) -> Result<usize> {
+        let baseline: Baseline = Baseline {
+            fingerprints: self
+                .fingerprints
+                .into_inner()
+                .map_err(|e| anyhow!(e.to_string()))?,
+        };
+        let file: File = File::create(path)
+            .with_context(|| format!("Failed to create baseline {}", path.display()))?;
+        serde_json::to_writer_pretty(file, &baseline)?;
+        Ok(baseline.fingerprints.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_written_baseline_round_trips() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let path: std::path::PathBuf = dir.path().join("baseline.json");
+
+        let writer: BaselineWriter = BaselineWriter::default();
+        writer.record("aws-keys:0123456789abcdef");
+        writer.record("aws-keys:0123456789abcdef");
+        writer.record("jwt:fedcba9876543210");
+        assert_eq!(writer.save(&path).unwrap(), 2);
+
+        let baseline: Baseline = Baseline::load(&path).unwrap();
+        assert!(baseline.contains("jwt:fedcba9876543210"));
+        assert!(!baseline.contains("jwt:0000000000000000"));
+    }
+}
@@ -0,0 +1,100 @@
+//! Collections: files that name a curated set of patterns, like `owasp-top10.json`,
+//! shipped in an index next to the patterns they list. They are installed into
+//! their own directory and searched as a unit with `gfr search @name`.
+
+use std::fs::{self, File};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::get_pattern_dir;
+pub(crate) use gfr_core::load::COLLECTIONS_DIR;
+
+/// A collection file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Collection {
+    /// JSON Schema reference (optional, for validation support).
+    #[serde(rename = "$schema")]
+    #[allow(dead_code)] // Only accepted, so editors can validate collection files.
+    schema: Option<String>,
+    /// What the patterns have in common.
+    pub(crate) description: Option<String>,
+    /// Names of the member patterns.
+    #[serde(rename = "collection")]
+    pub(crate) patterns: Vec<String>,
+}
+
+/// Whether a downloaded file is a collection rather than a pattern.
+pub(crate) fn is_collection(json: &Value) -> bool {
+    json.get("collection").is_some()
+}
+
+fn collection_path(name: &str) -> Result<PathBuf> {
+    Ok(get_pattern_dir()?
+        .join(COLLECTIONS_DIR)
+        .join(format!("{name}.json")))
+}
+
+/// Loads an installed collection, or `None` if there is none by that name.
+pub(crate) fn load_collection(name: &str) -> Result<Option<Collection>> {
+    let path: PathBuf = collection_path(name)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let file: File = File::open(&path)?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse collection from: {}", path.display()))
+        .map(Some)
+}
+
+pub(crate) fn save_collection(name: &str, json: &Value) -> Result<()> {
+    let path: PathBuf = collection_path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let file: File = File::create(&path)?;
+    serde_json::to_writer_pretty(file, json)?;
+    Ok(())
+}
+
+/// Names of the installed collections, sorted.
+pub(crate) fn list_collections() -> Result<Vec<String>> {
+    let dir: PathBuf = get_pattern_dir()?.join(COLLECTIONS_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry: fs::DirEntry| entry.path())
+        .filter(|path: &PathBuf| {
+            path.extension()
+                .is_some_and(|e: &std::ffi::OsStr| e == "json")
+        })
+        .filter_map(|path: PathBuf| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_files() {
+        let json: Value = serde_json::from_str(
+            r#"{"description": "OWASP Top 10", "collection": ["xss", "sqli"]}"#,
+        )
+        .unwrap();
+        assert!(is_collection(&json));
+        let collection: Collection = serde_json::from_value(json).unwrap();
+        assert_eq!(collection.patterns, ["xss", "sqli"]);
+
+        let pattern: Value = serde_json::from_str(r#"{"pattern": "<script"}"#).unwrap();
+        assert!(!is_collection(&pattern));
+        assert!(serde_json::from_str::<Collection>(r#"{"collection": [], "tags": []}"#).is_err());
+    }
+}
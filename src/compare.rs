@@ -0,0 +1,206 @@
+//! `gfr compare`: diffs two sets of scan results by fingerprint, so recurring
+//! scans can report only what changed since the last one.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+use serde_json::{json, Value};
+
+use crate::Styles;
+
+#[derive(Args, Debug)]
+pub(crate) struct CompareArgs {
+    /// Earlier results: `gfr search --json` output (or a JSON array of findings) or a baseline file.
+    old: PathBuf,
+    /// Later results, in any of the same forms.
+    new: PathBuf,
+    /// Print each change as a JSON object, one per line, with a "change" field.
+    #[arg(long)]
+    json: bool,
+    /// List persisting findings too, not just how many there are.
+    #[arg(long)]
+    persisting: bool,
+    /// Fail if the later results have findings the earlier ones didn't.
+    #[arg(long)]
+    fail_on_new: bool,
+}
+
+/// Findings of one result file, keyed by fingerprint. Baselines only know the
+/// fingerprints, so their findings are `{"fingerprint": ...}` objects.
+type Results = BTreeMap<String, Value>;
+
+/// How the later results differ from the earlier ones.
+#[derive(Debug, Default)]
+struct Comparison {
+    new: Vec<Value>,
+    fixed: Vec<Value>,
+    persisting: Vec<Value>,
+}
+
+/// Runs `gfr compare`.
+pub(crate) fn run_compare(args: &CompareArgs, styles: &Styles) -> Result<()> {
+    let comparison: Comparison = compare(&load(&args.old)?, &load(&args.new)?);
+    let mut sections: Vec<(&str, &[Value])> =
+        vec![("new", &comparison.new), ("fixed", &comparison.fixed)];
+    if args.persisting {
+        sections.push(("persisting", &comparison.persisting));
+    }
+
+    if args.json {
+        for (change, findings) in sections {
+            for finding in findings {
+                let mut line: Value = finding.clone();
+                line["change"] = json!(change);
+                println!("{}", serde_json::to_string(&line)?);
+            }
+        }
+    } else {
+        for (change, findings) in sections {
+            let (marker, style): (&str, owo_colors::Style) = match change {
+                "new" => ("+", styles.error),
+                "fixed" => ("-", styles.success),
+                _ => ("=", styles.dim),
+            };
+            let title: String = format!("{}{}", change[..1].to_uppercase(), &change[1..]);
+            println!(
+                "{} ({}):",
+                title.style(styles.title),
+                findings.len().to_string().style(styles.highlight)
+            );
+            for finding in findings {
+                println!("  {} {}", marker.style(style), describe(finding));
+            }
+        }
+        if !args.persisting {
+            println!(
+                "{} {}",
+                "Persisting:".style(styles.title),
+                comparison
+                    .persisting
+                    .len()
+                    .to_string()
+                    .style(styles.highlight)
+            );
+        }
+    }
+
+    if args.fail_on_new && !comparison.new.is_empty() {
+        return Err(anyhow!(
+            "{} new findings since {}.",
+            comparison.new.len(),
+            args.old.display()
+        ));
+    }
+    Ok(())
+}
+
+fn compare(old: &Results, new: &Results) -> Comparison {
+    let mut comparison: Comparison = Comparison::default();
+    for (fingerprint, finding) in new {
+        if old.contains_key(fingerprint) {
+            comparison.persisting.push(finding.clone());
+        } else {
+            comparison.new.push(finding.clone());
+        }
+    }
+    comparison.fixed = old
+        .iter()
+        .filter(|(fingerprint, _): &(&String, &Value)| !new.contains_key(*fingerprint))
+        .map(|(_, finding): (&String, &Value)| finding.clone())
+        .collect();
+    comparison
+}
+
+fn load(path: &Path) -> Result<Results> {
+    let text: String = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read results from {}", path.display()))?;
+    parse(&text).with_context(|| format!("Failed to parse results from {}", path.display()))
+}
+
+/// Reads a baseline, a JSON array of findings, or JSON lines of findings.
+/// A finding seen more than once keeps its first occurrence.
+fn parse(text: &str) -> Result<Results> {
+    let values: Vec<Value> = match serde_json::from_str::<Value>(text) {
+        Ok(Value::Object(object)) if object.contains_key("runs") => {
+            return Err(anyhow!(
+                "SARIF reports carry no fingerprints; compare `--json` results or baselines."
+            ));
+        }
+        Ok(Value::Object(object)) if object.contains_key("fingerprints") => object["fingerprints"]
+            .as_array()
+            .ok_or_else(|| anyhow!("Expected 'fingerprints' to be a list."))?
+            .iter()
+            .map(|fingerprint: &Value| json!({ "fingerprint": fingerprint }))
+            .collect(),
+        Ok(Value::Array(findings)) => findings,
+        // Anything else is read as one finding per line, which a single finding also is.
+        _ => text
+            .lines()
+            .filter(|line: &&str| !line.trim().is_empty())
+            .map(|line: &str| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("Expected a `gfr search --json` finding, got: {line}"))
+            })
+            .collect::<Result<_>>()?,
+    };
+
+    let mut results: Results = Results::new();
+    for value in values {
+        let fingerprint: String = value
+            .get("fingerprint")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Finding without a fingerprint: {value}"))?
+            .to_string();
+        results.entry(fingerprint).or_insert(value);
+    }
+    Ok(results)
+}
+
+/// `fingerprint  path:line  match`, with whatever the finding has of the latter.
+fn describe(finding: &Value) -> String {
+    let mut parts: Vec<String> = vec![finding["fingerprint"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string()];
+    if let Some(path) = finding.get("path").and_then(Value::as_str) {
+        match finding.get("line_number").and_then(Value::as_u64) {
+            Some(line) => parts.push(format!("{path}:{line}")),
+            None => parts.push(path.to_string()),
+        }
+    }
+    if let Some(matched) = finding.get("matched").and_then(Value::as_str) {
+        parts.push(matched.to_string());
+    }
+    parts.join("  ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_results_and_baselines() {
+        let yesterday: Results = parse(
+            r#"{"fingerprint":"aws:1","path":"./a.env","line_number":2,"matched":"AKIA1"}
+{"fingerprint":"aws:2","path":"./b.env","line_number":7,"matched":"AKIA2"}
+"#,
+        )
+        .unwrap();
+        let today: Results = parse(r#"{"fingerprints": ["aws:2", "jwt:3"]}"#).unwrap();
+
+        let comparison: Comparison = compare(&yesterday, &today);
+        assert_eq!(comparison.new, [json!({"fingerprint": "jwt:3"})]);
+        assert_eq!(comparison.fixed[0]["fingerprint"], "aws:1");
+        assert_eq!(comparison.persisting.len(), 1);
+        assert_eq!(describe(&comparison.fixed[0]), "aws:1  ./a.env:2  AKIA1");
+
+        let array: Results = parse(r#"[{"fingerprint":"aws:1"},{"fingerprint":"aws:1"}]"#).unwrap();
+        assert_eq!(array.len(), 1);
+        assert!(parse(r#"{"runs": []}"#).is_err());
+        assert!(parse(r#"{"path": "./a.env"}"#).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! `gfr search --estimate`: sizes up a scan before running it, by walking the
+//! target with the same filters and timing a search of a small sample.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Instant;
+
+use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder};
+use owo_colors::OwoColorize;
+
+use crate::{Pattern, Styles};
+use gfr_core::engine::PatternMatcher;
+use gfr_core::filetype;
+
+/// How many files are searched to measure throughput.
+const SAMPLE_FILES: usize = 32;
+
+/// Patterns that search the same file types, and how much of the target that is.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FileGroup {
+    /// Sorted file types searched, those of languages included; `None` covers
+    /// every file.
+    file_types: Option<Vec<String>>,
+    /// Sorted file types never searched.
+    exclude_file_types: Option<Vec<String>>,
+    patterns: Vec<String>,
+    files: u64,
+    bytes: u64,
+}
+
+impl FileGroup {
+    fn covers(&self, path: &Path) -> bool {
+        self.file_types.as_ref().is_none_or(|types: &Vec<String>| {
+            types.iter().any(|t: &String| filetype::is_of_type(path, t))
+        }) && !self
+            .exclude_file_types
+            .iter()
+            .flatten()
+            .any(|t: &String| filetype::is_of_type(path, t))
+    }
+}
+
+/// File types of a pattern sorted and without duplicates, or `None` if there
+/// are none or, with `include_bin`, they aren't applied.
+fn normalize_types(types: Option<&Vec<String>>, include_bin: bool) -> Option<Vec<String>> {
+    types
+        .filter(|_: &&Vec<String>| !include_bin)
+        .map(|types: &Vec<String>| {
+            let mut types: Vec<String> = types.clone();
+            types.sort();
+            types.dedup();
+            types
+        })
+        .filter(|types: &Vec<String>| !types.is_empty())
+}
+
+/// Groups patterns by the file types they search. With `include_bin`, file types
+/// aren't applied, so every pattern covers every file.
+fn group_patterns(patterns: &[Pattern], include_bin: bool) -> Vec<FileGroup> {
+    let mut groups: Vec<FileGroup> = Vec::new();
+    for p in patterns {
+        let file_types: Option<Vec<String>> =
+            normalize_types(Some(&p.included_types()), include_bin);
+        let exclude_file_types: Option<Vec<String>> =
+            normalize_types(p.exclude_file_types.as_ref(), include_bin);
+        match groups.iter_mut().find(|g: &&mut FileGroup| {
+            g.file_types == file_types && g.exclude_file_types == exclude_file_types
+        }) {
+            Some(group) => group.patterns.push(p.name.clone()),
+            None => groups.push(FileGroup {
+                file_types,
+                exclude_file_types,
+                patterns: vec![p.name.clone()],
+                ..FileGroup::default()
+            }),
+        }
+    }
+    groups
+}
+
+/// Walks the target and prints what a search of it would cover and roughly cost.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)] // An estimate needs no exact byte counts.
+pub(crate) fn run(
+    walk: ignore::Walk,
+    patterns: &[Pattern],
+    matcher: &PatternMatcher,
+    include_bin: bool,
+    threads: usize,
+    styles: &Styles,
+) {
+    let mut groups: Vec<FileGroup> = group_patterns(patterns, include_bin);
+    let mut files: Vec<(PathBuf, u64)> = Vec::new();
+    // Walk errors are reported by the search itself; here they only shrink the estimate.
+    for entry in walk.flatten() {
+        if !entry
+            .file_type()
+            .is_some_and(|ft: fs::FileType| ft.is_file())
+        {
+            continue;
+        }
+        let size: u64 = entry.metadata().map_or(0, |m: fs::Metadata| m.len());
+        for group in &mut groups {
+            if group.covers(entry.path()) {
+                group.files += 1;
+                group.bytes += size;
+            }
+        }
+        files.push((entry.into_path(), size));
+    }
+    let total_bytes: u64 = files.iter().map(|(_, size): &(PathBuf, u64)| size).sum();
+
+    println!(
+        "{} {} files ({})",
+        "Would scan".style(styles.dim),
+        files.len().to_string().style(styles.highlight),
+        format_bytes(total_bytes).style(styles.highlight)
+    );
+    for group in &groups {
+        let mut types: String = group
+            .file_types
+            .as_ref()
+            .map_or_else(|| "all files".to_string(), |t: &Vec<String>| t.join(", "));
+        if let Some(excluded) = &group.exclude_file_types {
+            types = format!("{types} except {}", excluded.join(", "));
+        }
+        println!(
+            "  {} {}: {} files ({})",
+            types.style(styles.info),
+            format!("[{}]", group.patterns.join(", ")).style(styles.dim),
+            group.files,
+            format_bytes(group.bytes)
+        );
+    }
+
+    let threads: usize = if threads == 0 {
+        thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get)
+    } else {
+        threads
+    };
+    match sample_throughput(&files, matcher, include_bin) {
+        Some((sampled, per_second)) => {
+            let seconds: f64 = total_bytes as f64 / per_second / threads as f64;
+            println!(
+                "{} {} files at {}/s per thread; about {} on {} {}.",
+                "Sampled".style(styles.dim),
+                sampled,
+                format_bytes(per_second as u64),
+                format_duration(seconds).style(styles.highlight),
+                threads,
+                if threads == 1 { "thread" } else { "threads" }
+            );
+        }
+        None => println!("{}", "Too little data to time a sample.".style(styles.dim)),
+    }
+}
+
+/// Searches an evenly spread sample of `files`, returning how many were searched
+/// and the bytes searched per second.
+fn sample_throughput(
+    files: &[(PathBuf, u64)],
+    matcher: &PatternMatcher,
+    include_bin: bool,
+) -> Option<(usize, f64)> {
+    let mut searcher: Searcher = SearcherBuilder::new()
+        .multi_line(true)
+        .binary_detection(if include_bin {
+            BinaryDetection::none()
+        } else {
+            BinaryDetection::quit(b'\x00')
+        })
+        .build();
+    let step: usize = (files.len() / SAMPLE_FILES).max(1);
+    let mut sampled: usize = 0;
+    let mut bytes: u64 = 0;
+    let start: Instant = Instant::now();
+    for (path, size) in files.iter().step_by(step).take(SAMPLE_FILES) {
+        let sink = grep_searcher::sinks::Bytes(|_: u64, _: &[u8]| Ok(true));
+        if searcher.search_path(matcher, path, sink).is_ok() {
+            sampled += 1;
+            bytes += size;
+        }
+    }
+    let elapsed: f64 = start.elapsed().as_secs_f64();
+    #[allow(clippy::cast_precision_loss)] // An estimate needs no exact byte counts.
+    (bytes > 0 && elapsed > 0.0).then(|| (sampled, bytes as f64 / elapsed))
+}
+
+#[allow(clippy::cast_precision_loss)] // Only shown with one decimal.
+pub(crate) fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value: f64 = bytes as f64 / 1024.0;
+    let mut unit: usize = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Rounded, non-negative seconds.
+fn format_duration(seconds: f64) -> String {
+    if seconds < 1.0 {
+        return "under a second".to_string();
+    }
+    let seconds: u64 = seconds.round() as u64;
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_patterns_and_formatting() {
+        let pattern = |name: &str, types: Option<&[&str]>| Pattern {
+            name: name.to_string(),
+            file_types: types.map(|t: &[&str]| t.iter().map(ToString::to_string).collect()),
+            ..Pattern::default()
+        };
+        let patterns: Vec<Pattern> = vec![
+            pattern("xss", Some(&["ts", "js"])),
+            pattern("secrets", None),
+            pattern("dom-sinks", Some(&["js", "ts", "js"])),
+        ];
+
+        let groups: Vec<FileGroup> = group_patterns(&patterns, false);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].patterns, ["xss", "dom-sinks"]);
+        assert!(groups[0].covers(Path::new("src/app.ts")));
+        assert!(!groups[0].covers(Path::new("README.md")));
+        assert!(groups[1].covers(Path::new("README.md")));
+        assert_eq!(group_patterns(&patterns, true).len(), 1);
+
+        let minified: Pattern = Pattern {
+            exclude_file_types: Some(vec!["min.js".to_string()]),
+            ..pattern("keys", None)
+        };
+        let groups: Vec<FileGroup> = group_patterns(&[pattern("secrets", None), minified], false);
+        assert_eq!(groups.len(), 2);
+        assert!(groups[1].covers(Path::new("app.js")));
+        assert!(!groups[1].covers(Path::new("app.min.js")));
+
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(3 * 1024 * 1024 / 2), "1.5 MB");
+        assert_eq!(format_duration(0.2), "under a second");
+        assert_eq!(format_duration(200.0), "3m 20s");
+        assert_eq!(format_duration(7500.0), "2h 5m");
+    }
+}
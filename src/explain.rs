@@ -0,0 +1,380 @@
+//! `gfr explain`: breaks a pattern down into the pieces a search runs, so it is
+//! clear which entry of `patterns` matched something unexpected and what the
+//! regex engine can use to skip ahead.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+use regex_syntax::hir::literal::{Extractor, Literal};
+use regex_syntax::hir::Hir;
+
+use crate::{load_pattern, load_pattern_file, Pattern, Styles};
+use gfr_core::engine::{Engine, PatternMatcher};
+
+/// Literal prefixes shown per branch before the rest are summarized.
+const SHOWN_PREFIXES: usize = 8;
+
+/// Branches of a `strings` pattern shown before the rest are summarized.
+const SHOWN_STRINGS: usize = 20;
+
+#[derive(Args, Debug)]
+pub(crate) struct ExplainArgs {
+    /// Name of a local pattern, or path to a pattern file.
+    target: String,
+}
+
+/// One source entry of a pattern: its `pattern`, or an entry of `patterns` or
+/// `strings`.
+#[derive(Debug, PartialEq, Eq)]
+struct Branch {
+    /// The field it came from, e.g. `patterns[2]`.
+    location: String,
+    regex: String,
+    /// Literals every match starts with one of, or `None` if a match can start
+    /// with too many different characters to list.
+    prefixes: Option<Vec<String>>,
+    /// Shortest and longest match in bytes; no longest if it is unbounded.
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    /// Uses lookaround or backreferences, which only the `backtrack` engine runs, so
+    /// its prefixes and lengths aren't worked out.
+    opaque: bool,
+}
+
+impl Branch {
+    fn new(pattern: &Pattern, location: String, regex: &str) -> Result<Self> {
+        let parsed: Result<Hir, regex_syntax::Error> = regex_syntax::ParserBuilder::new()
+            .case_insensitive(pattern.ignore_case)
+            .dot_matches_new_line(pattern.multiline)
+            .unicode(pattern.unicode)
+            .utf8(false)
+            .build()
+            .parse(regex);
+        if parsed.is_err() && pattern.engine == Some(Engine::Backtrack) {
+            PatternMatcher::new(regex, Engine::Backtrack)
+                .with_context(|| format!("{location} does not compile"))?;
+            return Ok(Self {
+                location,
+                regex: regex.to_string(),
+                prefixes: None,
+                min_len: None,
+                max_len: None,
+                opaque: true,
+            });
+        }
+        let hir: Hir = parsed.with_context(|| format!("{location} does not compile"))?;
+        let prefixes: Option<Vec<String>> = Extractor::new()
+            .extract(&hir)
+            .literals()
+            .filter(|literals: &&[Literal]| {
+                literals.iter().all(|literal: &Literal| !literal.is_empty())
+            })
+            .map(|literals: &[Literal]| {
+                literals
+                    .iter()
+                    .map(|literal: &Literal| {
+                        String::from_utf8_lossy(literal.as_bytes())
+                            .escape_debug()
+                            .to_string()
+                    })
+                    .collect()
+            });
+        Ok(Self {
+            location,
+            regex: regex.to_string(),
+            prefixes,
+            min_len: hir.properties().minimum_len(),
+            max_len: hir.properties().maximum_len(),
+            opaque: false,
+        })
+    }
+
+    /// An entry of `strings`, which matches only itself.
+    fn literal(location: String, string: &str) -> Self {
+        Self {
+            location,
+            regex: regex_syntax::escape(string),
+            prefixes: Some(vec![string.escape_debug().to_string()]),
+            min_len: Some(string.len()),
+            max_len: Some(string.len()),
+            opaque: false,
+        }
+    }
+}
+
+/// The branches of `pattern`, in the order they are combined into one regex.
+fn branches(pattern: &Pattern) -> Result<Vec<Branch>> {
+    match (&pattern.regex, &pattern.regex_list, &pattern.strings) {
+        (Some(regex), _, _) => Ok(vec![Branch::new(pattern, "pattern".to_string(), regex)?]),
+        (None, Some(list), _) => list
+            .iter()
+            .enumerate()
+            .map(|(i, regex): (usize, &String)| {
+                Branch::new(pattern, format!("patterns[{i}]"), regex)
+            })
+            .collect(),
+        (None, None, Some(strings)) => Ok(strings
+            .iter()
+            .enumerate()
+            .map(|(i, string): (usize, &String)| Branch::literal(format!("strings[{i}]"), string))
+            .collect()),
+        (None, None, None) => Ok(Vec::new()),
+    }
+}
+
+/// Prints the breakdown of a pattern.
+pub(crate) fn run_explain(args: &ExplainArgs, styles: &Styles) -> Result<()> {
+    let pattern: Pattern = if Path::new(&args.target).is_file() {
+        load_pattern_file(Path::new(&args.target))?
+    } else {
+        load_pattern(&args.target)?
+    };
+    let combined: String = pattern.get_raw_pattern()?;
+
+    println!(
+        "{}",
+        format!("Pattern '{}' v{}", pattern.name, pattern.version).style(styles.title)
+    );
+    if let Some(description) = &pattern.description {
+        println!("{}", description.style(styles.dim));
+    }
+    print_flags(&pattern, styles);
+    print_scope(&pattern, styles);
+    println!(
+        "{} {}",
+        "Keywords:".style(styles.highlight),
+        pattern.gate().map_or_else(
+            || "none; every line is searched".to_string(),
+            |keywords: &[String]| format!("only lines with {}", quoted(keywords)),
+        )
+    );
+    let branches: Vec<Branch> = branches(&pattern)?;
+    if pattern.literals().is_some() {
+        println!(
+            "{} aho-corasick, for literal strings",
+            "Engine:".style(styles.highlight)
+        );
+        println!(
+            "{} one automaton of {} strings",
+            "Compiled as:".style(styles.highlight),
+            branches.len()
+        );
+    } else {
+        println!(
+            "{} {}",
+            "Engine:".style(styles.highlight),
+            pattern.engine.unwrap_or_default()
+        );
+        println!("{} {combined}", "Compiled as:".style(styles.highlight));
+    }
+
+    println!(
+        "\n{}",
+        format!(
+            "{} branch(es), any of which reports a match:",
+            branches.len()
+        )
+        .style(styles.title)
+    );
+    let shown: usize = if pattern.literals().is_some() {
+        SHOWN_STRINGS
+    } else {
+        usize::MAX
+    };
+    for (i, branch) in branches.iter().enumerate().take(shown) {
+        print_branch(i + 1, branch, styles);
+    }
+    if branches.len() > shown {
+        println!("  ... and {} more", branches.len() - shown);
+    }
+    Ok(())
+}
+
+/// Prints which files `pattern` searches.
+fn print_scope(pattern: &Pattern, styles: &Styles) {
+    println!(
+        "{} {}",
+        "File types:".style(styles.highlight),
+        pattern
+            .file_types
+            .as_ref()
+            .filter(|types: &&Vec<String>| !types.is_empty())
+            .map_or_else(
+                || if pattern.included_types().is_empty() {
+                    "all files".to_string()
+                } else {
+                    "those of its languages".to_string()
+                },
+                |types: &Vec<String>| format!("only .{}", types.join(", .")),
+            )
+    );
+    for (label, entries) in [
+        ("Languages:", &pattern.languages),
+        ("Never searches:", &pattern.exclude_file_types),
+        ("Only paths matching:", &pattern.paths),
+        ("Never paths matching:", &pattern.paths_exclude),
+    ] {
+        if let Some(entries) = entries.as_ref().filter(|e: &&Vec<String>| !e.is_empty()) {
+            println!("{} {}", label.style(styles.highlight), entries.join(", "));
+        }
+    }
+}
+
+/// Prints how the flags of `pattern` change what its regex matches.
+fn print_flags(pattern: &Pattern, styles: &Styles) {
+    println!(
+        "\n{} {}",
+        "Case:".style(styles.highlight),
+        if pattern.ignore_case {
+            "insensitive (ignore_case)"
+        } else {
+            "sensitive"
+        }
+    );
+    println!(
+        "{} {}",
+        "Dot:".style(styles.highlight),
+        if pattern.multiline {
+            "also matches line breaks, so matches may span lines (multiline)"
+        } else {
+            "matches anything but a line break"
+        }
+    );
+    println!(
+        "{} {}",
+        "Unicode:".style(styles.highlight),
+        if pattern.unicode {
+            "\\w, \\d, \\b and case folding cover all of Unicode"
+        } else {
+            "\\w, \\d, \\b and case folding are ASCII-only (unicode: false)"
+        }
+    );
+    println!(
+        "{} {}",
+        "Words:".style(styles.highlight),
+        if pattern.word {
+            "whole words only (word)"
+        } else {
+            "matches anywhere, also inside words"
+        }
+    );
+}
+
+/// Prints the `number`th branch with what is known about its matches.
+fn print_branch(number: usize, branch: &Branch, styles: &Styles) {
+    println!(
+        "  {}. {} {}",
+        number,
+        branch.location.style(styles.dim),
+        branch.regex.style(styles.highlight)
+    );
+    if branch.opaque {
+        println!("     uses lookaround or backreferences; prefixes and length not worked out");
+        return;
+    }
+    let prefixes: String = match &branch.prefixes {
+        None => "none; a match can start with many different characters".to_string(),
+        Some(prefixes) if prefixes.len() > SHOWN_PREFIXES => format!(
+            "{} and {} more",
+            quoted(&prefixes[..SHOWN_PREFIXES]),
+            prefixes.len() - SHOWN_PREFIXES
+        ),
+        Some(prefixes) => quoted(prefixes),
+    };
+    println!("     literal prefixes: {prefixes}");
+    let length: String = match (branch.min_len, branch.max_len) {
+        (Some(1), Some(1)) => "1 byte".to_string(),
+        (Some(min), Some(max)) if min == max => format!("{min} bytes"),
+        (Some(min), Some(max)) => format!("{min} to {max} bytes"),
+        (Some(min), None) => format!("{min} bytes or more"),
+        (None, _) => "never matches".to_string(),
+    };
+    println!("     match length: {length}");
+}
+
+fn quoted(prefixes: &[String]) -> String {
+    prefixes
+        .iter()
+        .map(|prefix: &String| format!("\"{prefix}\""))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_branches_of_pattern() {
+        let pattern: Pattern = Pattern {
+            regex: None,
+            regex_list: Some(vec![
+                r"AKIA[0-9A-Z]{16}".to_string(),
+                r"(?:ghp|gho)_\w{36,}".to_string(),
+                r"\w+@\w+".to_string(),
+            ]),
+            ..Pattern::default()
+        };
+        let found: Vec<Branch> = branches(&pattern).unwrap();
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].location, "patterns[0]");
+        assert_eq!(
+            found[0].prefixes.as_deref(),
+            Some(&["AKIA".to_string()][..])
+        );
+        assert_eq!((found[0].min_len, found[0].max_len), (Some(20), Some(20)));
+        assert_eq!(
+            found[1].prefixes.as_deref(),
+            Some(&["ghp_".to_string(), "gho_".to_string()][..])
+        );
+        assert_eq!(found[1].max_len, None);
+        assert_eq!(found[2].prefixes, None);
+
+        let insensitive: Pattern = Pattern {
+            regex: Some("ab".to_string()),
+            ignore_case: true,
+            ..Pattern::default()
+        };
+        assert_eq!(
+            branches(&insensitive).unwrap()[0]
+                .prefixes
+                .as_ref()
+                .unwrap()
+                .len(),
+            4
+        );
+
+        let broken: Pattern = Pattern {
+            regex: Some("(unclosed".to_string()),
+            ..Pattern::default()
+        };
+        assert!(branches(&broken).is_err());
+
+        let lookbehind: Pattern = Pattern {
+            regex: Some(r"(?<=key=)\w+".to_string()),
+            engine: Some(Engine::Backtrack),
+            ..Pattern::default()
+        };
+        assert!(branches(&lookbehind).unwrap()[0].opaque);
+        assert!(branches(&Pattern {
+            engine: None,
+            ..lookbehind
+        })
+        .is_err());
+
+        let hosts: Pattern = Pattern {
+            strings: Some(vec!["db.internal".to_string(), "a\tb".to_string()]),
+            ..Pattern::default()
+        };
+        let found: Vec<Branch> = branches(&hosts).unwrap();
+        assert_eq!(found[0].location, "strings[0]");
+        assert_eq!(found[0].regex, r"db\.internal");
+        assert_eq!((found[0].min_len, found[0].max_len), (Some(11), Some(11)));
+        assert_eq!(
+            found[1].prefixes.as_deref(),
+            Some(&[r"a\tb".to_string()][..])
+        );
+    }
+}
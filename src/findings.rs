@@ -0,0 +1,218 @@
+//! `gfr findings`: reviews the findings recorded with `gfr search --record` and
+//! marks them, so later scans suppress false positives and accepted risks.
+
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Subcommand;
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+
+use crate::store::{self, FindingFilter, FindingStatus, FindingsStore, Occurrence, StoredFinding};
+use crate::{Styles, STDIN_LABEL};
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum FindingsAction {
+    /// List recorded findings, most recently seen first.
+    List {
+        /// Only list findings with this status.
+        #[arg(long, value_enum)]
+        status: Option<FindingStatus>,
+        /// Only list findings of this pattern.
+        #[arg(long, value_name = "NAME")]
+        pattern: Option<String>,
+        /// Only list findings last seen in scans of this target (an absolute path).
+        #[arg(long, value_name = "PATH")]
+        target: Option<String>,
+        /// Print each finding as a JSON object, one per line.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Show a recorded finding and every scan that found it.
+    Show {
+        /// Fingerprint of the finding (e.g., "aws-keys:3f2a9c1b0d4e5f6a").
+        fingerprint: String,
+    },
+    /// Set the status of findings given by fingerprint, or piped in as `gfr search --json` output.
+    Mark {
+        /// The status to set; `open` clears an earlier mark.
+        status: FindingStatus,
+        /// Fingerprints of the findings.
+        fingerprints: Vec<String>,
+        /// Why the findings got this status.
+        #[arg(long)]
+        note: Option<String>,
+    },
+}
+
+/// The part of a `gfr search --json` line needed to mark a finding.
+#[derive(Debug, Deserialize)]
+struct PipedFinding {
+    fingerprint: String,
+}
+
+/// Runs a `gfr findings` subcommand against the database at `db`, or the default one.
+pub(crate) fn run_findings(
+    action: FindingsAction,
+    db: Option<&Path>,
+    styles: &Styles,
+) -> Result<()> {
+    let path: PathBuf = match db {
+        Some(path) => path.to_path_buf(),
+        None => store::default_path()?,
+    };
+    let mut store: FindingsStore = FindingsStore::open(&path)?;
+    match action {
+        FindingsAction::List {
+            status,
+            pattern,
+            target,
+            json,
+        } => {
+            let findings: Vec<StoredFinding> = store.list(&FindingFilter {
+                status,
+                pattern: pattern.as_deref(),
+                target: target.as_deref(),
+            })?;
+            if json {
+                for finding in &findings {
+                    println!("{}", serde_json::to_string(finding)?);
+                }
+            } else if findings.is_empty() {
+                println!("  {}", "No recorded findings match.".style(styles.dim));
+            } else {
+                for finding in &findings {
+                    print_summary(finding, styles);
+                }
+            }
+        }
+        FindingsAction::Show { fingerprint } => {
+            let (finding, occurrences) = store
+                .show(&fingerprint)?
+                .ok_or_else(|| anyhow!("No recorded finding has fingerprint '{fingerprint}'."))?;
+            print_details(&finding, &occurrences, styles);
+        }
+        FindingsAction::Mark {
+            status,
+            fingerprints,
+            note,
+        } => {
+            let fingerprints: Vec<String> = if fingerprints.is_empty() {
+                read_piped_fingerprints()?
+            } else {
+                fingerprints
+            };
+            let unknown: usize = store.mark(&fingerprints, status, note.as_deref())?;
+            println!(
+                "{} Marked {} findings as {}.",
+                "✓".style(styles.success),
+                fingerprints.len().to_string().style(styles.highlight),
+                status.style(styles.highlight)
+            );
+            if unknown > 0 {
+                println!(
+                    "  {}",
+                    format!("{unknown} of them haven't been recorded yet; the mark applies once they are.")
+                        .style(styles.dim)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints `fingerprint [status] path: match` with when it was last seen.
+fn print_summary(finding: &StoredFinding, styles: &Styles) {
+    let seen: String = if finding.present {
+        format!("last seen {}", finding.last_seen)
+    } else {
+        format!("gone since {}", finding.last_seen)
+    };
+    println!(
+        "  {} {} {}: {}  {}",
+        finding.fingerprint.style(styles.highlight),
+        format!("[{}]", finding.status).style(status_style(finding.status, styles)),
+        finding.path.as_deref().unwrap_or(STDIN_LABEL),
+        finding.matched,
+        seen.style(styles.dim)
+    );
+}
+
+fn print_details(finding: &StoredFinding, occurrences: &[Occurrence], styles: &Styles) {
+    println!("{}", finding.fingerprint.style(styles.title));
+    let pattern: String = match &finding.severity {
+        Some(severity) => format!("{} ({severity})", finding.pattern),
+        None => finding.pattern.clone(),
+    };
+    let status: String = match &finding.note {
+        Some(note) => format!("{} ({note})", finding.status),
+        None => finding.status.to_string(),
+    };
+    let presence: &str = if finding.present {
+        "still present"
+    } else {
+        "not found by the latest scan"
+    };
+    let rows: [(&str, String); 6] = [
+        ("pattern", pattern),
+        (
+            "path",
+            finding
+                .path
+                .clone()
+                .unwrap_or_else(|| STDIN_LABEL.to_string()),
+        ),
+        ("match", finding.matched.clone()),
+        ("status", status),
+        ("first seen", finding.first_seen.clone()),
+        (
+            "last seen",
+            format!("{} in {}, {presence}", finding.last_seen, finding.target),
+        ),
+    ];
+    for (label, value) in rows {
+        println!("  {:<11}{value}", label.style(styles.dim));
+    }
+    println!("{}", "Sightings:".style(styles.title));
+    for occurrence in occurrences {
+        let line_number: String = occurrence
+            .line_number
+            .map_or_else(String::new, |n: u64| format!(":{n}"));
+        println!(
+            "  {} {} {}{line_number}: {}",
+            format!("#{}", occurrence.scan_id).style(styles.info),
+            occurrence.scanned_at.style(styles.dim),
+            occurrence.target,
+            occurrence.line.trim()
+        );
+    }
+}
+
+fn status_style(status: FindingStatus, styles: &Styles) -> owo_colors::Style {
+    match status {
+        FindingStatus::Open => styles.error,
+        FindingStatus::Fixed => styles.success,
+        FindingStatus::FalsePositive | FindingStatus::Accepted => styles.dim,
+    }
+}
+
+/// Reads fingerprints from `gfr search --json` output on stdin.
+fn read_piped_fingerprints() -> Result<Vec<String>> {
+    if io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "Provide fingerprints or pipe `gfr search --json` output into `gfr findings mark`."
+        ));
+    }
+    let mut fingerprints: Vec<String> = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line: String = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let finding: PipedFinding = serde_json::from_str(&line)
+            .with_context(|| format!("Expected a `gfr search --json` finding, got: {line}"))?;
+        fingerprints.push(finding.fingerprint);
+    }
+    Ok(fingerprints)
+}
@@ -0,0 +1,133 @@
+//! `gfr search --follow`: searches data appended to a growing file, like
+//! `tail -F`, reopening it when it is rotated or truncated.
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+/// How often the file is checked for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Collects appended data and hands it out a whole line at a time, so a match is
+/// never split across two reads.
+#[derive(Debug, Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+    /// Offset in the file of the first pending byte.
+    offset: u64,
+}
+
+impl LineBuffer {
+    /// Starts collecting at `offset` in the file.
+    fn at(offset: u64) -> Self {
+        Self {
+            pending: Vec::new(),
+            offset,
+        }
+    }
+
+    /// Adds `data` and returns the complete lines now available, with the file
+    /// offset they start at.
+    fn push(&mut self, data: &[u8]) -> Option<(Vec<u8>, u64)> {
+        self.pending.extend_from_slice(data);
+        let end: usize = self.pending.iter().rposition(|b: &u8| *b == b'\n')? + 1;
+        let rest: Vec<u8> = self.pending.split_off(end);
+        let lines: Vec<u8> = std::mem::replace(&mut self.pending, rest);
+        let offset: u64 = self.offset;
+        self.offset += lines.len() as u64;
+        Some((lines, offset))
+    }
+
+    /// Takes a final line that never got its terminator.
+    fn flush(&mut self) -> Option<(Vec<u8>, u64)> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let lines: Vec<u8> = std::mem::take(&mut self.pending);
+        let offset: u64 = self.offset;
+        self.offset += lines.len() as u64;
+        Some((lines, offset))
+    }
+}
+
+/// Follows `path` until the process is stopped, starting at its current end and
+/// calling `scan` with each run of complete lines and the offset it starts at.
+pub(crate) fn follow(
+    path: &Path,
+    mut scan: impl FnMut(&[u8], u64) -> io::Result<()>,
+) -> Result<()> {
+    let open = || File::open(path).with_context(|| format!("Failed to follow {}", path.display()));
+    let mut file: File = open()?;
+    let mut position: u64 = file.seek(SeekFrom::End(0))?;
+    let mut buffer: LineBuffer = LineBuffer::at(position);
+    let mut chunk: Vec<u8> = vec![0; 64 * 1024];
+
+    loop {
+        let read: usize = file.read(&mut chunk)?;
+        if read > 0 {
+            position += read as u64;
+            if let Some((lines, offset)) = buffer.push(&chunk[..read]) {
+                scan(&lines, offset)?;
+            }
+            continue;
+        }
+
+        // Nothing new: see whether the file was truncated or replaced.
+        let current: Metadata = file.metadata()?;
+        let truncated: bool = current.len() < position;
+        // Until a rotated file's successor appears, keep reading the old one.
+        let rotated: bool =
+            fs::metadata(path).is_ok_and(|latest: Metadata| !same_file(&current, &latest));
+        if truncated || rotated {
+            if let Some((lines, offset)) = buffer.flush() {
+                scan(&lines, offset)?;
+            }
+            if rotated {
+                file = open()?;
+            } else {
+                file.seek(SeekFrom::Start(0))?;
+            }
+            position = 0;
+            buffer = LineBuffer::at(0);
+            continue;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    (a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+/// Without inode numbers, a file created at a different time is taken to be a new one.
+#[cfg(not(unix))]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+    a.created().ok() == b.created().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_hands_out_whole_lines() {
+        let mut buffer: LineBuffer = LineBuffer::at(100);
+        assert_eq!(buffer.push(b"GET /a"), None);
+        assert_eq!(
+            buffer.push(b" 200\nGET /b 4"),
+            Some((b"GET /a 200\n".to_vec(), 100))
+        );
+        assert_eq!(
+            buffer.push(b"04\nGET /c"),
+            Some((b"GET /b 404\n".to_vec(), 111))
+        );
+        assert_eq!(buffer.flush(), Some((b"GET /c".to_vec(), 122)));
+        assert_eq!(buffer.flush(), None);
+    }
+}
@@ -0,0 +1,505 @@
+//! Downloads for `gfr install`: an HTTP client that sends a registry's token only
+//! to the server that registry lives on, and `github:OWNER/REPO[/PATH][@REF]`
+//! locations, fetched through the GitHub contents API so private repositories work.
+//! Responses are cached on disk and revalidated with `If-None-Match` and
+//! `If-Modified-Since`, so unchanged files aren't downloaded again.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use reqwest::header::{
+    HeaderMap, ACCEPT, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use reqwest::{Certificate, Proxy, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::{sha256_hex, CONFIG_DIR};
+
+/// Environment variable holding the token for indexes without one in `config.json`.
+pub(crate) const TOKEN_ENV: &str = "GFR_TOKEN";
+
+const GITHUB_PREFIX: &str = "github:";
+const GITHUB_API_HOST: &str = "api.github.com";
+
+/// A file in a GitHub repository, written `github:OWNER/REPO[/PATH][@REF]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct GitHubLocation {
+    pub(crate) owner: String,
+    repo: String,
+    /// Path of the file in the repository; `index.json` when omitted.
+    path: String,
+    /// Branch, tag or commit; the default branch when omitted.
+    reference: Option<String>,
+}
+
+impl GitHubLocation {
+    pub(crate) fn parse(location: &str) -> Option<Self> {
+        let rest: &str = location.strip_prefix(GITHUB_PREFIX)?;
+        let (rest, reference): (&str, Option<String>) = match rest.rsplit_once('@') {
+            Some((rest, reference)) => (rest, Some(reference.to_string())),
+            None => (rest, None),
+        };
+        let mut parts = rest.trim_matches('/').splitn(3, '/');
+        let owner: &str = parts.next().filter(|s: &&str| !s.is_empty())?;
+        let repo: &str = parts.next().filter(|s: &&str| !s.is_empty())?;
+        let path: &str = parts.next().unwrap_or("index.json");
+        Some(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            path: path.to_string(),
+            reference,
+        })
+    }
+
+    /// The location of `relative`, a path in the same repository relative to this file.
+    pub(crate) fn join(&self, relative: &str) -> String {
+        let dir: &str = self
+            .path
+            .rsplit_once('/')
+            .map_or("", |(dir, _): (&str, &str)| dir);
+        let relative: &str = relative.trim_start_matches("./");
+        let path: String = if dir.is_empty() {
+            relative.to_string()
+        } else {
+            format!("{dir}/{relative}")
+        };
+        Self {
+            path,
+            ..self.clone()
+        }
+        .to_string()
+    }
+
+    fn api_url(&self) -> String {
+        let url: String = format!(
+            "https://{GITHUB_API_HOST}/repos/{}/{}/contents/{}",
+            self.owner, self.repo, self.path
+        );
+        match &self.reference {
+            Some(reference) => format!("{url}?ref={reference}"),
+            None => url,
+        }
+    }
+}
+
+impl std::fmt::Display for GitHubLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{GITHUB_PREFIX}{}/{}/{}",
+            self.owner, self.repo, self.path
+        )?;
+        if let Some(reference) = &self.reference {
+            write!(f, "@{reference}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `location` is a `github:` shorthand.
+pub(crate) fn is_github(location: &str) -> bool {
+    location.starts_with(GITHUB_PREFIX)
+}
+
+/// The scheme, host and port a request for `location` goes to.
+fn origin_of(location: &str) -> Option<String> {
+    if is_github(location) {
+        return Some(format!("https://{GITHUB_API_HOST}"));
+    }
+    let url: Url = Url::parse(location).ok()?;
+    // Origins without a host, like those of `file:` URLs, all serialize as "null".
+    url.host_str()
+        .is_some()
+        .then(|| url.origin().ascii_serialization())
+}
+
+/// How often a download is tried before giving up.
+const MAX_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; it doubles for each one after.
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// A failed request, and whether trying again may help.
+struct Failure {
+    error: anyhow::Error,
+    transient: bool,
+}
+
+impl Failure {
+    fn fatal(error: anyhow::Error) -> Self {
+        Self {
+            error,
+            transient: false,
+        }
+    }
+}
+
+/// Validators of a cached response.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl CacheEntry {
+    fn from_headers(url: &str, headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .map(ToString::to_string)
+        };
+        Self {
+            url: url.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+/// Downloaded files with their validators, one pair of files per URL.
+#[derive(Clone)]
+struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key: String = sha256_hex(url.as_bytes());
+        (
+            self.dir.join(format!("{key}.json")),
+            self.dir.join(format!("{key}.body")),
+        )
+    }
+
+    fn load(&self, url: &str) -> Option<(CacheEntry, Vec<u8>)> {
+        let (entry_path, body_path): (PathBuf, PathBuf) = self.paths(url);
+        let entry: CacheEntry = serde_json::from_slice(&fs::read(entry_path).ok()?).ok()?;
+        // A hash collision is next to impossible, but a stale key must never serve another URL.
+        if entry.url != url {
+            return None;
+        }
+        Some((entry, fs::read(body_path).ok()?))
+    }
+
+    fn store(&self, entry: &CacheEntry, body: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let (entry_path, body_path): (PathBuf, PathBuf) = self.paths(&entry.url);
+        fs::write(body_path, body)?;
+        fs::write(entry_path, serde_json::to_vec(entry)?)?;
+        Ok(())
+    }
+}
+
+/// Network settings shared by every command that downloads something.
+#[derive(Args, Debug, Clone, Default)]
+pub(crate) struct NetworkOptions {
+    /// Never use the network: install and update work from cached downloads only,
+    /// and anything else that needs the network fails right away.
+    #[arg(
+        long,
+        global = true,
+        env = "GFR_OFFLINE",
+        value_parser = clap::builder::FalseyValueParser::new()
+    )]
+    pub(crate) offline: bool,
+    /// Send all requests through this proxy. Without it, `HTTPS_PROXY`,
+    /// `HTTP_PROXY`, `ALL_PROXY` and `NO_PROXY` are honored.
+    #[arg(long, global = true, value_name = "URL")]
+    proxy: Option<String>,
+    /// Also trust the certificate authorities in this PEM file, e.g. a corporate
+    /// proxy's.
+    #[arg(long, global = true, value_name = "PEM")]
+    cacert: Option<PathBuf>,
+    /// Accept any TLS certificate, valid or not. Anyone on the network path can
+    /// then substitute the patterns you download.
+    #[arg(long, global = true)]
+    pub(crate) insecure: bool,
+}
+
+/// HTTP client for indexes and pattern files, with the tokens to send to each origin.
+#[derive(Clone)]
+pub(crate) struct HttpClient {
+    client: reqwest::Client,
+    /// Origins and the token to send them.
+    tokens: Vec<(String, String)>,
+    cache: Option<HttpCache>,
+    /// Serve everything from the cache and never touch the network.
+    offline: bool,
+}
+
+impl HttpClient {
+    pub(crate) fn new(network: &NetworkOptions) -> Result<Self> {
+        // The GitHub API turns away requests without a user agent.
+        let mut builder: reqwest::ClientBuilder = reqwest::Client::builder()
+            .user_agent(concat!("gfr/", env!("CARGO_PKG_VERSION")))
+            .danger_accept_invalid_certs(network.insecure);
+        if let Some(proxy) = &network.proxy {
+            builder = builder
+                .proxy(Proxy::all(proxy).with_context(|| format!("Invalid proxy '{proxy}'"))?);
+        }
+        if let Some(path) = &network.cacert {
+            let pem: Vec<u8> =
+                fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            // A bundle may hold several certificates; all of them are trusted.
+            let certificates: Vec<Certificate> = Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid certificate in {}", path.display()))?;
+            if certificates.is_empty() {
+                return Err(anyhow!("No PEM certificates in {}", path.display()));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        let client: reqwest::Client = builder.build()?;
+        let cache: Option<HttpCache> = dirs::cache_dir().map(|dir: PathBuf| HttpCache {
+            dir: dir.join(CONFIG_DIR).join("http"),
+        });
+        Ok(Self {
+            client,
+            tokens: Vec::new(),
+            cache,
+            offline: network.offline,
+        })
+    }
+
+    /// Sends `token` with requests to the origin `index` is served from.
+    pub(crate) fn with_token(mut self, index: &str, token: Option<&str>) -> Self {
+        if let (Some(origin), Some(token)) = (origin_of(index), token) {
+            self.tokens.push((origin, token.to_string()));
+        }
+        self
+    }
+
+    /// Downloads `location`, trying again with a growing delay when the network
+    /// or the server fails in a way that may pass.
+    pub(crate) async fn get(&self, location: &str) -> Result<Vec<u8>> {
+        let mut attempt: u32 = 1;
+        loop {
+            match self.get_once(location).await {
+                Ok(body) => return Ok(body),
+                Err(failure) if failure.transient && attempt < MAX_ATTEMPTS => {
+                    tokio::time::sleep(RETRY_DELAY * 2_u32.pow(attempt - 1)).await;
+                    attempt += 1;
+                }
+                Err(failure) if failure.transient => {
+                    return Err(failure
+                        .error
+                        .context(format!("Gave up after {attempt} attempts")))
+                }
+                Err(failure) => return Err(failure.error),
+            }
+        }
+    }
+
+    async fn get_once(&self, location: &str) -> Result<Vec<u8>, Failure> {
+        let (url, github): (String, bool) = match GitHubLocation::parse(location) {
+            Some(github) => (github.api_url(), true),
+            None if is_github(location) => {
+                return Err(Failure::fatal(anyhow!(
+                    "'{location}' is not a valid location, use github:OWNER/REPO[/PATH][@REF]"
+                )))
+            }
+            None => (location.to_string(), false),
+        };
+        if self.offline {
+            return self
+                .cache
+                .as_ref()
+                .and_then(|cache: &HttpCache| cache.load(&url))
+                .map(|(_, body): (CacheEntry, Vec<u8>)| body)
+                .ok_or_else(|| {
+                    Failure::fatal(anyhow!(
+                        "{location} hasn't been downloaded before, and --offline forbids fetching it."
+                    ))
+                });
+        }
+        let mut request: reqwest::RequestBuilder = self.client.get(&url);
+        if github {
+            request = request.header(ACCEPT, "application/vnd.github.raw");
+        }
+        let token: Option<&str> = origin_of(&url).and_then(|origin: String| {
+            self.tokens
+                .iter()
+                .find(|(o, _): &&(String, String)| *o == origin)
+                .map(|(_, token): &(String, String)| token.as_str())
+        });
+        if let Some(token) = token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let cached: Option<(CacheEntry, Vec<u8>)> = self
+            .cache
+            .as_ref()
+            .and_then(|cache: &HttpCache| cache.load(&url));
+        if let Some((entry, _)) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response: reqwest::Response = request.send().await.map_err(|e: reqwest::Error| {
+            let transient: bool = e.is_connect() || e.is_timeout();
+            Failure {
+                error: anyhow::Error::new(e).context(format!("Failed to fetch {location}")),
+                transient,
+            }
+        })?;
+        let status: StatusCode = response.status();
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some((_, body)) = cached {
+                return Ok(body);
+            }
+        }
+        let private: bool = matches!(
+            status,
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND
+        );
+        if private && token.is_none() {
+            return Err(Failure::fatal(anyhow!(
+                "Failed to fetch {location}: {status}. If it is private, set {TOKEN_ENV} or a token for its registry in config.json."
+            )));
+        }
+        let response: reqwest::Response =
+            response
+                .error_for_status()
+                .map_err(|e: reqwest::Error| Failure {
+                    error: anyhow::Error::new(e).context(format!("Failed to fetch {location}")),
+                    transient: status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+                })?;
+        let entry: CacheEntry = CacheEntry::from_headers(&url, response.headers());
+        let body: Vec<u8> = response
+            .bytes()
+            .await
+            .map_err(|e: reqwest::Error| Failure {
+                error: anyhow::Error::new(e).context(format!("Failed to fetch {location}")),
+                transient: true,
+            })?
+            .to_vec();
+        // Kept even without validators, for --offline.
+        if let Some(cache) = &self.cache {
+            // The download worked; a cache that can't be written only costs the next run.
+            let _ = cache.store(&entry, &body);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_github_locations() {
+        let index: GitHubLocation = GitHubLocation::parse("github:acme/patterns@v2").unwrap();
+        assert_eq!(index.owner, "acme");
+        assert_eq!(index.to_string(), "github:acme/patterns/index.json@v2");
+        assert_eq!(
+            index.api_url(),
+            "https://api.github.com/repos/acme/patterns/contents/index.json?ref=v2"
+        );
+        assert_eq!(
+            index.join("./web/xss.json"),
+            "github:acme/patterns/web/xss.json@v2"
+        );
+
+        let nested: GitHubLocation =
+            GitHubLocation::parse("github:acme/patterns/sets/index.json").unwrap();
+        assert_eq!(
+            nested.join("xss.json"),
+            "github:acme/patterns/sets/xss.json"
+        );
+        assert!(GitHubLocation::parse("github:acme").is_none());
+        assert!(GitHubLocation::parse("https://github.com/acme/patterns").is_none());
+
+        assert_eq!(
+            origin_of("github:acme/patterns").as_deref(),
+            Some("https://api.github.com")
+        );
+        assert_eq!(
+            origin_of("https://patterns.corp.example/index.json").as_deref(),
+            Some("https://patterns.corp.example")
+        );
+        assert_ne!(
+            origin_of("http://127.0.0.1:8080/index.json"),
+            origin_of("http://127.0.0.1:9090/xss.json")
+        );
+    }
+
+    #[test]
+    fn test_http_cache_round_trip() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let cache: HttpCache = HttpCache {
+            dir: dir.path().join("http"),
+        };
+        let url: &str = "https://example.com/index.json";
+        assert!(cache.load(url).is_none());
+        let entry: CacheEntry = CacheEntry {
+            url: url.to_string(),
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        cache.store(&entry, b"{}").unwrap();
+        assert_eq!(cache.load(url), Some((entry, b"{}".to_vec())));
+        assert!(cache.load("https://example.com/other.json").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_offline_serves_only_the_cache() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let cache: HttpCache = HttpCache {
+            dir: dir.path().to_path_buf(),
+        };
+        let cached: &str = "https://example.com/index.json";
+        cache
+            .store(
+                &CacheEntry {
+                    url: cached.to_string(),
+                    ..CacheEntry::default()
+                },
+                b"{}",
+            )
+            .unwrap();
+        let network: NetworkOptions = NetworkOptions {
+            offline: true,
+            ..NetworkOptions::default()
+        };
+        let client: HttpClient = HttpClient {
+            cache: Some(cache),
+            ..HttpClient::new(&network).unwrap()
+        };
+        assert_eq!(client.get(cached).await.unwrap(), b"{}");
+        assert!(client
+            .get("https://example.com/xss.json")
+            .await
+            .unwrap_err()
+            .to_string()
+            .contains("--offline"));
+    }
+
+    #[test]
+    fn test_client_network_options() {
+        assert!(HttpClient::new(&NetworkOptions::default()).is_ok());
+        let proxied: NetworkOptions = NetworkOptions {
+            proxy: Some("http://127.0.0.1:3128".to_string()),
+            insecure: true,
+            ..NetworkOptions::default()
+        };
+        assert!(HttpClient::new(&proxied).is_ok());
+
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let pem: PathBuf = dir.path().join("ca.pem");
+        let missing: NetworkOptions = NetworkOptions {
+            cacert: Some(pem.clone()),
+            ..NetworkOptions::default()
+        };
+        let err: String = format!("{:#}", HttpClient::new(&missing).err().unwrap());
+        assert!(err.contains("ca.pem"));
+        fs::write(&pem, "not a certificate").unwrap();
+        assert!(HttpClient::new(&missing).is_err());
+    }
+}
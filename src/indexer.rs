@@ -0,0 +1,242 @@
+//! `gfr index`: writes the `index.json` of a directory of pattern files, so a
+//! self-hosted pattern repository doesn't have to maintain it by hand. Every file
+//! is validated first; the index is only written when all of them pass.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+
+use crate::collections::{self, Collection};
+use crate::validate::{self, Issue};
+use crate::{default_version, sha256_hex, Bundles, Index, IndexPattern, Styles};
+use gfr_core::jsonc;
+
+#[derive(Args, Debug)]
+pub(crate) struct IndexArgs {
+    /// Directory of pattern and collection files, searched recursively.
+    #[arg(default_value = ".")]
+    dir: PathBuf,
+    /// URL the directory is served from. Without it, file URLs are relative to
+    /// the index, which works for indexes on disk, on a web server and on GitHub.
+    #[arg(long, value_name = "URL")]
+    base_url: Option<String>,
+    /// Namespace patterns from this index are installed into.
+    #[arg(long)]
+    namespace: Option<String>,
+    /// Where to write the index. Defaults to `index.json` in the directory.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+/// Paths of the `.json` files under `dir`, relative to it and sorted. Hidden
+/// directories, index files and JSON schemas (`*.schema.json`) are skipped.
+fn json_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut pending: Vec<PathBuf> = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let read = fs::read_dir(dir.join(&relative))
+            .with_context(|| format!("Failed to read {}", dir.join(&relative).display()))?;
+        for entry in read.filter_map(Result::ok) {
+            let name: String = entry.file_name().to_string_lossy().into_owned();
+            let path: PathBuf = relative.join(&name);
+            if entry.path().is_dir() {
+                if !name.starts_with('.') {
+                    pending.push(path);
+                }
+            } else if name != "index.json"
+                && !name.to_ascii_lowercase().ends_with(".schema.json")
+                && path
+                    .extension()
+                    .is_some_and(|e: &std::ffi::OsStr| e.eq_ignore_ascii_case("json"))
+            {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The URL of `relative` in the index: joined to `base_url`, or as is.
+pub(crate) fn file_url(base_url: Option<&str>, relative: &Path) -> String {
+    let relative: String = relative
+        .components()
+        .map(|c: std::path::Component<'_>| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    match base_url {
+        Some(base) => format!("{}/{relative}", base.trim_end_matches('/')),
+        None => relative,
+    }
+}
+
+/// The index entry of one file, or what is wrong with it.
+pub(crate) fn index_entry(
+    dir: &Path,
+    relative: &Path,
+    base_url: Option<&str>,
+) -> Result<std::result::Result<IndexPattern, Vec<String>>> {
+    let path: PathBuf = dir.join(relative);
+    let body: Vec<u8> =
+        fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let name: String = relative
+        .file_stem()
+        .map(|stem: &std::ffi::OsStr| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let source: String = String::from_utf8_lossy(&body).into_owned();
+    let json: Value = match serde_json::from_str(&jsonc::strip(&source)) {
+        Ok(json) => json,
+        Err(e) => return Ok(Err(vec![format!("not valid JSON: {e}")])),
+    };
+    let field = |key: &str| json.get(key).and_then(Value::as_str).map(str::to_string);
+
+    if collections::is_collection(&json) {
+        if let Err(e) = serde_json::from_value::<Collection>(json.clone()) {
+            return Ok(Err(vec![format!("invalid collection: {e}")]));
+        }
+    } else {
+        let issues: Vec<Issue> = validate::check_source(&source, None);
+        if !issues.is_empty() {
+            return Ok(Err(issues.iter().map(ToString::to_string).collect()));
+        }
+    }
+
+    let signature: PathBuf = path.with_extension("json.minisig");
+    Ok(Ok(IndexPattern {
+        url: file_url(base_url, relative),
+        version: field("version").unwrap_or_else(default_version),
+        license: field("license"),
+        sha256: Some(sha256_hex(&body)),
+        signature: signature
+            .is_file()
+            .then(|| file_url(base_url, &relative.with_extension("json.minisig"))),
+        deprecated: json
+            .get("deprecated")
+            .and_then(Value::as_bool)
+            .unwrap_or_default(),
+        superseded_by: field("superseded_by"),
+        name,
+    }))
+}
+
+/// Builds the index of `args.dir`, failing with every invalid file listed.
+fn build_index(args: &IndexArgs, styles: &Styles) -> Result<Index> {
+    let mut patterns: Vec<IndexPattern> = Vec::new();
+    let mut seen: HashMap<String, PathBuf> = HashMap::new();
+    let mut invalid: usize = 0;
+    for relative in json_files(&args.dir)? {
+        // An index written under another name is not a pattern either.
+        if args.output.as_deref() == Some(&args.dir.join(&relative)) {
+            continue;
+        }
+        match index_entry(&args.dir, &relative, args.base_url.as_deref())? {
+            Ok(entry) => {
+                if let Some(first) = seen.insert(entry.name.clone(), relative.clone()) {
+                    return Err(anyhow!(
+                        "{} and {} would both be installed as '{}'.",
+                        first.display(),
+                        relative.display(),
+                        entry.name
+                    ));
+                }
+                patterns.push(entry);
+            }
+            Err(issues) => {
+                invalid += 1;
+                println!("{} {}", "✗".style(styles.error), relative.display());
+                for issue in issues {
+                    println!("    {issue}");
+                }
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(anyhow!(
+            "{invalid} files are invalid; no index was written."
+        ));
+    }
+    Ok(Index {
+        namespace: args.namespace.clone(),
+        patterns,
+        bundles: Bundles::new(),
+    })
+}
+
+/// Runs `gfr index`.
+pub(crate) fn run_index(args: &IndexArgs, styles: &Styles) -> Result<()> {
+    let index: Index = build_index(args, styles)?;
+    let output: PathBuf = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.dir.join("index.json"));
+    let mut file: File =
+        File::create(&output).with_context(|| format!("Failed to create {}", output.display()))?;
+    serde_json::to_writer_pretty(&mut file, &index)?;
+    writeln!(file)?;
+    println!(
+        "{} Indexed {} files in {}",
+        "✓".style(styles.success),
+        index.patterns.len().to_string().style(styles.highlight),
+        output.display().to_string().style(styles.highlight)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_index() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("web")).unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(
+            dir.path().join("web/xss.json"),
+            r#"{"version": "1.2.0", "license": "MIT", "pattern": "<script"}"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join("web/xss.json.minisig"), "sig").unwrap();
+        fs::write(dir.path().join("top10.json"), r#"{"collection": ["xss"]}"#).unwrap();
+        fs::write(dir.path().join(".git/config.json"), "{").unwrap();
+        fs::write(dir.path().join("index.json"), "{}").unwrap();
+        fs::write(dir.path().join("pattern.schema.json"), "{}").unwrap();
+
+        let args = |base_url: Option<&str>| IndexArgs {
+            dir: dir.path().to_path_buf(),
+            base_url: base_url.map(ToString::to_string),
+            namespace: Some("team".to_string()),
+            output: None,
+        };
+        let index: Index =
+            build_index(&args(Some("https://example.com/p/")), &Styles::new()).unwrap();
+        assert_eq!(index.namespace.as_deref(), Some("team"));
+        let xss: &IndexPattern = &index.patterns[1];
+        assert_eq!(
+            (xss.name.as_str(), xss.version.as_str(), xss.url.as_str()),
+            ("xss", "1.2.0", "https://example.com/p/web/xss.json")
+        );
+        assert_eq!(xss.license.as_deref(), Some("MIT"));
+        assert_eq!(
+            xss.signature.as_deref(),
+            Some("https://example.com/p/web/xss.json.minisig")
+        );
+        assert_eq!(index.patterns[0].url, "https://example.com/p/top10.json");
+
+        let relative: Index = build_index(&args(None), &Styles::new()).unwrap();
+        assert_eq!(relative.patterns[1].url, "web/xss.json");
+
+        fs::write(
+            dir.path().join("web/bad.json"),
+            r#"{"pattern": "(unclosed"}"#,
+        )
+        .unwrap();
+        assert!(build_index(&args(None), &Styles::new()).is_err());
+    }
+}
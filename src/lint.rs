@@ -0,0 +1,543 @@
+//! `gfr lint`: flags pattern constructs that make searches slow, noisy or hard to
+//! maintain, for pattern authors and pattern repository CI. gfr's default regex
+//! engine runs in linear time, but its `backtrack` engine and other tools using
+//! the same pattern packs don't, and nested quantifiers can hang a scan.
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use clap::{Args, ValueEnum};
+use owo_colors::OwoColorize;
+use regex_syntax::ast::{self, Ast, RepetitionKind, RepetitionRange};
+
+use crate::{
+    get_pattern_dir, list_pattern_names, load_pattern_file, pattern_file, validate, Pattern, Styles,
+};
+use gfr_core::engine::{Engine, PatternMatcher};
+
+#[derive(Args, Debug)]
+pub(crate) struct LintArgs {
+    /// Names of local patterns, or paths to pattern files.
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    targets: Vec<String>,
+    /// Lint every local pattern.
+    #[arg(long)]
+    all: bool,
+    /// Also fail on findings of this level. Errors always fail.
+    #[arg(long, value_enum, value_name = "LEVEL")]
+    deny: Option<Deny>,
+}
+
+/// Finding levels `--deny` can make fatal.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Deny {
+    /// Warnings and errors.
+    Warnings,
+    /// Notes, warnings and errors.
+    Notes,
+}
+
+impl Deny {
+    fn denies(self, level: Level) -> bool {
+        match self {
+            Self::Warnings => level >= Level::Warning,
+            Self::Notes => true,
+        }
+    }
+}
+
+/// How much a lint finding matters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Level {
+    Note,
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Level::Note => "note",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        })
+    }
+}
+
+/// A single problem found in a pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lint {
+    level: Level,
+    /// Short name of the check that found it, e.g. `nested-quantifier`.
+    rule: &'static str,
+    /// The field the problem is in, e.g. `pattern` or `patterns[2]`.
+    location: String,
+    message: String,
+}
+
+impl Lint {
+    fn new(
+        level: Level,
+        rule: &'static str,
+        location: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            level,
+            rule,
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Lints a pattern. Each regex of `patterns` is checked on its own.
+pub(crate) fn lint_pattern(pattern: &Pattern) -> Vec<Lint> {
+    let mut lints: Vec<Lint> = Vec::new();
+    if pattern
+        .description
+        .as_deref()
+        .is_none_or(|d: &str| d.trim().is_empty())
+    {
+        lints.push(Lint::new(
+            Level::Warning,
+            "missing-description",
+            "description",
+            "say what the pattern finds",
+        ));
+    }
+    if pattern.tags.as_ref().is_none_or(Vec::is_empty) {
+        lints.push(Lint::new(
+            Level::Note,
+            "missing-tags",
+            "tags",
+            "add tags so the pattern can be selected with --tags",
+        ));
+    }
+
+    let regexes: Vec<(String, &str)> = match (&pattern.regex, &pattern.regex_list) {
+        (Some(regex), _) => vec![("pattern".to_string(), regex.as_str())],
+        (None, Some(list)) => list
+            .iter()
+            .enumerate()
+            .map(|(i, regex): (usize, &String)| (format!("patterns[{i}]"), regex.as_str()))
+            .collect(),
+        (None, None) => Vec::new(),
+    };
+    for (i, (location, regex)) in regexes.iter().enumerate() {
+        if let Some((first, _)) = regexes[..i]
+            .iter()
+            .find(|(_, earlier): &&(String, &str)| earlier == regex)
+        {
+            lints.push(Lint::new(
+                Level::Warning,
+                "duplicate-branch",
+                location.clone(),
+                format!("same as {first}"),
+            ));
+            continue;
+        }
+        lint_regex(pattern, location, regex, &mut lints);
+    }
+    let strings: &[String] = pattern.strings.as_deref().unwrap_or_default();
+    for (i, string) in strings.iter().enumerate() {
+        let same = |earlier: &String| {
+            if pattern.ignore_case {
+                earlier.eq_ignore_ascii_case(string)
+            } else {
+                earlier == string
+            }
+        };
+        if let Some(first) = strings[..i].iter().position(same) {
+            lints.push(Lint::new(
+                Level::Warning,
+                "duplicate-branch",
+                format!("strings[{i}]"),
+                format!("same as strings[{first}]"),
+            ));
+        }
+    }
+    lints
+}
+
+/// Checks one regex of a pattern.
+fn lint_regex(pattern: &Pattern, location: &str, regex: &str, lints: &mut Vec<Lint>) {
+    let backtrack: bool = pattern.engine == Some(Engine::Backtrack);
+    if backtrack {
+        if let Err(e) = PatternMatcher::new(regex, Engine::Backtrack) {
+            lints.push(Lint::new(
+                Level::Error,
+                "invalid-regex",
+                location,
+                e.to_string(),
+            ));
+            return;
+        }
+    }
+    let tree: Ast = match ast::parse::Parser::new().parse(regex) {
+        Ok(tree) => tree,
+        // Lookaround and backreferences are beyond the checks below.
+        Err(_) if backtrack => return,
+        Err(e) => {
+            lints.push(Lint::new(
+                Level::Error,
+                "invalid-regex",
+                location,
+                e.kind().to_string(),
+            ));
+            return;
+        }
+    };
+    let matches_empty: bool = regex_syntax::ParserBuilder::new()
+        .case_insensitive(pattern.ignore_case)
+        .dot_matches_new_line(pattern.multiline)
+        .build()
+        .parse(regex)
+        .is_ok_and(|hir: regex_syntax::hir::Hir| hir.properties().minimum_len() == Some(0));
+    if matches_empty {
+        lints.push(Lint::new(
+            Level::Error,
+            "empty-match",
+            location,
+            "matches the empty string, so every line is reported",
+        ));
+    }
+    if let Some(repetition) = nested_repetition(&tree) {
+        lints.push(Lint::new(
+            Level::Warning,
+            "nested-quantifier",
+            location,
+            format!(
+                "`{}` repeats a repetition, which backtracking engines can take exponential time on",
+                source(regex, repetition.span())
+            ),
+        ));
+    }
+    if starts_with_wildcard(&tree) {
+        lints.push(Lint::new(
+            Level::Warning,
+            "leading-wildcard",
+            location,
+            "an unanchored `.*` or `.+` prefix only stretches the match to the start of the line and slows the search; drop it",
+        ));
+    }
+    let mut duplicates: Vec<&str> = Vec::new();
+    duplicate_branches(regex, &tree, &mut duplicates);
+    for branch in duplicates {
+        lints.push(Lint::new(
+            Level::Warning,
+            "duplicate-branch",
+            location,
+            format!("`{branch}` appears more than once in an alternation"),
+        ));
+    }
+}
+
+/// The text of `regex` that `span` covers.
+fn source<'a>(regex: &'a str, span: &ast::Span) -> &'a str {
+    &regex[span.start.offset..span.end.offset]
+}
+
+/// Whether a repetition has no upper bound, like `*`, `+` or `{2,}`.
+fn is_unbounded(kind: &RepetitionKind) -> bool {
+    matches!(
+        kind,
+        RepetitionKind::ZeroOrMore
+            | RepetitionKind::OneOrMore
+            | RepetitionKind::Range(RepetitionRange::AtLeast(_))
+    )
+}
+
+/// Whether `tree` can match the empty string, going by its structure.
+fn is_optional(tree: &Ast) -> bool {
+    match tree {
+        Ast::Empty(_) | Ast::Flags(_) | Ast::Assertion(_) => true,
+        Ast::Repetition(repetition) => {
+            matches!(
+                repetition.op.kind,
+                RepetitionKind::ZeroOrOne
+                    | RepetitionKind::ZeroOrMore
+                    | RepetitionKind::Range(
+                        RepetitionRange::Exactly(0)
+                            | RepetitionRange::AtLeast(0)
+                            | RepetitionRange::Bounded(0, _)
+                    )
+            ) || is_optional(&repetition.ast)
+        }
+        Ast::Group(group) => is_optional(&group.ast),
+        Ast::Alternation(alternation) => alternation.asts.iter().any(is_optional),
+        Ast::Concat(concat) => concat.asts.iter().all(is_optional),
+        _ => false,
+    }
+}
+
+/// Whether repeating `tree` lets one piece of text be split between iterations in
+/// many ways: it holds an unbounded repetition and nothing it requires besides.
+/// `(\w+\s?)` does; `(-\w+)` doesn't, because every iteration needs its `-`.
+fn is_ambiguous(tree: &Ast) -> bool {
+    match tree {
+        Ast::Repetition(repetition) => {
+            is_unbounded(&repetition.op.kind) || is_ambiguous(&repetition.ast)
+        }
+        Ast::Group(group) => is_ambiguous(&group.ast),
+        Ast::Alternation(alternation) => alternation.asts.iter().any(is_ambiguous),
+        Ast::Concat(concat) => concat
+            .asts
+            .iter()
+            .enumerate()
+            .any(|(i, item): (usize, &Ast)| {
+                is_ambiguous(item)
+                    && concat
+                        .asts
+                        .iter()
+                        .enumerate()
+                        .all(|(j, other): (usize, &Ast)| i == j || is_optional(other))
+            }),
+        _ => false,
+    }
+}
+
+/// The outermost unbounded repetition of an expression that is itself an
+/// unbounded repetition with little else around it, like `(a+)+` or `(\w+\s?)*`.
+fn nested_repetition(tree: &Ast) -> Option<&Ast> {
+    match tree {
+        Ast::Repetition(repetition)
+            if is_unbounded(&repetition.op.kind) && is_ambiguous(&repetition.ast) =>
+        {
+            Some(tree)
+        }
+        Ast::Repetition(repetition) => nested_repetition(&repetition.ast),
+        Ast::Group(group) => nested_repetition(&group.ast),
+        Ast::Alternation(alternation) => alternation.asts.iter().find_map(nested_repetition),
+        Ast::Concat(concat) => concat.asts.iter().find_map(nested_repetition),
+        _ => None,
+    }
+}
+
+/// Whether every match of `tree` can start with an unanchored `.*` or `.+`.
+fn starts_with_wildcard(tree: &Ast) -> bool {
+    match tree {
+        Ast::Repetition(repetition) => {
+            is_unbounded(&repetition.op.kind) && matches!(*repetition.ast, Ast::Dot(_))
+        }
+        Ast::Group(group) => starts_with_wildcard(&group.ast),
+        Ast::Alternation(alternation) => alternation.asts.iter().any(starts_with_wildcard),
+        Ast::Concat(concat) => concat
+            .asts
+            .iter()
+            .find(|item: &&Ast| !matches!(item, Ast::Flags(_)))
+            .is_some_and(starts_with_wildcard),
+        _ => false,
+    }
+}
+
+/// Collects the branches that occur more than once in an alternation of `tree`.
+fn duplicate_branches<'a>(regex: &'a str, tree: &Ast, duplicates: &mut Vec<&'a str>) {
+    match tree {
+        Ast::Alternation(alternation) => {
+            let branches: Vec<&str> = alternation
+                .asts
+                .iter()
+                .map(|branch: &Ast| source(regex, branch.span()))
+                .collect();
+            for (i, branch) in branches.iter().enumerate() {
+                if branches[..i].contains(branch) && !duplicates.contains(branch) {
+                    duplicates.push(branch);
+                }
+            }
+            for branch in &alternation.asts {
+                duplicate_branches(regex, branch, duplicates);
+            }
+        }
+        Ast::Repetition(repetition) => duplicate_branches(regex, &repetition.ast, duplicates),
+        Ast::Group(group) => duplicate_branches(regex, &group.ast, duplicates),
+        Ast::Concat(concat) => {
+            for item in &concat.asts {
+                duplicate_branches(regex, item, duplicates);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Resolves the files to lint from the command-line targets.
+fn resolve_targets(args: &LintArgs) -> Result<Vec<PathBuf>> {
+    if !args.all {
+        return args
+            .targets
+            .iter()
+            .map(|target: &String| validate::resolve_target(target))
+            .collect();
+    }
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let mut names: Vec<String> = if pattern_dir.exists() {
+        list_pattern_names(&pattern_dir)?
+    } else {
+        Vec::new()
+    };
+    names.sort();
+    Ok(names
+        .into_iter()
+        .filter_map(|name: String| pattern_file(&pattern_dir, &name))
+        .collect())
+}
+
+/// Lints the selected patterns, failing on errors and on the levels `--deny` names.
+pub(crate) fn run_lint(args: &LintArgs, styles: &Styles) -> Result<()> {
+    let targets: Vec<PathBuf> = resolve_targets(args)?;
+    if targets.is_empty() {
+        println!("{}", "No patterns to lint.".style(styles.dim));
+        return Ok(());
+    }
+
+    let mut counts: [usize; 3] = [0; 3];
+    let mut failing: usize = 0;
+    for path in &targets {
+        let lints: Vec<Lint> = match load_pattern_file(path) {
+            Ok(pattern) => lint_pattern(&pattern),
+            Err(e) => vec![Lint::new(
+                Level::Error,
+                "invalid-pattern",
+                "/",
+                format!("{e:#}"),
+            )],
+        };
+        if lints.is_empty() {
+            println!("{} {}", "✓".style(styles.success), path.display());
+            continue;
+        }
+        let fails: bool = lints.iter().any(|lint: &Lint| {
+            lint.level == Level::Error
+                || args.deny.is_some_and(|deny: Deny| deny.denies(lint.level))
+        });
+        failing += usize::from(fails);
+        println!(
+            "{} {}",
+            if fails { "✗" } else { "!" }.style(styles.error),
+            path.display()
+        );
+        for lint in &lints {
+            counts[lint.level as usize] += 1;
+            let level: String = format!("{}[{}]", lint.level, lint.rule);
+            let style = match lint.level {
+                Level::Error => styles.error,
+                Level::Warning => styles.highlight,
+                Level::Note => styles.info,
+            };
+            println!(
+                "    {} {}: {}",
+                level.style(style),
+                lint.location.style(styles.dim),
+                lint.message
+            );
+        }
+    }
+
+    println!(
+        "\n{} error(s), {} warning(s), {} note(s) in {} pattern file(s).",
+        counts[Level::Error as usize],
+        counts[Level::Warning as usize],
+        counts[Level::Note as usize],
+        targets.len()
+    );
+    if failing > 0 {
+        return Err(anyhow!("{failing} pattern file(s) failed linting."));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(pattern: &Pattern) -> Vec<(&'static str, String)> {
+        lint_pattern(pattern)
+            .into_iter()
+            .map(|lint: Lint| (lint.rule, lint.location))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_pattern() {
+        let clean: Pattern = Pattern {
+            description: Some("AWS access key IDs".to_string()),
+            tags: Some(vec!["secrets".to_string()]),
+            regex: Some(r"\bAKIA[0-9A-Z]{16}\b".to_string()),
+            ..Pattern::default()
+        };
+        assert!(lint_pattern(&clean).is_empty());
+
+        let noisy: Pattern = Pattern {
+            regex: None,
+            regex_list: Some(vec![
+                r"(\w+\s?)+=".to_string(),
+                r"(?i).*password".to_string(),
+                r"^.*anchored".to_string(),
+                r"token|secret|token".to_string(),
+                r"x*".to_string(),
+                r"(\w+\s?)+=".to_string(),
+                r"(unclosed".to_string(),
+                r"[a-z]{2,}(?:-[a-z]{2,})*".to_string(),
+            ]),
+            ..Pattern::default()
+        };
+        let found: Vec<(&str, String)> = rules(&noisy);
+        assert_eq!(
+            found,
+            [
+                ("missing-description", "description"),
+                ("missing-tags", "tags"),
+                ("nested-quantifier", "patterns[0]"),
+                ("leading-wildcard", "patterns[1]"),
+                ("duplicate-branch", "patterns[3]"),
+                ("empty-match", "patterns[4]"),
+                ("duplicate-branch", "patterns[5]"),
+                ("invalid-regex", "patterns[6]"),
+            ]
+            .map(|(rule, location): (&str, &str)| (rule, location.to_string()))
+        );
+        let lints: Vec<Lint> = lint_pattern(&noisy);
+        assert_eq!(lints[2].level, Level::Warning);
+        assert!(lints[2].message.starts_with(r"`(\w+\s?)+`"));
+        assert!(lints[4].message.starts_with("`token`"));
+        assert_eq!(lints[5].level, Level::Error);
+
+        let lookaround: Pattern = Pattern {
+            regex: None,
+            regex_list: Some(vec![
+                r"(?<=key=)\w+".to_string(),
+                r"(?<=key=(\w+".to_string(),
+                r"(\w+\s?)+=".to_string(),
+            ]),
+            engine: Some(Engine::Backtrack),
+            ..clean
+        };
+        assert_eq!(
+            rules(&lookaround),
+            [
+                ("invalid-regex", "patterns[1]"),
+                ("nested-quantifier", "patterns[2]"),
+            ]
+            .map(|(rule, location): (&str, &str)| (rule, location.to_string()))
+        );
+
+        let literals: Pattern = Pattern {
+            regex_list: None,
+            strings: Some(vec![
+                "db.internal".to_string(),
+                "staging.corp".to_string(),
+                "DB.Internal".to_string(),
+            ]),
+            ignore_case: true,
+            ..lookaround
+        };
+        assert_eq!(
+            rules(&literals),
+            [("duplicate-branch", "strings[2]".to_string())]
+        );
+
+        assert!(Deny::Warnings.denies(Level::Warning));
+        assert!(!Deny::Warnings.denies(Level::Note));
+        assert!(Deny::Notes.denies(Level::Note));
+    }
+}
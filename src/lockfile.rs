@@ -0,0 +1,545 @@
+//! `gfr.lock`: the exact pattern files that are installed, by URL and SHA-256, so
+//! `gfr install --locked` reproduces the same pattern set on another machine,
+//! e.g. in CI, instead of whatever the index points at today.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::collections::{self, Collection};
+use crate::http::{HttpClient, NetworkOptions};
+use crate::migrate;
+use crate::signing::{self, TrustedKeys};
+use crate::{
+    check_full_name, download_all, file_sha256, get_pattern_dir, load_manifest, load_user_config,
+    qualify, save_manifest, sha256_hex, Download, InstalledManifest, InstalledPattern, Pattern,
+    Styles, UserConfig,
+};
+
+/// Default lockfile name, read from and written to the current directory.
+pub(crate) const LOCKFILE: &str = "gfr.lock";
+
+/// Format version written to new lockfiles.
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Args, Debug)]
+pub(crate) struct LockArgs {
+    /// Where to write the lockfile.
+    #[arg(default_value = LOCKFILE)]
+    output: PathBuf,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Lockfile {
+    lock_version: u32,
+    patterns: Vec<LockedPattern>,
+}
+
+/// One installed pattern or collection file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LockedPattern {
+    name: String,
+    version: String,
+    /// URL or path the file was downloaded from.
+    url: String,
+    /// Hex-encoded SHA-256 of the file at `url`.
+    sha256: String,
+    /// Index the file was listed in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Configured registry that index belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    registry: Option<String>,
+    /// URL or path of the detached minisign signature of the file, checked
+    /// against `trusted_keys.pub` on every install.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+impl LockedPattern {
+    /// Checks the file downloaded as `body` against the locked checksum and, if
+    /// there are trusted keys, against `signature`, the text of the locked
+    /// signature file. Returns whether the signature was checked.
+    fn check(
+        &self,
+        body: &[u8],
+        signature: Option<&[u8]>,
+        trusted_keys: &TrustedKeys,
+    ) -> Result<bool> {
+        let checksum: String = sha256_hex(body);
+        if !checksum.eq_ignore_ascii_case(&self.sha256) {
+            return Err(anyhow!(
+                "Checksum mismatch for '{}': the lockfile says {}, {} is now {checksum}.",
+                self.name,
+                self.sha256,
+                self.url
+            ));
+        }
+        let (Some(signature_url), false) = (&self.signature, trusted_keys.is_empty()) else {
+            return Ok(false);
+        };
+        let signature: &[u8] =
+            signature.ok_or_else(|| anyhow!("No signature of '{}' was downloaded.", self.name))?;
+        trusted_keys
+            .verify(body, &String::from_utf8_lossy(signature))
+            .with_context(|| {
+                format!(
+                    "Signature check of '{}' failed ({signature_url})",
+                    self.name
+                )
+            })?;
+        Ok(true)
+    }
+
+    /// The manifest entry of the pattern once installed. Only a signature checked
+    /// on this install makes it signed; the lockfile's word isn't enough.
+    fn installed(self, local_sha256: Option<String>, signed: bool) -> InstalledPattern {
+        InstalledPattern {
+            version: self.version,
+            sha256: Some(self.sha256),
+            source: self.source,
+            registry: self.registry,
+            url: Some(self.url),
+            local_sha256,
+            pinned: false,
+            signed,
+            signature: self.signature.filter(|_| signed),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Locks every installed pattern. Patterns installed by an older gfr don't
+    /// record where they came from and have to be reinstalled first.
+    fn from_manifest(manifest: &InstalledManifest) -> Result<Self> {
+        let mut patterns: Vec<LockedPattern> = Vec::new();
+        let mut unlocked: Vec<&str> = Vec::new();
+        for (name, installed) in manifest {
+            let (Some(url), Some(sha256)) = (&installed.url, &installed.sha256) else {
+                unlocked.push(name);
+                continue;
+            };
+            patterns.push(LockedPattern {
+                name: name.clone(),
+                version: installed.version.clone(),
+                url: url.clone(),
+                sha256: sha256.clone(),
+                source: installed.source.clone(),
+                registry: installed.registry.clone(),
+                signature: installed.signature.clone().filter(|_| installed.signed),
+            });
+        }
+        if !unlocked.is_empty() {
+            unlocked.sort_unstable();
+            return Err(anyhow!(
+                "{} can't be locked because it was installed by an older gfr. Run `gfr install` to reinstall it.",
+                unlocked.join(", ")
+            ));
+        }
+        patterns.sort_by(|a: &LockedPattern, b: &LockedPattern| a.name.cmp(&b.name));
+        Ok(Self {
+            lock_version: LOCK_VERSION,
+            patterns,
+        })
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let file: File =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let lockfile: Self = serde_json::from_reader(file)
+            .with_context(|| format!("Failed to parse lockfile {}", path.display()))?;
+        if lockfile.lock_version > LOCK_VERSION {
+            return Err(anyhow!(
+                "{} was written by a newer gfr (lock_version {}).",
+                path.display(),
+                lockfile.lock_version
+            ));
+        }
+        // Names become paths in the pattern directory, and a lockfile can come
+        // with any pull request.
+        for locked in &lockfile.patterns {
+            check_full_name(&locked.name).with_context(|| format!("Invalid {}", path.display()))?;
+        }
+        Ok(lockfile)
+    }
+
+    /// Refuses a lockfile with patterns it lists no signature for, if
+    /// `require_signed` is set.
+    fn check_signed(&self, require_signed: bool, trusted_keys: &TrustedKeys) -> Result<()> {
+        if !require_signed {
+            return Ok(());
+        }
+        let unsigned: Vec<&str> = self
+            .patterns
+            .iter()
+            .filter(|p: &&LockedPattern| p.signature.is_none())
+            .map(|p: &LockedPattern| p.name.as_str())
+            .collect();
+        if !unsigned.is_empty() {
+            return Err(anyhow!(
+                "{} has no signature in the lockfile, and --require-signed was given. Nothing was installed.",
+                unsigned.join(", ")
+            ));
+        }
+        if trusted_keys.is_empty() {
+            return Err(anyhow!(
+                "--require-signed needs at least one key in {}",
+                signing::TRUSTED_KEYS_FILE
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Runs `gfr lock`.
+pub(crate) fn run_lock(args: &LockArgs, styles: &Styles) -> Result<()> {
+    let lockfile: Lockfile = Lockfile::from_manifest(&load_manifest()?)?;
+    let mut file: File = File::create(&args.output)
+        .with_context(|| format!("Failed to create {}", args.output.display()))?;
+    serde_json::to_writer_pretty(&mut file, &lockfile)?;
+    writeln!(file)?;
+    println!(
+        "{} Locked {} patterns in {}",
+        "✓".style(styles.success),
+        lockfile.patterns.len().to_string().style(styles.highlight),
+        args.output.display().to_string().style(styles.highlight)
+    );
+    Ok(())
+}
+
+/// Runs `gfr install --locked`: downloads exactly the files in the lockfile and
+/// checks them all against their recorded checksums and signatures before
+/// writing any of them.
+pub(crate) async fn install_locked(
+    path: &Path,
+    require_signed: bool,
+    network: &NetworkOptions,
+    styles: &Styles,
+) -> Result<()> {
+    let lockfile: Lockfile = Lockfile::load(path)?;
+    let trusted_keys: TrustedKeys = TrustedKeys::load()?;
+    lockfile.check_signed(require_signed, &trusted_keys)?;
+    let config: UserConfig = load_user_config()?;
+    // Each file is fetched with the token of the index it was installed from.
+    let mut client: HttpClient = HttpClient::new(network)?;
+    for locked in &lockfile.patterns {
+        if let Some(source) = &locked.source {
+            client = client.with_token(source, config.token(locked.registry.as_deref()).as_deref());
+        }
+    }
+    let mut manifest: InstalledManifest = load_manifest().unwrap_or_default();
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let mut unchanged_count: usize = 0;
+
+    let mut wanted: Vec<LockedPattern> = Vec::new();
+    for locked in lockfile.patterns {
+        // The file on disk has to be the one gfr wrote, too, not edited or deleted since.
+        let current: bool = manifest
+            .get(&locked.name)
+            .is_some_and(|p: &InstalledPattern| {
+                p.version == locked.version
+                    && p.sha256
+                        .as_deref()
+                        .is_some_and(|s: &str| s.eq_ignore_ascii_case(&locked.sha256))
+                    && p.signature == locked.signature
+                    && p.local_sha256.as_deref().is_some_and(|expected: &str| {
+                        file_sha256(&installed_path(&pattern_dir, &locked.name))
+                            .is_some_and(|actual: String| actual.eq_ignore_ascii_case(expected))
+                    })
+            });
+        if current {
+            unchanged_count += 1;
+        } else {
+            wanted.push(locked);
+        }
+    }
+
+    let jobs: Vec<(String, Option<String>)> = wanted
+        .iter()
+        .map(|locked: &LockedPattern| {
+            let signature: Option<String> = locked
+                .signature
+                .clone()
+                .filter(|_| !trusted_keys.is_empty());
+            (locked.url.clone(), signature)
+        })
+        .collect();
+    // A locked set is installed whole or not at all.
+    let downloads: Vec<Download> = download_all(&client, jobs)
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<Download>>>()
+        .context("Nothing was installed")?;
+    let mut signed: Vec<bool> = Vec::with_capacity(downloads.len());
+    for (locked, (body, signature)) in wanted.iter().zip(&downloads) {
+        signed.push(
+            locked
+                .check(body, signature.as_deref(), &trusted_keys)
+                .context("Nothing was installed")?,
+        );
+        println!(
+            "  -> Verified '{}' (v{}) from {}",
+            locked.name.style(styles.highlight),
+            locked.version,
+            locked.url.style(styles.dim)
+        );
+    }
+    let files: Vec<LockedFile> = wanted
+        .iter()
+        .zip(&downloads)
+        .map(|(locked, (body, _)): (&LockedPattern, &Download)| {
+            LockedFile::parse(&pattern_dir, &locked.name, body)
+        })
+        .collect::<Result<Vec<LockedFile>>>()
+        .context("Nothing was installed")?;
+
+    let installed_count: usize = files.len();
+    for ((locked, file), signed) in wanted.into_iter().zip(files).zip(signed) {
+        file.write()?;
+        let local_sha256: Option<String> = file_sha256(&file.path);
+        manifest.insert(locked.name.clone(), locked.installed(local_sha256, signed));
+    }
+    save_manifest(&manifest)?;
+    println!(
+        "\n{} Installed {} locked patterns, {} already matched {}.",
+        "✓".style(styles.success),
+        installed_count.to_string().style(styles.highlight),
+        unchanged_count.to_string().style(styles.highlight),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Where the installed pattern or collection `name` is, if it is installed.
+fn installed_path(pattern_dir: &Path, name: &str) -> PathBuf {
+    let pattern: PathBuf = pattern_dir.join(format!("{name}.json"));
+    if pattern.is_file() {
+        return pattern;
+    }
+    let short: &str = name
+        .rsplit_once('/')
+        .map_or(name, |(_, short): (&str, &str)| short);
+    pattern_dir
+        .join(collections::COLLECTIONS_DIR)
+        .join(format!("{short}.json"))
+}
+
+/// A verified download, checked and ready to be written where `gfr install`
+/// would have put it.
+#[derive(Debug)]
+struct LockedFile {
+    path: PathBuf,
+    json: Value,
+}
+
+impl LockedFile {
+    /// Checks the pattern or collection `name` downloaded as `body`.
+    fn parse(pattern_dir: &Path, name: &str, body: &[u8]) -> Result<Self> {
+        let mut json: Value = serde_json::from_slice(body)
+            .with_context(|| format!("Failed to parse locked file '{name}'"))?;
+        if collections::is_collection(&json) {
+            let collection: Collection = serde_json::from_value(json.clone())
+                .with_context(|| format!("Invalid collection '{name}'"))?;
+            let (namespace, short): (&str, &str) = name.rsplit_once('/').unwrap_or(("", name));
+            if !namespace.is_empty() {
+                json["collection"] = serde_json::json!(collection
+                    .patterns
+                    .iter()
+                    .map(|member: &String| qualify(namespace, member))
+                    .collect::<Vec<String>>());
+            }
+            let path: PathBuf = pattern_dir
+                .join(collections::COLLECTIONS_DIR)
+                .join(format!("{short}.json"));
+            return Ok(Self { path, json });
+        }
+        migrate::upgrade(&mut json).with_context(|| format!("Can't install '{name}'"))?;
+        let pattern: Pattern = serde_json::from_value(json.clone())
+            .with_context(|| format!("Invalid pattern '{name}'"))?;
+        pattern
+            .build_matcher()
+            .with_context(|| format!("Invalid regex in '{name}'"))?;
+        Ok(Self {
+            path: pattern_dir.join(format!("{name}.json")),
+            json,
+        })
+    }
+
+    fn write(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file: File = File::create(&self.path)?;
+        serde_json::to_writer_pretty(file, &self.json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockfile_from_manifest() {
+        let installed = |url: Option<&str>| InstalledPattern {
+            version: "1.2.0".to_string(),
+            sha256: Some("ab12".to_string()),
+            source: Some("https://example.com/index.json".to_string()),
+            registry: None,
+            url: url.map(ToString::to_string),
+            local_sha256: None,
+            pinned: false,
+            signed: url.is_some(),
+            signature: url.map(|url: &str| format!("{url}.minisig")),
+        };
+        let mut manifest: InstalledManifest = InstalledManifest::new();
+        manifest.insert(
+            "team/xss".to_string(),
+            installed(Some("https://example.com/xss.json")),
+        );
+        manifest.insert(
+            "team/jwt".to_string(),
+            installed(Some("https://example.com/jwt.json")),
+        );
+        let lockfile: Lockfile = Lockfile::from_manifest(&manifest).unwrap();
+        assert_eq!(lockfile.lock_version, LOCK_VERSION);
+        let names: Vec<&str> = lockfile
+            .patterns
+            .iter()
+            .map(|p: &LockedPattern| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["team/jwt", "team/xss"]);
+        assert_eq!(
+            lockfile.patterns[1].signature.as_deref(),
+            Some("https://example.com/xss.json.minisig")
+        );
+
+        let text: String = serde_json::to_string(&lockfile).unwrap();
+        assert_eq!(serde_json::from_str::<Lockfile>(&text).unwrap(), lockfile);
+
+        manifest.insert("legacy".to_string(), installed(None));
+        let err: String = Lockfile::from_manifest(&manifest).unwrap_err().to_string();
+        assert!(err.starts_with("legacy can't be locked"));
+    }
+
+    #[test]
+    fn test_lockfile_rejects_names_that_are_paths() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join(LOCKFILE);
+        let lock = |name: &str| {
+            let lockfile: Value = serde_json::json!({
+                "lock_version": 1,
+                "patterns": [{
+                    "name": name,
+                    "version": "1.0.0",
+                    "url": "https://example.com/x.json",
+                    "sha256": "ab12"
+                }]
+            });
+            fs::write(&path, lockfile.to_string()).unwrap();
+            Lockfile::load(&path)
+        };
+        assert!(lock("team/xss").is_ok());
+        for hostile in ["../../.bashrc", "/etc/cron.d/x", "team/../../x", "a/b/c"] {
+            assert!(lock(hostile).is_err(), "{hostile}");
+        }
+    }
+
+    #[test]
+    fn test_locked_files_are_checked_before_writing() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let pattern: &[u8] = br#"{"version": "1.0.0", "pattern": "<script"}"#;
+        let file: LockedFile = LockedFile::parse(dir.path(), "team/xss", pattern).unwrap();
+        assert_eq!(file.path, dir.path().join("team/xss.json"));
+        assert!(!file.path.exists());
+        file.write().unwrap();
+        assert_eq!(installed_path(dir.path(), "team/xss"), file.path);
+
+        let bad_regex: &[u8] = br#"{"version": "1.0.0", "pattern": "("}"#;
+        assert!(LockedFile::parse(dir.path(), "team/bad", bad_regex).is_err());
+        assert!(LockedFile::parse(dir.path(), "team/junk", b"not json").is_err());
+
+        let collection: &[u8] = br#"{"collection": ["xss"]}"#;
+        let file: LockedFile = LockedFile::parse(dir.path(), "team/web", collection).unwrap();
+        assert_eq!(file.json["collection"][0], "team/xss");
+        file.write().unwrap();
+        assert_eq!(installed_path(dir.path(), "team/web"), file.path);
+    }
+
+    fn locked(name: &str, body: &[u8], signature: bool) -> LockedPattern {
+        let url: String = format!("https://example.com/{name}.json");
+        LockedPattern {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            signature: signature.then(|| format!("{url}.minisig")),
+            url,
+            sha256: sha256_hex(body),
+            source: None,
+            registry: None,
+        }
+    }
+
+    #[test]
+    fn test_locked_require_signed() {
+        let mut lockfile: Lockfile = Lockfile {
+            lock_version: LOCK_VERSION,
+            patterns: vec![
+                locked("team/xss", b"", true),
+                locked("team/jwt", b"", false),
+            ],
+        };
+        let no_keys: TrustedKeys = TrustedKeys::default();
+        assert!(lockfile.check_signed(false, &no_keys).is_ok());
+        let err: String = lockfile
+            .check_signed(true, &no_keys)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("team/jwt has no signature"), "{err}");
+
+        lockfile.patterns.pop();
+        let err: String = lockfile
+            .check_signed(true, &no_keys)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(signing::TRUSTED_KEYS_FILE), "{err}");
+
+        // Trust is never taken from the lockfile's word.
+        let claimed: Result<Lockfile, serde_json::Error> = serde_json::from_str(
+            r#"{"lock_version": 1, "patterns": [{"name": "team/xss", "version": "1.0.0",
+                "url": "https://example.com/xss.json", "sha256": "ab12", "signed": true}]}"#,
+        );
+        assert!(claimed.is_err());
+    }
+
+    #[test]
+    fn test_locked_signatures_are_checked() {
+        let keys: TrustedKeys =
+            TrustedKeys::parse("RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3").unwrap();
+        let body: &[u8] = br#"{"version": "1.0.0", "pattern": "AKIA"}"#;
+        let signed: LockedPattern = locked("team/aws", body, true);
+        assert!(signed.check(body, None, &keys).is_err());
+        assert!(signed.check(body, Some(b"not a signature"), &keys).is_err());
+        assert!(signed
+            .check(b"other", None, &TrustedKeys::default())
+            .is_err());
+
+        // Without keys nothing is checked, so nothing counts as signed.
+        assert!(!signed.check(body, None, &TrustedKeys::default()).unwrap());
+        let installed: InstalledPattern = signed.installed(None, false);
+        assert_eq!(installed.signature, None);
+        // Nor is a file from a lockfile trusted as a local one, with or without
+        // the index it came from.
+        assert!(!installed.is_trusted());
+        let unsigned: LockedPattern = locked("team/aws", body, false);
+        assert!(!unsigned.check(body, None, &keys).unwrap());
+        assert!(!unsigned.installed(None, false).is_trusted());
+    }
+}
@@ -5,22 +5,99 @@
 #![deny(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 
-use std::collections::{HashMap, HashSet};
+mod aliases;
+mod allow;
+mod badge;
+mod baseline;
+mod bench;
+mod collections;
+mod compare;
+mod daemon;
+mod estimate;
+mod explain;
+mod findings;
+mod follow;
+mod http;
+mod indexer;
+mod lint;
+mod lockfile;
+mod migrate;
+mod ngram_index;
+mod pattern_tests;
+mod plugins;
+mod prune;
+mod publish;
+mod redact;
+mod sarif;
+mod scaffold;
+mod search_cache;
+mod serve;
+mod signing;
+mod sinks;
+mod store;
+mod sync;
+mod triage;
+mod tune;
+mod validate;
+mod verify;
+mod versions;
+mod watch;
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs::{self, File};
-use std::io::{self, IsTerminal, Write};
+use std::io::{self, IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::sync::{Arc, Mutex};
 
+use allow::{AllowList, ALLOW_FILE};
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
-use grep_printer::{ColorSpecs, StandardBuilder};
-use grep_regex::RegexMatcherBuilder;
-use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder};
+use baseline::{Baseline, BaselineWriter};
+use clap::{Parser, Subcommand, ValueEnum};
+use collections::Collection;
+use findings::FindingsAction;
+use gfr_core::encoding::TextEncoding;
+use gfr_core::engine::{Engine, PatternMatcher};
+use gfr_core::entropy::EntropyRule;
+use gfr_core::finding::{
+    finding_fingerprint, sha256_hex, trim_line_terminator, Finding, FindingSink, STDIN_LABEL,
+};
+use gfr_core::load::{
+    file_stems, get_pattern_dir, list_pattern_names, load_pattern, load_pattern_file, pattern_file,
+    resolve_pattern_name, Aliases, PatternFormat, BUNDLES_FILE, CONFIG_DIR,
+    INSTALLED_MANIFEST_FILE, USER_CONFIG_FILE,
+};
+use gfr_core::pattern::{self, combined_matcher, default_version, Pattern, Severity};
+use gfr_core::search::WalkOptions;
+use gfr_core::tags::TagExpr;
+use gfr_core::validators::Validator;
+use gfr_core::{filetype, jsonc, suppress, winpath};
+use grep_matcher::Matcher;
+use grep_printer::{ColorSpecs, StandardBuilder, SummaryBuilder, SummaryKind};
+use grep_searcher::{
+    BinaryDetection, MmapChoice, Searcher, SearcherBuilder, Sink, SinkContext, SinkFinish,
+    SinkMatch,
+};
+use http::{GitHubLocation, HttpClient, NetworkOptions};
 use ignore::{WalkBuilder, WalkState};
+use ngram_index::SearchIndex;
 use owo_colors::{OwoColorize, Style};
+use plugins::Plugins;
+use prune::DirectoryPruner;
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use termcolor::{ColorChoice, StandardStream};
+use signing::TrustedKeys;
+use sinks::SinkSpec;
+use store::{FindingStatus, FindingsStore};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
+use tokio::task::JoinSet;
+use triage::{TriageAction, TriageStore};
+use tune::Tuning;
+use verify::{VerificationSpec, VerifyOptions};
 
 fn get_color_choice() -> ColorChoice {
     if io::stdout().is_terminal() {
@@ -39,8 +116,18 @@ fn get_color_specs() -> ColorSpecs {
     }
 }
 
-const CONFIG_DIR: &str = "gfr";
-const INSTALLED_MANIFEST_FILE: &str = "installed.json";
+/// SPDX identifiers accepted by the `"permissive"` license policy shorthand.
+const PERMISSIVE_LICENSES: [&str; 9] = [
+    "MIT",
+    "Apache-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "ISC",
+    "0BSD",
+    "Unlicense",
+    "CC0-1.0",
+    "Zlib",
+];
 const DEFAULT_PATTERNS_URL: &str =
     "https://raw.githubusercontent.com/Kr1shna4garwal/gfr-patterns/refs/heads/main/index.json";
 const DEFAULT_PATTERN_SCHEMA_URL: &str = "https://raw.githubusercontent.com/Kr1shna4garwal/gfr-patterns/refs/heads/main/schemas/pattern.schema.json";
@@ -51,403 +138,3988 @@ const DEFAULT_PATTERN_SCHEMA_URL: &str = "https://raw.githubusercontent.com/Kr1s
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    network: NetworkOptions,
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)] // Parsed once per run; boxing buys nothing.
 enum Commands {
     /// Search for patterns in files or stdin.
-    Search {
-        /// The name of the pattern to search for (e.g., "rce", "ipv4")
-        pattern_name: Option<String>,
-
-        /// File or directory path to search. Defaults to current directory.
-        #[arg(default_value = ".")]
-        path: PathBuf,
-
-        /// Show search configuration and exit without searching.
-        #[arg(long, short)]
-        dump: bool,
-
-        /// Filter patterns by comma-separated tags (e.g., "web,security").
-        #[arg(long, value_delimiter = ',')]
-        tags: Option<Vec<String>>,
-
-        /// Filter patterns by author name.
-        #[arg(long)]
-        author: Option<String>,
-
-        /// Include binary files in the search.
-        #[arg(long)]
-        include_bin: bool,
-    },
+    Search(SearchArgs),
     /// List all available local patterns.
     List,
     /// Install or update patterns from a remote index file.
     Install {
-        /// Optional URL to a custom patterns index.json file.
-        #[arg(default_value = DEFAULT_PATTERNS_URL)]
-        url: String,
+        /// Only install these patterns from the index; `name@1.2.0` installs and
+        /// pins that version. A URL, `github:` location, directory or `.json` path
+        /// given here is read as `--url`.
+        names: Vec<String>,
+        /// URL or path of a custom patterns index (JSON, YAML or TOML), a directory of pattern
+        /// files to install without network access, or `github:OWNER/REPO[/PATH][@REF]`.
+        #[arg(long)]
+        url: Option<String>,
+        /// Install from this registry of `config.json` instead of the default index.
+        #[arg(long, value_name = "NAME", conflicts_with = "url")]
+        registry: Option<String>,
+        /// Only install the patterns of this bundle from the index.
+        #[arg(long, value_name = "NAME")]
+        bundle: Option<String>,
+        /// Install exactly the files recorded in a lockfile (`gfr.lock` by default),
+        /// failing if any of them changed since it was written.
+        #[arg(
+            long,
+            value_name = "LOCKFILE",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = lockfile::LOCKFILE,
+            conflicts_with_all = ["names", "url", "registry", "bundle"]
+        )]
+        locked: Option<PathBuf>,
+        /// Refuse patterns the index doesn't list a signature for, or with --locked,
+        /// that weren't signed when locked. Signatures are checked against the keys
+        /// in `trusted_keys.pub` either way.
+        #[arg(long)]
+        require_signed: bool,
+        /// Print which patterns would be added, updated or skipped, without
+        /// downloading or writing any of them.
+        #[arg(long, conflicts_with = "locked")]
+        dry_run: bool,
+        /// Overwrite installed patterns even if they were edited locally.
+        #[arg(long, conflicts_with = "locked")]
+        force: bool,
+    },
+    /// Upgrade already-installed patterns from a remote index file.
+    Update {
+        /// Optional URL to a custom patterns index (JSON, YAML or TOML).
+        url: Option<String>,
+        /// Only update from this registry of `config.json`.
+        #[arg(long, value_name = "NAME", conflicts_with = "url")]
+        registry: Option<String>,
+        /// Only list outdated patterns; don't download or write anything.
+        #[arg(long)]
+        check: bool,
+        /// Refuse patterns the index doesn't list a signature for.
+        #[arg(long, conflicts_with = "check")]
+        require_signed: bool,
+        /// Print which patterns would be updated or skipped, without writing anything.
+        #[arg(long, conflicts_with = "check")]
+        dry_run: bool,
+        /// Overwrite installed patterns even if they were edited locally.
+        #[arg(long, conflicts_with = "check")]
+        force: bool,
+        /// Replace deprecated patterns with the patterns that supersede them.
+        #[arg(long, conflicts_with = "check")]
+        migrate: bool,
     },
+    /// Write a lockfile of the installed patterns for `gfr install --locked`.
+    Lock(lockfile::LockArgs),
+    /// Write the index.json of a directory of pattern files, for hosting your own patterns.
+    Index(indexer::IndexArgs),
+    /// Index the trigrams of a directory, so `gfr search --index` skips files that can't match.
+    IndexBuild(ngram_index::IndexBuildArgs),
+    /// Create a new pattern repository with an example pattern, the schema and an index.
+    InitRepo(scaffold::InitRepoArgs),
+    /// Validate a local pattern, give it a new version and add it to a pattern repository checkout.
+    Publish(publish::PublishArgs),
+    /// Reconcile installed.json with pattern files added or deleted by hand.
+    Sync(sync::SyncArgs),
+    /// Go back to the version of a pattern that the last install or update replaced.
+    Rollback(versions::RollbackArgs),
     /// Save a new local pattern interactively.
     Save(SaveArgs),
+    /// Check pattern files against the schema and make sure their regexes compile.
+    Validate(validate::ValidateArgs),
+    /// Upgrade pattern files written for an older pattern schema version in place.
+    Migrate(migrate::MigrateArgs),
+    /// Flag slow, noisy or redundant constructs in patterns, e.g. for pattern repository CI.
+    Lint(lint::LintArgs),
+    /// Show the branches, literal prefixes, flags and file types of a pattern.
+    Explain(explain::ExplainArgs),
+    /// Time patterns against a directory or a generated corpus to find the slow ones.
+    Bench(bench::BenchArgs),
+    /// Run the match/no-match examples embedded in patterns.
+    Test(pattern_tests::TestArgs),
+    /// Remove installed or saved patterns.
+    Remove(RemoveArgs),
+    /// Define, remove or list short names for installed patterns.
+    Alias(aliases::AliasArgs),
+    /// Record true/false positive verdicts and review what they say about each pattern.
+    Triage {
+        #[command(subcommand)]
+        action: TriageAction,
+    },
+    /// Report new, fixed and persisting findings between two `--json` results or baselines.
+    Compare(compare::CompareArgs),
+    /// Review findings recorded with `gfr search --record` and mark them as false positives, accepted or fixed.
+    Findings {
+        /// Findings database to use instead of the default one.
+        #[arg(long, value_name = "PATH", global = true)]
+        db: Option<PathBuf>,
+        #[command(subcommand)]
+        action: FindingsAction,
+    },
+    /// Serve searches with the local patterns over HTTP.
+    Serve(serve::ServeArgs),
+    /// Keep compiled patterns in memory for `gfr search --daemon`.
+    Daemon(daemon::DaemonArgs),
+}
+
+#[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)] // Each bool is an independent command-line flag.
+pub struct SearchArgs {
+    /// The name of the pattern to search for (e.g., "rce", "ipv4"), or several
+    /// separated by commas (e.g., "rce,sqli,ssrf").
+    pattern_name: Option<String>,
+
+    /// File or directory path to search. Defaults to current directory.
+    #[arg(default_value = ".")]
+    path: PathBuf,
+
+    /// Show search configuration and exit without searching.
+    #[arg(long, short)]
+    dump: bool,
+
+    /// Report how many files and bytes the search would cover, and roughly how
+    /// long it would take, without running it.
+    #[arg(long)]
+    estimate: bool,
+
+    /// Filter patterns by tags: comma-separated tags that must all be present
+    /// (e.g., "web,security"), or an expression using and, or, not and parentheses
+    /// (e.g., "web and (xss or sqli) and not noisy").
+    #[arg(long, value_name = "EXPR", value_parser = TagExpr::parse)]
+    tags: Option<TagExpr>,
+
+    /// Filter patterns by author name.
+    #[arg(long)]
+    author: Option<String>,
+
+    /// Search with the patterns of a bundle or collection from the index they were
+    /// installed from. Same as naming it `@NAME`.
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["tags", "author", "two_pass"])]
+    bundle: Option<String>,
+
+    /// When a pattern name isn't installed but exactly one installed name is close
+    /// to it, search with that pattern instead of failing.
+    #[arg(long)]
+    fuzzy: bool,
+
+    /// Search with this pattern too. Can be repeated; with it, a single positional
+    /// argument is the path to search.
+    #[arg(short = 'p', long = "pattern", value_name = "NAME")]
+    patterns: Vec<String>,
+
+    /// Also search with the pattern defined in this file, e.g. one kept in the repo
+    /// being scanned. Named after the file, like installed patterns. Can be repeated.
+    #[arg(long, value_name = "PATH")]
+    pattern_file: Vec<PathBuf>,
+
+    /// Drop a pattern selected by --tags, --author or --bundle from this run. Can be repeated.
+    #[arg(long, value_name = "NAME", visible_alias = "exclude-pattern")]
+    skip_pattern: Vec<String>,
+
+    /// Run every selected pattern on this regex engine instead of the one it asks
+    /// for, e.g. `backtrack` for rules written with lookaround or backreferences.
+    #[arg(long, value_enum)]
+    engine: Option<Engine>,
+
+    /// Only match whole words, as if every selected pattern set `word`.
+    #[arg(short = 'w', long)]
+    word_regexp: bool,
+
+    /// Make `\w`, `\d`, `\b` and case folding ASCII-only, as if every selected
+    /// pattern set `unicode: false`. Faster, and `\w` no longer matches `é`.
+    /// Patterns on the backtrack engine stay Unicode-aware.
+    #[arg(long)]
+    no_unicode: bool,
+
+    /// Include binary files in the search.
+    #[arg(long)]
+    include_bin: bool,
+
+    /// Skip files larger than SIZE, e.g. 10M. K, M and G are powers of 1024.
+    /// Overrides `max_filesize` in config.json.
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_filesize: Option<u64>,
+
+    /// Descend at most N directories below each searched path; 0 searches only
+    /// the path itself.
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Search hidden files and directories, such as `.env` or `.aws/`, but not
+    /// `.git`. The default when a pattern is tagged `secrets`.
+    #[arg(long)]
+    hidden: bool,
+
+    /// Skip hidden files and directories, even with patterns tagged `secrets`.
+    #[arg(long, conflicts_with = "hidden")]
+    no_hidden: bool,
+
+    /// Follow symbolic links. Links that loop back to a directory above them
+    /// are reported and not followed.
+    #[arg(short = 'L', long)]
+    follow_links: bool,
+
+    /// Don't descend into directories on other file systems than the searched
+    /// path, such as network mounts or /proc.
+    #[arg(long)]
+    one_file_system: bool,
+
+    /// Only search files matching GLOB, or with a leading `!`, skip them, e.g.
+    /// `-g 'src/**' -g '!**/test/**'`. Can be given multiple times; applies on
+    /// top of the `file_types` of patterns.
+    #[arg(short = 'g', long, value_name = "GLOB")]
+    glob: Vec<String>,
+
+    /// Like --glob, ignoring case.
+    #[arg(long, value_name = "GLOB")]
+    iglob: Vec<String>,
+
+    /// Only search files of TYPE, e.g. `js` or `docker`; see --type-list. Can be
+    /// given multiple times.
+    #[arg(short = 't', long = "type", value_name = "TYPE")]
+    file_type: Vec<String>,
+
+    /// Skip files of TYPE, e.g. `minified`. Can be given multiple times.
+    #[arg(short = 'T', long, value_name = "TYPE")]
+    type_not: Vec<String>,
+
+    /// Only search files of LANGUAGE, e.g. `javascript` or `shell`, like the
+    /// `languages` of patterns. Can be given multiple times.
+    #[arg(long = "lang", value_name = "LANGUAGE", value_parser = parse_language)]
+    lang: Vec<String>,
+
+    /// Define a file type for --type and --type-not, or add a glob to one, e.g.
+    /// `tf:*.tf`. Can be given multiple times.
+    #[arg(long, value_name = "NAME:GLOB")]
+    type_add: Vec<String>,
+
+    /// Also type files without an extension by their first line, e.g. a `#!`
+    /// line, so scripts in `bin/` are searched by patterns for `sh` or `py`.
+    #[arg(long)]
+    detect_types: bool,
+
+    /// List the file types with their globs, including those of --type-add, and
+    /// exit.
+    #[arg(long)]
+    type_list: bool,
+
+    /// Decode files in this encoding, e.g. utf-16le or latin1. `auto` follows a
+    /// byte-order mark and also spots UTF-16 without one; `none` searches raw bytes.
+    #[arg(
+        long,
+        value_name = "LABEL",
+        default_value = "auto",
+        value_parser = TextEncoding::parse
+    )]
+    encoding: TextEncoding,
+
+    /// Show NUM lines after each match.
+    #[arg(short = 'A', long, value_name = "NUM")]
+    after_context: Option<usize>,
+
+    /// Show NUM lines before each match.
+    #[arg(short = 'B', long, value_name = "NUM")]
+    before_context: Option<usize>,
+
+    /// Show NUM lines before and after each match.
+    #[arg(short = 'C', long, value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Print the number of matches in each file instead of the matches themselves.
+    #[arg(short = 'c', long, conflicts_with_all = ["json", "format", "files_with_matches"])]
+    count: bool,
+
+    /// Print only the paths of files with at least one match.
+    #[arg(short = 'l', long, conflicts_with_all = ["json", "format"])]
+    files_with_matches: bool,
+
+    /// Print only the matched parts of each line.
+    #[arg(short = 'o', long)]
+    only_matching: bool,
+
+    /// Replace each match with TEMPLATE, which may reference capture groups as `$1` or `${name}`.
+    #[arg(short = 'r', long, value_name = "TEMPLATE")]
+    replace: Option<String>,
+
+    /// Print lines that don't match any of the selected patterns.
+    #[arg(short = 'v', long, conflicts_with_all = ["json", "format", "only_matching", "replace"])]
+    invert_match: bool,
+
+    /// Search with EXPENSIVE only in files where the faster CHEAP pattern matches.
+    #[arg(
+        long,
+        value_name = "CHEAP:EXPENSIVE",
+        value_parser = parse_two_pass,
+        conflicts_with_all = ["tags", "author", "invert_match"]
+    )]
+    two_pass: Option<(String, String)>,
+
+    /// Suppress matches whose line was already reported for the same pattern from another file.
+    #[arg(long)]
+    dedupe_lines: bool,
+
+    /// Print each match as a JSON object, one per line. Shorthand for `--format json`.
+    #[arg(long, conflicts_with = "format")]
+    json: bool,
+
+    /// Output format for search results.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Send findings to SINK: terminal, jsonl[:PATH], sarif[:PATH] or webhook:URL.
+    /// Can be repeated to write several outputs from one scan.
+    #[arg(
+        long,
+        value_name = "SINK",
+        conflicts_with_all = [
+            "json", "format", "count", "files_with_matches", "invert_match",
+            "only_matching", "context", "before_context", "after_context",
+        ]
+    )]
+    sink: Vec<SinkSpec>,
+
+    /// Only use patterns of at least this severity.
+    #[arg(long, value_enum)]
+    min_severity: Option<Severity>,
+
+    /// Only use patterns with one of these comma-separated severities.
+    #[arg(long, value_enum, value_delimiter = ',')]
+    severity: Option<Vec<Severity>>,
+
+    /// Drop matches whose Shannon entropy is below BITS per character, overriding
+    /// the `min_entropy` of every pattern.
+    #[arg(long, value_name = "BITS", conflicts_with = "invert_match")]
+    min_entropy: Option<f64>,
+
+    /// Check matched credentials against their service, for patterns that describe
+    /// how. This sends the matched secrets over the network.
+    #[arg(long, conflicts_with_all = ["invert_match", "count", "files_with_matches"])]
+    verify: bool,
+
+    /// Seconds to wait for each verification request.
+    #[arg(long, value_name = "SECS", default_value_t = 10, requires = "verify")]
+    verify_timeout: u64,
+
+    /// Most verification requests in flight at once.
+    #[arg(long, value_name = "NUM", default_value_t = 8, requires = "verify")]
+    verify_concurrency: usize,
+
+    /// Mask matched secrets in the output, keeping only their first and last NUM characters.
+    #[arg(
+        long,
+        value_name = "NUM",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "4",
+        conflicts_with_all = [
+            "invert_match", "count", "files_with_matches", "only_matching",
+            "context", "before_context", "after_context",
+        ]
+    )]
+    redact: Option<usize>,
+
+    /// Record this scan and its findings in the findings database at PATH,
+    /// by default `findings.db` in gfr's data directory.
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = [
+            "invert_match", "count", "files_with_matches", "only_matching",
+            "context", "before_context", "after_context",
+        ]
+    )]
+    #[allow(clippy::option_option)] // Unset, `--record` and `--record=PATH` all differ.
+    record: Option<Option<PathBuf>>,
+
+    /// Also report findings marked as false positives or accepted with `gfr findings mark`.
+    #[arg(long)]
+    show_triaged: bool,
+
+    /// Keep watching the path after the search, rescanning files as they change and
+    /// printing only findings that weren't reported yet.
+    #[arg(long, conflicts_with_all = ["estimate", "verify", "badge", "write_baseline", "record"])]
+    watch: bool,
+
+    /// Keep the file open and search data appended to it as it arrives, following it
+    /// across log rotation like `tail -F`. Starts at the end of the file; line numbers
+    /// aren't shown.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "watch", "estimate", "verify", "badge", "write_baseline", "record",
+            "count", "files_with_matches", "context", "before_context", "after_context",
+        ]
+    )]
+    follow: bool,
+
+    /// Write a status badge for the scan to PATH: SVG, or a shields.io endpoint file if PATH ends in `.json`.
+    #[arg(long, value_name = "PATH")]
+    badge: Option<PathBuf>,
+
+    /// Skip reparse points (symlinks, junctions, cloud and archive placeholders) on Windows,
+    /// so scanning a share never triggers recalls from remote storage.
+    #[arg(long)]
+    skip_reparse_points: bool,
+
+    /// Only report findings that aren't in the baseline at PATH.
+    #[arg(long, value_name = "PATH", conflicts_with = "invert_match")]
+    baseline: Option<PathBuf>,
+
+    /// Record every finding of this scan as the baseline at PATH.
+    #[arg(long, value_name = "PATH", conflicts_with = "invert_match")]
+    write_baseline: Option<PathBuf>,
+
+    /// Read known false positives from PATH instead of `.gfrallow` in the current directory.
+    #[arg(long, value_name = "PATH", conflicts_with = "no_allow")]
+    allow_file: Option<PathBuf>,
+
+    /// Report everything, ignoring any allow-list.
+    #[arg(long)]
+    no_allow: bool,
+
+    /// Report matches even when a `gfr:ignore` comment covers them.
+    #[arg(long)]
+    no_inline_ignores: bool,
+
+    /// Report findings as they are, without passing them through the plugins of
+    /// `config.json` and the patterns.
+    #[arg(long)]
+    no_plugins: bool,
+
+    /// Have the `gfr daemon` listening on SOCKET, or on the default socket, run
+    /// the search with patterns it keeps compiled between searches.
+    #[arg(
+        long,
+        value_name = "SOCKET",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = [
+            "dump", "estimate", "count", "files_with_matches", "only_matching", "replace",
+            "invert_match", "two_pass", "dedupe_lines", "verify", "watch", "follow",
+            "badge", "write_baseline", "context", "before_context", "after_context", "index",
+            // The daemon walks with its own threads and settings.
+            "skip_reparse_points", "threads", "no_parallel", "tune",
+        ]
+    )]
+    #[allow(clippy::option_option)] // Unset, `--daemon` and `--daemon=SOCKET` all differ.
+    daemon: Option<Option<PathBuf>>,
+
+    /// Only read files that the index built by `gfr index-build` shows may match,
+    /// using the index at PATH or the one built for the searched directory or a
+    /// directory above it. Files changed since the index was built are always read.
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = ["follow", "estimate", "invert_match"]
+    )]
+    #[allow(clippy::option_option)] // Unset, `--index` and `--index=PATH` all differ.
+    index: Option<Option<PathBuf>>,
+
+    /// Pick thread count and memory map usage from the machine and target ("off" keeps the defaults).
+    #[arg(long, value_enum, default_value_t = TuneMode::Auto)]
+    tune: TuneMode,
+
+    /// Search with N threads, instead of the number --tune picks.
+    #[arg(short = 'j', long, value_name = "N")]
+    threads: Option<NonZeroUsize>,
+
+    /// Search one file at a time on a single thread, in file name order, so runs
+    /// print the same output in the same order.
+    #[arg(long, conflicts_with = "threads")]
+    no_parallel: bool,
+}
+
+impl SearchArgs {
+    /// Resolves the effective output format, honoring the `--json` shorthand.
+    fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.format
+        }
+    }
+
+    /// The option of this search that needs the network, if any.
+    fn network_feature(&self) -> Option<&'static str> {
+        if self.verify {
+            return Some("--verify");
+        }
+        self.sink
+            .iter()
+            .any(|sink: &SinkSpec| matches!(sink, SinkSpec::Webhook(_)))
+            .then_some("A webhook sink")
+    }
+
+    /// Resolves where findings go. Without `--sink`, structured formats get a
+    /// stdout sink, and text output (an empty list) is left to the grep-style printers.
+    /// `--record` adds the findings database to either.
+    fn sink_specs(&self) -> Result<Vec<SinkSpec>> {
+        let mut specs: Vec<SinkSpec> = if self.sink.is_empty() {
+            match self.output_format() {
+                // Verification outcomes, redaction and recording apply to findings,
+                // which the text printers don't see.
+                OutputFormat::Text
+                    if self.verify || self.redact.is_some() || self.record.is_some() =>
+                {
+                    vec![SinkSpec::Terminal]
+                }
+                OutputFormat::Text => Vec::new(),
+                OutputFormat::Json => vec![SinkSpec::JsonLines(None)],
+                OutputFormat::Sarif => vec![SinkSpec::Sarif(None)],
+            }
+        } else {
+            self.sink.clone()
+        };
+        if self.record.is_some() {
+            let database: PathBuf = self.findings_db()?;
+            // Scans of the same directory are recorded under one target however it was named.
+            let target: String = if io::stdin().is_terminal() {
+                fs::canonicalize(&self.path)
+                    .unwrap_or_else(|_| self.path.clone())
+                    .display()
+                    .to_string()
+            } else {
+                STDIN_LABEL.to_string()
+            };
+            specs.push(SinkSpec::Record { database, target });
+        }
+        Ok(specs)
+    }
+
+    /// The findings database: the one named by `--record=PATH`, or the default.
+    fn findings_db(&self) -> Result<PathBuf> {
+        match &self.record {
+            Some(Some(path)) => Ok(path.clone()),
+            _ => store::default_path(),
+        }
+    }
+
+    /// Statuses set with `gfr findings mark`, or none before anything was recorded.
+    fn triaged_statuses(&self) -> Result<HashMap<String, FindingStatus>> {
+        let database: PathBuf = self.findings_db()?;
+        if !database.exists() {
+            return Ok(HashMap::new());
+        }
+        FindingsStore::open(&database)?.statuses()
+    }
+
+    /// When flags select the patterns (`--tags`, `--author`, `--bundle`,
+    /// `--pattern-file` or `--two-pass`), a single positional argument is the path
+    /// to search rather than a pattern name.
+    fn normalize(mut self) -> Result<Self> {
+        let selected_by_flags: bool = self.tags.is_some()
+            || self.author.is_some()
+            || self.bundle.is_some()
+            || !self.patterns.is_empty()
+            || !self.pattern_file.is_empty()
+            || self.two_pass.is_some();
+        if selected_by_flags && self.path == Path::new(".") {
+            if let Some(name) = self.pattern_name.take() {
+                self.path = PathBuf::from(name);
+            }
+        }
+        let named: bool = self.pattern_name.is_some() || !self.patterns.is_empty();
+        if self.two_pass.is_some() && named {
+            return Err(anyhow!(
+                "--two-pass names its own patterns; don't pass a pattern name as well."
+            ));
+        }
+        if self.bundle.is_some() && named {
+            return Err(anyhow!(
+                "--bundle selects its own patterns; don't pass a pattern name as well."
+            ));
+        }
+        Ok(self)
+    }
+
+    /// The patterns named by the positional argument and `-p`, without repeats.
+    fn pattern_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        let positional = self
+            .pattern_name
+            .iter()
+            .flat_map(|list: &String| list.split(','));
+        for name in positional.chain(self.patterns.iter().map(String::as_str)) {
+            let name: &str = name.trim();
+            if !name.is_empty() && !names.iter().any(|n: &String| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// Checks a pattern's severity against `--min-severity` and `--severity`.
+    fn allows_severity(&self, severity: Severity) -> bool {
+        self.min_severity
+            .is_none_or(|min: Severity| severity >= min)
+            && self
+                .severity
+                .as_ref()
+                .is_none_or(|wanted: &Vec<Severity>| wanted.contains(&severity))
+    }
+
+    /// Returns the summary printer mode requested by `--count` or `--files-with-matches`.
+    fn summary_kind(&self) -> Option<SummaryKind> {
+        if self.count {
+            Some(SummaryKind::CountMatches)
+        } else if self.files_with_matches {
+            Some(SummaryKind::PathWithMatch)
+        } else {
+            None
+        }
+    }
+
+    /// The file types the walk is limited to: those of --type and of --lang.
+    fn selected_types(&self) -> Vec<String> {
+        self.file_type.iter().chain(&self.lang).cloned().collect()
+    }
+
+    /// Whether to search hidden files: as `--hidden` or `--no-hidden` say, or else
+    /// when one of `patterns` looks for secrets, which dotfiles are full of.
+    fn search_hidden(&self, patterns: &[Pattern]) -> bool {
+        if self.hidden || self.no_hidden {
+            return self.hidden;
+        }
+        patterns.iter().any(|p: &Pattern| {
+            p.tags
+                .as_ref()
+                .is_some_and(|tags: &Vec<String>| tags.iter().any(|tag: &String| tag == "secrets"))
+        })
+    }
+
+    /// The largest file to search, from `--max-filesize` or else `config.json`.
+    fn max_filesize(&self) -> Result<Option<u64>> {
+        if self.max_filesize.is_some() {
+            return Ok(self.max_filesize);
+        }
+        load_user_config()?
+            .max_filesize
+            .as_deref()
+            .map(parse_size)
+            .transpose()
+            .map_err(|e: String| anyhow!("Invalid max_filesize in config.json: {e}"))
+    }
+
+    /// The number of search threads set with `-j` or `--no-parallel`, if any.
+    fn thread_count(&self) -> Option<usize> {
+        if self.no_parallel {
+            Some(1)
+        } else {
+            self.threads.map(NonZeroUsize::get)
+        }
+    }
+
+    /// The walker and searcher settings for `root`, with the threads of `-j` or
+    /// `--no-parallel` over the tuned ones.
+    fn tuning(&self, root: &Path) -> Tuning {
+        let mut tuning: Tuning = match self.tune {
+            TuneMode::Auto => Tuning::auto(root),
+            TuneMode::Off => Tuning::manual(),
+        };
+        tuning.threads = self.thread_count().unwrap_or(tuning.threads);
+        tuning
+    }
+
+    /// The walk of `root` these arguments ask for, the way `gfr_core` walks, on
+    /// as many threads as `tuning` says. Below `root`, it only enters the entries
+    /// `keep` accepts too.
+    fn walk_builder(
+        &self,
+        root: &Path,
+        tuning: &Tuning,
+        hidden: bool,
+        keep: impl Fn(&ignore::DirEntry) -> bool + Send + Sync + 'static,
+    ) -> Result<WalkBuilder> {
+        let skip_reparse_points: bool = self.skip_reparse_points;
+        let mut walk_builder: WalkBuilder = WalkOptions {
+            max_filesize: self.max_filesize()?,
+            max_depth: self.max_depth,
+            hidden,
+            follow_links: self.follow_links,
+            same_file_system: self.one_file_system,
+            globs: self.glob.clone(),
+            iglobs: self.iglob.clone(),
+            types: filetype::walk_types(&self.type_add, &self.selected_types(), &self.type_not)?,
+        }
+        .walk_builder(root, move |entry: &ignore::DirEntry| {
+            !(skip_reparse_points && winpath::is_reparse_point(entry)) && keep(entry)
+        })?;
+        walk_builder.threads(tuning.threads);
+        if self.no_parallel {
+            walk_builder.sort_by_file_name(|a: &OsStr, b: &OsStr| a.cmp(b));
+        }
+        Ok(walk_builder)
+    }
+
+    /// Returns the number of (before, after) context lines; `-A`/`-B` take precedence over `-C`.
+    fn context_lines(&self) -> (usize, usize) {
+        let context: usize = self.context.unwrap_or(0);
+        (
+            self.before_context.unwrap_or(context),
+            self.after_context.unwrap_or(context),
+        )
+    }
+}
+
+/// Parses a language into the built-in file type of its files, see
+/// [`filetype::language_type`].
+fn parse_language(value: &str) -> std::result::Result<String, String> {
+    filetype::require_language_type(value)
+        .map(ToString::to_string)
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Parses a file size: a number of bytes, optionally followed by K, M or G
+/// (with or without a trailing B) for KiB, MiB or GiB.
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let value: &str = value.trim();
+    let digits: usize = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit): (&str, &str) = value.split_at(digits);
+    let unit_upper: String = unit.trim().to_ascii_uppercase();
+    let shift: u32 = match unit_upper.strip_suffix('B').unwrap_or(&unit_upper) {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        _ => return Err(format!("unknown size unit '{unit}', expected K, M or G")),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n: u64| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("expected a size like 512K or 10M, not '{value}'"))
+}
+
+/// Parses `--two-pass CHEAP:EXPENSIVE` into its two pattern names.
+fn parse_two_pass(value: &str) -> std::result::Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((cheap, expensive)) if !cheap.is_empty() && !expensive.is_empty() => {
+            Ok((cheap.to_string(), expensive.to_string()))
+        }
+        _ => Err("expected two pattern names as CHEAP:EXPENSIVE".to_string()),
+    }
+}
+
+/// Whether search settings are tuned automatically.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum TuneMode {
+    /// Adapt to CPU count, file sizes and network filesystems.
+    Auto,
+    /// Use the walker and searcher defaults.
+    Off,
+}
+
+/// Supported output formats for `gfr search`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable, grep-style output.
+    Text,
+    /// One JSON object per match.
+    Json,
+    /// A SARIF 2.1.0 log for code scanning tools.
+    Sarif,
+}
+
+#[derive(Parser, Debug)]
+pub struct RemoveArgs {
+    /// Names of the patterns to remove.
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    names: Vec<String>,
+    /// Remove every local pattern.
+    #[arg(long)]
+    all: bool,
+    /// Skip the confirmation prompt.
+    #[arg(long, short = 'y')]
+    yes: bool,
+    /// Don't fail on names that aren't installed; drop any manifest entries left for them.
+    #[arg(long, short = 'f')]
+    force: bool,
 }
 
 #[derive(Parser, Debug)]
+#[allow(clippy::struct_excessive_bools)] // Each bool is an independent command-line flag.
 pub struct SaveArgs {
     /// The name for the new pattern (e.g., "xss").
     name: String,
-    /// The regular expression to search for.
-    pattern: String,
+    /// The regular expression to search for. Several are saved as `patterns`.
+    #[arg(required = true)]
+    patterns: Vec<String>,
+    /// Save the patterns as literal `strings`, matched exactly rather than as regexes.
+    #[arg(long, short = 'F')]
+    fixed_strings: bool,
+    /// Comma-separated words a line must contain for the pattern to be run on it.
+    #[arg(long, short = 'k', value_delimiter = ',')]
+    keywords: Option<Vec<String>>,
     /// Description of what the pattern finds.
     #[arg(long, short)]
     description: Option<String>,
     /// List of case-insensitive file extensions to search (e.g., "js,html,ts").
     #[arg(long, short, value_delimiter = ',')]
     file_types: Option<Vec<String>>,
+    /// File types never to search, e.g. generated files (e.g., "min.js,lock,map").
+    #[arg(long, value_delimiter = ',')]
+    exclude_file_types: Option<Vec<String>>,
+    /// Languages to search the files of (e.g., "javascript,typescript").
+    #[arg(long, value_delimiter = ',')]
+    languages: Option<Vec<String>>,
+    /// Only search files whose path below the searched directory matches this
+    /// regex (e.g., "^(config|deploy)/"). Can be repeated.
+    #[arg(long = "path", value_name = "REGEX")]
+    paths: Option<Vec<String>>,
+    /// Never search files whose path below the searched directory matches this
+    /// regex. Can be repeated.
+    #[arg(long = "path-exclude", value_name = "REGEX")]
+    paths_exclude: Option<Vec<String>>,
     /// Make the search case-insensitive.
     #[arg(long, short = 'i')]
     ignore_case: bool,
     /// Enable multi-line searching (dot matches newline).
     #[arg(long, short = 'm')]
     multiline: bool,
+    /// Only match whole words.
+    #[arg(long, short = 'w')]
+    word: bool,
+    /// Make `\w`, `\d`, `\b` and case folding ASCII-only.
+    #[arg(long)]
+    no_unicode: bool,
+    /// Regex engine to run the pattern on, e.g. `backtrack` for lookaround.
+    #[arg(long, value_enum)]
+    engine: Option<Engine>,
     /// The author of the pattern.
     #[arg(long, short = 'a')]
     author: Option<String>,
+    /// SPDX license identifier of the pattern (e.g., "MIT").
+    #[arg(long, short = 'l')]
+    license: Option<String>,
     /// Comma-separated tags for categorization.
     #[arg(long, short = 't', value_delimiter = ',')]
     tags: Option<Vec<String>>,
-}
-
-/// Represents a search pattern configuration loaded from a JSON file.
-#[derive(Debug, Clone, Deserialize, Serialize)]
-#[serde(deny_unknown_fields)]
-struct Pattern {
-    /// JSON Schema reference (optional, for validation support).
-    #[serde(rename = "$schema")]
-    schema: Option<String>,
-    /// Semantic version of the pattern file.
-    #[serde(default = "default_version")]
-    version: String,
-    /// Author of the pattern.
-    author: Option<String>,
-    /// A brief explanation of the pattern.
-    description: Option<String>,
-    /// A list of tags for categorization.
-    tags: Option<Vec<String>>,
-    /// A single regular expression. Use this or `regex_list`.
-    #[serde(rename = "pattern")]
-    regex: Option<String>,
-    /// A list of regular expressions. These will be combined into a single pattern.
-    #[serde(rename = "patterns")]
-    regex_list: Option<Vec<String>>,
-    /// A list of file extensions to specifically include in the search.
-    file_types: Option<Vec<String>>,
-    /// If true, the search will be case-insensitive.
-    #[serde(default)]
-    ignore_case: bool,
-    /// If true, enables multi-line searching.
-    #[serde(default)]
-    multiline: bool,
-}
-
-impl Pattern {
-    /// Combines `regex` and `regex_list` fields into a single regex string.
-    /// The patterns are joined with `|` to create a single regex.
-    fn get_raw_pattern(&self) -> Result<String> {
-        match (&self.regex, &self.regex_list) {
-            (Some(p), None) => Ok(p.clone()),
-            (None, Some(ps)) if !ps.is_empty() => Ok(format!("(?:{})", ps.join("|"))),
-            _ => Err(anyhow!(
-                "Pattern file must contain either a 'pattern' key or a non-empty 'patterns' key."
-            )),
-        }
-    }
+    /// How serious a match of the pattern is.
+    #[arg(long, short = 's', value_enum)]
+    severity: Option<Severity>,
+    /// File format to write the pattern in.
+    #[arg(long, value_enum, default_value_t = PatternFormat::Json)]
+    format: PatternFormat,
 }
 
 /// Represents the remote index file for installable patterns.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 struct Index {
+    /// Directory the patterns are installed into, making their full names
+    /// `namespace/name`. Derived from the index URL when not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    namespace: Option<String>,
     patterns: Vec<IndexPattern>,
+    /// Curated sets of pattern names, installed and searched with `--bundle`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    bundles: Bundles,
 }
 
-/// Represents a single pattern entry in the remote index.
-#[derive(Debug, Deserialize)]
-struct IndexPattern {
-    name: String,
-    version: String,
-    url: String,
-}
+/// Bundle names mapped to the names of the patterns they contain.
+type Bundles = BTreeMap<String, Vec<String>>;
 
-/// Represents the local manifest of installed patterns and their versions.
-type InstalledManifest = HashMap<String, String>;
+/// Names an index file in a directory is looked up by, in order.
+const INDEX_FILES: [&str; 4] = ["index.json", "index.yaml", "index.yml", "index.toml"];
 
-/// Default version for a new pattern.
-fn default_version() -> String {
-    "1.0.0".to_string()
+/// Formats an index can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum IndexFormat {
+    Json,
+    Yaml,
+    Toml,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let cli = Cli::parse();
-    let styles = Styles::new();
-
-    // The `if let Err` block handles all errors propagated with `?` from the subcommands.
-    if let Err(e) = run_command(cli.command, &styles).await {
-        eprintln!("{} {:#}", "Error:".style(styles.error), e);
-        exit(1);
+impl IndexFormat {
+    /// The format of the index at `location`: the one its extension names, else
+    /// JSON if `text` starts with `{`, TOML if it starts with a table header or
+    /// `key = value`, and YAML otherwise.
+    fn detect(location: &str, text: &str) -> Self {
+        let path: &str = location.split(['?', '#']).next().unwrap_or(location);
+        let extension: Option<String> = Path::new(path)
+            .extension()
+            .map(|e: &std::ffi::OsStr| e.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("json") => return Self::Json,
+            Some("yaml" | "yml") => return Self::Yaml,
+            Some("toml") => return Self::Toml,
+            _ => {}
+        }
+        let first: &str = text
+            .lines()
+            .map(str::trim)
+            .find(|line: &&str| !line.is_empty() && !line.starts_with('#'))
+            .unwrap_or_default();
+        let key: &str = first.split([':', '=']).next().unwrap_or_default();
+        if first.starts_with('{') {
+            Self::Json
+        } else if first.starts_with('[') || first[key.len()..].starts_with('=') {
+            Self::Toml
+        } else {
+            Self::Yaml
+        }
     }
-
-    Ok(())
 }
 
-/// Dispatches the appropriate function based on the parsed command.
-async fn run_command(command: Commands, styles: &Styles) -> Result<()> {
-    match command {
-        Commands::Search {
-            pattern_name,
-            path,
-            dump,
-            tags,
-            author,
-            include_bin,
-        } => {
-            if dump {
-                // Dump only supports a single pattern name for clarity.
-                let name_to_dump = pattern_name.ok_or_else(|| {
-                    anyhow!("--dump requires a single pattern_name to be specified.")
-                })?;
-                run_dump(&name_to_dump, styles)
+impl Index {
+    /// The namespace patterns from the index at `url` are installed into: the one
+    /// the index declares, else the owner of a GitHub or GitLab URL or `github:`
+    /// location, else its host, or for an index on disk, the name of its directory.
+    fn namespace(&self, url: &str) -> Result<String> {
+        let namespace: String = if let Some(namespace) = &self.namespace {
+            namespace.clone()
+        } else if let Some(github) = GitHubLocation::parse(url) {
+            github.owner
+        } else if !is_remote(url) {
+            // A directory of patterns, or the one an index file sits in.
+            let path: PathBuf = fs::canonicalize(url)
+                .with_context(|| format!("Can't derive a namespace from '{url}'"))?;
+            let dir: &Path = if path.is_dir() {
+                &path
             } else {
-                run_search(
-                    pattern_name,
-                    tags.as_deref(),
-                    author.as_deref(),
-                    &path,
-                    include_bin,
-                    styles,
-                )
-            }
+                path.parent().unwrap_or(&path)
+            };
+            dir.file_name()
+                .and_then(std::ffi::OsStr::to_str)
+                .unwrap_or_default()
+                .to_string()
+        } else {
+            let parsed: reqwest::Url = reqwest::Url::parse(url)
+                .with_context(|| format!("Can't derive a namespace from '{url}'"))?;
+            let host: &str = parsed.host_str().unwrap_or_default();
+            let owner: Option<&str> = parsed
+                .path_segments()
+                .and_then(|mut segments: std::str::Split<'_, char>| segments.next())
+                .filter(|owner: &&str| !owner.is_empty())
+                .filter(|_: &&str| {
+                    matches!(
+                        host,
+                        "raw.githubusercontent.com" | "github.com" | "gitlab.com"
+                    )
+                });
+            owner.unwrap_or(host).to_string()
+        };
+        let reserved: bool = namespace.is_empty()
+            || namespace.starts_with('.')
+            || namespace.contains(['/', '\\'])
+            || namespace == collections::COLLECTIONS_DIR;
+        if reserved {
+            return Err(anyhow!("'{namespace}' can't be used as a namespace."));
         }
-        Commands::List => run_list(styles),
-        Commands::Install { url } => run_install(&url, styles).await,
-        Commands::Save(args) => run_save(args, styles),
+        Ok(namespace)
     }
-}
 
-/// Executes the search operation based on provided filters.
-#[allow(clippy::too_many_lines)] // This function orchestrates the entire search logic.
-fn run_search(
-    pattern_name: Option<String>,
-    tags: Option<&[String]>,
-    author: Option<&str>,
-    path: &Path,
-    include_bin: bool,
-    styles: &Styles,
-) -> Result<()> {
-    // At least one filter must be provided to know what to search for.
-    if pattern_name.is_none() && tags.is_none() && author.is_none() {
-        return Err(anyhow!(
-            "Search requires a filter. Please provide a pattern name, --tags, or --author."
-        ));
+    /// Rejects pattern names that aren't plain file names, for each is installed
+    /// as `<namespace>/<name>.json` and an index may come from anyone.
+    fn check_names(&self) -> Result<()> {
+        for pattern in &self.patterns {
+            let name: &str = &pattern.name;
+            if !is_plain_name(name) {
+                return Err(anyhow!(
+                    "The index has a pattern named '{name}'; names can't be empty, start with '.' or contain '/', '\\' or '..'."
+                ));
+            }
+        }
+        Ok(())
     }
 
-    // Prevent conflicting usage: pattern name should not be combined with filters.
-    if pattern_name.is_some() && (tags.is_some() || author.is_some()) {
-        return Err(anyhow!(
-            "Cannot combine pattern name with --tags or --author filters. Use either a specific pattern name OR filters, not both."
-        ));
+    /// Parses an index in the format `location`'s extension names, or for other
+    /// locations, the format its contents look like.
+    fn parse(location: &str, body: &[u8]) -> Result<Self> {
+        let text: &str = std::str::from_utf8(body).context("The index is not UTF-8")?;
+        Ok(match IndexFormat::detect(location, text) {
+            IndexFormat::Json => serde_json::from_str(text)?,
+            IndexFormat::Yaml => serde_yaml::from_str(text)?,
+            IndexFormat::Toml => toml::from_str(text)?,
+        })
     }
 
-    let patterns_to_search = find_patterns_by_filter(pattern_name, tags, author, styles)?;
-    println!(
-        "{} {} patterns on path '{}'...",
-        "Searching with".style(styles.dim),
-        patterns_to_search.len().to_string().style(styles.highlight),
-        path.display().style(styles.highlight)
-    );
-
-    // --- Aggregate all patterns into a single configuration ---
-    let mut all_regexes: Vec<String> = Vec::new();
-    let mut all_file_types: HashSet<String> = HashSet::new();
-    let mut combined_ignore_case: bool = false;
-    let mut combined_multiline: bool = false;
+    /// The index file of a directory, if it has one.
+    fn file_in(dir: &Path) -> Option<PathBuf> {
+        INDEX_FILES
+            .iter()
+            .map(|name: &&str| dir.join(name))
+            .find(|path: &PathBuf| path.is_file())
+    }
 
-    for p in &patterns_to_search {
-        all_regexes.push(p.get_raw_pattern()?);
-        if let Some(fts) = &p.file_types {
-            all_file_types.extend(fts.iter().cloned());
+    /// An index of the pattern and collection files in `dir`, named after the files
+    /// and versioned by their `version` field.
+    fn from_directory(dir: &Path) -> Result<Self> {
+        let mut names: Vec<String> = file_stems(dir, &["json"])?;
+        names.sort();
+        let mut patterns: Vec<IndexPattern> = Vec::new();
+        for name in names {
+            let path: PathBuf = dir.join(format!("{name}.json"));
+            let json: serde_json::Value =
+                serde_json::from_str(&jsonc::strip(&fs::read_to_string(&path)?))
+                    .with_context(|| format!("Failed to parse JSON from: {}", path.display()))?;
+            let version: String = json
+                .get("version")
+                .and_then(serde_json::Value::as_str)
+                .map_or_else(default_version, str::to_string);
+            patterns.push(IndexPattern {
+                deprecated: json
+                    .get("deprecated")
+                    .and_then(serde_json::Value::as_bool)
+                    .unwrap_or_default(),
+                superseded_by: json
+                    .get("superseded_by")
+                    .and_then(serde_json::Value::as_str)
+                    .map(str::to_string),
+                name,
+                version,
+                signature: Some(format!("{}.minisig", path.display()))
+                    .filter(|signature: &String| Path::new(signature).is_file()),
+                url: path.display().to_string(),
+                license: None,
+                sha256: None,
+            });
         }
-        combined_ignore_case |= p.ignore_case;
-        combined_multiline |= p.multiline;
+        Ok(Self {
+            namespace: None,
+            patterns,
+            bundles: Bundles::new(),
+        })
     }
 
-    let mut flags: String = String::new();
-    if combined_ignore_case {
-        flags.push('i');
+    /// Rewrites the file and signature locations `resolve` returns a new one for.
+    fn resolve_locations(&mut self, resolve: impl Fn(&str) -> Option<String>) {
+        for p in &mut self.patterns {
+            if let Some(url) = resolve(&p.url) {
+                p.url = url;
+            }
+            if let Some(signature) = p.signature.as_mut() {
+                if let Some(resolved) = resolve(signature) {
+                    *signature = resolved;
+                }
+            }
+        }
     }
-    if combined_multiline {
-        flags.push('s');
+
+    /// Whether the index has a pattern by this short or full name.
+    fn has_pattern(&self, name: &str) -> bool {
+        let short: &str = name.rsplit_once('/').map_or(name, |(_, s): (&str, &str)| s);
+        self.patterns.iter().any(|p: &IndexPattern| p.name == short)
     }
-    let patterns_combined: String = all_regexes.join("|");
-    let final_pattern: String = if flags.is_empty() {
-        patterns_combined
-    } else {
-        format!("(?{flags}){patterns_combined}")
-    };
 
-    let matcher: grep_regex::RegexMatcher = RegexMatcherBuilder::new()
-        .line_terminator(Some(b'\n'))
-        .build(&final_pattern)?;
+    /// Whether the index has a bundle, or a collection file, by this name.
+    fn has_bundle(&self, bundle: &str) -> bool {
+        self.bundles.contains_key(bundle)
+            || self
+                .patterns
+                .iter()
+                .any(|p: &IndexPattern| p.name == bundle)
+    }
 
-    // --- Execute Search ---
-    if io::stdin().is_terminal() {
-        // Search the file system.
-        let mut walk_builder: WalkBuilder = WalkBuilder::new(path);
-        walk_builder.add_custom_ignore_filename(".gfrignore");
+    /// Narrows the index down to the named patterns, which may be given by their
+    /// full name in this index's namespace too.
+    fn retain_names(&mut self, names: &[String]) -> Result<()> {
+        let short: Vec<&str> = names
+            .iter()
+            .map(|name: &String| {
+                name.rsplit_once('/')
+                    .map_or(name.as_str(), |(_, s): (&str, &str)| s)
+            })
+            .collect();
+        let missing: Vec<&str> = short
+            .iter()
+            .copied()
+            .filter(|name: &&str| !self.patterns.iter().any(|p: &IndexPattern| p.name == *name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "The index has no pattern named {}.",
+                missing.join(", ")
+            ));
+        }
+        self.patterns
+            .retain(|p: &IndexPattern| short.contains(&p.name.as_str()));
+        Ok(())
+    }
 
-        if !all_file_types.is_empty() && !include_bin {
-            let mut override_builder: ignore::overrides::OverrideBuilder =
-                ignore::overrides::OverrideBuilder::new(path);
-            for ft in &all_file_types {
-                override_builder.add(&format!("*.{ft}"))?;
+    /// Keeps one entry per pattern of an index that lists several versions of it:
+    /// the version `requested` asks for, else the newest.
+    fn select_versions(&mut self, requested: &Requested) -> Result<()> {
+        let mut chosen: HashMap<String, usize> = HashMap::new();
+        for (position, p) in self.patterns.iter().enumerate() {
+            let pin: Option<&str> = requested.get(&p.name).and_then(Option::as_deref);
+            let better: bool = match (pin, chosen.get(&p.name)) {
+                (Some(pin), _) => p.version == pin,
+                (None, None) => true,
+                (None, Some(&other)) => {
+                    let newest: Option<Version> =
+                        Version::parse(&self.patterns[other].version).ok();
+                    Version::parse(&p.version)
+                        .is_ok_and(|v: Version| newest.is_none_or(|n: Version| v > n))
+                }
+            };
+            if better {
+                chosen.insert(p.name.clone(), position);
+            }
+        }
+        for (name, version) in requested {
+            if let (Some(version), false) = (version, chosen.contains_key(name)) {
+                let available: Vec<&str> = self
+                    .patterns
+                    .iter()
+                    .filter(|p: &&IndexPattern| &p.name == name)
+                    .map(|p: &IndexPattern| p.version.as_str())
+                    .collect();
+                if !available.is_empty() {
+                    return Err(anyhow!(
+                        "The index has no version {version} of '{name}'. Available: {}",
+                        available.join(", ")
+                    ));
+                }
             }
-            let overrides: ignore::overrides::Override = override_builder.build()?;
-            walk_builder.overrides(overrides);
         }
+        let mut position: usize = 0;
+        self.patterns.retain(|p: &IndexPattern| {
+            position += 1;
+            chosen.get(&p.name) == Some(&(position - 1))
+        });
+        Ok(())
+    }
 
-        walk_builder.build_parallel().run(|| {
-            let matcher: grep_regex::RegexMatcher = matcher.clone();
-            let mut searcher: Searcher = SearcherBuilder::new()
-                .binary_detection(if include_bin {
-                    // This disables binary detection, treating all files as text.
-                    BinaryDetection::none()
+    /// Deprecated patterns of the index that are installed, by manifest key, with
+    /// the full name of what supersedes them.
+    fn retired(
+        &self,
+        namespace: &str,
+        manifest: &InstalledManifest,
+    ) -> Vec<(String, Option<String>)> {
+        self.patterns
+            .iter()
+            .filter(|p: &&IndexPattern| p.deprecated)
+            .filter_map(|p: &IndexPattern| {
+                let key: String = [qualify(namespace, &p.name), p.name.clone()]
+                    .into_iter()
+                    .find(|key: &String| manifest.contains_key(key))?;
+                let replacement: Option<String> = p
+                    .superseded_by
+                    .as_ref()
+                    .map(|s: &String| qualify(namespace, s));
+                Some((key, replacement))
+            })
+            .collect()
+    }
+
+    /// Narrows the index down to the patterns of `bundle`, and the collection file
+    /// that defines it if the index ships one.
+    fn retain_bundle(&mut self, bundle: &str) -> Result<()> {
+        let members: &Vec<String> = self.bundles.get(bundle).ok_or_else(|| {
+            let available: Vec<&str> = self.bundles.keys().map(String::as_str).collect();
+            anyhow!(
+                "The index has no bundle '{bundle}'. Available bundles: {}",
+                if available.is_empty() {
+                    "none".to_string()
                 } else {
-                    // This is the default behavior: skip binary files.
-                    BinaryDetection::quit(b'\x00')
-                })
-                .build();
-            let mut printer: grep_printer::Standard<StandardStream> = StandardBuilder::new()
-                .color_specs(get_color_specs())
-                .build(StandardStream::stdout(get_color_choice()));
-
-            Box::new(
-                move |result: std::result::Result<ignore::DirEntry, ignore::Error>| {
-                    let entry: ignore::DirEntry = match result {
-                        Ok(entry) => entry,
-                        Err(err) => {
-                            eprintln!("{} {}", "Error:".style(styles.error), err);
-                            return WalkState::Continue;
+                    available.join(", ")
+                }
+            )
+        })?;
+        if let Some(missing) = members.iter().find(|name: &&String| {
+            !self
+                .patterns
+                .iter()
+                .any(|p: &IndexPattern| &&p.name == name)
+        }) {
+            return Err(anyhow!(
+                "Bundle '{bundle}' lists '{missing}', which isn't in the index."
+            ));
+        }
+        let members: Vec<String> = members.clone();
+        self.patterns
+            .retain(|p: &IndexPattern| members.contains(&p.name) || p.name == bundle);
+        Ok(())
+    }
+}
+
+/// Represents a single pattern entry in the remote index.
+#[derive(Debug, Deserialize, Serialize)]
+struct IndexPattern {
+    name: String,
+    version: String,
+    url: String,
+    /// SPDX license identifier, checked against the license policy before downloading.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    license: Option<String>,
+    /// Hex-encoded SHA-256 of the pattern file, used to detect content changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    /// URL or path of a detached minisign signature of the pattern file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    /// Retired; `gfr update --migrate` replaces it with `superseded_by`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    deprecated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    superseded_by: Option<String>,
+}
+
+/// How a pattern in the remote index compares to the locally installed copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PatternStatus {
+    NotInstalled,
+    /// The index has a newer version.
+    Outdated,
+    /// Same version, but the index checksum differs from the installed file.
+    ContentChanged,
+    /// An older version than the installed one was asked for.
+    Downgrade,
+    UpToDate,
+}
+
+impl IndexPattern {
+    fn status(&self, local: Option<&InstalledPattern>) -> Result<PatternStatus> {
+        let Some(local) = local else {
+            return Ok(PatternStatus::NotInstalled);
+        };
+        let remote_version: Version = Version::parse(&self.version)?;
+        if Version::parse(&local.version).map_or(true, |v: Version| v < remote_version) {
+            return Ok(PatternStatus::Outdated);
+        }
+        // Only trust a checksum comparison when both sides have one.
+        match (&local.sha256, &self.sha256) {
+            (Some(a), Some(b)) if !a.eq_ignore_ascii_case(b) => Ok(PatternStatus::ContentChanged),
+            _ => Ok(PatternStatus::UpToDate),
+        }
+    }
+}
+
+/// Represents the local manifest of installed patterns and their versions.
+type InstalledManifest = HashMap<String, InstalledPattern>;
+
+/// A single entry in the installed manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ManifestEntry")]
+struct InstalledPattern {
+    version: String,
+    /// Hex-encoded SHA-256 of the pattern file as downloaded.
+    sha256: Option<String>,
+    /// URL of the index the pattern was installed from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    /// Name of the configured registry that index belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    registry: Option<String>,
+    /// URL or path of the pattern file itself, for `gfr lock`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    /// Hex-encoded SHA-256 of the pattern file as written to disk, to notice local edits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_sha256: Option<String>,
+    /// Installed as `name@version` or rolled back; updates leave it alone.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pinned: bool,
+    /// Its signature was checked against `trusted_keys.pub` when it was installed.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    signed: bool,
+    /// URL or path of that signature, for `gfr lock`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+}
+
+impl InstalledPattern {
+    /// Whether the file was put in place by hand rather than downloaded, from an
+    /// index or a lockfile.
+    fn is_local(&self) -> bool {
+        self.source.is_none() && self.url.is_none()
+    }
+
+    /// Whether the pattern is trusted to say where `--verify` sends its matches:
+    /// it is local, or its signature was checked.
+    fn is_trusted(&self) -> bool {
+        self.is_local() || self.signed
+    }
+
+    /// Whether the file at `path` was edited since gfr wrote it. Entries from before
+    /// gfr recorded this can't tell and count as unmodified.
+    fn is_modified(&self, path: &Path) -> bool {
+        self.local_sha256.as_deref().is_some_and(|expected: &str| {
+            file_sha256(path).is_some_and(|actual: String| !actual.eq_ignore_ascii_case(expected))
+        })
+    }
+}
+
+/// On-disk forms of a manifest entry. Older versions of gfr stored only the version string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ManifestEntry {
+    Legacy(String),
+    Current {
+        version: String,
+        #[serde(default)]
+        sha256: Option<String>,
+        #[serde(default)]
+        source: Option<String>,
+        #[serde(default)]
+        registry: Option<String>,
+        #[serde(default)]
+        url: Option<String>,
+        #[serde(default)]
+        local_sha256: Option<String>,
+        #[serde(default)]
+        pinned: bool,
+        #[serde(default)]
+        signed: bool,
+        #[serde(default)]
+        signature: Option<String>,
+    },
+}
+
+impl From<ManifestEntry> for InstalledPattern {
+    fn from(entry: ManifestEntry) -> Self {
+        match entry {
+            ManifestEntry::Legacy(version) => Self {
+                version,
+                sha256: None,
+                source: None,
+                registry: None,
+                url: None,
+                local_sha256: None,
+                pinned: false,
+                signed: false,
+                signature: None,
+            },
+            ManifestEntry::Current {
+                version,
+                sha256,
+                source,
+                registry,
+                url,
+                local_sha256,
+                pinned,
+                signed,
+                signature,
+            } => Self {
+                version,
+                sha256,
+                source,
+                registry,
+                url,
+                local_sha256,
+                pinned,
+                signed,
+                signature,
+            },
+        }
+    }
+}
+
+/// User configuration stored as `config.json` in the pattern directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct UserConfig {
+    /// License policy enforced by `gfr install`.
+    license_policy: Option<LicensePolicy>,
+    /// Indexes to install from instead of the default one.
+    registries: Vec<Registry>,
+    /// WebAssembly modules every finding of `gfr search` is passed through.
+    plugins: Vec<String>,
+    /// Files larger than this, e.g. `"10M"`, are skipped by `gfr search` unless
+    /// `--max-filesize` says otherwise.
+    max_filesize: Option<String>,
+}
+
+/// A named pattern index configured in `config.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Registry {
+    name: String,
+    url: String,
+    /// Registries with a higher priority are asked first for a pattern.
+    #[serde(default)]
+    priority: i32,
+    /// Sent as a bearer token to the registry's server; `GFR_TOKEN` is used when unset.
+    token: Option<String>,
+}
+
+/// An index to install from, and the configured registry it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexSource {
+    url: String,
+    registry: Option<String>,
+    token: Option<String>,
+}
+
+impl IndexSource {
+    /// A client that authenticates to this index's server only.
+    fn client(&self, network: &NetworkOptions) -> Result<HttpClient> {
+        Ok(HttpClient::new(network)?.with_token(&self.url, self.token.as_deref()))
+    }
+}
+
+impl UserConfig {
+    /// The indexes `gfr install` and `gfr update` use: an explicit URL or registry,
+    /// else all configured registries by priority, else the default index.
+    fn index_sources(
+        &self,
+        url: Option<String>,
+        registry: Option<&str>,
+    ) -> Result<Vec<IndexSource>> {
+        if let Some(url) = url {
+            return Ok(vec![IndexSource {
+                url,
+                registry: None,
+                token: self.token(None),
+            }]);
+        }
+        let mut registries: Vec<&Registry> = match registry {
+            Some(name) => vec![self
+                .registries
+                .iter()
+                .find(|r: &&Registry| r.name == name)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = self
+                        .registries
+                        .iter()
+                        .map(|r: &Registry| r.name.as_str())
+                        .collect();
+                    anyhow!(
+                        "No registry named '{name}' in config.json. Configured registries: {}",
+                        if known.is_empty() {
+                            "none".to_string()
+                        } else {
+                            known.join(", ")
                         }
-                    };
-                    if entry
-                        .file_type()
-                        .is_some_and(|ft: fs::FileType| ft.is_file())
-                    {
-                        let search_result: std::result::Result<(), io::Error> = searcher
-                            .search_path(
-                                &matcher,
-                                entry.path(),
-                                printer.sink_with_path(&matcher, entry.path()),
-                            );
-                        if let Err(e) = search_result {
-                            eprintln!("{}: {}", entry.path().display().style(styles.error), e);
+                    )
+                })?],
+            None => self.registries.iter().collect(),
+        };
+        if registries.is_empty() {
+            return Ok(vec![IndexSource {
+                url: DEFAULT_PATTERNS_URL.to_string(),
+                registry: None,
+                token: None,
+            }]);
+        }
+        registries.sort_by_key(|r: &&Registry| std::cmp::Reverse(r.priority));
+        Ok(registries
+            .into_iter()
+            .map(|r: &Registry| IndexSource {
+                url: r.url.clone(),
+                registry: Some(r.name.clone()),
+                token: self.token(Some(&r.name)),
+            })
+            .collect())
+    }
+
+    /// The token for a configured registry, or for any other index, from `GFR_TOKEN`.
+    fn token(&self, registry: Option<&str>) -> Option<String> {
+        registry
+            .and_then(|name: &str| self.registries.iter().find(|r: &&Registry| r.name == name))
+            .and_then(|r: &Registry| r.token.clone())
+            .or_else(|| std::env::var(http::TOKEN_ENV).ok())
+            .filter(|token: &String| !token.is_empty())
+    }
+}
+
+/// Restricts which pattern licenses may be installed.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LicensePolicy {
+    /// Accepted SPDX identifiers. `"permissive"` expands to a list of common permissive licenses.
+    allow: Vec<String>,
+    /// If true, patterns that don't declare a license are accepted too.
+    allow_unlicensed: bool,
+}
+
+impl LicensePolicy {
+    /// Checks a pattern license against the policy, explaining why it was rejected.
+    fn check(&self, license: Option<&str>) -> std::result::Result<(), String> {
+        let Some(license) = license else {
+            return if self.allow_unlicensed {
+                Ok(())
+            } else {
+                Err("no license declared".to_string())
+            };
+        };
+        let allowed: bool = self.allow.iter().any(|a: &String| {
+            a.eq_ignore_ascii_case(license)
+                || (a.eq_ignore_ascii_case("permissive")
+                    && PERMISSIVE_LICENSES
+                        .iter()
+                        .any(|p: &&str| p.eq_ignore_ascii_case(license)))
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err(format!("license '{license}' is not allowed by policy"))
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let styles = Styles::new();
+
+    if cli.network.insecure {
+        eprintln!(
+            "{} --insecure turns off TLS certificate checks: downloaded patterns could have been tampered with.",
+            "WARNING:".style(styles.error)
+        );
+    }
+
+    // The `if let Err` block handles all errors propagated with `?` from the subcommands.
+    if let Err(e) = run_command(cli.command, &cli.network, &styles).await {
+        eprintln!("{} {:#}", "Error:".style(styles.error), e);
+        exit(1);
+    }
+
+    Ok(())
+}
+
+/// Runs `gfr search`, or prints the pattern with `--dump`.
+fn search_or_dump(args: SearchArgs, network: &NetworkOptions, styles: &Styles) -> Result<()> {
+    if args.type_list {
+        run_type_list(&args.type_add, styles)
+    } else if args.dump {
+        // Dump only supports a single pattern name for clarity.
+        let names: Vec<String> = args.pattern_names();
+        let [name_to_dump] = names.as_slice() else {
+            return Err(anyhow!(
+                "--dump requires a single pattern_name to be specified."
+            ));
+        };
+        run_dump(name_to_dump, styles)
+    } else {
+        if let Some(feature) = args.network_feature().filter(|_| network.offline) {
+            return Err(anyhow!(
+                "{feature} needs the network, which --offline forbids."
+            ));
+        }
+        let args: SearchArgs = args.normalize()?;
+        match &args.daemon {
+            Some(socket) => daemon::search(&args, socket.as_deref(), styles),
+            None => run_search(&args, styles),
+        }
+    }
+}
+
+/// Dispatches the appropriate function based on the parsed command.
+async fn run_command(command: Commands, network: &NetworkOptions, styles: &Styles) -> Result<()> {
+    match command {
+        Commands::Search(args) => search_or_dump(args, network, styles),
+        Commands::List => run_list(styles),
+        Commands::Install {
+            mut names,
+            mut url,
+            registry,
+            bundle,
+            locked,
+            require_signed,
+            dry_run,
+            force,
+        } => {
+            if let Some(path) = locked {
+                return lockfile::install_locked(&path, require_signed, network, styles).await;
+            }
+            if url.is_none() && registry.is_none() {
+                url = take_index_location(&mut names);
+            }
+            if bundle.is_some() && !names.is_empty() {
+                return Err(anyhow!(
+                    "--bundle selects its own patterns; don't name patterns as well."
+                ));
+            }
+            let (names, requested): (Vec<String>, Requested) = parse_requested(&names);
+            let sources: Vec<IndexSource> =
+                load_user_config()?.index_sources(url, registry.as_deref())?;
+            let options: InstallOptions = InstallOptions {
+                installed_only: false,
+                require_signed,
+                dry_run,
+                force,
+                migrate: false,
+                requested: &requested,
+                network,
+            };
+            run_install(&sources, &names, bundle.as_deref(), options, styles).await
+        }
+        Commands::Update {
+            url,
+            registry,
+            check,
+            require_signed,
+            dry_run,
+            force,
+            migrate,
+        } => {
+            let sources: Vec<IndexSource> =
+                load_user_config()?.index_sources(url, registry.as_deref())?;
+            if check {
+                for source in &sources {
+                    run_update_check(source, network, styles).await?;
+                }
+                Ok(())
+            } else {
+                let options: InstallOptions = InstallOptions {
+                    installed_only: true,
+                    require_signed,
+                    dry_run,
+                    force,
+                    migrate,
+                    requested: &Requested::new(),
+                    network,
+                };
+                run_install(&sources, &[], None, options, styles).await
+            }
+        }
+        Commands::Lock(args) => lockfile::run_lock(&args, styles),
+        Commands::Index(args) => indexer::run_index(&args, styles),
+        Commands::IndexBuild(args) => ngram_index::run_index_build(&args, styles),
+        Commands::InitRepo(args) => scaffold::run_init_repo(&args, styles),
+        Commands::Publish(args) => publish::run_publish(&args, styles),
+        Commands::Sync(args) => sync::run_sync(&args, styles),
+        Commands::Rollback(args) => versions::run_rollback(&args, styles),
+        Commands::Save(args) => run_save(args, styles),
+        Commands::Validate(args) => validate::run_validate(args, network, styles).await,
+        Commands::Migrate(args) => migrate::run_migrate(&args, styles),
+        Commands::Lint(args) => lint::run_lint(&args, styles),
+        Commands::Explain(args) => explain::run_explain(&args, styles),
+        Commands::Bench(args) => bench::run_bench(&args, styles),
+        Commands::Test(args) => pattern_tests::run_test(&args, styles),
+        Commands::Remove(args) => run_remove(&args, styles),
+        Commands::Alias(args) => aliases::run_alias(&args, styles),
+        Commands::Triage { action } => triage::run_triage(action, styles),
+        Commands::Compare(args) => compare::run_compare(&args, styles),
+        Commands::Findings { db, action } => findings::run_findings(action, db.as_deref(), styles),
+        Commands::Serve(args) => serve::run_serve(&args, styles).await,
+        Commands::Daemon(args) => daemon::run_daemon(&args, styles).await,
+    }
+}
+
+/// The patterns a search runs with: those selected by name, filters and pattern
+/// files, with the severity filters and the engine, `-w` and `--no-unicode`
+/// overrides applied. Warns about deprecated ones.
+fn select_patterns(args: &SearchArgs, styles: &Styles) -> Result<Vec<Pattern>> {
+    let pattern_names: Vec<String> = match &args.two_pass {
+        Some((_, expensive)) => vec![expensive.clone()],
+        None => args.pattern_names(),
+    };
+    let tags: Option<&TagExpr> = args.tags.as_ref();
+    let author: Option<&str> = args.author.as_deref();
+    let bundle: Option<&str> = args.bundle.as_deref();
+
+    // At least one filter must be provided to know what to search for.
+    let filtered: bool =
+        !pattern_names.is_empty() || tags.is_some() || author.is_some() || bundle.is_some();
+    if !filtered && args.pattern_file.is_empty() {
+        return Err(anyhow!(
+            "Search requires a filter. Please provide a pattern name, --tags, --author, --bundle, or --pattern-file."
+        ));
+    }
+
+    // Prevent conflicting usage: pattern name should not be combined with filters.
+    if !pattern_names.is_empty() && (tags.is_some() || author.is_some()) {
+        return Err(anyhow!(
+            "Cannot combine pattern name with --tags or --author filters. Use either a specific pattern name OR filters, not both."
+        ));
+    }
+
+    let only_plain_names: bool =
+        !pattern_names.is_empty() && !pattern_names.iter().any(|n: &String| n.starts_with('@'));
+    if only_plain_names && !args.skip_pattern.is_empty() {
+        return Err(anyhow!(
+            "--skip-pattern only applies when selecting patterns with --tags, --author, --bundle or @collection."
+        ));
+    }
+
+    let mut patterns: Vec<Pattern> = if filtered {
+        find_patterns_by_filter(
+            &pattern_names,
+            tags,
+            author,
+            bundle,
+            &args.skip_pattern,
+            args.fuzzy,
+            styles,
+        )?
+    } else {
+        Vec::new()
+    };
+    // A pattern file takes the place of an installed pattern of the same name.
+    for pattern_file in &args.pattern_file {
+        let pattern: Pattern = load_pattern_file(pattern_file)?;
+        patterns.retain(|p: &Pattern| p.name != pattern.name);
+        patterns.push(pattern);
+    }
+    patterns.retain(|p: &Pattern| args.allows_severity(p.severity.unwrap_or_default()));
+    for p in &mut patterns {
+        p.engine = args.engine.or(p.engine);
+        p.word |= args.word_regexp;
+        if args.no_unicode && p.engine != Some(Engine::Backtrack) {
+            p.unicode = false;
+        }
+    }
+    if patterns.is_empty() {
+        return Err(anyhow!(
+            "None of the selected patterns match the severity filter."
+        ));
+    }
+    for p in patterns.iter().filter(|p: &&Pattern| p.deprecated) {
+        eprintln!(
+            "{} Pattern '{}' is deprecated{}.",
+            "Warning:".style(styles.error),
+            p.name.style(styles.highlight),
+            p.superseded_by
+                .as_ref()
+                .map(|s: &String| format!("; use '{s}' instead"))
+                .unwrap_or_default()
+        );
+    }
+    Ok(patterns)
+}
+
+/// Executes the search operation based on provided filters.
+#[allow(clippy::too_many_lines)] // This function orchestrates the entire search logic.
+fn run_search(args: &SearchArgs, styles: &Styles) -> Result<()> {
+    let path: &Path = &args.path;
+    let include_bin: bool = args.include_bin;
+    let mut sink_specs: Vec<SinkSpec> = args.sink_specs()?;
+    // Anything else on stdout would corrupt machine-readable output there.
+    let show_progress: bool = !sink_specs.iter().any(SinkSpec::writes_stdout)
+        && args.summary_kind().is_none()
+        && !args.invert_match
+        && !args.estimate;
+    let (before_context, after_context): (usize, usize) = args.context_lines();
+    let patterns_to_search: Vec<Pattern> = select_patterns(args, styles)?;
+    let plugins: Plugins = if args.no_plugins || args.invert_match {
+        Plugins::default()
+    } else {
+        Plugins::load(
+            &load_user_config()?.plugins,
+            &patterns_to_search,
+            &get_pattern_dir()?,
+        )?
+    };
+    // Plugins see findings, which the text printers don't produce.
+    if !plugins.is_empty() && sink_specs.is_empty() {
+        if args.summary_kind().is_some()
+            || args.only_matching
+            || before_context > 0
+            || after_context > 0
+        {
+            return Err(anyhow!(
+                "Plugins post-process findings, so they don't work with --count, --files-with-matches, --only-matching or context lines. Pass --no-plugins to skip them."
+            ));
+        }
+        sink_specs.push(SinkSpec::Terminal);
+    }
+    // Keep stdout clean for anything meant to be consumed by other tools.
+    if show_progress {
+        println!(
+            "{} {} patterns on path '{}'...",
+            "Searching with".style(styles.dim),
+            patterns_to_search.len().to_string().style(styles.highlight),
+            path.display().style(styles.highlight)
+        );
+    }
+
+    // Verdicts from `gfr triage` tell how trustworthy each pattern has been so far.
+    let confidence: HashMap<String, f64> = TriageStore::load().unwrap_or_default().confidence();
+    if show_progress {
+        for p in &patterns_to_search {
+            if let Some(c) = confidence.get(&p.name) {
+                println!(
+                    "  {} {} {}",
+                    p.name.style(styles.highlight),
+                    "confidence".style(styles.dim),
+                    format!("{:.0}%", c * 100.0).style(styles.info)
+                );
+            }
+        }
+    }
+
+    if (args.watch || args.follow) && !sink_specs.iter().all(SinkSpec::streams) {
+        return Err(anyhow!(
+            "--watch and --follow never finish, so they only work with output written as it comes in (text, --json, terminal and jsonl sinks)."
+        ));
+    }
+
+    // Findings marked with `gfr findings mark` keep their status in later scans.
+    let triaged: HashMap<String, FindingStatus> = args.triaged_statuses()?;
+
+    // --- Compile the patterns, each with its own flags and file types ---
+    // With --include-bin, file types aren't applied at all.
+    let covered: Vec<Pattern> = patterns_to_search
+        .iter()
+        .cloned()
+        .map(|p: Pattern| Pattern {
+            file_types: p.file_types.filter(|_: &Vec<String>| !include_bin),
+            exclude_file_types: p.exclude_file_types.filter(|_: &Vec<String>| !include_bin),
+            languages: p.languages.filter(|_: &Vec<String>| !include_bin),
+            ..p
+        })
+        .collect();
+    // The walk can only skip files by type when every pattern is limited to some.
+    let all_file_types: HashSet<String> = if covered
+        .iter()
+        .all(|p: &Pattern| !p.included_types().is_empty())
+    {
+        covered.iter().flat_map(Pattern::included_types).collect()
+    } else {
+        HashSet::new()
+    };
+    // Every match is attributed to the pattern that produced it, so each
+    // pattern also gets a matcher of its own.
+    let named_matchers: Vec<(String, PatternMatcher)> = patterns_to_search
+        .iter()
+        .map(|p: &Pattern| {
+            let matcher: PatternMatcher = p
+                .build_matcher()
+                .with_context(|| format!("Pattern '{}' does not compile", p.name))?;
+            Ok((p.name.clone(), matcher))
+        })
+        .collect::<Result<_>>()?;
+    let everything: Arc<Scope> = Arc::new(Scope {
+        patterns: (0..covered.len()).collect(),
+        matcher: combined_matcher(&covered.iter().collect::<Vec<&Pattern>>())?,
+    });
+    // Text output labels lines with their pattern once there is more than one.
+    // Inverted matches and bare path listings have nothing to attribute.
+    let attribute: bool = sink_specs.is_empty()
+        && named_matchers.len() > 1
+        && !args.invert_match
+        && args.summary_kind() != Some(SummaryKind::PathWithMatch);
+    let prefilter: Option<PatternMatcher> = args
+        .two_pass
+        .as_ref()
+        .map(|(cheap, _): &(String, String)| {
+            let mut cheap: Pattern = load_pattern(cheap)?;
+            cheap.engine = args.engine.or(cheap.engine);
+            cheap.build_matcher()
+        })
+        .transpose()
+        .context("Failed to load the first pattern of --two-pass")?;
+    let allow: Option<AllowList> = if args.no_allow {
+        None
+    } else {
+        match &args.allow_file {
+            Some(file) => AllowList::load(file, true)?,
+            None => AllowList::load(Path::new(ALLOW_FILE), false)?,
+        }
+    };
+    let severities: HashMap<String, Severity> = patterns_to_search
+        .iter()
+        .filter_map(|p: &Pattern| Some((p.name.clone(), p.severity?)))
+        .collect();
+    let sinks: Vec<Box<dyn sinks::Sink>> = sink_specs
+        .iter()
+        .map(|spec: &SinkSpec| spec.open(named_matchers.len() > 1))
+        .collect::<Result<_>>()?;
+    let entropy: HashMap<String, EntropyRule> = patterns_to_search
+        .iter()
+        .filter(|_: &&Pattern| !args.invert_match)
+        .filter_map(|p: &Pattern| {
+            let rule: EntropyRule = EntropyRule {
+                min: args.min_entropy.or(p.min_entropy)?,
+                group: p.entropy_group.clone(),
+            };
+            Some((p.name.clone(), rule))
+        })
+        .collect();
+    let validators: HashMap<String, Vec<Validator>> = patterns_to_search
+        .iter()
+        .filter(|_: &&Pattern| !args.invert_match)
+        .filter_map(|p: &Pattern| Some((p.name.clone(), p.validators.clone()?)))
+        .filter(|(_, v): &(String, Vec<Validator>)| !v.is_empty())
+        .collect();
+    let mut plan: SearchPlan = SearchPlan {
+        named_matchers,
+        patterns: covered,
+        everything,
+        scopes: Mutex::new(HashMap::new()),
+        attribute,
+        prefilter,
+        allow,
+        inline_ignores: !args.no_inline_ignores,
+        detect_types: args.detect_types,
+        baseline: args.baseline.as_deref().map(Baseline::load).transpose()?,
+        baseline_writer: args
+            .write_baseline
+            .as_ref()
+            .map(|_: &PathBuf| BaselineWriter::default()),
+        severities,
+        entropy,
+        validators,
+        sinks,
+        held_for_verification: args.verify.then(|| Mutex::new(Vec::new())),
+        redact: args.redact,
+        suppressed: triaged
+            .iter()
+            .filter(|(_, s): &(&String, &FindingStatus)| !args.show_triaged && s.suppresses())
+            .map(|(fingerprint, _): (&String, &FindingStatus)| fingerprint.clone())
+            .collect(),
+        triaged,
+        summary: args.summary_kind(),
+        deduper: args.dedupe_lines.then(LineDeduper::default),
+        confidence,
+        only_matching: args.only_matching,
+        replacement: args
+            .replace
+            .as_ref()
+            .map(|r: &String| r.as_bytes().to_vec()),
+        tally: args
+            .badge
+            .as_ref()
+            .map(|_: &PathBuf| Mutex::new(HashMap::new())),
+        reported: args.watch.then(|| Mutex::new(HashSet::new())),
+        plugins,
+    };
+
+    // --- Execute Search ---
+    if args.follow {
+        if !path.is_file() {
+            return Err(anyhow!(
+                "--follow needs a file to follow, not '{}'.",
+                path.display()
+            ));
+        }
+        if show_progress {
+            println!(
+                "{} '{}' for appended lines...",
+                "Following".style(styles.dim),
+                path.display().style(styles.highlight)
+            );
+        }
+        // Each read is searched on its own, so line numbers would restart every time.
+        let mut builder: SearcherBuilder = SearcherBuilder::new();
+        builder.line_number(false).multi_line(true);
+        args.encoding.configure(&mut builder);
+        let mut searcher: Searcher = builder.build();
+        let mut printer: Printer = plan.printer();
+        follow::follow(path, |bytes: &[u8], offset: u64| {
+            plan.search(
+                &mut searcher,
+                &mut printer,
+                Source::Appended {
+                    path,
+                    bytes,
+                    offset,
+                },
+                &plan.everything,
+            )
+        })?;
+    } else if io::stdin().is_terminal() {
+        // Search the file system.
+        let root: PathBuf = winpath::search_root(path);
+        let tuning: Tuning = args.tuning(&root);
+        let hidden: bool = args.search_hidden(&patterns_to_search);
+        // Every file outside the patterns' types is skipped anyway, so whole
+        // branches without any of them need not be walked.
+        let pruner: Option<DirectoryPruner> = (!all_file_types.is_empty())
+            .then(|| DirectoryPruner::new(&all_file_types).detecting_types(args.detect_types));
+        let mut walk_builder: WalkBuilder =
+            args.walk_builder(&root, &tuning, hidden, move |entry: &ignore::DirEntry| {
+                !entry
+                    .file_type()
+                    .is_some_and(|ft: fs::FileType| ft.is_dir())
+                    || pruner
+                        .as_ref()
+                        .is_none_or(|p: &DirectoryPruner| p.should_descend(entry.path()))
+            })?;
+        // Files typed by their content match none of these globs.
+        if !all_file_types.is_empty() && !args.detect_types {
+            let mut override_builder: ignore::overrides::OverrideBuilder =
+                ignore::overrides::OverrideBuilder::new(&root);
+            for glob in all_file_types
+                .iter()
+                .flat_map(|ft: &String| filetype::globs(ft))
+            {
+                override_builder.add(&glob)?;
+            }
+            let overrides: ignore::overrides::Override = override_builder.build()?;
+            walk_builder.overrides(overrides);
+        }
+
+        if args.estimate {
+            estimate::run(
+                walk_builder.build(),
+                &patterns_to_search,
+                &plan.everything.matcher,
+                include_bin,
+                tuning.threads,
+                styles,
+            );
+            return Ok(());
+        }
+
+        let content_index: Option<SearchIndex> = match &args.index {
+            Some(_) if matches!(args.encoding, TextEncoding::Label(_)) => {
+                return Err(anyhow!(
+                    "--index holds the raw bytes of files, so it only works with --encoding auto or none."
+                ));
+            }
+            Some(index) => {
+                let index: SearchIndex =
+                    SearchIndex::open(index.as_deref(), &root, &plan.patterns)?;
+                if show_progress {
+                    println!(
+                        "{} {} of {} indexed files may match",
+                        "Index:".style(styles.dim),
+                        index.candidate_files().to_string().style(styles.highlight),
+                        index.indexed_files().to_string().style(styles.highlight)
+                    );
+                }
+                Some(index)
+            }
+            None => None,
+        };
+
+        // Without `changed`, every file is searched; with it, only the files that
+        // `--watch` saw change, given relative to the root.
+        let search_walk = |changed: Option<&HashSet<PathBuf>>| {
+            // Makes the search function of one walker thread.
+            let visitor = || -> Box<
+                dyn FnMut(std::result::Result<ignore::DirEntry, ignore::Error>) -> WalkState
+                    + Send
+                    + '_,
+            > {
+                let plan: &SearchPlan = &plan;
+                let root: &Path = &root;
+                let content_index: Option<&SearchIndex> = content_index.as_ref();
+                // Only matchers of multiline patterns lack a line terminator, so
+                // everything else is still searched line by line.
+                let mut builder: SearcherBuilder = SearcherBuilder::new();
+                builder
+                    .multi_line(true)
+                    .binary_detection(if include_bin {
+                        // This disables binary detection, treating all files as text.
+                        BinaryDetection::none()
+                    } else {
+                        // This is the default behavior: skip binary files.
+                        BinaryDetection::quit(b'\x00')
+                    })
+                    .memory_map(if tuning.mmap {
+                        // SAFETY: Same trade-off ripgrep makes: a file truncated while mapped
+                        // may abort the process, which only matters for files being rewritten mid-scan.
+                        unsafe { MmapChoice::auto() }
+                    } else {
+                        MmapChoice::never()
+                    })
+                    .before_context(before_context)
+                    .after_context(after_context)
+                    .invert_match(args.invert_match);
+                args.encoding.configure(&mut builder);
+                let mut searcher: Searcher = builder.build();
+                let mut printer: Printer = plan.printer();
+
+                Box::new(
+                    move |result: std::result::Result<ignore::DirEntry, ignore::Error>| {
+                        let entry: ignore::DirEntry = match result {
+                            Ok(entry) => entry,
+                            Err(err) => {
+                                eprintln!("{} {}", "Error:".style(styles.error), err);
+                                return WalkState::Continue;
+                            }
+                        };
+                        let wanted: bool = changed.is_none_or(|changed: &HashSet<PathBuf>| {
+                            entry
+                                .path()
+                                .strip_prefix(root)
+                                .is_ok_and(|relative: &Path| changed.contains(relative))
+                        });
+                        if wanted
+                            && entry
+                                .file_type()
+                                .is_some_and(|ft: fs::FileType| ft.is_file())
+                        {
+                            // A file named on the command line is searched by every pattern.
+                            let typed: Option<&Path> = (entry.depth() > 0).then(|| entry.path());
+                            let outcome: Result<()> =
+                                plan.scope(typed, root).and_then(|scope: Option<Arc<Scope>>| {
+                                    let Some(scope) = scope else {
+                                        return Ok(());
+                                    };
+                                    if content_index.is_some_and(|index: &SearchIndex| {
+                                        !index.may_match(&entry, &scope.patterns)
+                                    }) {
+                                        return Ok(());
+                                    }
+                                    // UTF-16 without a byte-order mark gets a searcher of its own.
+                                    let mut sniffed: Option<Searcher> =
+                                        args.encoding.searcher_for(&builder, entry.path());
+                                    Ok(plan.search(
+                                        sniffed.as_mut().unwrap_or(&mut searcher),
+                                        &mut printer,
+                                        Source::File(entry.path()),
+                                        &scope,
+                                    )?)
+                                });
+                            if let Err(e) = outcome {
+                                eprintln!("{}: {}", entry.path().display().style(styles.error), e);
+                            }
                         }
+                        WalkState::Continue
+                    },
+                )
+            };
+            if args.no_parallel {
+                let mut visit = visitor();
+                for result in walk_builder.build() {
+                    if matches!(visit(result), WalkState::Quit) {
+                        break;
                     }
-                    WalkState::Continue
-                },
+                }
+            } else {
+                walk_builder.build_parallel().run(visitor);
+            }
+        };
+        search_walk(None);
+        if args.watch {
+            if show_progress {
+                println!(
+                    "{} '{}' for changes...",
+                    "Watching".style(styles.dim),
+                    path.display().style(styles.highlight)
+                );
+            }
+            watch::watch(&root, styles, |changed: &HashSet<PathBuf>| {
+                search_walk(Some(changed));
+            })?;
+        }
+    } else if args.watch {
+        return Err(anyhow!(
+            "--watch only applies to searching files, not piped input."
+        ));
+    } else if args.estimate {
+        return Err(anyhow!(
+            "--estimate only applies to searching files, not piped input."
+        ));
+    } else if args.index.is_some() {
+        return Err(anyhow!(
+            "--index only applies to searching files, not piped input."
+        ));
+    } else {
+        // If data is piped to stdin, search it instead of files.
+        let mut builder: SearcherBuilder = SearcherBuilder::new();
+        builder
+            .multi_line(true)
+            .before_context(before_context)
+            .after_context(after_context)
+            .invert_match(args.invert_match);
+        args.encoding.configure(&mut builder);
+        let mut searcher: Searcher = builder.build();
+        let mut printer: Printer = plan.printer();
+        if plan.attribute
+            || plan.prefilter.is_some()
+            || plan.inline_ignores && (before_context > 0 || after_context > 0)
+        {
+            // These make more than one pass, and stdin can only be read once.
+            let mut input: Vec<u8> = Vec::new();
+            io::stdin().read_to_end(&mut input)?;
+            plan.search(
+                &mut searcher,
+                &mut printer,
+                Source::Buffered(&input),
+                &plan.everything,
+            )?;
+        } else {
+            plan.search(&mut searcher, &mut printer, Source::Stdin, &plan.everything)?;
+        }
+    }
+
+    if let Some(held) = plan.held_for_verification.take() {
+        let mut findings: Vec<Finding> = held.into_inner().map_err(|e| anyhow!(e.to_string()))?;
+        let specs: HashMap<String, VerificationSpec> =
+            verification_specs(&patterns_to_search, styles)?;
+        if specs.is_empty() {
+            eprintln!(
+                "{} None of the selected patterns describe how to verify their matches.",
+                "Warning:".style(styles.error)
+            );
+        }
+        verify::verify_findings(
+            &mut findings,
+            &specs,
+            VerifyOptions {
+                timeout: std::time::Duration::from_secs(args.verify_timeout),
+                concurrency: args.verify_concurrency,
+            },
+        )?;
+        plan.deliver(&findings)?;
+    }
+    for sink in plan.sinks {
+        sink.finish(&patterns_to_search)?;
+    }
+
+    if let (Some(baseline_path), Some(writer)) = (&args.write_baseline, plan.baseline_writer) {
+        let count: usize = writer.save(baseline_path)?;
+        eprintln!(
+            "{} Wrote {} findings to baseline {}",
+            "✓".style(styles.success),
+            count.to_string().style(styles.highlight),
+            baseline_path.display().style(styles.highlight)
+        );
+    }
+
+    if let (Some(badge_path), Some(tally)) = (&args.badge, plan.tally) {
+        let counts: HashMap<String, usize> =
+            tally.into_inner().map_err(|e| anyhow!(e.to_string()))?;
+        badge::write(
+            badge_path,
+            &badge::Badge::from_counts(&counts, &plan.severities),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// The compiled search shared by all walker threads.
+#[allow(clippy::struct_excessive_bools)] // Each bool mirrors a command-line flag.
+struct SearchPlan {
+    /// One matcher per pattern, used to attribute matches.
+    named_matchers: Vec<(String, PatternMatcher)>,
+    /// The patterns of `named_matchers`, in the same order. Their file types are
+    /// dropped with `--include-bin`.
+    patterns: Vec<Pattern>,
+    /// Every pattern; searches files without a path or that all patterns cover.
+    everything: Arc<Scope>,
+    /// Scopes for files only some patterns cover, built the first time a file
+    /// with those patterns comes up.
+    scopes: Mutex<HashMap<Vec<usize>, Arc<Scope>>>,
+    /// Whether text output runs one labelled pass per pattern.
+    attribute: bool,
+    /// The cheap pattern of `--two-pass`; files it doesn't match are skipped.
+    prefilter: Option<PatternMatcher>,
+    /// Known false positives that are never reported.
+    allow: Option<AllowList>,
+    /// Honor `gfr:ignore` comments next to matches.
+    inline_ignores: bool,
+    /// Type files without an extension by their first line, for `file_types`.
+    detect_types: bool,
+    /// Findings accepted earlier with `--baseline`; only new ones are reported.
+    baseline: Option<Baseline>,
+    /// Gathers fingerprints for `--write-baseline`.
+    baseline_writer: Option<BaselineWriter>,
+    /// Declared severity of each pattern that has one.
+    severities: HashMap<String, Severity>,
+    /// Entropy thresholds of the patterns that have one.
+    entropy: HashMap<String, EntropyRule>,
+    /// Validators of the patterns that list any.
+    validators: HashMap<String, Vec<Validator>>,
+    /// Where structured findings go. Empty for grep-style text output.
+    sinks: Vec<Box<dyn sinks::Sink>>,
+    /// With `--verify`, findings are gathered here and checked together once the search is done.
+    held_for_verification: Option<Mutex<Vec<Finding>>>,
+    /// Characters kept at each end of masked matches, with `--redact`.
+    redact: Option<usize>,
+    /// Statuses of findings marked with `gfr findings mark`, attached to structured findings.
+    triaged: HashMap<String, FindingStatus>,
+    /// Fingerprints marked as false positives or accepted, unless `--show-triaged` keeps them.
+    suppressed: HashSet<String>,
+    /// Set when only per-file summaries (counts or paths) are wanted.
+    summary: Option<SummaryKind>,
+    /// Set with `--dedupe-lines`.
+    deduper: Option<LineDeduper>,
+    /// Per-pattern confidence from triage history, attached to structured findings.
+    confidence: HashMap<String, f64>,
+    /// Print only the matched parts of lines.
+    only_matching: bool,
+    /// The `--replace` template for matches.
+    replacement: Option<Vec<u8>>,
+    /// Reported matches per pattern, kept for `--badge`. Unattributed text matches count under "".
+    tally: Option<Mutex<HashMap<String, usize>>>,
+    /// With `--watch`, fingerprints reported so far, so rescans only show new findings.
+    reported: Option<Mutex<HashSet<String>>>,
+    /// Modules findings are passed through before they are reported.
+    plugins: Plugins,
+}
+
+/// The patterns that search a file, found from their `file_types`.
+#[derive(Debug)]
+struct Scope {
+    /// Indices of the patterns into `SearchPlan::named_matchers`.
+    patterns: Vec<usize>,
+    /// Matches any of these patterns, each with its own flags; drives the searcher.
+    matcher: PatternMatcher,
+}
+
+/// The per-thread printer matching the requested output. Text printers come as
+/// one per pattern when matches are attributed, otherwise as a single printer.
+enum Printer {
+    Standard(Vec<grep_printer::Standard<PrefixWriter<StandardStream>>>),
+    Summary(Vec<grep_printer::Summary<PrefixWriter<StandardStream>>>),
+    Findings,
+}
+
+/// What a single search reads from.
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    File(&'a Path),
+    /// Piped input read into memory so it can be searched more than once.
+    Buffered(&'a [u8]),
+    Stdin,
+    /// Data appended to a followed file, starting `offset` bytes into it.
+    Appended {
+        path: &'a Path,
+        bytes: &'a [u8],
+        offset: u64,
+    },
+}
+
+impl<'a> Source<'a> {
+    fn path(self) -> Option<&'a Path> {
+        match self {
+            Source::File(path) | Source::Appended { path, .. } => Some(path),
+            Source::Buffered(_) | Source::Stdin => None,
+        }
+    }
+}
+
+impl SearchPlan {
+    fn printer(&self) -> Printer {
+        // Labels are only written when matches are attributed.
+        let writers = || {
+            let labels: Vec<String> = if self.attribute {
+                self.named_matchers
+                    .iter()
+                    .map(
+                        |(name, _): &(String, PatternMatcher)| match self.severities.get(name) {
+                            Some(severity) => format!("[{name} {severity}] "),
+                            None => format!("[{name}] "),
+                        },
+                    )
+                    .collect()
+            } else {
+                vec![String::new()]
+            };
+            labels.into_iter().map(|label: String| {
+                PrefixWriter::new(StandardStream::stdout(get_color_choice()), label)
+            })
+        };
+        if let Some(kind) = self.summary {
+            Printer::Summary(
+                writers()
+                    .map(|w: PrefixWriter<StandardStream>| {
+                        SummaryBuilder::new()
+                            .kind(kind)
+                            .color_specs(get_color_specs())
+                            .build(w)
+                    })
+                    .collect(),
+            )
+        } else if self.sinks.is_empty() {
+            Printer::Standard(
+                writers()
+                    .map(|w: PrefixWriter<StandardStream>| {
+                        StandardBuilder::new()
+                            .color_specs(get_color_specs())
+                            .only_matching(self.only_matching)
+                            .replacement(self.replacement.clone())
+                            .build(w)
+                    })
+                    .collect(),
             )
+        } else {
+            Printer::Findings
+        }
+    }
+
+    /// The scope of the patterns covering `path`, found in a walk of `root`, or
+    /// `None` if none do. Without a path, e.g. for piped input or a file named on
+    /// the command line, every pattern applies.
+    fn scope(&self, path: Option<&Path>, root: &Path) -> Result<Option<Arc<Scope>>> {
+        let Some(path) = path else {
+            return Ok(Some(Arc::clone(&self.everything)));
+        };
+        let patterns: Vec<usize> = pattern::covering(&self.patterns, root, path, self.detect_types);
+        if patterns.is_empty() {
+            return Ok(None);
+        }
+        if patterns.len() == self.patterns.len() {
+            return Ok(Some(Arc::clone(&self.everything)));
+        }
+        let mut scopes = self
+            .scopes
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(scope) = scopes.get(&patterns) {
+            return Ok(Some(Arc::clone(scope)));
+        }
+        let scope: Arc<Scope> = Arc::new(Scope {
+            matcher: combined_matcher(
+                &patterns
+                    .iter()
+                    .map(|i: &usize| &self.patterns[*i])
+                    .collect::<Vec<&Pattern>>(),
+            )?,
+            patterns: patterns.clone(),
+        });
+        scopes.insert(patterns, Arc::clone(&scope));
+        Ok(Some(scope))
+    }
+
+    /// The matchers driving the text printers, with the index of the printer and
+    /// the pattern each belongs to.
+    fn text_matchers<'s>(
+        &'s self,
+        scope: &'s Scope,
+    ) -> Vec<(usize, Option<&'s str>, &'s PatternMatcher)> {
+        if self.attribute {
+            scope
+                .patterns
+                .iter()
+                .map(|i: &usize| {
+                    let (name, m): &(String, PatternMatcher) = &self.named_matchers[*i];
+                    (*i, Some(name.as_str()), m)
+                })
+                .collect()
+        } else {
+            // A lone pattern owns every match even without labels.
+            let pattern: Option<&str> = match scope.patterns.as_slice() {
+                [i] => Some(self.named_matchers[*i].0.as_str()),
+                _ => None,
+            };
+            vec![(0, pattern, &scope.matcher)]
+        }
+    }
+
+    /// The patterns of `scope` a line printed for `pattern` may hold matches of.
+    fn candidates<'s>(
+        &'s self,
+        scope: &'s Scope,
+        pattern: Option<&'s str>,
+    ) -> impl Iterator<Item = &'s (String, PatternMatcher)> {
+        scope
+            .patterns
+            .iter()
+            .map(|i: &usize| &self.named_matchers[*i])
+            .filter(move |(name, _): &&(String, PatternMatcher)| {
+                pattern.is_none_or(|p: &str| p == name)
+            })
+    }
+
+    /// With context lines and inline ignores, the lines worth showing as context:
+    /// those around the matches of `matcher` that no `gfr:ignore` comment
+    /// suppresses. Found in a pass of its own, for the searcher emits context
+    /// before the match it belongs to.
+    fn shown_context(
+        &self,
+        searcher: &mut Searcher,
+        matcher: &PatternMatcher,
+        pattern: Option<&str>,
+        source: Source<'_>,
+    ) -> io::Result<Option<Vec<RangeInclusive<u64>>>> {
+        if !self.inline_ignores || searcher.before_context() == 0 && searcher.after_context() == 0 {
+            return Ok(None);
+        }
+        let mut scan: ContextScan = ContextScan {
+            pattern,
+            before: searcher.before_context() as u64,
+            after: searcher.after_context() as u64,
+            shown: Vec::new(),
+        };
+        run_searcher(searcher, matcher, source, &mut scan)?;
+        Ok(Some(scan.shown))
+    }
+
+    /// Searches a single source with the patterns of `scope` and reports the results.
+    fn search(
+        &self,
+        searcher: &mut Searcher,
+        printer: &mut Printer,
+        source: Source<'_>,
+        scope: &Scope,
+    ) -> io::Result<()> {
+        let path: Option<&Path> = source.path();
+        let path_string: Option<String> = path.map(winpath::display);
+        let path_str: Option<&str> = path_string.as_deref();
+        let shown_path: Option<&Path> = path_str.map(Path::new);
+
+        // The cheap pattern of `--two-pass` decides which files get searched at all.
+        if let Some(prefilter) = &self.prefilter {
+            if !Self::has_match(searcher, prefilter, source)? {
+                return Ok(());
+            }
+        }
+        // Per-pattern passes are only worth it for the few files that match at all.
+        if self.attribute && !Self::has_match(searcher, &scope.matcher, source)? {
+            return Ok(());
+        }
+
+        match printer {
+            Printer::Standard(printers) => {
+                for (i, pattern, matcher) in self.text_matchers(scope) {
+                    let standard: &mut grep_printer::Standard<PrefixWriter<StandardStream>> =
+                        &mut printers[i];
+                    let sink = match shown_path {
+                        Some(path) => standard.sink_with_path(matcher, path),
+                        None => standard.sink(matcher),
+                    };
+                    let shown: Option<Vec<RangeInclusive<u64>>> =
+                        self.shown_context(searcher, matcher, pattern, source)?;
+                    let sink = FilterSink::new(self, scope, pattern, path_str, sink)
+                        .showing_context(shown);
+                    run_searcher(searcher, matcher, source, sink)?;
+                }
+                Ok(())
+            }
+            Printer::Summary(printers) => {
+                let label: &Path = shown_path.unwrap_or(Path::new(STDIN_LABEL));
+                for (i, pattern, matcher) in self.text_matchers(scope) {
+                    let summary: &mut grep_printer::Summary<PrefixWriter<StandardStream>> =
+                        &mut printers[i];
+                    let sink = FilterSink::new(
+                        self,
+                        scope,
+                        pattern,
+                        path_str,
+                        summary.sink_with_path(matcher, label),
+                    );
+                    run_searcher(searcher, matcher, source, sink)?;
+                }
+                Ok(())
+            }
+            Printer::Findings => {
+                let mut sink: FindingSink = FindingSink::new(
+                    self.candidates(scope, None).collect(),
+                    path,
+                    self.replacement.as_deref(),
+                    self.inline_ignores,
+                );
+                run_searcher(searcher, &scope.matcher, source, &mut sink)?;
+                let mut findings: Vec<Finding> = sink.into_findings();
+                findings.retain(|f: &Finding| self.keep_finding(f));
+                for finding in &mut findings {
+                    if let Source::Appended { offset, .. } = source {
+                        finding.byte_offset += offset;
+                    }
+                    finding.confidence = self.confidence.get(&finding.pattern).copied();
+                    finding.severity = self.severities.get(&finding.pattern).copied();
+                    finding.status = self.triaged.get(&finding.fingerprint).copied();
+                }
+                let findings: Vec<Finding> = self
+                    .plugins
+                    .apply(findings)
+                    .map_err(|e: anyhow::Error| io::Error::other(format!("{e:#}")))?;
+                for finding in &findings {
+                    self.record(&finding.pattern, 1)?;
+                }
+                self.emit(&findings)
+            }
+        }
+    }
+
+    /// Checks whether `matcher` matches the source, stopping at the first match.
+    fn has_match(
+        searcher: &mut Searcher,
+        matcher: &PatternMatcher,
+        source: Source<'_>,
+    ) -> io::Result<bool> {
+        let mut found: bool = false;
+        run_searcher(
+            searcher,
+            matcher,
+            source,
+            grep_searcher::sinks::Bytes(|_: u64, _: &[u8]| {
+                found = true;
+                Ok(false)
+            }),
+        )?;
+        Ok(found)
+    }
+
+    /// Decides whether a matched line should be printed by the text printers.
+    /// Without attribution `pattern` is `None`, but identical lines match identically.
+    fn keep_line(
+        &self,
+        scope: &Scope,
+        pattern: Option<&str>,
+        path: Option<&str>,
+        line: &[u8],
+    ) -> bool {
+        if !self.line_passes_checks(scope, pattern, line) {
+            return false;
+        }
+        if self
+            .allow
+            .as_ref()
+            .is_some_and(|a: &AllowList| a.allows(path, line))
+        {
+            return false;
+        }
+        if self.baseline.is_some()
+            || self.baseline_writer.is_some()
+            || !self.suppressed.is_empty()
+            || self.reported.is_some()
+        {
+            let fingerprints: Vec<String> = self.line_fingerprints(scope, pattern, path, line);
+            if let Some(writer) = &self.baseline_writer {
+                for fingerprint in &fingerprints {
+                    writer.record(fingerprint);
+                }
+            }
+            // A line stays if any of its matches is new and not suppressed.
+            if !fingerprints.is_empty()
+                && fingerprints.iter().all(|f: &String| {
+                    self.baseline
+                        .as_ref()
+                        .is_some_and(|b: &Baseline| b.contains(f))
+                        || self.suppressed.contains(f)
+                })
+            {
+                return false;
+            }
+            if !fingerprints.is_empty() && !self.first_report(&fingerprints) {
+                return false;
+            }
+        }
+        self.deduper.as_ref().is_none_or(|d: &LineDeduper| {
+            !d.is_duplicate(
+                pattern.unwrap_or_default(),
+                &String::from_utf8_lossy(line),
+                path,
+            )
+        })
+    }
+
+    /// Decides whether an attributed finding should be reported.
+    fn keep_finding(&self, finding: &Finding) -> bool {
+        let passes_checks: bool = self
+            .named_matchers
+            .iter()
+            .find(|(name, _): &&(String, PatternMatcher)| name == &finding.pattern)
+            .is_none_or(|(name, m): &(String, PatternMatcher)| {
+                self.match_passes(name, m, finding.matched.as_bytes())
+            });
+        if !passes_checks {
+            return false;
+        }
+        if self
+            .allow
+            .as_ref()
+            .is_some_and(|a: &AllowList| a.allows(finding.path.as_deref(), finding.line.as_bytes()))
+        {
+            return false;
+        }
+        if let Some(writer) = &self.baseline_writer {
+            writer.record(&finding.fingerprint);
+        }
+        if self
+            .baseline
+            .as_ref()
+            .is_some_and(|b: &Baseline| b.contains(&finding.fingerprint))
+            || self.suppressed.contains(&finding.fingerprint)
+            || !self.first_report(std::slice::from_ref(&finding.fingerprint))
+        {
+            return false;
+        }
+        self.deduper.as_ref().is_none_or(|d: &LineDeduper| {
+            !d.is_duplicate(&finding.pattern, &finding.line, finding.path.as_deref())
+        })
+    }
+
+    /// Whether any of `fingerprints` is reported for the first time with `--watch`,
+    /// remembering them all. Always true without it.
+    fn first_report(&self, fingerprints: &[String]) -> bool {
+        let Some(reported) = &self.reported else {
+            return true;
+        };
+        let mut reported = reported
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let mut new: bool = false;
+        for fingerprint in fingerprints {
+            new |= reported.insert(fingerprint.clone());
+        }
+        new
+    }
+
+    /// Whether a match of the pattern `name` passes its entropy threshold and validators.
+    fn match_passes(&self, name: &str, matcher: &PatternMatcher, text: &[u8]) -> bool {
+        self.entropy
+            .get(name)
+            .is_none_or(|r: &EntropyRule| r.passes(matcher, text))
+            && self.validators.get(name).is_none_or(|v: &Vec<Validator>| {
+                v.iter()
+                    .all(|validator: &Validator| validator.accepts(text))
+            })
+    }
+
+    /// Whether a text output line holds a match that passes its pattern's checks.
+    /// Without attribution, a match of any pattern of the scope will do.
+    fn line_passes_checks(&self, scope: &Scope, pattern: Option<&str>, line: &[u8]) -> bool {
+        if self.entropy.is_empty() && self.validators.is_empty() {
+            return true;
+        }
+        self.candidates(scope, pattern)
+            .any(|(name, matcher): &(String, PatternMatcher)| {
+                let mut passed: bool = false;
+                // A failing matcher counts as no match, dropping the line.
+                let _ = matcher.find_iter(line, |m: grep_matcher::Match| {
+                    passed = self.match_passes(name, matcher, &line[m]);
+                    !passed
+                });
+                passed
+            })
+    }
+
+    /// Fingerprints of every match on a text output line, as structured output
+    /// would compute them. Without attribution, all patterns of the scope are tried.
+    fn line_fingerprints(
+        &self,
+        scope: &Scope,
+        pattern: Option<&str>,
+        path: Option<&str>,
+        line: &[u8],
+    ) -> Vec<String> {
+        let mut fingerprints: Vec<String> = Vec::new();
+        for (name, matcher) in self.candidates(scope, pattern) {
+            // A failing matcher just leaves the line without fingerprints, keeping it reported.
+            let _ = matcher.find_iter(line, |m: grep_matcher::Match| {
+                let matched: String = String::from_utf8_lossy(&line[m]).into_owned();
+                fingerprints.push(finding_fingerprint(name, path, &matched));
+                true
+            });
+        }
+        fingerprints
+    }
+
+    /// Adds `count` reported matches for `pattern` to the badge tally.
+    fn record(&self, pattern: &str, count: usize) -> io::Result<()> {
+        if let Some(tally) = &self.tally {
+            *tally
+                .lock()
+                .map_err(|e| io::Error::other(e.to_string()))?
+                .entry(pattern.to_string())
+                .or_default() += count;
+        }
+        Ok(())
+    }
+
+    /// Hands a file's findings to every sink.
+    fn emit(&self, findings: &[Finding]) -> io::Result<()> {
+        if let Some(held) = &self.held_for_verification {
+            held.lock()
+                .map_err(|e| io::Error::other(e.to_string()))?
+                .extend_from_slice(findings);
+            return Ok(());
+        }
+        self.deliver(findings)
+    }
+
+    /// Writes findings to every sink, masking their matches first with `--redact`.
+    fn deliver(&self, findings: &[Finding]) -> io::Result<()> {
+        let redacted: Vec<Finding>;
+        let findings: &[Finding] = match self.redact {
+            Some(keep) => {
+                redacted = findings
+                    .iter()
+                    .cloned()
+                    .map(|mut f: Finding| {
+                        redact::redact_finding(&mut f, keep);
+                        f
+                    })
+                    .collect();
+                &redacted
+            }
+            None => findings,
+        };
+        for sink in &self.sinks {
+            sink.write(findings)?;
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a printer's sink and drops matched lines rejected by `SearchPlan::keep_line`.
+struct FilterSink<'a, S> {
+    plan: &'a SearchPlan,
+    scope: &'a Scope,
+    pattern: Option<&'a str>,
+    path: Option<&'a str>,
+    inner: S,
+    /// The only lines context may be shown of, if not all; see
+    /// [`SearchPlan::shown_context`].
+    shown_context: Option<Vec<RangeInclusive<u64>>>,
+    /// The last line passed on, to separate groups of context the dropped
+    /// lines split up.
+    last_line: Option<u64>,
+}
+
+impl<'a, S: Sink<Error = io::Error>> FilterSink<'a, S> {
+    fn new(
+        plan: &'a SearchPlan,
+        scope: &'a Scope,
+        pattern: Option<&'a str>,
+        path: Option<&'a str>,
+        inner: S,
+    ) -> Self {
+        Self {
+            plan,
+            scope,
+            pattern,
+            path,
+            inner,
+            shown_context: None,
+            last_line: None,
+        }
+    }
+
+    /// Only passes on context lines within `shown`, when given.
+    fn showing_context(mut self, shown: Option<Vec<RangeInclusive<u64>>>) -> Self {
+        self.shown_context = shown;
+        self
+    }
+
+    /// Notes that lines `first..=last` are passed on, separating them from the
+    /// lines before when dropped context left a gap.
+    fn pass_on(&mut self, searcher: &Searcher, first: u64, last: u64) -> io::Result<bool> {
+        let gap: bool = self.shown_context.is_some()
+            && self.last_line.is_some_and(|line: u64| first > line + 1);
+        self.last_line = Some(last);
+        if gap {
+            self.inner.context_break(searcher)
+        } else {
+            Ok(true)
+        }
+    }
+}
+
+/// Whether every line of `mat`, a match of `pattern`, is suppressed by an
+/// inline `gfr:ignore` comment. A block of lines matched by a multiline pattern
+/// is only suppressed as a whole.
+fn is_suppressed_match(pattern: Option<&str>, mat: &SinkMatch<'_>) -> bool {
+    let mut previous: Option<&[u8]> =
+        suppress::previous_line(mat.buffer(), mat.bytes_range_in_buffer().start);
+    mat.lines().all(|line: &[u8]| {
+        let line: &[u8] = trim_line_terminator(line);
+        let suppressed: bool = suppress::is_suppressed(pattern, line, previous);
+        previous = Some(line);
+        suppressed
+    })
+}
+
+/// Finds the lines around the matches of a pattern that no inline `gfr:ignore`
+/// comment suppresses, `before` lines above and `after` lines below each.
+struct ContextScan<'a> {
+    pattern: Option<&'a str>,
+    before: u64,
+    after: u64,
+    shown: Vec<RangeInclusive<u64>>,
+}
+
+impl Sink for ContextScan<'_> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> io::Result<bool> {
+        if let Some(first) = mat.line_number() {
+            if !is_suppressed_match(self.pattern, mat) {
+                let last: u64 = first + mat.lines().count().saturating_sub(1) as u64;
+                self.shown
+                    .push(first.saturating_sub(self.before)..=last + self.after);
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl<S: Sink<Error = io::Error>> Sink for FilterSink<'_, S> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> io::Result<bool> {
+        let keep: bool = !(self.plan.inline_ignores && is_suppressed_match(self.pattern, mat))
+            && self.plan.keep_line(
+                self.scope,
+                self.pattern,
+                self.path,
+                trim_line_terminator(mat.bytes()),
+            );
+        if keep {
+            self.plan.record(self.pattern.unwrap_or_default(), 1)?;
+            if let Some(first) = mat.line_number() {
+                let last: u64 = first + mat.lines().count().saturating_sub(1) as u64;
+                if !self.pass_on(searcher, first, last)? {
+                    return Ok(false);
+                }
+            }
+            self.inner.matched(searcher, mat)
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn context(&mut self, searcher: &Searcher, context: &SinkContext<'_>) -> io::Result<bool> {
+        let Some(line) = context.line_number() else {
+            return self.inner.context(searcher, context);
+        };
+        if self
+            .shown_context
+            .as_ref()
+            .is_some_and(|shown: &Vec<RangeInclusive<u64>>| {
+                !shown
+                    .iter()
+                    .any(|lines: &RangeInclusive<u64>| lines.contains(&line))
+            })
+        {
+            return Ok(true);
+        }
+        if !self.pass_on(searcher, line, line)? {
+            return Ok(false);
+        }
+        self.inner.context(searcher, context)
+    }
+
+    fn context_break(&mut self, searcher: &Searcher) -> io::Result<bool> {
+        // With context dropped, breaks follow the gaps between passed on lines.
+        if self.shown_context.is_some() {
+            return Ok(true);
+        }
+        self.inner.context_break(searcher)
+    }
+
+    fn binary_data(&mut self, searcher: &Searcher, binary_byte_offset: u64) -> io::Result<bool> {
+        self.inner.binary_data(searcher, binary_byte_offset)
+    }
+
+    fn begin(&mut self, searcher: &Searcher) -> io::Result<bool> {
+        self.inner.begin(searcher)
+    }
+
+    fn finish(&mut self, searcher: &Searcher, finish: &SinkFinish) -> io::Result<()> {
+        self.inner.finish(searcher, finish)
+    }
+}
+
+/// Remembers which file first reported each (pattern, line) pair, for `--dedupe-lines`.
+#[derive(Debug, Default)]
+struct LineDeduper {
+    first_seen: Mutex<HashMap<(String, String), Option<String>>>,
+}
+
+impl LineDeduper {
+    /// Records the pair and returns true if it was already reported from a different file.
+    fn is_duplicate(&self, pattern: &str, line: &str, path: Option<&str>) -> bool {
+        let mut first_seen = self
+            .first_seen
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        match first_seen.entry((pattern.to_string(), line.to_string())) {
+            Entry::Occupied(entry) => entry.get().as_deref() != path,
+            Entry::Vacant(entry) => {
+                entry.insert(path.map(str::to_string));
+                false
+            }
+        }
+    }
+}
+
+/// The verification requests `--verify` may send, by pattern. Those of patterns
+/// downloaded from an index or a lockfile go wherever that file says, so only
+/// local patterns and ones whose signature was checked are trusted with matches.
+fn verification_specs(
+    patterns: &[Pattern],
+    styles: &Styles,
+) -> Result<HashMap<String, VerificationSpec>> {
+    let manifest: InstalledManifest = load_manifest()?;
+    let mut specs: HashMap<String, VerificationSpec> = HashMap::new();
+    for pattern in patterns {
+        let Some(spec) = &pattern.verification else {
+            continue;
+        };
+        let trusted: bool = manifest
+            .get(&pattern.name)
+            .is_none_or(InstalledPattern::is_trusted);
+        let host: Result<String> = if trusted {
+            verify::target_host(spec)
+        } else {
+            Err(anyhow!("it was downloaded without a checked signature"))
+        };
+        match host {
+            Ok(host) => {
+                eprintln!(
+                    "{} matches of '{}' with {}",
+                    "Verifying".style(styles.dim),
+                    pattern.name.style(styles.highlight),
+                    host.style(styles.highlight)
+                );
+                specs.insert(pattern.name.clone(), spec.clone());
+            }
+            Err(e) => eprintln!(
+                "{} Not verifying matches of '{}': {e:#}.",
+                "Warning:".style(styles.error),
+                pattern.name.style(styles.highlight)
+            ),
+        }
+    }
+    Ok(specs)
+}
+
+/// Runs `searcher` over `source`.
+fn run_searcher<S: Sink<Error = io::Error>>(
+    searcher: &mut Searcher,
+    matcher: &PatternMatcher,
+    source: Source<'_>,
+    sink: S,
+) -> io::Result<()> {
+    match source {
+        Source::File(path) => searcher.search_path(matcher, path, sink),
+        Source::Buffered(bytes) | Source::Appended { bytes, .. } => {
+            searcher.search_slice(matcher, bytes, sink)
+        }
+        Source::Stdin => searcher.search_reader(matcher, io::stdin(), sink),
+    }
+}
+
+/// Writes a colored label at the start of every line, so text output can show
+/// which pattern produced each match. An empty label passes output through.
+struct PrefixWriter<W> {
+    inner: W,
+    label: String,
+    label_color: ColorSpec,
+    /// The color the printer last asked for, restored after each label.
+    current_color: Option<ColorSpec>,
+    at_line_start: bool,
+}
+
+impl<W: WriteColor> PrefixWriter<W> {
+    fn new(inner: W, label: String) -> Self {
+        let mut label_color: ColorSpec = ColorSpec::new();
+        label_color.set_fg(Some(Color::Cyan)).set_bold(true);
+        Self {
+            inner,
+            label,
+            label_color,
+            current_color: None,
+            at_line_start: true,
+        }
+    }
+
+    fn write_label(&mut self) -> io::Result<()> {
+        self.inner.set_color(&self.label_color)?;
+        self.inner.write_all(self.label.as_bytes())?;
+        self.inner.reset()?;
+        if let Some(spec) = &self.current_color {
+            self.inner.set_color(spec)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: WriteColor> Write for PrefixWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.label.is_empty() {
+            return self.inner.write(buf);
+        }
+        let mut rest: &[u8] = buf;
+        while !rest.is_empty() {
+            if self.at_line_start {
+                self.write_label()?;
+            }
+            let end: usize = rest
+                .iter()
+                .position(|b: &u8| *b == b'\n')
+                .map_or(rest.len(), |i: usize| i + 1);
+            self.inner.write_all(&rest[..end])?;
+            self.at_line_start = rest[end - 1] == b'\n';
+            rest = &rest[end..];
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: WriteColor> WriteColor for PrefixWriter<W> {
+    fn supports_color(&self) -> bool {
+        self.inner.supports_color()
+    }
+
+    fn set_color(&mut self, spec: &ColorSpec) -> io::Result<()> {
+        self.current_color = Some(spec.clone());
+        self.inner.set_color(spec)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.current_color = None;
+        self.inner.reset()
+    }
+
+    fn is_synchronous(&self) -> bool {
+        self.inner.is_synchronous()
+    }
+}
+
+// --- Installing ---
+
+/// Removes and returns the first of the names given to `gfr install` that is
+/// really an index: a URL, `github:` location, directory or `.json`, `.yaml` or
+/// `.toml` path.
+fn take_index_location(names: &mut Vec<String>) -> Option<String> {
+    let position: usize = names.iter().position(|n: &String| {
+        let path: &Path = Path::new(n);
+        n.contains("://")
+            || http::is_github(n)
+            || path.is_dir()
+            || path.extension().is_some_and(|e: &std::ffi::OsStr| {
+                ["json", "yaml", "yml", "toml"]
+                    .iter()
+                    .any(|known: &&str| e.eq_ignore_ascii_case(known))
+            })
+    })?;
+    Some(names.remove(position))
+}
+
+/// Patterns named on the command line by short name, with the version asked for
+/// as in `aws-keys@1.2.0`.
+type Requested = BTreeMap<String, Option<String>>;
+
+/// Splits `name[@version]` arguments into the plain names and what they ask for.
+fn parse_requested(args: &[String]) -> (Vec<String>, Requested) {
+    let mut names: Vec<String> = Vec::new();
+    let mut requested: Requested = Requested::new();
+    for arg in args {
+        let (name, version): (&str, Option<String>) = match arg.rsplit_once('@') {
+            Some((name, version)) => (name, Some(version.trim_start_matches('v').to_string())),
+            None => (arg, None),
+        };
+        let short: &str = name.rsplit_once('/').map_or(name, |(_, s): (&str, &str)| s);
+        requested.insert(short.to_string(), version);
+        names.push(name.to_string());
+    }
+    (names, requested)
+}
+
+/// How `gfr install` and `gfr update` treat the patterns of an index.
+#[derive(Debug, Clone, Copy)]
+#[allow(clippy::struct_excessive_bools)] // Each bool mirrors a command-line flag.
+struct InstallOptions<'a> {
+    /// Leave patterns that aren't installed yet alone (`gfr update`).
+    installed_only: bool,
+    /// Skip patterns without a signature.
+    require_signed: bool,
+    /// Only report what would change.
+    dry_run: bool,
+    /// Overwrite patterns that were edited locally.
+    force: bool,
+    /// Install what supersedes deprecated patterns and remove those.
+    migrate: bool,
+    /// Patterns named on the command line; pinned ones are left alone otherwise.
+    requested: &'a Requested,
+    network: &'a NetworkOptions,
+}
+
+/// Installs from each source in turn. Named patterns and bundles come from the
+/// first source, by priority, that has them.
+async fn run_install(
+    sources: &[IndexSource],
+    names: &[String],
+    bundle: Option<&str>,
+    options: InstallOptions<'_>,
+    styles: &Styles,
+) -> Result<()> {
+    let mut remaining: Vec<String> = names.to_vec();
+    for source in sources {
+        let client: HttpClient = source.client(options.network)?;
+        let mut index: Index = fetch_index(&client, &source.url, styles).await?;
+        let several: bool = sources.len() > 1;
+        if !names.is_empty() {
+            let here: Vec<String>;
+            (here, remaining) = remaining
+                .into_iter()
+                .partition(|name: &String| !several || index.has_pattern(name));
+            if here.is_empty() {
+                continue;
+            }
+            index.retain_names(&here)?;
+        }
+        if let Some(bundle) = bundle {
+            if several && !index.has_bundle(bundle) {
+                continue;
+            }
+            select_bundle(&client, &mut index, bundle).await?;
+            index.select_versions(options.requested)?;
+            return install_index(&client, index, source, options, styles).await;
+        }
+        index.select_versions(options.requested)?;
+        install_index(&client, index, source, options, styles).await?;
+    }
+    if let Some(bundle) = bundle {
+        return Err(anyhow!("No configured registry has a bundle '{bundle}'."));
+    }
+    if !remaining.is_empty() {
+        return Err(anyhow!(
+            "No configured registry has a pattern named {}.",
+            remaining.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Narrows `index` down to `bundle`, which is either one of its bundles or a
+/// collection file in it.
+async fn select_bundle(client: &HttpClient, index: &mut Index, bundle: &str) -> Result<()> {
+    // A collection file in the index defines a bundle too.
+    let collection_url: Option<String> = index
+        .patterns
+        .iter()
+        .find(|p: &&IndexPattern| p.name == bundle)
+        .map(|p: &IndexPattern| p.url.clone());
+    match collection_url {
+        Some(collection_url) if !index.bundles.contains_key(bundle) => {
+            let collection: Collection =
+                serde_json::from_slice(&fetch(client, &collection_url).await?).with_context(
+                    || format!("Failed to parse collection '{bundle}' from {collection_url}"),
+                )?;
+            index
+                .bundles
+                .insert(bundle.to_string(), collection.patterns);
+            index.retain_bundle(bundle)?;
+            // Installed as a collection file below, not recorded as a bundle.
+            index.bundles.remove(bundle);
+        }
+        _ => index.retain_bundle(bundle)?,
+    }
+    Ok(())
+}
+
+/// A pattern of the index that will be downloaded and installed.
+struct PendingInstall {
+    remote: IndexPattern,
+    full_name: String,
+    /// Installed at the top level by a gfr from before namespaces.
+    legacy: bool,
+    /// Version installed now, if any.
+    installed_version: Option<String>,
+    status: PatternStatus,
+    /// Asked for as `name@version`.
+    pinned: bool,
+}
+
+impl PendingInstall {
+    fn update(&self) -> bool {
+        self.installed_version.is_some()
+    }
+
+    /// What is about to happen, or with `dry_run` what would, e.g.
+    /// "Updating 'xss' (v1.0.0 -> v1.1.0)".
+    fn action(&self, dry_run: bool) -> String {
+        let verb = |doing: &str, would: &str| {
+            if dry_run {
+                format!("Would {would}")
+            } else {
+                doing.to_string()
+            }
+        };
+        let (full_name, version): (&str, &str) = (&self.full_name, &self.remote.version);
+        match &self.installed_version {
+            None => format!(
+                "{} '{full_name}' (v{version})",
+                verb("Installing", "install")
+            ),
+            Some(installed) if self.status == PatternStatus::Outdated => format!(
+                "{} '{full_name}' (v{installed} -> v{version})",
+                verb("Updating", "update")
+            ),
+            Some(installed) if self.status == PatternStatus::Downgrade => format!(
+                "{} '{full_name}' (v{installed} -> v{version})",
+                verb("Downgrading", "downgrade")
+            ),
+            Some(_) => format!(
+                "{} '{full_name}' (v{version}, content changed)",
+                verb("Refreshing", "refresh")
+            ),
+        }
+    }
+}
+
+/// Installs new patterns from the index and refreshes those whose version or checksum changed.
+#[allow(clippy::too_many_lines)] // Fetching, policy checks and bookkeeping happen per pattern.
+async fn install_index(
+    client: &HttpClient,
+    index: Index,
+    source: &IndexSource,
+    options: InstallOptions<'_>,
+    styles: &Styles,
+) -> Result<()> {
+    let trusted_keys: TrustedKeys = TrustedKeys::load()?;
+    if options.require_signed && trusted_keys.is_empty() {
+        return Err(anyhow!(
+            "--require-signed needs at least one key in {}",
+            signing::TRUSTED_KEYS_FILE
+        ));
+    }
+    let url: &str = &source.url;
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let license_policy: Option<LicensePolicy> = load_user_config()?.license_policy;
+    let mut manifest: InstalledManifest = load_manifest().unwrap_or_default();
+    let mut updated_count: i32 = 0;
+    let mut added_count: i32 = 0;
+    let mut unchanged_count: i32 = 0;
+    let mut rejected_count: i32 = 0;
+    let mut kept_count: i32 = 0;
+    let namespace: String = index.namespace(url)?;
+    let origin: String = absolute_location(url)?;
+
+    let retired: Vec<(String, Option<String>)> = index.retired(&namespace, &manifest);
+    let replacements: HashSet<&str> = retired
+        .iter()
+        .filter(|_| options.migrate)
+        .filter_map(|(_, replacement): &(String, Option<String>)| replacement.as_deref())
+        .collect();
+    let retirement_notice = |old: &str, replacement: Option<&str>| match replacement {
+        Some(new) => println!(
+            "{} '{}' is deprecated; '{}' supersedes it. Run `gfr update --migrate` to switch.",
+            "!".style(styles.error),
+            old.style(styles.highlight),
+            new.style(styles.highlight)
+        ),
+        None => println!(
+            "{} '{}' is deprecated and has no replacement.",
+            "!".style(styles.error),
+            old.style(styles.highlight)
+        ),
+    };
+
+    let mut pending: Vec<PendingInstall> = Vec::new();
+    for remote_pattern in index.patterns {
+        let full_name: String = qualify(&namespace, &remote_pattern.name);
+        // Patterns installed before namespaces sit at the top level under their short name.
+        let legacy: bool =
+            !manifest.contains_key(&full_name) && manifest.contains_key(&remote_pattern.name);
+        let local: Option<&InstalledPattern> = manifest.get(if legacy {
+            &remote_pattern.name
+        } else {
+            &full_name
         });
-    } else {
-        // If data is piped to stdin, search it instead of files.
-        let mut printer: grep_printer::Standard<StandardStream> = StandardBuilder::new()
-            .color_specs(get_color_specs())
-            .build(StandardStream::stdout(get_color_choice()));
-        let mut searcher: Searcher = Searcher::new();
-        searcher.search_reader(&matcher, io::stdin(), printer.sink(&matcher))?;
+        let requested: Option<&Option<String>> = options.requested.get(&remote_pattern.name);
+        let pinned: bool = requested.is_some_and(Option::is_some);
+        // Pinned patterns only change when they are named.
+        if requested.is_none() && local.is_some_and(|l: &InstalledPattern| l.pinned) {
+            unchanged_count += 1;
+            continue;
+        }
+        let mut status: PatternStatus = remote_pattern.status(local)?;
+        if pinned
+            && status == PatternStatus::UpToDate
+            && local.is_some_and(|l: &InstalledPattern| l.version != remote_pattern.version)
+        {
+            status = PatternStatus::Downgrade;
+        }
+
+        if status == PatternStatus::UpToDate {
+            unchanged_count += 1;
+            // Entries from before lockfiles don't say where their file lives.
+            let key: &String = if legacy {
+                &remote_pattern.name
+            } else {
+                &full_name
+            };
+            if let Some(entry) = manifest.get_mut(key) {
+                if entry.url.is_none() {
+                    entry.url = Some(absolute_location(&remote_pattern.url)?);
+                }
+                // Naming a pattern pins it with a version and unpins it without one.
+                if requested.is_some() {
+                    entry.pinned = pinned;
+                }
+            }
+            continue;
+        }
+        // `gfr update` only refreshes what is already there, and what a migration brings in.
+        if options.installed_only
+            && status == PatternStatus::NotInstalled
+            && !replacements.contains(full_name.as_str())
+        {
+            continue;
+        }
+        // A pattern on its way out isn't worth updating.
+        if options.migrate && remote_pattern.deprecated && remote_pattern.superseded_by.is_some() {
+            continue;
+        }
+        let local_file: PathBuf = pattern_dir.join(format!(
+            "{}.json",
+            if legacy {
+                &remote_pattern.name
+            } else {
+                &full_name
+            }
+        ));
+        if !options.force && local.is_some_and(|l: &InstalledPattern| l.is_modified(&local_file)) {
+            println!(
+                "  -> Keeping '{}': {}",
+                full_name.style(styles.highlight),
+                "edited locally since it was installed".style(styles.error)
+            );
+            kept_count += 1;
+            continue;
+        }
+
+        if let Some(Err(reason)) = license_policy
+            .as_ref()
+            .map(|policy: &LicensePolicy| policy.check(remote_pattern.license.as_deref()))
+        {
+            println!(
+                "  -> Skipping '{}': {}",
+                full_name.style(styles.highlight),
+                reason.style(styles.error)
+            );
+            rejected_count += 1;
+            continue;
+        }
+        if options.require_signed && remote_pattern.signature.is_none() {
+            println!(
+                "  -> Skipping '{}': {}",
+                full_name.style(styles.highlight),
+                "the index lists no signature".style(styles.error)
+            );
+            rejected_count += 1;
+            continue;
+        }
+
+        pending.push(PendingInstall {
+            installed_version: local.map(|l: &InstalledPattern| l.version.clone()),
+            remote: remote_pattern,
+            full_name,
+            legacy,
+            status,
+            pinned,
+        });
+    }
+
+    if options.dry_run {
+        for pending in &pending {
+            println!(
+                "  -> {} from {}",
+                pending.action(true).style(styles.highlight),
+                pending.remote.url.style(styles.dim)
+            );
+        }
+        for (old, replacement) in &retired {
+            match replacement {
+                Some(new) if options.migrate => println!(
+                    "  -> {}",
+                    format!("Would migrate '{old}' to '{new}'").style(styles.highlight)
+                ),
+                _ => retirement_notice(old, replacement.as_deref()),
+            }
+        }
+        let (updates, additions): (Vec<&PendingInstall>, Vec<&PendingInstall>) =
+            pending.iter().partition(|p: &&PendingInstall| p.update());
+        println!(
+            "\n{} Dry run: would add {} new and update {} existing patterns; {} unchanged, {} skipped. Nothing was written.",
+            "i".style(styles.dim),
+            additions.len().to_string().style(styles.highlight),
+            updates.len().to_string().style(styles.highlight),
+            unchanged_count.to_string().style(styles.highlight),
+            (rejected_count + kept_count).to_string().style(styles.highlight)
+        );
+        return Ok(());
+    }
+    fs::create_dir_all(&pattern_dir)?;
+
+    let jobs: Vec<(String, Option<String>)> = pending
+        .iter()
+        .map(|p: &PendingInstall| {
+            let signature: Option<String> = p
+                .remote
+                .signature
+                .clone()
+                .filter(|_| !trusted_keys.is_empty());
+            (p.remote.url.clone(), signature)
+        })
+        .collect();
+    let downloads: Vec<Result<Download>> = download_all(client, jobs).await?;
+
+    // Checks a downloaded file and writes it to disk. `Ok(false)` means the pattern
+    // was rejected by policy, an error that it couldn't be installed.
+    let install_download = |pending: &PendingInstall, body: &[u8], signature: Option<Vec<u8>>| {
+        let remote_pattern: &IndexPattern = &pending.remote;
+        let full_name: &str = &pending.full_name;
+        if let Some(expected) = &remote_pattern.sha256 {
+            let checksum: String = sha256_hex(body);
+            if !expected.eq_ignore_ascii_case(&checksum) {
+                return Err(anyhow!(
+                    "checksum mismatch: index says {expected}, downloaded file is {checksum}"
+                ));
+            }
+        }
+        if let (Some(signature_url), Some(signature)) = (&remote_pattern.signature, signature) {
+            trusted_keys
+                .verify(body, &String::from_utf8_lossy(&signature))
+                .with_context(|| format!("signature check failed ({signature_url})"))?;
+        }
+        // Hand-written files may carry comments; what is written below is plain JSON.
+        let mut pattern_json: serde_json::Value = std::str::from_utf8(body)
+            .map_err(anyhow::Error::from)
+            .and_then(|text: &str| Ok(serde_json::from_str(&jsonc::strip(text))?))
+            .with_context(|| format!("Failed to parse pattern JSON from {}", remote_pattern.url))?;
+
+        if collections::is_collection(&pattern_json) {
+            // Collections only name patterns, so there is no license to check.
+            let collection: Collection = serde_json::from_value(pattern_json.clone())
+                .with_context(|| format!("Invalid collection '{full_name}'"))?;
+            // Members are patterns of the same index, so they share its namespace.
+            pattern_json["collection"] = serde_json::json!(collection
+                .patterns
+                .iter()
+                .map(|member: &String| qualify(&namespace, member))
+                .collect::<Vec<String>>());
+            collections::save_collection(&remote_pattern.name, &pattern_json)?;
+            return Ok(true);
+        }
+        migrate::upgrade(&mut pattern_json)
+            .with_context(|| format!("Can't install '{full_name}'"))?;
+        // Validate it's a valid Pattern struct before saving, it will save a lot of headaches later.
+        let pattern: Pattern = serde_json::from_value(pattern_json.clone())
+            .with_context(|| format!("Invalid pattern '{full_name}'"))?;
+        if let Err(e) = pattern.build_matcher() {
+            println!("{}", format!("invalid regex: {e:#}").style(styles.error));
+            return Ok(false);
+        }
+
+        // The pattern file itself may declare a license the index didn't mention.
+        if let Some(Err(reason)) = license_policy
+            .as_ref()
+            .map(|policy: &LicensePolicy| policy.check(pattern.license.as_deref()))
+        {
+            println!("{}", reason.style(styles.error));
+            return Ok(false);
+        }
+        record_index_license(&mut pattern_json, remote_pattern.license.as_deref());
+
+        let namespace_dir: PathBuf = pattern_dir.join(&namespace);
+        fs::create_dir_all(&namespace_dir)?;
+        let file_path: PathBuf = namespace_dir.join(format!("{}.json", remote_pattern.name));
+        let file: File = File::create(&file_path)?;
+        serde_json::to_writer_pretty(file, &pattern_json)?;
+        Ok(true)
+    };
+
+    let mut failed: Vec<(String, anyhow::Error)> = Vec::new();
+    for (pending, download) in pending.into_iter().zip(downloads) {
+        print!(
+            "  -> {} from {}... ",
+            pending.action(false).style(styles.highlight),
+            pending.remote.url.style(styles.dim)
+        );
+        io::stdout().flush()?;
+
+        // The copy being replaced is kept for `gfr rollback`.
+        let key: &str = if pending.legacy {
+            &pending.remote.name
+        } else {
+            &pending.full_name
+        };
+        let previous: Option<(InstalledPattern, Vec<u8>)> = manifest
+            .get(key)
+            .cloned()
+            .zip(fs::read(pattern_dir.join(format!("{key}.json"))).ok());
+        let installed: Result<(bool, String, bool)> =
+            download.and_then(|(body, signature): Download| {
+                let signed: bool = signature.is_some();
+                let written: bool = install_download(&pending, &body, signature)?;
+                Ok((written, sha256_hex(&body), signed))
+            });
+        let (checksum, signed): (String, bool) = match installed {
+            Ok((true, checksum, signed)) => (checksum, signed),
+            Ok((false, _, _)) => {
+                rejected_count += 1;
+                continue;
+            }
+            Err(e) => {
+                println!("{}", "Failed".style(styles.error));
+                failed.push((pending.full_name, e));
+                continue;
+            }
+        };
+        println!("{}", "Done".style(styles.success));
+
+        let PendingInstall {
+            remote: remote_pattern,
+            full_name,
+            legacy,
+            installed_version,
+            pinned,
+            ..
+        } = pending;
+        if let Some((entry, body)) = previous {
+            versions::remember(&full_name, &entry, &body)?;
+        }
+        if installed_version.is_some() {
+            updated_count += 1;
+        } else {
+            added_count += 1;
+        }
+        if legacy {
+            let legacy_file: PathBuf = pattern_dir.join(format!("{}.json", remote_pattern.name));
+            if legacy_file.is_file() {
+                fs::remove_file(&legacy_file)?;
+            }
+            manifest.remove(&remote_pattern.name);
+        }
+        let local_sha256: Option<String> =
+            file_sha256(&pattern_dir.join(format!("{full_name}.json")));
+        manifest.insert(
+            full_name,
+            InstalledPattern {
+                url: Some(absolute_location(&remote_pattern.url)?),
+                version: remote_pattern.version,
+                sha256: Some(checksum),
+                source: Some(origin.clone()),
+                registry: source.registry.clone(),
+                local_sha256,
+                pinned,
+                signed,
+                signature: remote_pattern
+                    .signature
+                    .as_deref()
+                    .filter(|_| signed)
+                    .map(absolute_location)
+                    .transpose()?,
+            },
+        );
+    }
+
+    for (old, replacement) in &retired {
+        match replacement {
+            Some(new) if options.migrate && manifest.contains_key(new) => {
+                let old_file: PathBuf = pattern_dir.join(format!("{old}.json"));
+                if old_file.is_file() {
+                    fs::remove_file(&old_file)?;
+                }
+                manifest.remove(old);
+                println!(
+                    "  -> Migrated '{}' to '{}'",
+                    old.style(styles.highlight),
+                    new.style(styles.highlight)
+                );
+            }
+            Some(new) if options.migrate => println!(
+                "{} Kept '{}': '{}' could not be installed.",
+                "!".style(styles.error),
+                old.style(styles.highlight),
+                new.style(styles.highlight)
+            ),
+            _ => retirement_notice(old, replacement.as_deref()),
+        }
+    }
+
+    save_manifest(&manifest)?;
+    let bundles: Bundles = index
+        .bundles
+        .into_iter()
+        .map(|(bundle, members): (String, Vec<String>)| {
+            let members: Vec<String> = members
+                .iter()
+                .map(|member: &String| qualify(&namespace, member))
+                .collect();
+            (bundle, members)
+        })
+        .collect();
+    save_bundles(&bundles)?;
+    println!(
+        "\n{} Installation complete. Added {} new, updated {} existing, {} unchanged patterns.",
+        "✓".style(styles.success),
+        added_count.to_string().style(styles.highlight),
+        updated_count.to_string().style(styles.highlight),
+        unchanged_count.to_string().style(styles.highlight)
+    );
+    if rejected_count > 0 {
+        println!(
+            "{} {} patterns were rejected by the license policy, as unsigned or as invalid.",
+            "!".style(styles.error),
+            rejected_count.to_string().style(styles.highlight)
+        );
+    }
+    if kept_count > 0 {
+        println!(
+            "{} {} patterns were edited locally and kept; pass --force to overwrite them.",
+            "!".style(styles.error),
+            kept_count.to_string().style(styles.highlight)
+        );
+    }
+    if !failed.is_empty() {
+        println!(
+            "{} {} patterns could not be installed:",
+            "✗".style(styles.error),
+            failed.len().to_string().style(styles.highlight)
+        );
+        for (name, error) in &failed {
+            println!(
+                "  - {}: {}",
+                name.style(styles.highlight),
+                format!("{error:#}").style(styles.dim)
+            );
+        }
+        return Err(anyhow!(
+            "{} of the index's patterns failed to install.",
+            failed.len()
+        ));
     }
 
     Ok(())
 }
 
-async fn run_install(url: &str, styles: &Styles) -> Result<()> {
+/// Most pattern files downloaded at once.
+const DOWNLOAD_CONCURRENCY: usize = 8;
+
+/// A downloaded pattern file, and its signature if one is checked.
+type Download = (Vec<u8>, Option<Vec<u8>>);
+
+/// Downloads each file and optional signature in `jobs`, at most
+/// `DOWNLOAD_CONCURRENCY` at a time, on a single progress line. A failed job
+/// doesn't stop the others; the results are in the order of `jobs`.
+async fn download_all(
+    client: &HttpClient,
+    jobs: Vec<(String, Option<String>)>,
+) -> Result<Vec<Result<Download>>> {
+    let total: usize = jobs.len();
+    if total == 0 {
+        return Ok(Vec::new());
+    }
+    let terminal: bool = io::stdout().is_terminal();
+    let mut downloads: Vec<Option<Result<Download>>> = (0..total).map(|_| None).collect();
+    let mut running: JoinSet<(usize, Result<Download>)> = JoinSet::new();
+    let mut jobs = jobs.into_iter().enumerate();
+    let mut done: usize = 0;
+
+    let outcome: Result<()> = async {
+        loop {
+            while running.len() < DOWNLOAD_CONCURRENCY {
+                let Some((position, (url, signature))) = jobs.next() else {
+                    break;
+                };
+                let client: HttpClient = client.clone();
+                running.spawn(async move {
+                    let download = async {
+                        let body: Vec<u8> = fetch(&client, &url).await?;
+                        let signature: Option<Vec<u8>> = match signature {
+                            Some(signature) => Some(fetch(&client, &signature).await?),
+                            None => None,
+                        };
+                        Ok((body, signature))
+                    };
+                    (position, download.await)
+                });
+            }
+            let Some(joined) = running.join_next().await else {
+                return Ok(());
+            };
+            let (position, download): (usize, Result<Download>) = joined?;
+            downloads[position] = Some(download);
+            done += 1;
+            // Off a terminal, only the final count is worth a line.
+            if terminal || done == total {
+                print!("\r  -> Downloaded {done}/{total} files");
+                io::stdout().flush()?;
+            }
+        }
+    }
+    .await;
+    // End the progress line whether or not everything arrived.
+    println!();
+    outcome?;
+    Ok(downloads.into_iter().flatten().collect())
+}
+
+/// Whether an index or pattern location is a URL rather than a path on disk.
+fn is_remote(location: &str) -> bool {
+    location.starts_with("http://") || location.starts_with("https://") || http::is_github(location)
+}
+
+/// A URL as is, or a path made absolute so the manifest stays valid from any directory.
+fn absolute_location(location: &str) -> Result<String> {
+    if is_remote(location) {
+        return Ok(location.to_string());
+    }
+    Ok(fs::canonicalize(location)?.display().to_string())
+}
+
+/// Reads an index or pattern file from a URL or, for offline installs, from disk.
+async fn fetch(client: &HttpClient, location: &str) -> Result<Vec<u8>> {
+    if !is_remote(location) {
+        return fs::read(location).with_context(|| format!("Failed to read {location}"));
+    }
+    client.get(location).await
+}
+
+/// Loads an index from a URL, an index file on disk, or a directory of pattern
+/// files. Relative pattern paths in an index on disk are relative to the index.
+async fn fetch_index(client: &HttpClient, url: &str, styles: &Styles) -> Result<Index> {
     println!(
         "{} Fetching pattern index from {}...",
         "i".style(styles.info),
         url.style(styles.highlight)
     );
 
-    let client: reqwest::Client = reqwest::Client::new();
-    let index: Index = client
-        .get(url)
-        .send()
-        .await?
-        .json()
-        .await
-        .with_context(|| format!("Failed to fetch or parse index from {url}"))?;
+    let local: &Path = Path::new(url);
+    let index_in_dir: Option<PathBuf> = Index::file_in(local).filter(|_| local.is_dir());
+    let index: Index = if local.is_dir() && index_in_dir.is_none() {
+        Index::from_directory(local)?
+    } else {
+        let index_file: String = index_in_dir.map_or_else(
+            || url.to_string(),
+            |path: PathBuf| path.display().to_string(),
+        );
+        let mut index: Index = fetch(client, &index_file)
+            .await
+            .and_then(|body: Vec<u8>| Index::parse(&index_file, &body))
+            .with_context(|| format!("Failed to fetch or parse index from {url}"))?;
+        if !is_remote(&index_file) {
+            let base: &Path = Path::new(&index_file).parent().unwrap_or(Path::new("."));
+            index.resolve_locations(|location: &str| {
+                (!is_remote(location) && Path::new(location).is_relative())
+                    .then(|| base.join(location).display().to_string())
+            });
+        } else if let Some(github) = GitHubLocation::parse(&index_file) {
+            // Files of a `github:` index are usually given relative to it, in the same repository.
+            index.resolve_locations(|location: &str| {
+                (!location.contains("://") && !http::is_github(location))
+                    .then(|| github.join(location))
+            });
+        } else if let Ok(base) = reqwest::Url::parse(&index_file) {
+            // As written by `gfr index` without --base-url.
+            index.resolve_locations(|location: &str| {
+                (!location.contains("://") && !http::is_github(location))
+                    .then(|| base.join(location).ok())
+                    .flatten()
+                    .map(String::from)
+            });
+        }
+        index
+    };
+    index.check_names()?;
 
     println!(
         "{} Found {} patterns in index.",
         "✓".style(styles.success),
         index.patterns.len().to_string().style(styles.highlight),
     );
+    Ok(index)
+}
 
-    let pattern_dir: PathBuf = get_pattern_dir()?;
-    fs::create_dir_all(&pattern_dir)?;
-    let mut manifest: HashMap<String, String> = load_manifest().unwrap_or_default();
-    let mut updated_count: i32 = 0;
-    let mut added_count: i32 = 0;
+/// Writes the license an index declared for a pattern into the pattern file, if
+/// the file declares none, so `gfr list` and `gfr dump` show what it was
+/// installed under.
+fn record_index_license(pattern_json: &mut serde_json::Value, license: Option<&str>) {
+    if let (Some(license), Some(fields)) = (license, pattern_json.as_object_mut()) {
+        if fields.get("license").is_none_or(serde_json::Value::is_null) {
+            fields.insert("license".to_string(), serde_json::json!(license));
+        }
+    }
+}
 
-    for remote_pattern in index.patterns {
-        let local_version_str: Option<&String> = manifest.get(&remote_pattern.name);
-        let remote_version: Version = Version::parse(&remote_pattern.version)?;
-
-        let should_install: bool = local_version_str
-            .and_then(|local_v_str: &String| Version::parse(local_v_str).ok())
-            .is_none_or(|local_version: Version| local_version < remote_version);
-
-        if should_install {
-            print!(
-                "  -> Installing/Updating '{}' (v{}) from {}... ",
-                remote_pattern.name.style(styles.highlight),
-                remote_pattern.version,
-                remote_pattern.url.style(styles.dim)
-            );
-            io::stdout().flush()?;
-
-            let pattern_response: reqwest::Response =
-                client.get(&remote_pattern.url).send().await?;
-            let pattern_json: serde_json::Value =
-                pattern_response.json().await.with_context(|| {
-                    format!(
-                        "Failed to fetch or parse pattern JSON from {}",
-                        remote_pattern.url
-                    )
-                })?;
+/// Whether `name` can be used as a file name inside the pattern directory.
+fn is_plain_name(name: &str) -> bool {
+    !(name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) || name.contains(".."))
+}
 
-            // Validate it's a valid Pattern struct before saving, it will save a lot of headaches later.
-            let _: Pattern = serde_json::from_value(pattern_json.clone())?;
+/// Rejects full names other than `name` and `namespace/name` made of plain file
+/// names, for files and lockfiles that name installed patterns can come from anyone.
+fn check_full_name(full_name: &str) -> Result<()> {
+    let plain: bool = match full_name.split_once('/') {
+        Some((namespace, name)) => {
+            namespace != collections::COLLECTIONS_DIR
+                && is_plain_name(namespace)
+                && is_plain_name(name)
+        }
+        None => is_plain_name(full_name),
+    };
+    if plain {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "'{full_name}' is not a pattern name; names are 'name' or 'namespace/name', and neither part can be empty, start with '.' or contain '\\' or '..'."
+        ))
+    }
+}
 
-            let file_path: PathBuf = pattern_dir.join(format!("{}.json", remote_pattern.name));
-            let file: File = File::create(&file_path)?;
-            serde_json::to_writer_pretty(file, &pattern_json)?;
+/// The full name of pattern `name` of an index installed into `namespace`.
+fn qualify(namespace: &str, name: &str) -> String {
+    if name.contains('/') {
+        name.to_string()
+    } else {
+        format!("{namespace}/{name}")
+    }
+}
 
-            println!("{}", "Done".style(styles.success));
+/// Prints installed patterns that the index has newer copies of, without writing anything.
+async fn run_update_check(
+    source: &IndexSource,
+    network: &NetworkOptions,
+    styles: &Styles,
+) -> Result<()> {
+    let url: &str = &source.url;
+    let mut index: Index = fetch_index(&source.client(network)?, url, styles).await?;
+    index.select_versions(&Requested::new())?;
+    let manifest: InstalledManifest = load_manifest()?;
 
-            if local_version_str.is_some() {
-                updated_count += 1;
-            } else {
-                added_count += 1;
+    let mut outdated: Vec<(&IndexPattern, &InstalledPattern, PatternStatus)> = Vec::new();
+    let namespace: String = index.namespace(url)?;
+    for remote_pattern in &index.patterns {
+        let local: Option<&InstalledPattern> = manifest
+            .get(&qualify(&namespace, &remote_pattern.name))
+            .or_else(|| manifest.get(&remote_pattern.name))
+            .filter(|local: &&InstalledPattern| !local.pinned);
+        if let Some(local) = local {
+            let status: PatternStatus = remote_pattern.status(Some(local))?;
+            if matches!(
+                status,
+                PatternStatus::Outdated | PatternStatus::ContentChanged
+            ) {
+                outdated.push((remote_pattern, local, status));
             }
-            manifest.insert(remote_pattern.name, remote_pattern.version);
         }
     }
 
-    save_manifest(&manifest)?;
+    if outdated.is_empty() {
+        println!(
+            "{} All installed patterns are up to date.",
+            "✓".style(styles.success)
+        );
+        return Ok(());
+    }
+
+    outdated.sort_by(|a: &(&IndexPattern, &InstalledPattern, PatternStatus), b| {
+        a.0.name.cmp(&b.0.name)
+    });
+    let width: usize = outdated
+        .iter()
+        .map(|(remote, _, _): &(&IndexPattern, &InstalledPattern, PatternStatus)| remote.name.len())
+        .max()
+        .unwrap_or_default()
+        .max("PATTERN".len());
     println!(
-        "\n{} Installation complete. Added {} new, updated {} existing patterns.",
-        "✓".style(styles.success),
-        added_count.to_string().style(styles.highlight),
-        updated_count.to_string().style(styles.highlight)
+        "\n{}",
+        format!(
+            "{:<width$}  {:<10}  {:<10}  NOTE",
+            "PATTERN", "INSTALLED", "AVAILABLE"
+        )
+        .style(styles.title)
+    );
+    for (remote, local, status) in &outdated {
+        let note: &str = if *status == PatternStatus::ContentChanged {
+            "content changed"
+        } else {
+            ""
+        };
+        println!(
+            "{}  {:<10}  {:<10}  {}",
+            format!("{:<width$}", remote.name).style(styles.highlight),
+            local.version,
+            remote.version,
+            note.style(styles.dim)
+        );
+    }
+    println!(
+        "\n{} {} patterns can be updated. Run `gfr update` to apply.",
+        "i".style(styles.info),
+        outdated.len().to_string().style(styles.highlight)
     );
 
     Ok(())
@@ -465,19 +4137,13 @@ fn run_list(styles: &Styles) -> Result<()> {
         return Ok(());
     }
 
-    let mut patterns: Vec<(String, Option<Pattern>)> = Vec::new();
-    for entry in fs::read_dir(pattern_dir)?.filter_map(Result::ok) {
-        let path: PathBuf = entry.path();
-        if path
-            .extension()
-            .is_some_and(|e: &std::ffi::OsStr| e == "json")
-            && entry.file_name().to_str() != Some(INSTALLED_MANIFEST_FILE)
-        {
-            if let Some(name) = path.file_stem().and_then(std::ffi::OsStr::to_str) {
-                patterns.push((name.to_string(), load_pattern(name).ok()));
-            }
-        }
-    }
+    let mut patterns: Vec<(String, Result<Pattern>)> = list_pattern_names(&pattern_dir)?
+        .into_iter()
+        .map(|name: String| {
+            let pattern: Result<Pattern> = load_pattern(&name);
+            (name, pattern)
+        })
+        .collect();
 
     if patterns.is_empty() {
         println!(
@@ -487,39 +4153,109 @@ fn run_list(styles: &Styles) -> Result<()> {
         return Ok(());
     }
 
-    patterns.sort_by(|a: &(String, Option<Pattern>), b: &(String, Option<Pattern>)| a.0.cmp(&b.0));
+    patterns.sort_by(|a: &(String, Result<Pattern>), b: &(String, Result<Pattern>)| a.0.cmp(&b.0));
+    let aliases: Aliases = aliases::load_aliases()?;
+    let manifest: InstalledManifest = load_manifest()?;
 
-    for (name, pattern_opt) in patterns {
-        if let Some(p) = pattern_opt {
-            let desc: &str = p.description.as_deref().unwrap_or("No description");
-            let tags: String = p
-                .tags
-                .map(|t: Vec<String>| format!("[{}]", t.join(", ")))
-                .unwrap_or_default();
-            println!(
-                "  {} {} - {}",
-                name.style(styles.highlight),
-                tags.style(styles.info),
-                desc.style(styles.dim)
-            );
-            println!(
-                "    v{} by {}",
-                p.version,
-                p.author.as_deref().unwrap_or("Unknown").style(styles.dim)
-            );
-        } else {
-            println!(
-                "  {} - {}",
-                name.style(styles.error),
-                "Invalid pattern file".style(styles.error)
-            );
+    let has_invalid: bool = patterns
+        .iter()
+        .any(|(_, p): &(String, Result<Pattern>)| p.is_err());
+    for (name, pattern_result) in patterns {
+        match pattern_result {
+            Ok(p) => {
+                let desc: &str = p.description.as_deref().unwrap_or("No description");
+                let tags: String = p
+                    .tags
+                    .map(|t: Vec<String>| format!("[{}]", t.join(", ")))
+                    .unwrap_or_default();
+                let modified: bool = manifest.get(&name).is_some_and(|i: &InstalledPattern| {
+                    pattern_file(&pattern_dir, &name)
+                        .is_some_and(|path: PathBuf| i.is_modified(&path))
+                });
+                println!(
+                    "  {} {}{} - {}",
+                    name.style(styles.highlight),
+                    tags.style(styles.info),
+                    if modified { " (modified locally)" } else { "" }.style(styles.error),
+                    desc.style(styles.dim)
+                );
+                println!(
+                    "    v{} by {} ({})",
+                    p.version,
+                    p.author.as_deref().unwrap_or("Unknown").style(styles.dim),
+                    p.license
+                        .as_deref()
+                        .unwrap_or("no license")
+                        .style(styles.dim)
+                );
+                if let Some(severity) = p.severity {
+                    println!("    severity: {}", severity.style(styles.info));
+                }
+                let alias_names: Vec<&str> = aliases
+                    .iter()
+                    .filter(|(_, target): &(&String, &String)| **target == name)
+                    .map(|(alias, _): (&String, &String)| alias.as_str())
+                    .collect();
+                if !alias_names.is_empty() {
+                    println!("    aliases: {}", alias_names.join(", ").style(styles.info));
+                }
+            }
+            Err(e) => {
+                println!(
+                    "  {} - {}",
+                    name.style(styles.error),
+                    "Invalid pattern file".style(styles.error)
+                );
+                println!("    {}", format!("{e:#}").style(styles.dim));
+            }
         }
     }
+    print_collections(styles)?;
+    if has_invalid {
+        println!(
+            "\n{}",
+            "Run `gfr validate <name>` for details on invalid patterns.".style(styles.dim)
+        );
+    }
+
+    Ok(())
+}
 
+/// Lists installed collections with their member patterns.
+fn print_collections(styles: &Styles) -> Result<()> {
+    let collection_names: Vec<String> = collections::list_collections()?;
+    if !collection_names.is_empty() {
+        println!(
+            "\n{}",
+            "Collections (search with @name):".style(styles.title)
+        );
+    }
+    for name in collection_names {
+        match collections::load_collection(&name) {
+            Ok(Some(collection)) => {
+                println!(
+                    "  {} - {}",
+                    format!("@{name}").style(styles.highlight),
+                    collection
+                        .description
+                        .as_deref()
+                        .unwrap_or("No description")
+                        .style(styles.dim)
+                );
+                println!("    {}", collection.patterns.join(", "));
+            }
+            Ok(None) => {}
+            Err(e) => println!(
+                "  {} - {}",
+                format!("@{name}").style(styles.error),
+                format!("{e:#}").style(styles.dim)
+            ),
+        }
+    }
     Ok(())
 }
 
-/// Saves a new pattern to a JSON file.
+/// Saves a new pattern to a JSON or YAML file.
 fn run_save(args: SaveArgs, styles: &Styles) -> Result<()> {
     if args.name.contains(['.', '/', '\\']) {
         return Err(anyhow!(
@@ -530,44 +4266,202 @@ fn run_save(args: SaveArgs, styles: &Styles) -> Result<()> {
 
     let pattern_dir = get_pattern_dir()?;
     fs::create_dir_all(&pattern_dir)?;
-    let pattern_file_path = pattern_dir.join(format!("{}.json", args.name));
+    let pattern_file_path: PathBuf =
+        pattern_dir.join(format!("{}.{}", args.name, args.format.extension()));
+
+    if pattern_file(&pattern_dir, &args.name).is_some() {
+        return Err(anyhow!(
+            "Pattern '{}' already exists.",
+            args.name.style(styles.highlight)
+        ));
+    }
+
+    let (regex, regex_list, strings): (Option<String>, Option<Vec<String>>, Option<Vec<String>>) =
+        if args.fixed_strings {
+            (None, None, Some(args.patterns))
+        } else if let [regex] = args.patterns.as_slice() {
+            (Some(regex.clone()), None, None)
+        } else {
+            (None, Some(args.patterns), None)
+        };
+    let new_pattern = Pattern {
+        name: args.name.clone(),
+        schema: Some(DEFAULT_PATTERN_SCHEMA_URL.to_string())
+            .filter(|_| args.format == PatternFormat::Json),
+        schema_version: Some(migrate::SCHEMA_VERSION),
+        version: "1.0.0".to_string(),
+        author: args.author,
+        license: args.license,
+        description: args.description,
+        tags: args.tags,
+        severity: args.severity,
+        regex,
+        regex_list,
+        strings,
+        keywords: args.keywords,
+        file_types: args.file_types,
+        exclude_file_types: args.exclude_file_types,
+        languages: args.languages,
+        paths: args.paths,
+        paths_exclude: args.paths_exclude,
+        ignore_case: args.ignore_case,
+        multiline: args.multiline,
+        word: args.word,
+        unicode: !args.no_unicode,
+        engine: args.engine,
+        tests: None,
+        min_entropy: None,
+        entropy_group: None,
+        validators: None,
+        verification: None,
+        plugins: None,
+        deprecated: false,
+        superseded_by: None,
+    };
+    let mut file: File = File::create(&pattern_file_path)?;
+    match args.format {
+        PatternFormat::Json => serde_json::to_writer_pretty(file, &new_pattern)?,
+        PatternFormat::Yaml => {
+            // YAML editors pick the schema up from this comment rather than a `$schema` key.
+            writeln!(
+                file,
+                "# yaml-language-server: $schema={DEFAULT_PATTERN_SCHEMA_URL}"
+            )?;
+            let mut value: serde_yaml::Value = serde_yaml::to_value(&new_pattern)?;
+            if let Some(fields) = value.as_mapping_mut() {
+                fields.retain(|_, field: &mut serde_yaml::Value| !field.is_null());
+            }
+            serde_yaml::to_writer(file, &value)?;
+        }
+    }
+
+    println!(
+        "{} Pattern '{}' saved to {}",
+        "✓".style(styles.success),
+        args.name.style(styles.highlight),
+        pattern_file_path.display().style(styles.dim)
+    );
+
+    Ok(())
+}
+
+/// Deletes pattern files and their entries in the installed manifest.
+fn run_remove(args: &RemoveArgs, styles: &Styles) -> Result<()> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let local: Vec<String> = if pattern_dir.exists() {
+        list_pattern_names(&pattern_dir)?
+    } else {
+        Vec::new()
+    };
+    let mut manifest: InstalledManifest = load_manifest()?;
+
+    let names: Vec<String> = if args.all {
+        let mut all: Vec<String> = local.clone();
+        all.sort();
+        all
+    } else {
+        let requested: Vec<String> = args
+            .names
+            .iter()
+            .map(|name: &String| {
+                Ok(resolve_pattern_name(&pattern_dir, name)?.unwrap_or_else(|| name.clone()))
+            })
+            .collect::<Result<_>>()?;
+        select_removals(&requested, &local, &manifest, args.force)?
+    };
+    if names.is_empty() {
+        println!("{}", "No patterns to remove.".style(styles.dim));
+        return Ok(());
+    }
+
+    if !args.yes {
+        if !io::stdin().is_terminal() {
+            return Err(anyhow!(
+                "Refusing to remove patterns without confirmation; pass --yes to skip the prompt."
+            ));
+        }
+        print!(
+            "Remove {} pattern(s): {}? [y/N] ",
+            names.len(),
+            names.join(", ").style(styles.highlight)
+        );
+        io::stdout().flush()?;
+        let mut answer: String = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            println!("{}", "Aborted.".style(styles.dim));
+            return Ok(());
+        }
+    }
+
+    for name in &names {
+        if let Some(pattern_file) = pattern_file(&pattern_dir, name) {
+            fs::remove_file(&pattern_file)
+                .with_context(|| format!("Failed to remove {}", pattern_file.display()))?;
+        }
+        manifest.remove(name);
+        println!(
+            "{} Removed '{}'",
+            "✓".style(styles.success),
+            name.style(styles.highlight)
+        );
+    }
+    if pattern_dir.exists() {
+        save_manifest(&manifest)?;
+    }
 
-    if pattern_file_path.exists() {
+    Ok(())
+}
+
+/// Resolves the names given to `gfr remove`, in order and without duplicates.
+/// A name is known if it has a local file or a manifest entry; unknown names are
+/// an error unless `force` is set, in which case they're left out.
+fn select_removals(
+    names: &[String],
+    local: &[String],
+    manifest: &InstalledManifest,
+    force: bool,
+) -> Result<Vec<String>> {
+    let mut selected: Vec<String> = Vec::new();
+    let mut unknown: Vec<&str> = Vec::new();
+    for name in names {
+        if selected.contains(name) {
+            continue;
+        }
+        if local.contains(name) || manifest.contains_key(name) {
+            selected.push(name.clone());
+        } else if !force {
+            unknown.push(name);
+        }
+    }
+    if !unknown.is_empty() {
         return Err(anyhow!(
-            "Pattern '{}' already exists.",
-            args.name.style(styles.highlight)
+            "Unknown pattern(s): {}. Use --force to ignore them.",
+            unknown.join(", ")
         ));
     }
+    Ok(selected)
+}
 
-    let new_pattern = Pattern {
-        schema: Some(DEFAULT_PATTERN_SCHEMA_URL.to_string()),
-        version: "1.0.0".to_string(),
-        author: args.author,
-        description: args.description,
-        tags: args.tags,
-        regex: Some(args.pattern),
-        regex_list: None,
-        file_types: args.file_types,
-        ignore_case: args.ignore_case,
-        multiline: args.multiline,
-    };
-    let file = File::create(&pattern_file_path)?;
-    serde_json::to_writer_pretty(file, &new_pattern)?;
-
-    println!(
-        "{} Pattern '{}' saved to {}",
-        "✓".style(styles.success),
-        args.name.style(styles.highlight),
-        pattern_file_path.display().style(styles.dim)
-    );
-
+/// Prints the file types `--type` takes, and pattern `file_types` too unless
+/// they come from `additions`.
+fn run_type_list(additions: &[String], styles: &Styles) -> Result<()> {
+    for def in filetype::definitions(additions)? {
+        println!(
+            "{}: {}",
+            def.name().style(styles.highlight),
+            def.globs().join(", ")
+        );
+    }
     Ok(())
 }
 
 /// Prints the configuration of a pattern without executing a search.
 fn run_dump(pattern_name: &str, styles: &Styles) -> Result<()> {
     let pattern: Pattern = load_pattern(pattern_name)?;
-    let pattern_path: PathBuf = get_pattern_dir()?.join(format!("{pattern_name}.json"));
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let pattern_path: PathBuf = pattern_file(&pattern_dir, &pattern.name)
+        .unwrap_or_else(|| pattern_dir.join(format!("{}.json", pattern.name)));
 
     println!(
         "{}",
@@ -578,6 +4472,11 @@ fn run_dump(pattern_name: &str, styles: &Styles) -> Result<()> {
         "Loaded from:".style(styles.dim),
         pattern_path.display()
     );
+    println!(
+        "{} {}",
+        "License:".style(styles.dim),
+        pattern.license.as_deref().unwrap_or("none declared")
+    );
     println!("---");
     let json_string: String = serde_json::to_string_pretty(&pattern)?;
     println!("{json_string}");
@@ -587,73 +4486,66 @@ fn run_dump(pattern_name: &str, styles: &Styles) -> Result<()> {
 
 // --- Filesystem and Pattern Loading Utilities ---
 
-fn get_pattern_dir() -> Result<PathBuf> {
-    dirs::config_dir()
-        .ok_or_else(|| anyhow!("Could not determine user's config directory."))
-        .map(|dir: PathBuf| dir.join(CONFIG_DIR))
-}
-
-fn load_pattern(name: &str) -> Result<Pattern> {
-    let pattern_file: PathBuf = get_pattern_dir()?.join(format!("{name}.json"));
-    if !pattern_file.exists() {
-        return Err(anyhow!(
-            "Pattern file not found: {}",
-            pattern_file.display()
-        ));
-    }
-    let file: File = File::open(&pattern_file)?;
-    serde_json::from_reader(file)
-        .with_context(|| format!("Failed to parse JSON from: {}", pattern_file.display()))
-}
-
 fn find_patterns_by_filter(
-    name: Option<String>,
-    tags: Option<&[String]>,
+    names: &[String],
+    tags: Option<&TagExpr>,
     author: Option<&str>,
+    bundle: Option<&str>,
+    skip: &[String],
+    fuzzy: bool,
     styles: &Styles,
 ) -> Result<Vec<Pattern>> {
-    if let Some(name) = name {
-        let p: Pattern = load_pattern(&name).with_context(|| {
-            format!(
-                "Failed to load pattern '{}'. Try '{}' to see available patterns.",
-                name.style(styles.highlight),
-                "gfr list".style(styles.highlight)
-            )
-        })?;
-        return Ok(vec![p]);
-    }
-
     let mut matched_patterns: Vec<Pattern> = Vec::new();
-    let pattern_dir: PathBuf = get_pattern_dir()?;
-    if !pattern_dir.exists() {
-        return Ok(matched_patterns); // No patterns to filter.
-    }
+    if !names.is_empty() {
+        // `@name` stands for all patterns of a collection or bundle.
+        for name in names {
+            let loaded: Vec<Pattern> = match name.strip_prefix('@') {
+                Some(bundle) => load_bundle_patterns(bundle, styles)?,
+                None => vec![resolve_pattern(name, fuzzy, styles)?],
+            };
+            for p in loaded {
+                if !matched_patterns.iter().any(|m: &Pattern| m.name == p.name) {
+                    matched_patterns.push(p);
+                }
+            }
+        }
+    } else if let Some(bundle) = bundle {
+        matched_patterns = load_bundle_patterns(bundle, styles)?;
+    } else {
+        let pattern_dir: PathBuf = get_pattern_dir()?;
+        if !pattern_dir.exists() {
+            return Ok(matched_patterns); // No patterns to filter.
+        }
 
-    for entry in fs::read_dir(pattern_dir)?.filter_map(Result::ok) {
-        let path: PathBuf = entry.path();
-        if path
-            .extension()
-            .is_some_and(|e: &std::ffi::OsStr| e == "json")
-            && entry.file_name().to_str() != Some(INSTALLED_MANIFEST_FILE)
-        {
-            if let Some(name) = path.file_stem().and_then(std::ffi::OsStr::to_str) {
-                if let Ok(p) = load_pattern(name) {
-                    let author_match: bool =
-                        author.is_none_or(|a: &str| p.author.as_deref() == Some(a));
-                    let tags_match: bool = tags.is_none_or(|search_tags: &[String]| {
-                        p.tags.as_ref().is_some_and(|p_tags: &Vec<String>| {
-                            search_tags.iter().all(|st: &String| p_tags.contains(st))
-                        })
-                    });
-
-                    if author_match && tags_match {
-                        matched_patterns.push(p);
-                    }
+        for name in list_pattern_names(&pattern_dir)? {
+            if let Ok(p) = load_pattern(&name) {
+                let author_match: bool =
+                    author.is_none_or(|a: &str| p.author.as_deref() == Some(a));
+                let tags_match: bool = tags.is_none_or(|expr: &TagExpr| {
+                    expr.matches(p.tags.as_deref().unwrap_or_default())
+                });
+
+                if author_match && tags_match {
+                    matched_patterns.push(p);
                 }
             }
         }
     }
 
+    for skipped in skip {
+        if !matched_patterns
+            .iter()
+            .any(|p: &Pattern| &p.name == skipped)
+        {
+            eprintln!(
+                "{} --skip-pattern '{}' is not among the selected patterns.",
+                "Warning:".style(styles.error),
+                skipped.style(styles.highlight)
+            );
+        }
+    }
+    matched_patterns.retain(|p: &Pattern| !skip.contains(&p.name));
+
     if matched_patterns.is_empty() {
         return Err(anyhow!(
             "No patterns found matching the specified criteria."
@@ -663,6 +4555,125 @@ fn find_patterns_by_filter(
     Ok(matched_patterns)
 }
 
+/// Loads a pattern named on the command line. A name that isn't installed gets
+/// suggestions of close ones, or with `fuzzy`, the one close name if there is one.
+fn resolve_pattern(name: &str, fuzzy: bool, styles: &Styles) -> Result<Pattern> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let aliases: Aliases = aliases::load_aliases()?;
+    if resolve_pattern_name(&pattern_dir, name)?.is_none()
+        && !aliases.contains_key(name)
+        && pattern_dir.exists()
+    {
+        let mut known: Vec<String> = list_pattern_names(&pattern_dir)?;
+        known.extend(aliases.into_keys());
+        let suggestions: Vec<String> = close_names(name, known);
+        if let [only] = suggestions.as_slice() {
+            if fuzzy {
+                eprintln!(
+                    "{} Using '{}' for '{}'.",
+                    "Note:".style(styles.info),
+                    only.style(styles.highlight),
+                    name.style(styles.highlight)
+                );
+                return load_pattern(only);
+            }
+        }
+        if !suggestions.is_empty() {
+            let quoted: Vec<String> = suggestions
+                .iter()
+                .map(|s: &String| format!("'{}'", s.style(styles.highlight)))
+                .collect();
+            return Err(anyhow!(
+                "Pattern '{}' is not installed. Did you mean {}?",
+                name.style(styles.highlight),
+                quoted.join(" or ")
+            ));
+        }
+    }
+    load_pattern(name).with_context(|| {
+        format!(
+            "Failed to load pattern '{}'. Try '{}' to see available patterns.",
+            name.style(styles.highlight),
+            "gfr list".style(styles.highlight)
+        )
+    })
+}
+
+/// Up to three of `available` within a few typos of `name`, closest first. A short
+/// name is compared with the short names of namespaced patterns.
+fn close_names(name: &str, available: Vec<String>) -> Vec<String> {
+    let allowed: usize = (name.chars().count() / 3).max(1);
+    let mut close: Vec<(usize, String)> = available
+        .into_iter()
+        .map(|candidate: String| {
+            let compared: &str = match candidate.rsplit_once('/') {
+                Some((_, short)) if !name.contains('/') => short,
+                _ => &candidate,
+            };
+            (strsim::damerau_levenshtein(name, compared), candidate)
+        })
+        .filter(|(distance, _): &(usize, String)| *distance <= allowed)
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(3)
+        .map(|(_, candidate): (usize, String)| candidate)
+        .collect()
+}
+
+/// Loads every pattern of a bundle recorded by `gfr install`.
+fn load_bundle_patterns(bundle: &str, styles: &Styles) -> Result<Vec<Pattern>> {
+    let install_hint: String = format!("gfr install --bundle {bundle}");
+    let members: Vec<String> = match collections::load_collection(bundle)? {
+        Some(collection) => collection.patterns,
+        None => load_bundles()?.remove(bundle).ok_or_else(|| {
+            anyhow!(
+                "Unknown bundle '{}'. Try '{}'.",
+                bundle.style(styles.highlight),
+                install_hint.style(styles.highlight)
+            )
+        })?,
+    };
+    members
+        .iter()
+        .map(|name: &String| {
+            load_pattern(name).with_context(|| {
+                format!(
+                    "Bundle '{bundle}' includes '{name}', which isn't installed. Try '{}'.",
+                    install_hint.style(styles.highlight)
+                )
+            })
+        })
+        .collect()
+}
+
+fn load_bundles() -> Result<Bundles> {
+    let bundles_path: PathBuf = get_pattern_dir()?.join(BUNDLES_FILE);
+    if !bundles_path.exists() {
+        return Ok(Bundles::new());
+    }
+    let file: File = File::open(&bundles_path)?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse bundles from: {}", bundles_path.display()))
+}
+
+/// Records the bundles of an index, keeping those learned from other indexes.
+fn save_bundles(bundles: &Bundles) -> Result<()> {
+    if bundles.is_empty() {
+        return Ok(());
+    }
+    let mut saved: Bundles = load_bundles().unwrap_or_default();
+    saved.extend(
+        bundles
+            .iter()
+            .map(|(k, v): (&String, &Vec<String>)| (k.clone(), v.clone())),
+    );
+    let file: File = File::create(get_pattern_dir()?.join(BUNDLES_FILE))?;
+    serde_json::to_writer_pretty(file, &saved)?;
+    Ok(())
+}
+
 fn load_manifest() -> Result<InstalledManifest> {
     let manifest_path: PathBuf = get_pattern_dir()?.join(INSTALLED_MANIFEST_FILE);
     if !manifest_path.exists() {
@@ -672,6 +4683,21 @@ fn load_manifest() -> Result<InstalledManifest> {
     Ok(serde_json::from_reader(file)?)
 }
 
+fn load_user_config() -> Result<UserConfig> {
+    let config_path: PathBuf = get_pattern_dir()?.join(USER_CONFIG_FILE);
+    if !config_path.exists() {
+        return Ok(UserConfig::default());
+    }
+    let file: File = File::open(&config_path)?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse config from: {}", config_path.display()))
+}
+
+/// The SHA-256 of the file at `path`, or `None` if it can't be read.
+fn file_sha256(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|data: Vec<u8>| sha256_hex(&data))
+}
+
 fn save_manifest(manifest: &InstalledManifest) -> Result<()> {
     let manifest_path: PathBuf = get_pattern_dir()?.join(INSTALLED_MANIFEST_FILE);
     let file: File = File::create(manifest_path)?;
@@ -720,48 +4746,801 @@ impl Styles {
 mod tests {
     use super::*;
 
-    impl Default for Pattern {
-        fn default() -> Self {
-            Self {
-                schema: None,
-                version: "0.0.0".to_string(),
-                author: None,
-                description: None,
-                tags: None,
-                regex: None,
-                regex_list: None,
-                file_types: None,
-                ignore_case: false,
-                multiline: false,
-            }
+    #[test]
+    fn test_license_policy() {
+        let policy: LicensePolicy = LicensePolicy {
+            allow: vec!["permissive".to_string(), "MPL-2.0".to_string()],
+            allow_unlicensed: false,
+        };
+        assert!(policy.check(Some("MIT")).is_ok());
+        assert!(policy.check(Some("apache-2.0")).is_ok());
+        assert!(policy.check(Some("MPL-2.0")).is_ok());
+        assert!(policy.check(Some("GPL-3.0-only")).is_err());
+        assert!(policy.check(None).is_err());
+    }
+
+    #[test]
+    fn test_manifest_reads_legacy_entries() {
+        let manifest: InstalledManifest = serde_json::from_str(
+            r#"{"old": "1.0.0", "new": {"version": "2.0.0", "sha256": "abc"}}"#,
+        )
+        .unwrap();
+        assert_eq!(manifest["old"].version, "1.0.0");
+        assert!(manifest["old"].sha256.is_none());
+        assert_eq!(manifest["new"].version, "2.0.0");
+        assert_eq!(manifest["new"].sha256.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_context_lines_precedence() {
+        let args: Cli = Cli::parse_from(["gfr", "search", "x", "-C", "3", "-A", "1"]);
+        let Commands::Search(search) = args.command else {
+            panic!("expected search command");
+        };
+        assert_eq!(search.context_lines(), (3, 1));
+    }
+
+    #[test]
+    fn test_line_deduper_only_suppresses_other_files() {
+        let deduper: LineDeduper = LineDeduper::default();
+        assert!(!deduper.is_duplicate("key", "api_key = 1", Some("a.js")));
+        assert!(!deduper.is_duplicate("key", "api_key = 1", Some("a.js")));
+        assert!(deduper.is_duplicate("key", "api_key = 1", Some("vendor/a.js")));
+        assert!(!deduper.is_duplicate("token", "api_key = 1", Some("vendor/a.js")));
+    }
+
+    #[test]
+    fn test_prefix_writer_labels_every_line() {
+        let mut writer: PrefixWriter<termcolor::NoColor<Vec<u8>>> =
+            PrefixWriter::new(termcolor::NoColor::new(Vec::new()), "[key] ".to_string());
+        writer.write_all(b"a.js:1:one\na.js:").unwrap();
+        writer.write_all(b"2:two\n").unwrap();
+        assert_eq!(
+            writer.inner.into_inner(),
+            b"[key] a.js:1:one\n[key] a.js:2:two\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_select_removals_rejects_unknown_names_without_force() {
+        let local: Vec<String> = vec!["xss".to_string()];
+        let mut manifest: InstalledManifest = HashMap::new();
+        manifest.insert(
+            "aws-keys".to_string(),
+            InstalledPattern {
+                version: "1.0.0".to_string(),
+                sha256: None,
+                source: None,
+                registry: None,
+                url: None,
+                local_sha256: None,
+                pinned: false,
+                signed: false,
+                signature: None,
+            },
+        );
+        let names: Vec<String> = ["xss", "aws-keys", "xss", "nope"]
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert!(select_removals(&names, &local, &manifest, false).is_err());
+        assert_eq!(
+            select_removals(&names, &local, &manifest, true).unwrap(),
+            vec!["xss".to_string(), "aws-keys".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_index_pattern_status() {
+        let remote: IndexPattern = IndexPattern {
+            name: "xss".to_string(),
+            version: "1.2.0".to_string(),
+            url: String::new(),
+            license: None,
+            sha256: Some("ABCD".to_string()),
+            signature: None,
+            deprecated: false,
+            superseded_by: None,
+        };
+        let installed = |version: &str, sha256: Option<&str>| InstalledPattern {
+            version: version.to_string(),
+            sha256: sha256.map(ToString::to_string),
+            source: None,
+            registry: None,
+            url: None,
+            local_sha256: None,
+            pinned: false,
+            signed: false,
+            signature: None,
+        };
+
+        assert_eq!(remote.status(None).unwrap(), PatternStatus::NotInstalled);
+        assert_eq!(
+            remote.status(Some(&installed("1.1.0", None))).unwrap(),
+            PatternStatus::Outdated
+        );
+        assert_eq!(
+            remote
+                .status(Some(&installed("1.2.0", Some("ffff"))))
+                .unwrap(),
+            PatternStatus::ContentChanged
+        );
+        assert_eq!(
+            remote
+                .status(Some(&installed("1.2.0", Some("abcd"))))
+                .unwrap(),
+            PatternStatus::UpToDate
+        );
+        assert_eq!(
+            remote.status(Some(&installed("1.2.0", None))).unwrap(),
+            PatternStatus::UpToDate
+        );
+    }
+
+    #[test]
+    fn test_severity_filters() {
+        let min: SearchArgs =
+            SearchArgs::parse_from(["search", "secrets", "--min-severity", "high"]);
+        assert!(min.allows_severity(Severity::Critical));
+        assert!(!min.allows_severity(Severity::Medium));
+
+        let exact: SearchArgs =
+            SearchArgs::parse_from(["search", "secrets", "--severity", "low,high"]);
+        assert!(exact.allows_severity(Severity::Low));
+        assert!(!exact.allows_severity(Severity::Critical));
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("2gb"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("10T").is_err());
+        assert_eq!(parse_size("10B"), Ok(10));
+        assert!(parse_size("10KBB").is_err());
+        assert!(parse_size("10BBB").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("").is_err());
+    }
+
+    #[test]
+    fn test_lang_selects_types() {
+        let args: SearchArgs = SearchArgs::parse_from([
+            "search",
+            "xss",
+            "-t",
+            "html",
+            "--lang",
+            "JavaScript",
+            "--lang",
+            "shell",
+        ]);
+        assert_eq!(args.selected_types(), ["html", "js", "sh"]);
+        assert!(parse_language("klingon").is_err());
+    }
+
+    /// The files a search with `flags` walks in `root`, relative to it.
+    fn walked(root: &Path, flags: &[&str]) -> Vec<String> {
+        let args: SearchArgs =
+            SearchArgs::parse_from(["search", "aws"].iter().chain(flags).copied());
+        let tuning: Tuning = args.tuning(root);
+        let mut files: Vec<String> = args
+            .walk_builder(root, &tuning, false, |_: &ignore::DirEntry| true)
+            .unwrap()
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry: &ignore::DirEntry| entry.path().is_file())
+            .map(|entry: ignore::DirEntry| {
+                winpath::display(entry.path().strip_prefix(root).unwrap())
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_daemon_refuses_walk_settings_it_would_ignore() {
+        let parse = |flags: &[&str]| {
+            SearchArgs::try_parse_from(["search", "aws", "--daemon"].iter().chain(flags).copied())
+        };
+        assert!(parse(&[]).is_ok());
+        for flags in [
+            &["--no-parallel"][..],
+            &["-j", "2"],
+            &["--tune", "off"],
+            &["--skip-reparse-points"],
+        ] {
+            assert!(parse(flags).is_err(), "{flags:?}");
+        }
+    }
+
+    #[test]
+    fn test_max_depth_cuts_off_the_walk() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        for file in ["top.env", "a/mid.env", "a/b/deep.env"] {
+            fs::write(dir.path().join(file), "").unwrap();
         }
+        assert_eq!(walked(dir.path(), &[]).len(), 3);
+        assert_eq!(
+            walked(dir.path(), &["--max-depth", "2"]),
+            ["a/mid.env", "top.env"]
+        );
+        assert_eq!(walked(dir.path(), &["--max-depth", "1"]), ["top.env"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_links_walks_linked_directories() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let elsewhere: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::write(elsewhere.path().join("linked.env"), "").unwrap();
+        fs::write(dir.path().join("own.env"), "").unwrap();
+        std::os::unix::fs::symlink(elsewhere.path(), dir.path().join("shared")).unwrap();
+        assert_eq!(walked(dir.path(), &[]), ["own.env"]);
+        assert_eq!(
+            walked(dir.path(), &["-L"]),
+            ["own.env", "shared/linked.env"]
+        );
+        // Both stay on the temporary directory's file system.
+        assert_eq!(
+            walked(dir.path(), &["-L", "--one-file-system"]),
+            ["own.env", "shared/linked.env"]
+        );
+    }
+
+    #[test]
+    fn test_thread_count_reaches_the_walker() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let threads = |flags: &[&str]| -> usize {
+            let args: SearchArgs = SearchArgs::parse_from(
+                ["search", "aws", "--tune", "off"]
+                    .iter()
+                    .chain(flags)
+                    .copied(),
+            );
+            args.tuning(dir.path()).threads
+        };
+        assert_eq!(threads(&["-j", "3"]), 3);
+        assert_eq!(threads(&["--no-parallel"]), 1);
+        assert_eq!(threads(&[]), 0);
+
+        // Without parallelism, files are visited in name order.
+        for file in ["c.env", "a.env", "b/z.env", "b/y.env"] {
+            let path: PathBuf = dir.path().join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let args: SearchArgs = SearchArgs::parse_from(["search", "aws", "--no-parallel"]);
+        let tuning: Tuning = args.tuning(dir.path());
+        let visited: Vec<String> = args
+            .walk_builder(dir.path(), &tuning, false, |_: &ignore::DirEntry| true)
+            .unwrap()
+            .build()
+            .filter_map(Result::ok)
+            .filter(|entry: &ignore::DirEntry| entry.depth() > 0)
+            .map(|entry: ignore::DirEntry| {
+                winpath::display(entry.path().strip_prefix(dir.path()).unwrap())
+            })
+            .collect();
+        assert_eq!(visited, ["a.env", "b", "b/y.env", "b/z.env", "c.env"]);
+    }
+
+    #[test]
+    fn test_search_hidden() {
+        let plain: Pattern = Pattern::default();
+        let secrets: Pattern = Pattern {
+            tags: Some(vec!["secrets".to_string()]),
+            ..Pattern::default()
+        };
+        let search = |flags: &[&str]| -> SearchArgs {
+            SearchArgs::parse_from(["search"].iter().chain(flags).chain(&["."]))
+        };
+        assert!(!search(&[]).search_hidden(std::slice::from_ref(&plain)));
+        assert!(search(&[]).search_hidden(&[plain.clone(), secrets.clone()]));
+        assert!(search(&["--hidden"]).search_hidden(&[plain]));
+        assert!(!search(&["--no-hidden"]).search_hidden(&[secrets]));
+    }
+
+    #[test]
+    fn test_parse_two_pass() {
+        assert_eq!(
+            parse_two_pass("aws-hint:aws-keys"),
+            Ok(("aws-hint".to_string(), "aws-keys".to_string()))
+        );
+        assert!(parse_two_pass("aws-keys").is_err());
+        assert!(parse_two_pass(":aws-keys").is_err());
+
+        let args: SearchArgs =
+            SearchArgs::parse_from(["search", "--two-pass", "aws-hint:aws-keys", "./src"])
+                .normalize()
+                .unwrap();
+        assert_eq!(args.pattern_name, None);
+        assert_eq!(args.path, PathBuf::from("./src"));
+    }
+
+    #[test]
+    fn test_index_retain_bundle() {
+        let index_json: &str = r#"{
+            "patterns": [
+                {"name": "xss", "version": "1.0.0", "url": "https://example.com/xss.json"},
+                {"name": "ssrf", "version": "1.0.0", "url": "https://example.com/ssrf.json"},
+                {"name": "aws-keys", "version": "1.0.0", "url": "https://example.com/aws.json"}
+            ],
+            "bundles": {"web-recon": ["xss", "ssrf"], "broken": ["xss", "sqli"]}
+        }"#;
+        let mut index: Index = serde_json::from_str(index_json).unwrap();
+        assert!(index.retain_bundle("cloud").is_err());
+        assert!(index.retain_bundle("broken").is_err());
+        index.retain_bundle("web-recon").unwrap();
+        let names: Vec<&str> = index
+            .patterns
+            .iter()
+            .map(|p: &IndexPattern| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["xss", "ssrf"]);
+    }
+
+    #[test]
+    fn test_pattern_names_from_list_and_flags() {
+        let args: SearchArgs =
+            SearchArgs::parse_from(["search", "rce, sqli,ssrf", "-p", "sqli", "-p", "xss"]);
+        assert_eq!(args.pattern_names(), ["rce", "sqli", "ssrf", "xss"]);
+
+        let args: SearchArgs = SearchArgs::parse_from(["search", "-p", "rce", "./src"])
+            .normalize()
+            .unwrap();
+        assert_eq!(args.pattern_names(), ["rce"]);
+        assert_eq!(args.path, PathBuf::from("./src"));
+    }
+
+    #[test]
+    fn test_exclude_pattern_is_skip_pattern() {
+        let args: SearchArgs = SearchArgs::parse_from([
+            "search",
+            "--tags",
+            "secrets",
+            "--exclude-pattern",
+            "generic-api-key",
+            "--skip-pattern",
+            "jwt",
+        ]);
+        assert_eq!(args.skip_pattern, ["generic-api-key", "jwt"]);
+    }
+
+    #[test]
+    fn test_close_names_suggests_likely_typos() {
+        let available = || -> Vec<String> {
+            ["aws-keys", "aws-key", "gcp-keys", "jwt"]
+                .map(String::from)
+                .to_vec()
+        };
+        assert_eq!(
+            close_names("aws-kyes", available()),
+            ["aws-keys", "aws-key"]
+        );
+        assert_eq!(close_names("jtw", available()), ["jwt"]);
+        assert!(close_names("sqli", available()).is_empty());
+    }
+
+    #[test]
+    fn test_index_namespace() {
+        let index = |namespace: Option<&str>| Index {
+            namespace: namespace.map(ToString::to_string),
+            patterns: Vec::new(),
+            bundles: Bundles::new(),
+        };
+        assert_eq!(
+            index(None).namespace(DEFAULT_PATTERNS_URL).unwrap(),
+            "Kr1shna4garwal"
+        );
+        assert_eq!(
+            index(None)
+                .namespace("https://patterns.example.com/index.json")
+                .unwrap(),
+            "patterns.example.com"
+        );
+        assert_eq!(
+            index(Some("team")).namespace(DEFAULT_PATTERNS_URL).unwrap(),
+            "team"
+        );
+        assert!(index(Some("../up"))
+            .namespace(DEFAULT_PATTERNS_URL)
+            .is_err());
+    }
+
+    #[test]
+    fn test_index_retain_names() {
+        let index_json: &str = r#"{
+            "patterns": [
+                {"name": "xss", "version": "1.0.0", "url": "https://example.com/xss.json"},
+                {"name": "ssrf", "version": "1.0.0", "url": "https://example.com/ssrf.json"},
+                {"name": "aws-keys", "version": "1.0.0", "url": "https://example.com/aws.json"}
+            ]
+        }"#;
+        let mut index: Index = serde_json::from_str(index_json).unwrap();
+        assert!(index
+            .retain_names(&["xss".to_string(), "sqli".to_string()])
+            .is_err());
+        index
+            .retain_names(&["Kr1shna4garwal/aws-keys".to_string(), "xss".to_string()])
+            .unwrap();
+        let names: Vec<&str> = index
+            .patterns
+            .iter()
+            .map(|p: &IndexPattern| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["xss", "aws-keys"]);
     }
+
+    #[test]
+    fn test_index_from_directory() {
+        let tmp: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let dir: PathBuf = tmp.path().join("team-rules");
+        fs::create_dir(&dir).unwrap();
+        fs::write(
+            dir.join("internal-token.json"),
+            r#"{"pattern": "itk_[0-9a-f]{32}", "version": "2.1.0"}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("jwt.json"), r#"{"pattern": "eyJ"}"#).unwrap();
+        fs::write(dir.join("notes.txt"), "not a pattern").unwrap();
+
+        let index: Index = Index::from_directory(&dir).unwrap();
+        let entries: Vec<(&str, &str)> = index
+            .patterns
+            .iter()
+            .map(|p: &IndexPattern| (p.name.as_str(), p.version.as_str()))
+            .collect();
+        assert_eq!(entries, [("internal-token", "2.1.0"), ("jwt", "1.0.0")]);
+        assert!(!is_remote(&index.patterns[0].url));
+        assert_eq!(
+            index.namespace(&dir.display().to_string()).unwrap(),
+            "team-rules"
+        );
+    }
+
+    #[test]
+    fn test_index_sources_by_priority() {
+        let config: UserConfig = serde_json::from_str(
+            r#"{"registries": [
+                {"name": "community", "url": "https://example.com/index.json"},
+                {"name": "corp", "url": "https://corp.example/index.json", "priority": 10}
+            ]}"#,
+        )
+        .unwrap();
+        let names = |sources: Vec<IndexSource>| -> Vec<Option<String>> {
+            sources
+                .into_iter()
+                .map(|s: IndexSource| s.registry)
+                .collect()
+        };
+        assert_eq!(
+            names(config.index_sources(None, None).unwrap()),
+            [Some("corp".to_string()), Some("community".to_string())]
+        );
+        assert_eq!(
+            names(config.index_sources(None, Some("community")).unwrap()),
+            [Some("community".to_string())]
+        );
+        assert!(config.index_sources(None, Some("missing")).is_err());
+        let local: Vec<IndexSource> = config
+            .index_sources(Some("./local".to_string()), None)
+            .unwrap();
+        assert_eq!(local.len(), 1);
+        assert_eq!(local[0].url, "./local");
+        assert_eq!(local[0].registry, None);
+        assert_eq!(
+            UserConfig::default().index_sources(None, None).unwrap()[0].url,
+            DEFAULT_PATTERNS_URL
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_all_keeps_going() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let present: PathBuf = dir.path().join("xss.json");
+        fs::write(&present, b"{}").unwrap();
+        let missing: PathBuf = dir.path().join("sqli.json");
+        let jobs: Vec<(String, Option<String>)> = vec![
+            (missing.display().to_string(), None),
+            (present.display().to_string(), None),
+        ];
+        let downloads: Vec<Result<Download>> =
+            download_all(&HttpClient::new(&NetworkOptions::default()).unwrap(), jobs)
+                .await
+                .unwrap();
+        assert!(downloads[0].is_err());
+        assert_eq!(downloads[1].as_ref().unwrap().0, b"{}");
+    }
+
+    #[test]
+    fn test_pending_install_action() {
+        let pending = |installed: Option<&str>, status: PatternStatus| PendingInstall {
+            remote: serde_json::from_value(serde_json::json!({
+                "name": "xss",
+                "version": "1.1.0",
+                "url": "https://example.com/xss.json"
+            }))
+            .unwrap(),
+            full_name: "team/xss".to_string(),
+            legacy: false,
+            installed_version: installed.map(ToString::to_string),
+            status,
+            pinned: false,
+        };
+        let new: PendingInstall = pending(None, PatternStatus::NotInstalled);
+        assert!(!new.update());
+        assert_eq!(new.action(false), "Installing 'team/xss' (v1.1.0)");
+        assert_eq!(new.action(true), "Would install 'team/xss' (v1.1.0)");
+        let outdated: PendingInstall = pending(Some("1.0.0"), PatternStatus::Outdated);
+        assert!(outdated.update());
+        assert_eq!(
+            outdated.action(true),
+            "Would update 'team/xss' (v1.0.0 -> v1.1.0)"
+        );
+        assert_eq!(
+            pending(Some("1.1.0"), PatternStatus::ContentChanged).action(false),
+            "Refreshing 'team/xss' (v1.1.0, content changed)"
+        );
+    }
+
     #[test]
-    fn test_get_raw_pattern_logic() {
-        let p1: Pattern = Pattern {
-            regex: Some("abc".to_string()),
-            ..Default::default()
+    fn test_installed_pattern_is_modified() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("xss.json");
+        fs::write(&path, "{}").unwrap();
+        let mut installed: InstalledPattern = InstalledPattern {
+            version: "1.0.0".to_string(),
+            sha256: None,
+            source: None,
+            registry: None,
+            url: None,
+            local_sha256: None,
+            pinned: false,
+            signed: false,
+            signature: None,
         };
-        assert_eq!(p1.get_raw_pattern().unwrap(), "abc");
+        assert!(!installed.is_modified(&path));
+        installed.local_sha256 = file_sha256(&path);
+        assert!(!installed.is_modified(&path));
+        fs::write(&path, r#"{"pattern": "tuned"}"#).unwrap();
+        assert!(installed.is_modified(&path));
+    }
 
-        let p2: Pattern = Pattern {
-            regex_list: Some(vec!["a".to_string(), "b".to_string()]),
-            ..Default::default()
+    #[test]
+    fn test_index_select_versions() {
+        let index_json: &str = r#"{
+            "patterns": [
+                {"name": "aws-keys", "version": "1.2.0", "url": "https://example.com/aws-1.2.0.json"},
+                {"name": "aws-keys", "version": "1.10.0", "url": "https://example.com/aws.json"},
+                {"name": "xss", "version": "1.0.0", "url": "https://example.com/xss.json"}
+            ]
+        }"#;
+        let versions = |index: &Index| -> Vec<(String, String)> {
+            index
+                .patterns
+                .iter()
+                .map(|p: &IndexPattern| (p.name.clone(), p.version.clone()))
+                .collect()
         };
-        assert_eq!(p2.get_raw_pattern().unwrap(), "(?:a|b)");
+        let mut index: Index = serde_json::from_str(index_json).unwrap();
+        index.select_versions(&Requested::new()).unwrap();
+        assert_eq!(
+            versions(&index),
+            [
+                ("aws-keys".to_string(), "1.10.0".to_string()),
+                ("xss".to_string(), "1.0.0".to_string())
+            ]
+        );
+
+        let (names, requested): (Vec<String>, Requested) =
+            parse_requested(&["team/aws-keys@v1.2.0".to_string(), "xss".to_string()]);
+        assert_eq!(names, ["team/aws-keys", "xss"]);
+        let mut index: Index = serde_json::from_str(index_json).unwrap();
+        index.select_versions(&requested).unwrap();
+        assert_eq!(
+            versions(&index)[0],
+            ("aws-keys".to_string(), "1.2.0".to_string())
+        );
+
+        let (_, missing): (Vec<String>, Requested) =
+            parse_requested(&["aws-keys@2.0.0".to_string()]);
+        let mut index: Index = serde_json::from_str(index_json).unwrap();
+        let err: String = index.select_versions(&missing).unwrap_err().to_string();
+        assert!(err.contains("Available: 1.2.0, 1.10.0"));
+    }
+
+    #[test]
+    fn test_index_retired() {
+        let index: Index = serde_json::from_str(
+            r#"{
+                "patterns": [
+                    {"name": "aws-keys", "version": "1.0.0", "url": "a.json", "deprecated": true, "superseded_by": "cloud-keys"},
+                    {"name": "cloud-keys", "version": "1.0.0", "url": "c.json"},
+                    {"name": "old-xss", "version": "1.0.0", "url": "x.json", "deprecated": true},
+                    {"name": "jwt", "version": "1.0.0", "url": "j.json", "deprecated": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let installed: InstalledPattern =
+            serde_json::from_value(serde_json::json!({"version": "1.0.0"})).unwrap();
+        let mut manifest: InstalledManifest = HashMap::new();
+        manifest.insert("team/aws-keys".to_string(), installed.clone());
+        manifest.insert("old-xss".to_string(), installed);
+        let mut retired: Vec<(String, Option<String>)> = index.retired("team", &manifest);
+        retired.sort();
+        assert_eq!(
+            retired,
+            [
+                ("old-xss".to_string(), None),
+                (
+                    "team/aws-keys".to_string(),
+                    Some("team/cloud-keys".to_string())
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_index_parse_formats() {
+        let json: &str =
+            r#"{"patterns": [{"name": "xss", "version": "1.0.0", "url": "xss.json"}]}"#;
+        let yaml: &str = "# team patterns\nnamespace: team\npatterns:\n  - name: xss\n    version: 1.0.0\n    url: xss.json\nbundles:\n  web: [xss]\n";
+        let toml: &str = "namespace = \"team\"\n\n[[patterns]]\nname = \"xss\"\nversion = \"1.0.0\"\nurl = \"xss.json\"\n";
+        assert_eq!(
+            IndexFormat::detect("https://x.test/index", json),
+            IndexFormat::Json
+        );
+        assert_eq!(
+            IndexFormat::detect("https://x.test/index", yaml),
+            IndexFormat::Yaml
+        );
+        assert_eq!(
+            IndexFormat::detect("https://x.test/index", toml),
+            IndexFormat::Toml
+        );
+        assert_eq!(
+            IndexFormat::detect("idx/index.YML?ref=main", json),
+            IndexFormat::Yaml
+        );
+        assert_eq!(IndexFormat::detect("index.toml", ""), IndexFormat::Toml);
+
+        for (location, body) in [("index.json", json), ("index.yaml", yaml), ("index", toml)] {
+            let index: Index = Index::parse(location, body.as_bytes()).unwrap();
+            assert_eq!(index.patterns[0].name, "xss");
+            assert_eq!(index.patterns[0].version, "1.0.0");
+        }
+        let index: Index = Index::parse("index.yaml", yaml.as_bytes()).unwrap();
+        assert_eq!(index.namespace.as_deref(), Some("team"));
+        assert_eq!(index.bundles["web"], ["xss"]);
+        assert!(Index::parse("index.toml", yaml.as_bytes()).is_err());
+    }
+
+    /// Records the line numbers a sink is handed, context lines negated and
+    /// breaks as 0.
+    #[derive(Default)]
+    struct LineRecorder(Vec<i64>);
+
+    impl Sink for LineRecorder {
+        type Error = io::Error;
+
+        fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> io::Result<bool> {
+            self.0.push(mat.line_number().unwrap().cast_signed());
+            Ok(true)
+        }
+
+        fn context(&mut self, _searcher: &Searcher, context: &SinkContext<'_>) -> io::Result<bool> {
+            self.0.push(-context.line_number().unwrap().cast_signed());
+            Ok(true)
+        }
 
-        let p3: Pattern = Pattern {
-            regex: None,
-            regex_list: Some(vec![]),
-            ..Default::default()
+        fn context_break(&mut self, _searcher: &Searcher) -> io::Result<bool> {
+            self.0.push(0);
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_suppressed_matches_show_no_context() {
+        let matcher: PatternMatcher = PatternMatcher::new("AKIA", Engine::Rust).unwrap();
+        let plan: SearchPlan = SearchPlan {
+            named_matchers: vec![("aws".to_string(), matcher.clone())],
+            patterns: vec![Pattern::default()],
+            everything: Arc::new(Scope {
+                patterns: vec![0],
+                matcher: matcher.clone(),
+            }),
+            scopes: Mutex::default(),
+            attribute: false,
+            prefilter: None,
+            allow: None,
+            inline_ignores: true,
+            detect_types: false,
+            baseline: None,
+            baseline_writer: None,
+            severities: HashMap::new(),
+            entropy: HashMap::new(),
+            validators: HashMap::new(),
+            sinks: Vec::new(),
+            held_for_verification: None,
+            redact: None,
+            triaged: HashMap::new(),
+            suppressed: HashSet::new(),
+            summary: None,
+            deduper: None,
+            confidence: HashMap::new(),
+            only_matching: false,
+            replacement: None,
+            tally: None,
+            reported: None,
+            plugins: Plugins::default(),
         };
-        assert!(p3.get_raw_pattern().is_err());
+        let lines = |text: &[u8]| -> Vec<i64> {
+            let mut searcher: Searcher = SearcherBuilder::new()
+                .before_context(1)
+                .after_context(1)
+                .build();
+            let source: Source<'_> = Source::Buffered(text);
+            let shown: Option<Vec<RangeInclusive<u64>>> = plan
+                .shown_context(&mut searcher, &matcher, None, source)
+                .unwrap();
+            let mut recorder: LineRecorder = LineRecorder::default();
+            let sink = FilterSink::new(&plan, &plan.everything, None, None, &mut recorder)
+                .showing_context(shown);
+            run_searcher(&mut searcher, &matcher, source, sink).unwrap();
+            recorder.0
+        };
+        // Lines 2 and 4 are only context of the suppressed match on line 3.
+        assert_eq!(
+            lines(b"a\nb\nk = AKIA1 // gfr:ignore\nc\nd\ne\nk = AKIA2\nf\n"),
+            [-6, 7, -8]
+        );
+        // Dropping line 3 splits the context of the matches around it in two.
+        assert_eq!(
+            lines(b"AKIA0\nb\nAKIA1 // gfr:ignore\nd\nAKIA2\n"),
+            [1, -2, 0, -4, 5]
+        );
+    }
+
+    #[test]
+    fn test_record_index_license() {
+        let mut unlicensed: serde_json::Value = serde_json::json!({ "license": null });
+        record_index_license(&mut unlicensed, Some("MIT"));
+        assert_eq!(unlicensed["license"], "MIT");
+        let mut licensed: serde_json::Value = serde_json::json!({ "license": "Apache-2.0" });
+        record_index_license(&mut licensed, Some("MIT"));
+        assert_eq!(licensed["license"], "Apache-2.0");
+    }
 
-        let p4: Pattern = Pattern {
-            regex: None,
-            regex_list: None,
-            ..Default::default()
+    #[test]
+    fn test_index_rejects_names_that_are_paths() {
+        let index = |name: &str| -> Index {
+            let body: String = serde_json::json!({
+                "patterns": [{ "name": name, "version": "1.0.0", "url": "x.json" }]
+            })
+            .to_string();
+            Index::parse("index.json", body.as_bytes()).unwrap()
         };
-        assert!(p4.get_raw_pattern().is_err());
+        assert!(index("aws-keys").check_names().is_ok());
+        assert!(check_full_name("team/aws-keys").is_ok());
+        assert!(check_full_name("aws-keys").is_ok());
+        for hostile in [
+            "../x",
+            "/etc/x",
+            "team/../x",
+            "a/b/c",
+            "team/",
+            "collections/x",
+            "team\\x",
+        ] {
+            assert!(check_full_name(hostile).is_err(), "{hostile}");
+        }
+        for hostile in ["../../.bashrc", "a/b", "a\\b", ".hidden", "..", ""] {
+            assert!(index(hostile).check_names().is_err(), "{hostile}");
+        }
     }
 }
@@ -0,0 +1,136 @@
+//! `gfr migrate`: writes the schema upgrades that loading applies in memory back
+//! to pattern files on disk.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+
+pub(crate) use gfr_core::migrate::{read_value, upgrade, SCHEMA_VERSION};
+
+use crate::{
+    file_sha256, get_pattern_dir, list_pattern_names, load_manifest, pattern_file, save_manifest,
+    validate, InstalledManifest, InstalledPattern, PatternFormat, Styles,
+};
+
+#[derive(Args, Debug)]
+pub(crate) struct MigrateArgs {
+    /// Names of local patterns, or paths to pattern files.
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    targets: Vec<String>,
+    /// Migrate every local pattern.
+    #[arg(long)]
+    all: bool,
+    /// Show which files would be upgraded without writing them.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+/// `json` in the format of the file at `path`.
+fn render(path: &Path, json: &Value) -> Result<String> {
+    Ok(match PatternFormat::of(path) {
+        PatternFormat::Json => serde_json::to_string_pretty(json)? + "\n",
+        PatternFormat::Yaml => serde_yaml::to_string(json)?,
+    })
+}
+
+/// Resolves the files to migrate from the command-line targets, with the name of
+/// the local pattern each one is, if any.
+fn resolve_targets(args: &MigrateArgs) -> Result<Vec<(Option<String>, PathBuf)>> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    if args.all {
+        let mut names: Vec<String> = if pattern_dir.exists() {
+            list_pattern_names(&pattern_dir)?
+        } else {
+            Vec::new()
+        };
+        names.sort();
+        return Ok(names
+            .into_iter()
+            .filter_map(|name: String| {
+                pattern_file(&pattern_dir, &name).map(|path: PathBuf| (Some(name), path))
+            })
+            .collect());
+    }
+    args.targets
+        .iter()
+        .map(|target: &String| {
+            let path: PathBuf = validate::resolve_target(target)?;
+            let name: Option<String> = path
+                .strip_prefix(&pattern_dir)
+                .ok()
+                .map(|relative: &Path| relative.with_extension("").display().to_string());
+            Ok((name, path))
+        })
+        .collect()
+}
+
+/// Runs `gfr migrate`. Installed patterns that weren't edited locally stay
+/// unmodified in the manifest, so `gfr update` keeps updating them.
+pub(crate) fn run_migrate(args: &MigrateArgs, styles: &Styles) -> Result<()> {
+    let targets: Vec<(Option<String>, PathBuf)> = resolve_targets(args)?;
+    let mut manifest: InstalledManifest = load_manifest()?;
+    let mut migrated: usize = 0;
+    let mut failed: usize = 0;
+    for (name, path) in &targets {
+        let source: String = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut json: Value = match read_value(path, &source) {
+            Ok(json) => json,
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {e:#}", "✗".style(styles.error), path.display());
+                continue;
+            }
+        };
+        let written: u64 = match upgrade(&mut json) {
+            Ok(SCHEMA_VERSION) => continue,
+            Ok(written) => written,
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {e:#}", "✗".style(styles.error), path.display());
+                continue;
+            }
+        };
+        json["schema_version"] = Value::from(SCHEMA_VERSION);
+        migrated += 1;
+        println!(
+            "  -> {} {} from schema version {written} to {SCHEMA_VERSION}",
+            if args.dry_run {
+                "Would migrate"
+            } else {
+                "Migrated"
+            },
+            path.display().style(styles.highlight)
+        );
+        if args.dry_run {
+            continue;
+        }
+        let installed: Option<&mut InstalledPattern> = name
+            .as_ref()
+            .and_then(|name: &String| manifest.get_mut(name))
+            .filter(|installed: &&mut InstalledPattern| !installed.is_modified(path));
+        fs::write(path, render(path, &json)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        if let Some(installed) = installed {
+            installed.local_sha256 = file_sha256(path);
+        }
+    }
+    if migrated > 0 && !args.dry_run && get_pattern_dir()?.exists() {
+        save_manifest(&manifest)?;
+    }
+
+    if migrated == 0 && failed == 0 {
+        println!(
+            "{} All pattern files are at schema version {SCHEMA_VERSION}.",
+            "✓".style(styles.success)
+        );
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} pattern file(s) could not be migrated."));
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+//! `gfr index-build`: indexes the trigrams of a tree once, so that
+//! `gfr search --index` only reads the files that can hold a match of its patterns.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use gfr_core::ngram::{self, BuildStats, NgramFilter, NgramIndex};
+use owo_colors::OwoColorize;
+
+use crate::{sha256_hex, Pattern, Styles, CONFIG_DIR};
+
+/// Where indexes go unless `--output` says otherwise, within the cache directory.
+const INDEXES_DIR: &str = "indexes";
+
+#[derive(Args, Debug)]
+pub(crate) struct IndexBuildArgs {
+    /// Directory to index.
+    #[arg(default_value = ".")]
+    path: PathBuf,
+    /// Where to write the index. Defaults to a file in gfr's cache directory that
+    /// `gfr search --index` finds for this directory and everything below it.
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<PathBuf>,
+}
+
+/// The default index of the directory at the canonical path `root`.
+fn default_index_path(root: &Path) -> Result<PathBuf> {
+    let key: String = sha256_hex(root.to_string_lossy().as_bytes());
+    dirs::cache_dir()
+        .ok_or_else(|| anyhow!("Could not determine user's cache directory."))
+        .map(|dir: PathBuf| {
+            dir.join(CONFIG_DIR)
+                .join(INDEXES_DIR)
+                .join(format!("{}.gfrindex", &key[..16]))
+        })
+}
+
+pub(crate) fn run_index_build(args: &IndexBuildArgs, styles: &Styles) -> Result<()> {
+    let root: PathBuf = fs::canonicalize(&args.path)
+        .with_context(|| format!("Can't index '{}'", args.path.display()))?;
+    let out: PathBuf = match &args.output {
+        Some(path) => path.clone(),
+        None => default_index_path(&root)?,
+    };
+    println!(
+        "{} '{}'...",
+        "Indexing".style(styles.dim),
+        root.display().style(styles.highlight)
+    );
+    let stats: BuildStats = ngram::build_index(&root, &out)?;
+    println!(
+        "{} Indexed {} files with {} distinct trigrams into {}",
+        "✓".style(styles.success),
+        stats.files.to_string().style(styles.highlight),
+        stats.trigrams.to_string().style(styles.highlight),
+        out.display().style(styles.highlight)
+    );
+    if stats.skipped > 0 {
+        println!(
+            "  {} {} binary, large or unusually varied files, which searches always read",
+            "Left out".style(styles.dim),
+            stats.skipped.to_string().style(styles.highlight)
+        );
+    }
+    Ok(())
+}
+
+/// The content index of a search, for the files of its walk.
+#[derive(Debug)]
+pub(crate) struct SearchIndex {
+    filter: NgramFilter,
+    /// The root of the walk, as given to the walker.
+    walk_root: PathBuf,
+    /// Where the walk root is within the indexed directory.
+    offset: PathBuf,
+}
+
+impl SearchIndex {
+    /// Opens the index at `path`, or without one, the default index of `root` or
+    /// of the closest directory above it that has one.
+    pub(crate) fn open(path: Option<&Path>, root: &Path, patterns: &[Pattern]) -> Result<Self> {
+        let canonical: PathBuf =
+            fs::canonicalize(root).with_context(|| format!("Can't search '{}'", root.display()))?;
+        let path: PathBuf = match path {
+            Some(path) => path.to_path_buf(),
+            None => canonical
+                .ancestors()
+                .map(default_index_path)
+                .collect::<Result<Vec<PathBuf>>>()?
+                .into_iter()
+                .find(|index: &PathBuf| index.is_file())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "No index covers '{}'; build one with `gfr index-build`.",
+                        root.display()
+                    )
+                })?,
+        };
+        let index: NgramIndex = NgramIndex::open(&path)?;
+        let offset: PathBuf = canonical
+            .strip_prefix(index.root())
+            .map_err(|_| {
+                anyhow!(
+                    "The index {} covers '{}', not '{}'.",
+                    path.display(),
+                    index.root().display(),
+                    root.display()
+                )
+            })?
+            .to_path_buf();
+        Ok(Self {
+            filter: index.filter(patterns)?,
+            walk_root: root.to_path_buf(),
+            offset,
+        })
+    }
+
+    pub(crate) fn indexed_files(&self) -> usize {
+        self.filter.indexed_files()
+    }
+
+    pub(crate) fn candidate_files(&self) -> usize {
+        self.filter.candidate_files()
+    }
+
+    /// Whether any of the patterns at `patterns` may match the walked file.
+    pub(crate) fn may_match(&self, entry: &ignore::DirEntry, patterns: &[usize]) -> bool {
+        let Ok(metadata) = entry.metadata() else {
+            return true;
+        };
+        let Ok(relative) = entry.path().strip_prefix(&self.walk_root) else {
+            return true;
+        };
+        self.filter
+            .may_match(&self.offset.join(relative), &metadata, patterns)
+    }
+}
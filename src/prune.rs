@@ -0,0 +1,124 @@
+//! Skips directory branches that cannot contain files of the requested types.
+//!
+//! Before the walker descends into a directory, a bounded probe looks at its
+//! entries. A branch is pruned only when the probe saw all of it and found no
+//! candidate file, so pruning never hides a file that would have been searched.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use gfr_core::filetype;
+
+/// Maximum number of entries a single probe may inspect.
+const PROBE_ENTRY_LIMIT: usize = 256;
+/// Maximum directory depth a single probe explores below the directory being checked.
+const PROBE_DEPTH_LIMIT: usize = 3;
+
+/// Decides whether a directory is worth descending into for a set of file types:
+/// extensions or the names of built-in types.
+#[derive(Debug, Clone)]
+pub(crate) struct DirectoryPruner {
+    file_types: Vec<String>,
+    extensions: HashSet<String>,
+    /// Files without an extension may be typed by their content.
+    extensionless: bool,
+}
+
+impl DirectoryPruner {
+    pub(crate) fn new<'a>(file_types: impl IntoIterator<Item = &'a String>) -> Self {
+        let file_types: Vec<String> = file_types.into_iter().cloned().collect();
+        Self {
+            extensions: file_types
+                .iter()
+                .map(|e: &String| e.to_ascii_lowercase())
+                .collect(),
+            file_types,
+            extensionless: false,
+        }
+    }
+
+    /// Also descends for files without an extension, which `--detect-types` may
+    /// find to be of the types by their first line.
+    pub(crate) fn detecting_types(mut self, yes: bool) -> Self {
+        self.extensionless = yes;
+        self
+    }
+
+    /// Returns false only if `dir` provably contains no candidate files.
+    pub(crate) fn should_descend(&self, dir: &Path) -> bool {
+        let mut budget: usize = PROBE_ENTRY_LIMIT;
+        self.may_contain_candidates(dir, 0, &mut budget)
+    }
+
+    fn may_contain_candidates(&self, dir: &Path, depth: usize, budget: &mut usize) -> bool {
+        let Ok(entries) = fs::read_dir(dir) else {
+            // Let the walker report the error instead of silently skipping.
+            return true;
+        };
+        for entry in entries {
+            let Ok(entry) = entry else {
+                return true;
+            };
+            if *budget == 0 {
+                return true;
+            }
+            *budget -= 1;
+
+            let Ok(file_type) = entry.file_type() else {
+                return true;
+            };
+            if file_type.is_dir() {
+                if depth + 1 >= PROBE_DEPTH_LIMIT
+                    || self.may_contain_candidates(&entry.path(), depth + 1, budget)
+                {
+                    return true;
+                }
+            } else if file_type.is_symlink() || self.is_candidate(&entry.path()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn is_candidate(&self, path: &Path) -> bool {
+        self.extensionless && path.extension().is_none()
+            || path
+                .extension()
+                .and_then(std::ffi::OsStr::to_str)
+                .is_some_and(|e: &str| self.extensions.contains(&e.to_ascii_lowercase()))
+            || self
+                .file_types
+                .iter()
+                .any(|t: &String| filetype::is_of_type(path, t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prunes_only_branches_without_candidates() {
+        let root: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let assets: std::path::PathBuf = root.path().join("assets/img");
+        let src: std::path::PathBuf = root.path().join("src/nested");
+        fs::create_dir_all(&assets).unwrap();
+        fs::create_dir_all(&src).unwrap();
+        fs::write(assets.join("logo.png"), b"").unwrap();
+        fs::write(src.join("app.JS"), b"").unwrap();
+
+        let pruner: DirectoryPruner = DirectoryPruner::new(&["js".to_string()]);
+        assert!(!pruner.should_descend(&root.path().join("assets")));
+        assert!(pruner.should_descend(&root.path().join("src")));
+    }
+
+    #[test]
+    fn test_keeps_branches_deeper_than_the_probe() {
+        let root: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("a/b/c/d")).unwrap();
+
+        let pruner: DirectoryPruner = DirectoryPruner::new(&["js".to_string()]);
+        assert!(pruner.should_descend(&root.path().join("a")));
+    }
+}
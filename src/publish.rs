@@ -0,0 +1,464 @@
+//! `gfr publish`: puts a local pattern into a checkout of a pattern repository,
+//! ready to be committed and sent as a pull request. The pattern is validated,
+//! given its new version, optionally signed with minisign, and its `index.json`
+//! entry is written with the new checksum. An index listing several versions of
+//! the pattern keeps them and gains an entry for the new one.
+
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, ValueEnum};
+use owo_colors::OwoColorize;
+use semver::Version;
+use serde_json::Value;
+
+use crate::migrate;
+use crate::validate::{self, Issue};
+use crate::{default_version, indexer, is_remote, Bundles, Index, IndexPattern, Styles};
+
+#[derive(Args, Debug)]
+pub(crate) struct PublishArgs {
+    /// Name of a local pattern, or path to a pattern file.
+    target: String,
+    /// Checkout of the pattern repository to publish into.
+    #[arg(long, default_value = ".")]
+    repo: PathBuf,
+    /// Publish as this version.
+    #[arg(long, value_name = "VERSION", conflicts_with = "bump")]
+    set_version: Option<String>,
+    /// Publish as the next major, minor or patch version after the published one.
+    #[arg(long, value_enum)]
+    bump: Option<Bump>,
+    /// Sign the published file with `minisign`, which must be on the PATH.
+    #[arg(long)]
+    sign: bool,
+    /// Minisign secret key to sign with, instead of minisign's default.
+    #[arg(long, value_name = "FILE", requires = "sign")]
+    secret_key: Option<PathBuf>,
+}
+
+/// Which part of the version `--bump` increments.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Bump {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl Bump {
+    fn apply(self, version: &Version) -> Version {
+        match self {
+            Bump::Major => Version::new(version.major + 1, 0, 0),
+            Bump::Minor => Version::new(version.major, version.minor + 1, 0),
+            Bump::Patch => Version::new(version.major, version.minor, version.patch + 1),
+        }
+    }
+}
+
+/// The version to publish: the one asked for, else the local version, which
+/// has to be newer than the published one.
+fn next_version(
+    local: &Version,
+    published: Option<&Version>,
+    set_version: Option<&str>,
+    bump: Option<Bump>,
+) -> Result<Version> {
+    let version: Version = match (set_version, bump) {
+        (Some(version), _) => Version::parse(version.trim_start_matches('v'))
+            .with_context(|| format!("'{version}' is not a semantic version"))?,
+        (None, Some(bump)) => bump.apply(published.map_or(local, |p: &Version| p.max(local))),
+        (None, None) => local.clone(),
+    };
+    match published {
+        Some(published) if version <= *published => Err(anyhow!(
+            "v{published} is already published; pass --bump or --set-version to publish a newer version."
+        )),
+        _ => Ok(version),
+    }
+}
+
+/// `source` with its `version` field set to `version`.
+fn with_version(source: &str, version: &Version) -> Result<String> {
+    let mut json: Value = serde_json::from_str(source)?;
+    let object: &mut serde_json::Map<String, Value> = json
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("A pattern file must hold a JSON object."))?;
+    object.insert("version".to_string(), Value::String(version.to_string()));
+    Ok(serde_json::to_string_pretty(&json)? + "\n")
+}
+
+/// What publishing a pattern changes in the repository.
+#[derive(Debug)]
+struct Publication {
+    index: Index,
+    /// Position of the pattern's entry in `index`, if it was published before.
+    position: Option<usize>,
+    /// Whether the new version is added next to the listed ones instead of
+    /// replacing the newest.
+    append: bool,
+    /// Path of the pattern file in the repository.
+    relative: PathBuf,
+    version: Version,
+    body: String,
+    /// Listed versions whose checksum was dropped because the new version
+    /// overwrote the file they share with it.
+    stale: Vec<String>,
+}
+
+impl Publication {
+    /// Works out where and as which version `source` is published into `repo`.
+    fn plan(repo: &Path, name: &str, source: &str, args: &PublishArgs) -> Result<Self> {
+        let issues: Vec<Issue> = validate::check_source(source, None);
+        if !issues.is_empty() {
+            let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+            return Err(anyhow!(
+                "'{name}' is not a valid pattern:\n    {}",
+                issues.join("\n    ")
+            ));
+        }
+        let index: Index = load_index(repo)?;
+        // A versioned index lists a pattern more than once; the newest entry is
+        // the one its file belongs to.
+        let position: Option<usize> = index
+            .patterns
+            .iter()
+            .enumerate()
+            .filter(|(_, p): &(usize, &IndexPattern)| p.name == name)
+            .max_by_key(|(_, p): &(usize, &IndexPattern)| Version::parse(&p.version).ok())
+            .map(|(position, _): (usize, &IndexPattern)| position);
+        let published: Option<Version> = position
+            .and_then(|position: usize| Version::parse(&index.patterns[position].version).ok());
+
+        let json: Value = serde_json::from_str(source)?;
+        let local: String = json
+            .get("version")
+            .and_then(Value::as_str)
+            .map_or_else(default_version, str::to_string);
+        let local: Version =
+            Version::parse(&local).with_context(|| format!("'{name}' has no semantic version"))?;
+        let version: Version = next_version(
+            &local,
+            published.as_ref(),
+            args.set_version.as_deref(),
+            args.bump,
+        )?;
+
+        let local_url: Option<&str> = position
+            .map(|position: usize| index.patterns[position].url.as_str())
+            .filter(|url: &&str| !is_remote(url) && !Path::new(url).is_absolute());
+        // Files named after their version, like `xss/1.0.0.json`, get a new file
+        // per version.
+        let per_version: Option<String> = local_url
+            .zip(published.as_ref())
+            .filter(|(url, published): &(&str, &Version)| url.contains(&published.to_string()))
+            .map(|(url, published): (&str, &Version)| {
+                url.replace(&published.to_string(), &version.to_string())
+            });
+        let listed: usize = index.patterns.iter().filter(|p| p.name == name).count();
+        let append: bool = per_version.is_some() || listed > 1;
+        let relative: PathBuf = per_version
+            .or_else(|| local_url.map(str::to_string))
+            .map_or_else(|| PathBuf::from(format!("{name}.json")), PathBuf::from);
+        Ok(Self {
+            body: with_version(source, &version)?,
+            index,
+            position,
+            append,
+            relative,
+            version,
+            stale: Vec::new(),
+        })
+    }
+
+    /// Writes the pattern file, signed with minisign if `sign` is set, and the index.
+    fn write(mut self, repo: &Path, sign: bool, secret_key: Option<&Path>) -> Result<Self> {
+        let path: PathBuf = repo.join(&self.relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, &self.body)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        let signature: PathBuf = path.with_extension("json.minisig");
+        if sign {
+            minisign(&path, secret_key)?;
+        } else if signature.is_file() {
+            // A signature of the old file would fail every install.
+            fs::remove_file(&signature)?;
+        }
+
+        let mut entry: IndexPattern = indexer::index_entry(repo, &self.relative, None)?
+            .map_err(|issues: Vec<String>| anyhow!("{}", issues.join("; ")))?;
+        match self.position {
+            Some(position) => {
+                let existing: &IndexPattern = &self.index.patterns[position];
+                // Keep the URL scheme the index already uses.
+                if is_remote(&existing.url) {
+                    entry.signature = signature
+                        .is_file()
+                        .then(|| format!("{}.minisig", existing.url));
+                    entry.url.clone_from(&existing.url);
+                }
+                if self.append {
+                    // Older versions served from the same file now get the new
+                    // content, which their checksum no longer matches.
+                    for other in &mut self.index.patterns {
+                        if other.name == entry.name && other.url == entry.url {
+                            other.sha256 = None;
+                            other.signature.clone_from(&entry.signature);
+                            self.stale.push(other.version.clone());
+                        }
+                    }
+                    self.index.patterns.push(entry);
+                } else {
+                    self.index.patterns[position] = entry;
+                }
+            }
+            None => self.index.patterns.push(entry),
+        }
+        let index_path: PathBuf = repo.join("index.json");
+        let mut file: File = File::create(&index_path)
+            .with_context(|| format!("Failed to create {}", index_path.display()))?;
+        serde_json::to_writer_pretty(&mut file, &self.index)?;
+        writeln!(file)?;
+        Ok(self)
+    }
+}
+
+/// The repository's index, or an empty one if it has none yet.
+fn load_index(repo: &Path) -> Result<Index> {
+    let path: PathBuf = repo.join("index.json");
+    if !path.exists() {
+        return Ok(Index {
+            namespace: None,
+            patterns: Vec::new(),
+            bundles: Bundles::new(),
+        });
+    }
+    let file: File = File::open(&path)?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse index from: {}", path.display()))
+}
+
+/// Signs `path` with the minisign command, which asks for the key's password.
+fn minisign(path: &Path, secret_key: Option<&Path>) -> Result<()> {
+    let mut command: Command = Command::new("minisign");
+    command.arg("-S").arg("-m").arg(path);
+    if let Some(secret_key) = secret_key {
+        command.arg("-s").arg(secret_key);
+    }
+    let status: ExitStatus = command.status().map_err(|e: std::io::Error| {
+        if e.kind() == ErrorKind::NotFound {
+            anyhow!("--sign needs minisign on the PATH: https://jedisct1.github.io/minisign/")
+        } else {
+            anyhow!("Failed to run minisign: {e}")
+        }
+    })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("minisign failed to sign {}", path.display()))
+    }
+}
+
+/// Runs `gfr publish`.
+pub(crate) fn run_publish(args: &PublishArgs, styles: &Styles) -> Result<()> {
+    let source_path: PathBuf = validate::resolve_target(&args.target)?;
+    let name: String = source_path
+        .file_stem()
+        .map(|stem: &std::ffi::OsStr| stem.to_string_lossy().into_owned())
+        .ok_or_else(|| anyhow!("{} is not a pattern file", source_path.display()))?;
+    if !args.repo.is_dir() {
+        return Err(anyhow!(
+            "No pattern repository checkout at {}.",
+            args.repo.display()
+        ));
+    }
+    // Indexes serve plain JSON of the current schema version, so YAML patterns are
+    // converted, comments dropped and older files upgraded.
+    let mut json: Value = migrate::read_value(
+        &source_path,
+        &fs::read_to_string(&source_path)
+            .with_context(|| format!("Failed to read {}", source_path.display()))?,
+    )?;
+    migrate::upgrade(&mut json)?;
+    let source: String = serde_json::to_string_pretty(&json)?;
+    let plan: Publication = Publication::plan(&args.repo, &name, &source, args)?;
+
+    let published: Publication = plan.write(&args.repo, args.sign, args.secret_key.as_deref())?;
+    println!(
+        "{} Published '{}' v{} to {}",
+        "✓".style(styles.success),
+        name.style(styles.highlight),
+        published.version.style(styles.highlight),
+        args.repo.join(&published.relative).display()
+    );
+    if !published.stale.is_empty() {
+        eprintln!(
+            "{} v{} of '{name}' share {} with v{}, so their checksums were dropped from the index.",
+            "Warning:".style(styles.error),
+            published.stale.join(", v"),
+            published.relative.display(),
+            published.version
+        );
+    }
+    println!(
+        "\n{}\n    git -C {} add -A\n    git -C {} commit -m \"Publish {name} v{}\"\n    git -C {} push",
+        "Commit the changes and open a pull request:".style(styles.title),
+        args.repo.display(),
+        args.repo.display(),
+        published.version,
+        args.repo.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256_hex;
+
+    #[test]
+    fn test_publish_into_repo() {
+        let v = |version: &str| Version::parse(version).unwrap();
+        assert_eq!(
+            next_version(&v("1.0.0"), Some(&v("1.2.3")), None, Some(Bump::Minor)).unwrap(),
+            v("1.3.0")
+        );
+        assert_eq!(
+            next_version(&v("2.0.0"), Some(&v("1.2.3")), None, Some(Bump::Patch)).unwrap(),
+            v("2.0.1")
+        );
+        assert!(next_version(&v("1.2.3"), Some(&v("1.2.3")), None, None).is_err());
+        assert!(next_version(&v("1.0.0"), None, Some("nope"), None).is_err());
+
+        let repo: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(repo.path().join("web")).unwrap();
+        fs::write(repo.path().join("web/xss.json.minisig"), "old").unwrap();
+        fs::write(
+            repo.path().join("index.json"),
+            r#"{"patterns": [{"name": "xss", "version": "1.0.0", "url": "web/xss.json",
+                "signature": "web/xss.json.minisig"}]}"#,
+        )
+        .unwrap();
+        let args = |bump: Option<Bump>| PublishArgs {
+            target: "xss".to_string(),
+            repo: repo.path().to_path_buf(),
+            set_version: None,
+            bump,
+            sign: false,
+            secret_key: None,
+        };
+        let source: &str = r#"{"version": "1.0.0", "pattern": "<script"}"#;
+        assert!(Publication::plan(repo.path(), "xss", source, &args(None)).is_err());
+        assert!(
+            Publication::plan(repo.path(), "xss", "{\"pattern\": \"(\"}", &args(None)).is_err()
+        );
+
+        Publication::plan(repo.path(), "xss", source, &args(Some(Bump::Minor)))
+            .unwrap()
+            .write(repo.path(), false, None)
+            .unwrap();
+        let written: Value =
+            serde_json::from_slice(&fs::read(repo.path().join("web/xss.json")).unwrap()).unwrap();
+        assert_eq!(written["version"], "1.1.0");
+        assert!(!repo.path().join("web/xss.json.minisig").exists());
+        let index: Index = load_index(repo.path()).unwrap();
+        assert_eq!(index.patterns.len(), 1);
+        let entry: &IndexPattern = &index.patterns[0];
+        assert_eq!(
+            (
+                entry.version.as_str(),
+                entry.url.as_str(),
+                entry.signature.as_deref()
+            ),
+            ("1.1.0", "web/xss.json", None)
+        );
+        assert_eq!(
+            entry.sha256.as_deref(),
+            Some(sha256_hex(&fs::read(repo.path().join("web/xss.json")).unwrap()).as_str())
+        );
+
+        Publication::plan(repo.path(), "jwt", r#"{"pattern": "eyJ"}"#, &args(None))
+            .unwrap()
+            .write(repo.path(), false, None)
+            .unwrap();
+        let index: Index = load_index(repo.path()).unwrap();
+        assert_eq!(index.patterns[1].url, "jwt.json");
+    }
+
+    #[test]
+    fn test_publish_keeps_listed_versions() {
+        let args = |repo: &Path| PublishArgs {
+            target: "xss".to_string(),
+            repo: repo.to_path_buf(),
+            set_version: Some("1.2.0".to_string()),
+            bump: None,
+            sign: false,
+            secret_key: None,
+        };
+        let source: &str = r#"{"version": "1.0.0", "pattern": "<script"}"#;
+
+        // One file per version: the new version gets its own file.
+        let repo: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(repo.path().join("xss")).unwrap();
+        fs::write(repo.path().join("xss/1.1.0.json"), "old").unwrap();
+        fs::write(
+            repo.path().join("index.json"),
+            r#"{"patterns": [
+                {"name": "xss", "version": "1.0.0", "url": "xss/1.0.0.json", "sha256": "aa"},
+                {"name": "xss", "version": "1.1.0", "url": "xss/1.1.0.json", "sha256": "bb"}]}"#,
+        )
+        .unwrap();
+        let published: Publication =
+            Publication::plan(repo.path(), "xss", source, &args(repo.path()))
+                .unwrap()
+                .write(repo.path(), false, None)
+                .unwrap();
+        assert!(published.stale.is_empty());
+        assert_eq!(
+            fs::read_to_string(repo.path().join("xss/1.1.0.json")).unwrap(),
+            "old"
+        );
+        let index: Index = load_index(repo.path()).unwrap();
+        let listed: Vec<(&str, &str, Option<&str>)> = index
+            .patterns
+            .iter()
+            .map(|p: &IndexPattern| (p.version.as_str(), p.url.as_str(), p.sha256.as_deref()))
+            .collect();
+        assert_eq!(listed.len(), 3);
+        assert_eq!(
+            &listed[..2],
+            &[
+                ("1.0.0", "xss/1.0.0.json", Some("aa")),
+                ("1.1.0", "xss/1.1.0.json", Some("bb"))
+            ]
+        );
+        assert_eq!((listed[2].0, listed[2].1), ("1.2.0", "xss/1.2.0.json"));
+
+        // One shared file: the older entries stay, without their stale checksums.
+        let repo: tempfile::TempDir = tempfile::tempdir().unwrap();
+        fs::write(
+            repo.path().join("index.json"),
+            r#"{"patterns": [
+                {"name": "xss", "version": "1.0.0", "url": "xss.json", "sha256": "aa"},
+                {"name": "xss", "version": "1.1.0", "url": "xss.json", "sha256": "bb"}]}"#,
+        )
+        .unwrap();
+        let published: Publication =
+            Publication::plan(repo.path(), "xss", source, &args(repo.path()))
+                .unwrap()
+                .write(repo.path(), false, None)
+                .unwrap();
+        assert_eq!(published.stale, ["1.0.0", "1.1.0"]);
+        let index: Index = load_index(repo.path()).unwrap();
+        assert_eq!(index.patterns.len(), 3);
+        assert!(index.patterns[..2]
+            .iter()
+            .all(|p: &IndexPattern| p.sha256.is_none()));
+        assert_eq!(index.patterns[2].version, "1.2.0");
+        assert!(index.patterns[2].sha256.is_some());
+    }
+}
@@ -0,0 +1,150 @@
+//! SARIF 2.1.0 report generation for `gfr search --format sarif`.
+
+use serde_json::{json, Value};
+
+use crate::{Finding, Pattern, Severity};
+
+const SARIF_SCHEMA_URL: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Builds a SARIF log with one rule per pattern and one result per finding.
+pub(crate) fn build_report(patterns: &[Pattern], findings: &[Finding]) -> Value {
+    let rules: Vec<Value> = patterns.iter().map(rule).collect();
+    let results: Vec<Value> = findings
+        .iter()
+        .map(|f: &Finding| {
+            let rule_index: Option<usize> =
+                patterns.iter().position(|p: &Pattern| p.name == f.pattern);
+            result(f, rule_index)
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA_URL,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// Maps a pattern to a SARIF `reportingDescriptor`.
+fn rule(pattern: &Pattern) -> Value {
+    let description: &str = pattern.description.as_deref().unwrap_or(&pattern.name);
+    json!({
+        "id": pattern.name,
+        "name": pattern.name,
+        "shortDescription": { "text": description },
+        "defaultConfiguration": { "level": level(pattern.severity) },
+        "properties": {
+            "tags": pattern.tags.clone().unwrap_or_default(),
+            "version": pattern.version,
+        }
+    })
+}
+
+/// Maps a finding to a SARIF `result` with its physical location.
+fn result(finding: &Finding, rule_index: Option<usize>) -> Value {
+    let uri: String = finding
+        .path
+        .as_deref()
+        .map_or_else(|| "stdin".to_string(), artifact_uri);
+    let mut region: Value = json!({
+        "startColumn": finding.column,
+        "endColumn": finding.column + finding.matched.len() as u64,
+        "snippet": { "text": finding.line },
+    });
+    if let Some(line) = finding.line_number {
+        region["startLine"] = json!(line);
+    }
+
+    let mut result: Value = json!({
+        "ruleId": finding.pattern,
+        "level": level(finding.severity),
+        "message": { "text": format!("Pattern '{}' matched '{}'", finding.pattern, finding.matched) },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": uri },
+                "region": region,
+            }
+        }]
+    });
+    if let Some(index) = rule_index {
+        result["ruleIndex"] = json!(index);
+    }
+    if let Some(verification) = finding.verification {
+        result["properties"]["verification"] = json!(verification);
+    }
+    if let Some(status) = finding.status {
+        result["properties"]["status"] = json!(status);
+    }
+    if !finding.annotations.is_empty() {
+        result["properties"]["annotations"] = json!(finding.annotations);
+    }
+    result
+}
+
+/// Maps a pattern severity to a SARIF result level.
+fn level(severity: Option<Severity>) -> &'static str {
+    match severity {
+        Some(Severity::Critical | Severity::High) => "error",
+        Some(Severity::Low | Severity::Info) => "note",
+        Some(Severity::Medium) | None => "warning",
+    }
+}
+
+/// Turns a filesystem path into a relative, forward-slashed artifact URI.
+fn artifact_uri(path: &str) -> String {
+    let uri: String = path.replace('\\', "/");
+    uri.strip_prefix("./").map(str::to_string).unwrap_or(uri)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_report_links_results_to_rules() {
+        let pattern: Pattern = Pattern {
+            name: "secrets".to_string(),
+            description: Some("Finds secrets".to_string()),
+            tags: Some(vec!["security".to_string()]),
+            regex: Some("key".to_string()),
+            ..Default::default()
+        };
+        let finding: Finding = Finding {
+            fingerprint: "secrets:0123456789abcdef".to_string(),
+            pattern: "secrets".to_string(),
+            severity: Some(Severity::High),
+            path: Some("./src/app.js".to_string()),
+            line_number: Some(3),
+            column: 7,
+            byte_offset: 40,
+            matched: "key".to_string(),
+            line: "const key = 1;".to_string(),
+            ..Default::default()
+        };
+
+        let report: Value = build_report(&[pattern], &[finding]);
+        let run: &Value = &report["runs"][0];
+        assert_eq!(report["version"], "2.1.0");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "secrets");
+        assert_eq!(
+            run["tool"]["driver"]["rules"][0]["properties"]["tags"][0],
+            "security"
+        );
+        let location: &Value = &run["results"][0]["locations"][0]["physicalLocation"];
+        assert_eq!(run["results"][0]["ruleIndex"], 0);
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(location["artifactLocation"]["uri"], "src/app.js");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["endColumn"], 10);
+    }
+}
@@ -0,0 +1,224 @@
+//! `gfr init-repo`: creates a new pattern repository with the layout of the
+//! community one, so a team can host its own registry right away: a `patterns`
+//! directory with an example pattern, the pattern schema, a README and the index.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+
+use crate::{indexer, Bundles, Index, IndexPattern, Styles};
+
+/// Where pattern files go in a new repository.
+const PATTERNS_DIR: &str = "patterns";
+/// Path of the pattern schema in a new repository.
+const SCHEMA_PATH: &str = "schemas/pattern.schema.json";
+/// Path of the example pattern in a new repository.
+const EXAMPLE_PATH: &str = "patterns/example-api-key.json";
+
+/// JSON Schema of pattern files, for editors and CI checks of the new repository.
+const PATTERN_SCHEMA: &str = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "gfr pattern",
+  "type": "object",
+  "additionalProperties": false,
+  "properties": {
+    "$schema": { "type": "string" },
+    "schema_version": { "type": "integer", "minimum": 1 },
+    "version": { "type": "string", "pattern": "^\\d+\\.\\d+\\.\\d+" },
+    "author": { "type": "string" },
+    "license": { "type": "string", "description": "SPDX license identifier" },
+    "description": { "type": "string" },
+    "tags": { "type": "array", "items": { "type": "string" } },
+    "severity": { "enum": ["info", "low", "medium", "high", "critical"] },
+    "pattern": { "type": "string" },
+    "patterns": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+    "strings": { "type": "array", "items": { "type": "string", "minLength": 1 }, "minItems": 1 },
+    "keywords": { "type": "array", "items": { "type": "string", "minLength": 1 } },
+    "file_types": { "type": "array", "items": { "type": "string" } },
+    "exclude_file_types": { "type": "array", "items": { "type": "string" } },
+    "languages": { "type": "array", "items": { "type": "string" } },
+    "paths": { "type": "array", "items": { "type": "string" } },
+    "paths_exclude": { "type": "array", "items": { "type": "string" } },
+    "ignore_case": { "type": "boolean" },
+    "multiline": { "type": "boolean" },
+    "word": { "type": "boolean" },
+    "unicode": { "type": "boolean" },
+    "engine": { "enum": ["rust", "backtrack"] },
+    "tests": {
+      "type": "object",
+      "additionalProperties": false,
+      "properties": {
+        "match": { "type": "array", "items": { "type": "string" } },
+        "no_match": { "type": "array", "items": { "type": "string" } }
+      }
+    },
+    "min_entropy": { "type": "number", "minimum": 0 },
+    "entropy_group": { "type": "string" },
+    "validators": {
+      "type": "array",
+      "items": { "enum": ["luhn", "jwt", "base64-json", "uuid"] }
+    },
+    "verification": {
+      "type": "object",
+      "required": ["url"],
+      "properties": {
+        "method": { "type": "string" },
+        "url": { "type": "string" },
+        "headers": { "type": "object", "additionalProperties": { "type": "string" } },
+        "body": { "type": "string" },
+        "success_status": { "type": "array", "items": { "type": "integer" } },
+        "success_body": { "type": "string" },
+        "failure_status": { "type": "array", "items": { "type": "integer" } }
+      }
+    },
+    "plugins": { "type": "array", "items": { "type": "string", "minLength": 1 } },
+    "deprecated": { "type": "boolean" },
+    "superseded_by": { "type": "string" }
+  },
+  "oneOf": [{ "required": ["pattern"] }, { "required": ["patterns"] }, { "required": ["strings"] }]
+}
+"#;
+
+/// A pattern to copy from, with the tests `gfr test` runs.
+const EXAMPLE_PATTERN: &str = r#"{
+  "$schema": "../schemas/pattern.schema.json",
+  "schema_version": 2,
+  "version": "1.0.0",
+  "description": "Example: API keys of the form EXAMPLE-<32 hex digits>. Replace with your own patterns.",
+  "tags": ["example", "secrets"],
+  "severity": "medium",
+  "pattern": "EXAMPLE-[0-9a-f]{32}",
+  "tests": {
+    "match": ["key = EXAMPLE-0123456789abcdef0123456789abcdef"],
+    "no_match": ["EXAMPLE-not-a-key"]
+  }
+}
+"#;
+
+#[derive(Args, Debug)]
+pub(crate) struct InitRepoArgs {
+    /// Directory to create the repository in. It must be empty or not exist yet.
+    dir: PathBuf,
+    /// Namespace patterns from this repository are installed into.
+    #[arg(long)]
+    namespace: Option<String>,
+}
+
+/// The README of a new repository, describing how to add and install patterns.
+fn readme(name: &str) -> String {
+    format!(
+        "# {name}
+
+Patterns for [gfr](https://github.com/Kr1shna4garwal/gfr).
+
+## Adding a pattern
+
+1. Add a file to `{PATTERNS_DIR}/`, starting from `{EXAMPLE_PATH}`. Editors that
+   understand JSON Schema check it against `{SCHEMA_PATH}`.
+2. Check it with `gfr validate <file>` and `gfr test <file>`.
+3. Regenerate the index with `gfr index . --namespace <namespace>`, or use
+   `gfr publish <pattern> --repo .` to do all of the above for a local pattern.
+
+## Installing
+
+```bash
+gfr install --url <url of this repository's index.json>
+```
+"
+    )
+}
+
+/// Writes the files of a new repository into `dir`.
+fn scaffold(dir: &Path, namespace: Option<&str>) -> Result<Index> {
+    if dir.is_file() {
+        return Err(anyhow!("{} is a file.", dir.display()));
+    }
+    if dir.is_dir() && fs::read_dir(dir)?.next().is_some() {
+        return Err(anyhow!(
+            "{} is not empty; init-repo only creates new repositories.",
+            dir.display()
+        ));
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let name: String = fs::canonicalize(dir)?.file_name().map_or_else(
+        || "patterns".to_string(),
+        |name: &std::ffi::OsStr| name.to_string_lossy().into_owned(),
+    );
+    for (path, contents) in [
+        (SCHEMA_PATH, PATTERN_SCHEMA.to_string()),
+        (EXAMPLE_PATH, EXAMPLE_PATTERN.to_string()),
+        ("README.md", readme(&name)),
+    ] {
+        let path: PathBuf = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+
+    let example: IndexPattern = indexer::index_entry(dir, Path::new(EXAMPLE_PATH), None)?
+        .map_err(|issues: Vec<String>| anyhow!("{}", issues.join("; ")))?;
+    let index: Index = Index {
+        namespace: namespace.map(str::to_string),
+        patterns: vec![example],
+        bundles: Bundles::new(),
+    };
+    let mut file: File = File::create(dir.join("index.json"))?;
+    serde_json::to_writer_pretty(&mut file, &index)?;
+    writeln!(file)?;
+    Ok(index)
+}
+
+/// Runs `gfr init-repo`.
+pub(crate) fn run_init_repo(args: &InitRepoArgs, styles: &Styles) -> Result<()> {
+    scaffold(&args.dir, args.namespace.as_deref())?;
+    println!(
+        "{} Created a pattern repository in {}",
+        "✓".style(styles.success),
+        args.dir.display().to_string().style(styles.highlight)
+    );
+    for path in ["index.json", SCHEMA_PATH, EXAMPLE_PATH, "README.md"] {
+        println!("    {}", path.style(styles.dim));
+    }
+    println!(
+        "\nAdd your patterns under {PATTERNS_DIR}/, run `gfr index {}`, and install with `gfr install --url {}`.",
+        args.dir.display(),
+        args.dir.join("index.json").display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate;
+
+    #[test]
+    fn test_scaffold_repository() {
+        let root: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let dir: PathBuf = root.path().join("team-patterns");
+        let index: Index = scaffold(&dir, Some("team")).unwrap();
+        assert_eq!(index.namespace.as_deref(), Some("team"));
+        assert_eq!(index.patterns[0].name, "example-api-key");
+        assert_eq!(index.patterns[0].url, EXAMPLE_PATH);
+        assert!(fs::read_to_string(dir.join("README.md"))
+            .unwrap()
+            .starts_with("# team-patterns"));
+
+        let schema: serde_json::Value = serde_json::from_str(PATTERN_SCHEMA).unwrap();
+        let schema: jsonschema::Validator = jsonschema::validator_for(&schema).unwrap();
+        assert!(validate::check_source(EXAMPLE_PATTERN, Some(&schema)).is_empty());
+        assert!(
+            !validate::check_source(r#"{"pattern": "a", "colour": "red"}"#, Some(&schema))
+                .is_empty()
+        );
+
+        // Running it again would overwrite the repository.
+        assert!(scaffold(&dir, None).is_err());
+    }
+}
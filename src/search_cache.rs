@@ -0,0 +1,94 @@
+//! Compiled searches kept between the requests of `gfr serve` and `gfr daemon`,
+//! so that each set of patterns is only compiled the first time it comes up.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use anyhow::Result;
+use gfr_core::Search;
+use serde::Serialize;
+
+use crate::sha256_hex;
+
+/// Compiled searches kept at most; the cache starts over once it is full.
+const MAX_CACHED_SEARCHES: usize = 64;
+
+/// Compiled searches by what they were compiled from.
+#[derive(Debug, Default)]
+pub(crate) struct SearchCache {
+    searches: Mutex<HashMap<String, Arc<Search>>>,
+}
+
+impl SearchCache {
+    /// Identifies a search by everything compiled into it: its patterns, whole,
+    /// so that a changed pattern is compiled anew, and its options.
+    pub(crate) fn key(compiled: &impl Serialize) -> Result<String> {
+        Ok(sha256_hex(&serde_json::to_vec(compiled)?))
+    }
+
+    /// The search cached under `key`, or else the one `build` compiles, which
+    /// is cached from then on.
+    pub(crate) fn get_or_build<E>(
+        &self,
+        key: String,
+        build: impl FnOnce() -> Result<Search, E>,
+    ) -> Result<Arc<Search>, E> {
+        let mut searches = self.searches.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(search) = searches.get(&key) {
+            return Ok(Arc::clone(search));
+        }
+        let search: Arc<Search> = Arc::new(build()?);
+        if searches.len() >= MAX_CACHED_SEARCHES {
+            searches.clear();
+        }
+        searches.insert(key, Arc::clone(&search));
+        Ok(search)
+    }
+
+    /// How many searches are cached.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.searches
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gfr_core::{Pattern, SearchBuilder};
+
+    #[test]
+    fn test_cache_starts_over_once_full() {
+        let cache: SearchCache = SearchCache::default();
+        let build = || {
+            SearchBuilder::new()
+                .add_pattern(Pattern {
+                    regex: Some("AKIA".to_string()),
+                    ..Pattern::default()
+                })
+                .build()
+        };
+        for i in 0..MAX_CACHED_SEARCHES {
+            cache
+                .get_or_build(SearchCache::key(&i).unwrap(), build)
+                .unwrap();
+        }
+        assert_eq!(cache.len(), MAX_CACHED_SEARCHES);
+        let first: Arc<Search> = cache
+            .get_or_build(SearchCache::key(&0).unwrap(), build)
+            .unwrap();
+        assert!(Arc::ptr_eq(
+            &first,
+            &cache
+                .get_or_build(SearchCache::key(&0).unwrap(), build)
+                .unwrap()
+        ));
+        cache
+            .get_or_build(SearchCache::key(&"new").unwrap(), build)
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+//! Minisign signatures for installed patterns. An index entry may point at a
+//! detached `.minisig` file; `gfr install` checks it against the keys in
+//! `trusted_keys.pub` before the pattern is written to disk.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use minisign_verify::{PublicKey, Signature};
+
+use crate::get_pattern_dir;
+
+/// Minisign public keys, one per line, as printed by `minisign -G` or copied from
+/// a `.pub` file. Comment lines are ignored.
+pub(crate) const TRUSTED_KEYS_FILE: &str = "trusted_keys.pub";
+
+/// Keys whose signatures `gfr install` accepts.
+#[derive(Default)]
+pub(crate) struct TrustedKeys {
+    keys: Vec<PublicKey>,
+}
+
+impl TrustedKeys {
+    pub(crate) fn load() -> Result<Self> {
+        let path: PathBuf = get_pattern_dir()?.join(TRUSTED_KEYS_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text: String = fs::read_to_string(&path)?;
+        Self::parse(&text).with_context(|| format!("Invalid key in {}", path.display()))
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Self> {
+        let keys: Vec<PublicKey> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line: &&str| {
+                !line.is_empty()
+                    && !line.starts_with('#')
+                    && !line.starts_with("untrusted comment:")
+            })
+            .map(|line: &str| PublicKey::from_base64(line).map_err(|e| anyhow!("'{line}': {e}")))
+            .collect::<Result<Vec<PublicKey>>>()?;
+        Ok(Self { keys })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Checks that `signature`, the text of a `.minisig` file, is a signature of
+    /// `data` by one of the trusted keys.
+    pub(crate) fn verify(&self, data: &[u8], signature: &str) -> Result<()> {
+        let signature: Signature =
+            Signature::decode(signature).map_err(|e| anyhow!("unreadable signature: {e}"))?;
+        // Signatures made by minisign before 0.8 sign the file itself rather than its hash.
+        if self
+            .keys
+            .iter()
+            .any(|key: &PublicKey| key.verify(data, &signature, true).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(anyhow!("not signed by any key in {TRUSTED_KEYS_FILE}"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vector from the minisign-verify crate.
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RWQf6LRCGA9i59SLOFxz6NxvASXDJeRtuZykwQepbDEGt87ig1BNpWaVWuNrm73YiIiJbq71Wi+dP9eKL8OC351vwIasSSbXxwA=
+trusted comment: timestamp:1555779966\tfile:test
+QtKMXWyYcwdpZAlPF7tE2ENJkRd1ujvKjlj1m9RtHTBnZPa5WKU5uWRs5GoP5M/VqE81QFuMKI5k/SfNQUaOAA==";
+
+    #[test]
+    fn test_trusted_keys_verify() {
+        let keys: TrustedKeys = TrustedKeys::parse(
+            "# release key\nuntrusted comment: minisign public key E7620F1842B4E81F\nRWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3\n",
+        )
+        .unwrap();
+        assert!(!keys.is_empty());
+        assert!(keys.verify(b"test", SIGNATURE).is_ok());
+        assert!(keys.verify(b"Test", SIGNATURE).is_err());
+        assert!(TrustedKeys::default().verify(b"test", SIGNATURE).is_err());
+        assert!(TrustedKeys::parse("not-a-key").is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! Destinations for structured search results, picked with repeated `--sink` flags.
+//!
+//! Every sink receives the findings of each searched file as they come in, from
+//! all walker threads, and is finished once the search is done. Sinks that need
+//! the whole run, like SARIF reports and webhooks, collect until then.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use termcolor::{Color, ColorSpec, StandardStream, WriteColor};
+
+use crate::store::{self, FindingsStore};
+use crate::verify::Verification;
+use crate::{get_color_choice, sarif, Finding, Pattern};
+
+/// A destination for findings.
+pub(crate) trait Sink: Send + Sync {
+    /// Takes the findings of one searched source.
+    fn write(&self, findings: &[Finding]) -> io::Result<()>;
+
+    /// Completes the output once every source has been searched.
+    fn finish(self: Box<Self>, _patterns: &[Pattern]) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A parsed `--sink` value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SinkSpec {
+    /// Grep-style lines on stdout, one per finding.
+    Terminal,
+    /// One JSON object per finding, to the file or stdout.
+    JsonLines(Option<PathBuf>),
+    /// A SARIF 2.1.0 log, to the file or stdout.
+    Sarif(Option<PathBuf>),
+    /// A JSON array of all findings, sent as a POST request to the URL after the search.
+    Webhook(String),
+    /// A scan of `target` recorded in the findings database, from `--record`.
+    Record { database: PathBuf, target: String },
+}
+
+impl SinkSpec {
+    /// Whether the sink prints machine-readable data to stdout, which must not be
+    /// mixed with anything else.
+    pub(crate) fn writes_stdout(&self) -> bool {
+        matches!(self, SinkSpec::JsonLines(None) | SinkSpec::Sarif(None))
+    }
+
+    /// Whether findings are written as they come in, rather than once the search is done.
+    pub(crate) fn streams(&self) -> bool {
+        matches!(self, SinkSpec::Terminal | SinkSpec::JsonLines(_))
+    }
+
+    /// Opens the sink. Output files are created right away, so a bad path fails
+    /// before the search starts. `label` prefixes terminal lines with their pattern.
+    pub(crate) fn open(&self, label: bool) -> Result<Box<dyn Sink>> {
+        Ok(match self {
+            SinkSpec::Terminal => Box::new(Terminal { label }),
+            SinkSpec::JsonLines(path) => Box::new(JsonLines {
+                out: Mutex::new(writer(path.as_ref())?),
+            }),
+            SinkSpec::Sarif(path) => Box::new(Sarif {
+                out: Mutex::new(writer(path.as_ref())?),
+                collected: Mutex::new(Vec::new()),
+            }),
+            SinkSpec::Webhook(url) => Box::new(Webhook {
+                url: url.clone(),
+                collected: Mutex::new(Vec::new()),
+            }),
+            SinkSpec::Record { database, target } => Box::new(Record {
+                store: Mutex::new(FindingsStore::open(database)?),
+                target: target.clone(),
+                started_at: store::now(),
+                collected: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+}
+
+impl FromStr for SinkSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let (kind, target): (&str, Option<&str>) = match value.split_once(':') {
+            Some((kind, target)) => (kind, Some(target).filter(|t: &&str| !t.is_empty())),
+            None => (value, None),
+        };
+        match (kind, target) {
+            ("terminal", None) => Ok(SinkSpec::Terminal),
+            ("jsonl", path) => Ok(SinkSpec::JsonLines(path.map(PathBuf::from))),
+            ("sarif", path) => Ok(SinkSpec::Sarif(path.map(PathBuf::from))),
+            ("webhook", Some(url)) if url.starts_with("http://") || url.starts_with("https://") => {
+                Ok(SinkSpec::Webhook(url.to_string()))
+            }
+            ("webhook", _) => Err("expected 'webhook:<http(s) URL>'".to_string()),
+            ("terminal", Some(_)) => Err("the terminal sink takes no target".to_string()),
+            _ => Err(format!(
+                "unknown sink '{kind}', expected terminal, jsonl[:PATH], sarif[:PATH] or webhook:URL"
+            )),
+        }
+    }
+}
+
+/// Opens `path` for writing, or stdout without one.
+fn writer(path: Option<&PathBuf>) -> Result<Box<dyn Write + Send>> {
+    Ok(match path {
+        Some(path) => {
+            Box::new(BufWriter::new(File::create(path).with_context(|| {
+                format!("Failed to create output file {}", path.display())
+            })?))
+        }
+        None => Box::new(io::stdout()),
+    })
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> io::Result<std::sync::MutexGuard<'_, T>> {
+    mutex.lock().map_err(|e| io::Error::other(e.to_string()))
+}
+
+fn into_inner<T>(mutex: Mutex<T>) -> Result<T> {
+    mutex.into_inner().map_err(|e| anyhow!(e.to_string()))
+}
+
+/// Prints `[pattern severity] path:line:text` with the match highlighted.
+struct Terminal {
+    label: bool,
+}
+
+impl Terminal {
+    fn print(&self, out: &mut impl WriteColor, finding: &Finding) -> io::Result<()> {
+        if self.label {
+            out.set_color(ColorSpec::new().set_fg(Some(Color::Cyan)).set_bold(true))?;
+            match finding.severity {
+                Some(severity) => write!(out, "[{} {severity}] ", finding.pattern)?,
+                None => write!(out, "[{}] ", finding.pattern)?,
+            }
+            out.reset()?;
+        }
+        if let Some(verification) = finding.verification {
+            let color: Color = match verification {
+                Verification::Verified => Color::Red,
+                Verification::Unverified => Color::Green,
+                Verification::Unknown => Color::Yellow,
+            };
+            out.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true))?;
+            write!(out, "[{verification}] ")?;
+            out.reset()?;
+        }
+        if let Some(status) = finding.status {
+            out.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
+            write!(out, "[{status}] ")?;
+            out.reset()?;
+        }
+        for (key, value) in &finding.annotations {
+            out.set_color(ColorSpec::new().set_fg(Some(Color::Blue)))?;
+            match value.as_str() {
+                Some(text) => write!(out, "[{key}: {text}] ")?,
+                None => write!(out, "[{key}: {value}] ")?,
+            }
+            out.reset()?;
+        }
+        if let Some(path) = &finding.path {
+            out.set_color(ColorSpec::new().set_fg(Some(Color::Magenta)))?;
+            write!(out, "{path}")?;
+            out.reset()?;
+            write!(out, ":")?;
+        }
+        if let Some(line_number) = finding.line_number {
+            out.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
+            write!(out, "{line_number}")?;
+            out.reset()?;
+            write!(out, ":")?;
+        }
+
+        let start: usize = usize::try_from(finding.column - 1).unwrap_or(usize::MAX);
+        let end: usize = start.saturating_add(finding.matched.len());
+        let shown: &str = finding.replacement.as_deref().unwrap_or(&finding.matched);
+        // Lines decoded lossily may no longer line up with the match offsets.
+        match (finding.line.get(..start), finding.line.get(end..)) {
+            (Some(before), Some(after)) => {
+                write!(out, "{before}")?;
+                out.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
+                write!(out, "{shown}")?;
+                out.reset()?;
+                writeln!(out, "{after}")
+            }
+            _ => writeln!(out, "{}", finding.line),
+        }
+    }
+}
+
+impl Sink for Terminal {
+    fn write(&self, findings: &[Finding]) -> io::Result<()> {
+        let stdout: StandardStream = StandardStream::stdout(get_color_choice());
+        let mut out = stdout.lock();
+        for finding in findings {
+            self.print(&mut out, finding)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes one JSON object per finding, holding the lock so output from parallel
+/// searches is never interleaved.
+struct JsonLines {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Sink for JsonLines {
+    fn write(&self, findings: &[Finding]) -> io::Result<()> {
+        if findings.is_empty() {
+            return Ok(());
+        }
+        let mut buffer: Vec<u8> = Vec::new();
+        for finding in findings {
+            serde_json::to_writer(&mut buffer, finding)?;
+            buffer.push(b'\n');
+        }
+        lock(&self.out)?.write_all(&buffer)
+    }
+
+    fn finish(self: Box<Self>, _patterns: &[Pattern]) -> Result<()> {
+        into_inner(self.out)?.flush()?;
+        Ok(())
+    }
+}
+
+/// Sorts findings from a parallel walk, which finish in any order, so reports
+/// stay stable between runs.
+fn sorted(mut findings: Vec<Finding>) -> Vec<Finding> {
+    findings.sort_by(|a: &Finding, b: &Finding| {
+        (&a.path, a.byte_offset, &a.pattern).cmp(&(&b.path, b.byte_offset, &b.pattern))
+    });
+    findings
+}
+
+/// SARIF is a single document, so findings are gathered from all threads first.
+struct Sarif {
+    out: Mutex<Box<dyn Write + Send>>,
+    collected: Mutex<Vec<Finding>>,
+}
+
+impl Sink for Sarif {
+    fn write(&self, findings: &[Finding]) -> io::Result<()> {
+        lock(&self.collected)?.extend_from_slice(findings);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>, patterns: &[Pattern]) -> Result<()> {
+        let findings: Vec<Finding> = sorted(into_inner(self.collected)?);
+        let report: serde_json::Value = sarif::build_report(patterns, &findings);
+        let mut out: Box<dyn Write + Send> = into_inner(self.out)?;
+        serde_json::to_writer_pretty(&mut out, &report)?;
+        writeln!(out)?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// POSTs every finding of the run to a URL as one JSON array, e.g. to notify a
+/// chat channel or feed a report pipeline. Nothing is sent when nothing was found.
+struct Webhook {
+    url: String,
+    collected: Mutex<Vec<Finding>>,
+}
+
+impl Sink for Webhook {
+    fn write(&self, findings: &[Finding]) -> io::Result<()> {
+        lock(&self.collected)?.extend_from_slice(findings);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>, _patterns: &[Pattern]) -> Result<()> {
+        let findings: Vec<Finding> = sorted(into_inner(self.collected)?);
+        if findings.is_empty() {
+            return Ok(());
+        }
+        // The search runs inside the async runtime, which can't be blocked on directly.
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(async {
+                reqwest::Client::new()
+                    .post(&self.url)
+                    .json(&findings)
+                    .send()
+                    .await?
+                    .error_for_status()
+            })
+        })
+        .with_context(|| format!("Failed to post findings to {}", self.url))?;
+        Ok(())
+    }
+}
+
+/// Records the run as one scan in the findings database once it is complete.
+struct Record {
+    store: Mutex<FindingsStore>,
+    target: String,
+    started_at: i64,
+    collected: Mutex<Vec<Finding>>,
+}
+
+impl Sink for Record {
+    fn write(&self, findings: &[Finding]) -> io::Result<()> {
+        lock(&self.collected)?.extend_from_slice(findings);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>, patterns: &[Pattern]) -> Result<()> {
+        let findings: Vec<Finding> = sorted(into_inner(self.collected)?);
+        into_inner(self.store)?.record_scan(&self.target, self.started_at, patterns, &findings)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sink_specs() {
+        assert_eq!("terminal".parse(), Ok(SinkSpec::Terminal));
+        assert_eq!("jsonl".parse(), Ok(SinkSpec::JsonLines(None)));
+        assert_eq!(
+            "sarif:out/gfr.sarif".parse(),
+            Ok(SinkSpec::Sarif(Some(PathBuf::from("out/gfr.sarif"))))
+        );
+        assert_eq!(
+            "webhook:https://hooks.example.com/gfr".parse(),
+            Ok(SinkSpec::Webhook(
+                "https://hooks.example.com/gfr".to_string()
+            ))
+        );
+        assert!("webhook".parse::<SinkSpec>().is_err());
+        assert!("csv:out.csv".parse::<SinkSpec>().is_err());
+        assert!(SinkSpec::JsonLines(None).writes_stdout());
+        assert!(!SinkSpec::Terminal.writes_stdout());
+    }
+
+    #[test]
+    fn test_terminal_highlights_match() {
+        let finding: Finding = Finding {
+            fingerprint: "aws:0123456789abcdef".to_string(),
+            pattern: "aws".to_string(),
+            path: Some("./a.txt".to_string()),
+            line_number: Some(4),
+            column: 3,
+            byte_offset: 9,
+            matched: "AKIA".to_string(),
+            line: "k=AKIA;".to_string(),
+            ..Default::default()
+        };
+        let mut out: termcolor::NoColor<Vec<u8>> = termcolor::NoColor::new(Vec::new());
+        Terminal { label: true }.print(&mut out, &finding).unwrap();
+        assert_eq!(
+            String::from_utf8(out.into_inner()).unwrap(),
+            "[aws] ./a.txt:4:k=AKIA;\n"
+        );
+    }
+}
@@ -0,0 +1,437 @@
+//! The findings database that `gfr search --record` writes each scan into, so
+//! repeated scans of the same target build up a history keyed by fingerprint.
+//! Findings marked with `gfr findings mark` keep their status across scans.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use serde::Serialize;
+
+pub(crate) use gfr_core::finding::FindingStatus;
+
+use crate::{Finding, Pattern, CONFIG_DIR};
+
+pub(crate) const STORE_FILE: &str = "findings.db";
+
+/// Bumped whenever `SCHEMA` changes, so older databases can be migrated.
+const SCHEMA_VERSION: i64 = 2;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY,
+    target TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER NOT NULL,
+    gfr_version TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS scan_patterns (
+    scan_id INTEGER NOT NULL REFERENCES scans (id),
+    pattern TEXT NOT NULL,
+    version TEXT NOT NULL,
+    PRIMARY KEY (scan_id, pattern)
+);
+CREATE TABLE IF NOT EXISTS findings (
+    fingerprint TEXT PRIMARY KEY,
+    pattern TEXT NOT NULL,
+    severity TEXT,
+    path TEXT,
+    matched TEXT NOT NULL,
+    first_scan INTEGER NOT NULL REFERENCES scans (id),
+    last_scan INTEGER NOT NULL REFERENCES scans (id)
+);
+CREATE TABLE IF NOT EXISTS occurrences (
+    scan_id INTEGER NOT NULL REFERENCES scans (id),
+    fingerprint TEXT NOT NULL REFERENCES findings (fingerprint),
+    line_number INTEGER,
+    column INTEGER NOT NULL,
+    line TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS occurrences_by_fingerprint ON occurrences (fingerprint);
+CREATE TABLE IF NOT EXISTS triage (
+    fingerprint TEXT PRIMARY KEY,
+    status TEXT NOT NULL,
+    note TEXT,
+    marked_at INTEGER NOT NULL
+);
+";
+
+/// Columns of a `StoredFinding`, in the order `StoredFinding::from_row` reads them.
+const FINDING_QUERY: &str = "
+SELECT f.fingerprint, f.pattern, f.severity, f.path, f.matched,
+       COALESCE(t.status, 'open'), t.note,
+       datetime(first.started_at, 'unixepoch'), datetime(last.started_at, 'unixepoch'),
+       last.target, f.last_scan = (SELECT MAX(id) FROM scans WHERE target = last.target)
+FROM findings f
+JOIN scans first ON first.id = f.first_scan
+JOIN scans last ON last.id = f.last_scan
+LEFT JOIN triage t ON t.fingerprint = f.fingerprint
+";
+
+/// A finding as the database remembers it across scans.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct StoredFinding {
+    pub(crate) fingerprint: String,
+    pub(crate) pattern: String,
+    pub(crate) severity: Option<String>,
+    pub(crate) path: Option<String>,
+    pub(crate) matched: String,
+    pub(crate) status: FindingStatus,
+    pub(crate) note: Option<String>,
+    /// UTC time of the first scan that found it.
+    pub(crate) first_seen: String,
+    /// UTC time of the last scan that found it.
+    pub(crate) last_seen: String,
+    /// Target of the last scan that found it.
+    pub(crate) target: String,
+    /// Whether the latest scan of that target still found it.
+    pub(crate) present: bool,
+}
+
+impl StoredFinding {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            fingerprint: row.get(0)?,
+            pattern: row.get(1)?,
+            severity: row.get(2)?,
+            path: row.get(3)?,
+            matched: row.get(4)?,
+            status: FindingStatus::parse(&row.get::<_, String>(5)?),
+            note: row.get(6)?,
+            first_seen: row.get(7)?,
+            last_seen: row.get(8)?,
+            target: row.get(9)?,
+            present: row.get(10)?,
+        })
+    }
+}
+
+/// One sighting of a finding in a recorded scan.
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Occurrence {
+    pub(crate) scan_id: i64,
+    pub(crate) target: String,
+    /// UTC time the scan started.
+    pub(crate) scanned_at: String,
+    pub(crate) line_number: Option<u64>,
+    pub(crate) column: u64,
+    pub(crate) line: String,
+}
+
+/// Narrows `FindingsStore::list` down; unset fields match everything.
+#[derive(Debug, Default)]
+pub(crate) struct FindingFilter<'a> {
+    pub(crate) status: Option<FindingStatus>,
+    pub(crate) pattern: Option<&'a str>,
+    pub(crate) target: Option<&'a str>,
+}
+
+/// Where the findings database lives unless `--record` names another file.
+pub(crate) fn default_path() -> Result<PathBuf> {
+    dirs::data_dir()
+        .ok_or_else(|| anyhow!("Could not determine user's data directory."))
+        .map(|dir: PathBuf| dir.join(CONFIG_DIR).join(STORE_FILE))
+}
+
+/// Seconds since the Unix epoch.
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d: std::time::Duration| {
+            i64::try_from(d.as_secs()).unwrap_or(i64::MAX)
+        })
+}
+
+/// An open findings database.
+pub(crate) struct FindingsStore {
+    conn: Connection,
+}
+
+impl FindingsStore {
+    /// Opens the database at `path`, creating it and its directory if needed.
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p: &&Path| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let conn: Connection = Connection::open(path)
+            .with_context(|| format!("Failed to open findings database {}", path.display()))?;
+        let version: i64 =
+            conn.query_row("PRAGMA user_version", [], |row: &rusqlite::Row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(anyhow!(
+                "Findings database {} was written by a newer gfr; please upgrade.",
+                path.display()
+            ));
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self { conn })
+    }
+
+    /// Records one scan of `target` with the patterns it used and what it found,
+    /// returning the new scan's id.
+    pub(crate) fn record_scan(
+        &mut self,
+        target: &str,
+        started_at: i64,
+        patterns: &[Pattern],
+        findings: &[Finding],
+    ) -> Result<i64> {
+        let tx: Transaction = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO scans (target, started_at, finished_at, gfr_version) VALUES (?1, ?2, ?3, ?4)",
+            params![target, started_at, now(), env!("CARGO_PKG_VERSION")],
+        )?;
+        let scan_id: i64 = tx.last_insert_rowid();
+        for pattern in patterns {
+            tx.execute(
+                "INSERT OR REPLACE INTO scan_patterns (scan_id, pattern, version) VALUES (?1, ?2, ?3)",
+                params![scan_id, pattern.name, pattern.version],
+            )?;
+        }
+        for finding in findings {
+            tx.execute(
+                "INSERT INTO findings (fingerprint, pattern, severity, path, matched, first_scan, last_scan)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
+                 ON CONFLICT (fingerprint) DO UPDATE SET
+                     severity = excluded.severity, matched = excluded.matched, last_scan = excluded.last_scan",
+                params![
+                    finding.fingerprint,
+                    finding.pattern,
+                    finding.severity.map(|s: crate::Severity| s.to_string()),
+                    finding.path,
+                    finding.matched,
+                    scan_id,
+                ],
+            )?;
+            tx.execute(
+                "INSERT INTO occurrences (scan_id, fingerprint, line_number, column, line) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    scan_id,
+                    finding.fingerprint,
+                    finding.line_number,
+                    finding.column,
+                    finding.line,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(scan_id)
+    }
+
+    /// Recorded findings matching `filter`, most recently seen first.
+    pub(crate) fn list(&self, filter: &FindingFilter) -> Result<Vec<StoredFinding>> {
+        let query: String = format!(
+            "{FINDING_QUERY}
+             WHERE (?1 IS NULL OR COALESCE(t.status, 'open') = ?1)
+               AND (?2 IS NULL OR f.pattern = ?2)
+               AND (?3 IS NULL OR last.target = ?3)
+             ORDER BY f.last_scan DESC, f.fingerprint"
+        );
+        let status: Option<String> = filter.status.map(|s: FindingStatus| s.to_string());
+        let mut statement: rusqlite::Statement = self.conn.prepare(&query)?;
+        let findings: Vec<StoredFinding> = statement
+            .query_map(
+                params![status, filter.pattern, filter.target],
+                StoredFinding::from_row,
+            )?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(findings)
+    }
+
+    /// A recorded finding and every scan that saw it, oldest first.
+    pub(crate) fn show(
+        &self,
+        fingerprint: &str,
+    ) -> Result<Option<(StoredFinding, Vec<Occurrence>)>> {
+        let query: String = format!("{FINDING_QUERY} WHERE f.fingerprint = ?1");
+        let Some(finding) = self
+            .conn
+            .query_row(&query, [fingerprint], StoredFinding::from_row)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+        let mut statement: rusqlite::Statement = self.conn.prepare(
+            "SELECT o.scan_id, s.target, datetime(s.started_at, 'unixepoch'), o.line_number, o.column, o.line
+             FROM occurrences o JOIN scans s ON s.id = o.scan_id
+             WHERE o.fingerprint = ?1
+             ORDER BY o.scan_id, o.line_number",
+        )?;
+        let occurrences: Vec<Occurrence> = statement
+            .query_map([fingerprint], |row: &Row| {
+                Ok(Occurrence {
+                    scan_id: row.get(0)?,
+                    target: row.get(1)?,
+                    scanned_at: row.get(2)?,
+                    line_number: row.get(3)?,
+                    column: row.get(4)?,
+                    line: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Some((finding, occurrences)))
+    }
+
+    /// Sets the status of findings by fingerprint, returning how many of them
+    /// haven't been recorded yet. Those still take effect once they are found.
+    pub(crate) fn mark(
+        &mut self,
+        fingerprints: &[String],
+        status: FindingStatus,
+        note: Option<&str>,
+    ) -> Result<usize> {
+        let tx: Transaction = self.conn.transaction()?;
+        let mut unknown: usize = 0;
+        for fingerprint in fingerprints {
+            let recorded: bool = tx
+                .query_row(
+                    "SELECT 1 FROM findings WHERE fingerprint = ?1",
+                    [fingerprint],
+                    |_: &Row| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !recorded {
+                unknown += 1;
+            }
+            tx.execute(
+                "INSERT INTO triage (fingerprint, status, note, marked_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT (fingerprint) DO UPDATE SET
+                     status = excluded.status, note = excluded.note, marked_at = excluded.marked_at",
+                params![fingerprint, status.to_string(), note, now()],
+            )?;
+        }
+        tx.commit()?;
+        Ok(unknown)
+    }
+
+    /// The status of every marked finding, keyed by fingerprint.
+    pub(crate) fn statuses(&self) -> Result<HashMap<String, FindingStatus>> {
+        let mut statement: rusqlite::Statement = self
+            .conn
+            .prepare("SELECT fingerprint, status FROM triage WHERE status != 'open'")?;
+        let statuses: HashMap<String, FindingStatus> = statement
+            .query_map([], |row: &Row| {
+                Ok((row.get(0)?, FindingStatus::parse(&row.get::<_, String>(1)?)))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(statuses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn finding(fingerprint: &str) -> Finding {
+        Finding {
+            fingerprint: fingerprint.to_string(),
+            pattern: "aws-keys".to_string(),
+            path: Some("./app.env".to_string()),
+            line_number: Some(2),
+            column: 5,
+            byte_offset: 12,
+            matched: "AKIA0000".to_string(),
+            line: "KEY=AKIA0000".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_record_scans_tracks_first_and_last_sighting() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let mut store: FindingsStore =
+            FindingsStore::open(&dir.path().join("findings.db")).unwrap();
+        let pattern: Pattern = Pattern {
+            name: "aws-keys".to_string(),
+            version: "1.2.0".to_string(),
+            ..Default::default()
+        };
+
+        let first: i64 = store
+            .record_scan(
+                "/src",
+                1,
+                std::slice::from_ref(&pattern),
+                &[finding("aws-keys:1")],
+            )
+            .unwrap();
+        let second: i64 = store
+            .record_scan(
+                "/src",
+                2,
+                &[pattern],
+                &[finding("aws-keys:1"), finding("aws-keys:2")],
+            )
+            .unwrap();
+
+        let sightings: (i64, i64) = store
+            .conn
+            .query_row(
+                "SELECT first_scan, last_scan FROM findings WHERE fingerprint = 'aws-keys:1'",
+                [],
+                |row: &rusqlite::Row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(sightings, (first, second));
+        let occurrences: i64 = store
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM occurrences",
+                [],
+                |row: &rusqlite::Row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(occurrences, 3);
+        let version: String = store
+            .conn
+            .query_row(
+                "SELECT version FROM scan_patterns WHERE scan_id = ?1",
+                [second],
+                |row: &rusqlite::Row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(version, "1.2.0");
+    }
+
+    #[test]
+    fn test_marked_findings_keep_their_status() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let mut store: FindingsStore =
+            FindingsStore::open(&dir.path().join("findings.db")).unwrap();
+        store
+            .record_scan("/src", 1, &[], &[finding("aws-keys:1")])
+            .unwrap();
+
+        let unknown: usize = store
+            .mark(
+                &["aws-keys:1".to_string(), "aws-keys:2".to_string()],
+                FindingStatus::FalsePositive,
+                Some("test fixture"),
+            )
+            .unwrap();
+        assert_eq!(unknown, 1);
+        assert!(store.statuses().unwrap()["aws-keys:2"].suppresses());
+
+        let marked: Vec<StoredFinding> = store
+            .list(&FindingFilter {
+                status: Some(FindingStatus::FalsePositive),
+                ..FindingFilter::default()
+            })
+            .unwrap();
+        assert_eq!(marked.len(), 1);
+        assert_eq!(marked[0].note.as_deref(), Some("test fixture"));
+        assert!(marked[0].present);
+
+        store.record_scan("/src", 2, &[], &[]).unwrap();
+        let (finding, occurrences) = store.show("aws-keys:1").unwrap().unwrap();
+        assert!(!finding.present);
+        assert_eq!(occurrences.len(), 1);
+        assert!(store.show("aws-keys:3").unwrap().is_none());
+    }
+}
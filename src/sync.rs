@@ -0,0 +1,221 @@
+//! `gfr sync`: reconciles `installed.json` with the pattern directory after files
+//! were added or deleted by hand. Pattern files in a namespace directory that the
+//! manifest doesn't know are adopted, and entries whose file is gone are dropped.
+//! Saved patterns at the top level are the user's own and are left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+
+use crate::collections::COLLECTIONS_DIR;
+use crate::{
+    get_pattern_dir, list_pattern_names, load_manifest, load_pattern_file, save_manifest,
+    sha256_hex, InstalledManifest, InstalledPattern, Pattern, Styles,
+};
+
+#[derive(Args, Debug)]
+pub(crate) struct SyncArgs {
+    /// Delete pattern files the manifest doesn't know instead of adopting them.
+    #[arg(long)]
+    prune: bool,
+}
+
+/// What `gfr sync` found.
+#[derive(Debug, Default)]
+struct SyncPlan {
+    /// Unmanaged files that are valid patterns, with the entries to record for them.
+    adopt: Vec<(String, InstalledPattern)>,
+    /// Manifest entries without a file.
+    stale: Vec<String>,
+    /// Unmanaged files that can't be adopted, and why.
+    conflicts: Vec<(String, anyhow::Error)>,
+}
+
+impl SyncPlan {
+    fn scan(pattern_dir: &Path, manifest: &InstalledManifest) -> Result<Self> {
+        let mut plan: Self = Self::default();
+        let mut names: Vec<String> = list_pattern_names(pattern_dir)?;
+        names.sort();
+        for name in names {
+            if !name.contains('/') || manifest.contains_key(&name) {
+                continue;
+            }
+            match adoptable(&pattern_dir.join(format!("{name}.json"))) {
+                Ok(installed) => plan.adopt.push((name, installed)),
+                Err(e) => plan.conflicts.push((name, e)),
+            }
+        }
+        plan.stale = manifest
+            .keys()
+            .filter(|name: &&String| !installed_file_exists(pattern_dir, name))
+            .cloned()
+            .collect();
+        plan.stale.sort();
+        Ok(plan)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.adopt.is_empty() && self.stale.is_empty() && self.conflicts.is_empty()
+    }
+}
+
+/// A manifest entry for a pattern file that was put in place by hand.
+fn adoptable(path: &Path) -> Result<InstalledPattern> {
+    let body: Vec<u8> = fs::read(path)?;
+    let pattern: Pattern = load_pattern_file(path).context("not a valid pattern")?;
+    pattern.build_matcher().context("invalid regex")?;
+    let checksum: String = sha256_hex(&body);
+    Ok(InstalledPattern {
+        version: pattern.version,
+        sha256: Some(checksum.clone()),
+        source: None,
+        registry: None,
+        url: None,
+        local_sha256: Some(checksum),
+        pinned: false,
+        signed: false,
+        signature: None,
+    })
+}
+
+/// Whether the file of an installed pattern or collection is still there.
+fn installed_file_exists(pattern_dir: &Path, name: &str) -> bool {
+    // Collections are kept under their short name in a directory of their own.
+    let short: &str = name
+        .rsplit_once('/')
+        .map_or(name, |(_, short): (&str, &str)| short);
+    pattern_dir.join(format!("{name}.json")).is_file()
+        || pattern_dir
+            .join(COLLECTIONS_DIR)
+            .join(format!("{short}.json"))
+            .is_file()
+}
+
+/// Runs `gfr sync`.
+pub(crate) fn run_sync(args: &SyncArgs, styles: &Styles) -> Result<()> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    if !pattern_dir.exists() {
+        return Err(anyhow!(
+            "No pattern directory found. Use `gfr install` to get started."
+        ));
+    }
+    let mut manifest: InstalledManifest = load_manifest()?;
+    let plan: SyncPlan = SyncPlan::scan(&pattern_dir, &manifest)?;
+    if plan.is_empty() {
+        println!(
+            "{} installed.json matches the pattern directory.",
+            "✓".style(styles.success)
+        );
+        return Ok(());
+    }
+
+    let mut unmanaged: Vec<String> = Vec::new();
+    for (name, installed) in plan.adopt {
+        if args.prune {
+            unmanaged.push(name);
+            continue;
+        }
+        println!(
+            "  -> Adopted '{}' (v{})",
+            name.style(styles.highlight),
+            installed.version
+        );
+        manifest.insert(name, installed);
+    }
+    for name in &plan.stale {
+        println!(
+            "  -> Dropped '{}': its file is gone",
+            name.style(styles.highlight)
+        );
+        manifest.remove(name);
+    }
+    let conflict_count: usize = if args.prune { 0 } else { plan.conflicts.len() };
+    for (name, reason) in plan.conflicts {
+        if args.prune {
+            unmanaged.push(name);
+            continue;
+        }
+        println!(
+            "  {} '{}' can't be adopted: {}",
+            "!".style(styles.error),
+            name.style(styles.highlight),
+            format!("{reason:#}").style(styles.error)
+        );
+    }
+    for name in &unmanaged {
+        let path: PathBuf = pattern_dir.join(format!("{name}.json"));
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        println!("  -> Deleted '{}'", name.style(styles.highlight));
+    }
+    save_manifest(&manifest)?;
+    if conflict_count > 0 {
+        println!(
+            "\n{} {} files need fixing by hand; `gfr sync --prune` deletes them.",
+            "!".style(styles.error),
+            conflict_count.to_string().style(styles.highlight)
+        );
+    } else {
+        println!(
+            "\n{} installed.json is in sync with the pattern directory.",
+            "✓".style(styles.success)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_plan_scan() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let pattern_dir: &Path = dir.path();
+        fs::create_dir_all(pattern_dir.join("team")).unwrap();
+        fs::create_dir_all(pattern_dir.join(COLLECTIONS_DIR)).unwrap();
+        let pattern: &str = r#"{"version": "2.0.0", "pattern": "eyJ[a-z]+"}"#;
+        fs::write(pattern_dir.join("team/jwt.json"), pattern).unwrap();
+        fs::write(pattern_dir.join("team/broken.json"), "{").unwrap();
+        fs::write(pattern_dir.join("team/xss.json"), pattern).unwrap();
+        fs::write(pattern_dir.join("mine.json"), pattern).unwrap();
+        fs::write(
+            pattern_dir.join(COLLECTIONS_DIR).join("web.json"),
+            r#"{"collection": ["xss"]}"#,
+        )
+        .unwrap();
+
+        let installed = || InstalledPattern {
+            version: "1.0.0".to_string(),
+            sha256: None,
+            source: None,
+            registry: None,
+            url: None,
+            local_sha256: None,
+            pinned: false,
+            signed: false,
+            signature: None,
+        };
+        let mut manifest: InstalledManifest = InstalledManifest::new();
+        manifest.insert("team/xss".to_string(), installed());
+        manifest.insert("team/web".to_string(), installed());
+        manifest.insert("team/gone".to_string(), installed());
+
+        let plan: SyncPlan = SyncPlan::scan(pattern_dir, &manifest).unwrap();
+        let adopted: Vec<(&str, &str)> = plan
+            .adopt
+            .iter()
+            .map(|(name, p): &(String, InstalledPattern)| (name.as_str(), p.version.as_str()))
+            .collect();
+        assert_eq!(adopted, [("team/jwt", "2.0.0")]);
+        assert_eq!(plan.stale, ["team/gone"]);
+        let conflicts: Vec<&str> = plan
+            .conflicts
+            .iter()
+            .map(|(name, _): &(String, anyhow::Error)| name.as_str())
+            .collect();
+        assert_eq!(conflicts, ["team/broken"]);
+    }
+}
@@ -0,0 +1,300 @@
+//! `gfr triage`: records true/false positive verdicts for findings and turns
+//! them into per-pattern confidence scores and allow-list suggestions.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{self, BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Subcommand, ValueEnum};
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+use crate::{get_pattern_dir, Styles};
+use gfr_core::load::TRIAGE_FILE;
+
+/// Minimum number of false positives in one directory before suggesting to allow all of it.
+const DIRECTORY_SUGGESTION_THRESHOLD: usize = 2;
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum TriageAction {
+    /// Record a verdict for findings given by fingerprint, or piped in as `gfr search --json` output.
+    Mark {
+        /// The verdict to record.
+        verdict: Verdict,
+        /// Fingerprints of the findings (e.g., "aws-keys:3f2a9c1b0d4e5f6a").
+        fingerprints: Vec<String>,
+    },
+    /// Show verdict counts and confidence for each pattern.
+    Stats,
+    /// Suggest allow-list entries based on recorded false positives.
+    Suggest,
+}
+
+/// Whether a finding was a real issue.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum Verdict {
+    TruePositive,
+    FalsePositive,
+}
+
+/// A verdict recorded for one finding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TriageRecord {
+    pattern: String,
+    verdict: Verdict,
+    path: Option<String>,
+    matched: Option<String>,
+}
+
+/// The subset of a `gfr search --json` line needed to record a verdict.
+#[derive(Debug, Deserialize)]
+struct PipedFinding {
+    fingerprint: String,
+    pattern: String,
+    path: Option<String>,
+    matched: String,
+}
+
+/// All recorded verdicts, keyed by finding fingerprint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct TriageStore {
+    records: BTreeMap<String, TriageRecord>,
+}
+
+impl TriageStore {
+    pub(crate) fn load() -> Result<Self> {
+        let path: PathBuf = get_pattern_dir()?.join(TRIAGE_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file: File = File::open(&path)?;
+        serde_json::from_reader(file)
+            .with_context(|| format!("Failed to parse triage history from: {}", path.display()))
+    }
+
+    fn save(&self) -> Result<()> {
+        let pattern_dir: PathBuf = get_pattern_dir()?;
+        fs::create_dir_all(&pattern_dir)?;
+        let file: File = File::create(pattern_dir.join(TRIAGE_FILE))?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    /// Returns `(true positives, false positives)` per pattern.
+    fn counts(&self) -> BTreeMap<&str, (u32, u32)> {
+        let mut counts: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+        for record in self.records.values() {
+            let entry: &mut (u32, u32) = counts.entry(record.pattern.as_str()).or_default();
+            match record.verdict {
+                Verdict::TruePositive => entry.0 += 1,
+                Verdict::FalsePositive => entry.1 += 1,
+            }
+        }
+        counts
+    }
+
+    /// Estimates how likely each triaged pattern's findings are real, from 0.0 to 1.0.
+    /// Smoothed so a single verdict doesn't swing a pattern to 0% or 100%.
+    pub(crate) fn confidence(&self) -> HashMap<String, f64> {
+        self.counts()
+            .into_iter()
+            .map(|(pattern, (tp, fp)): (&str, (u32, u32))| {
+                (
+                    pattern.to_string(),
+                    f64::from(tp + 1) / f64::from(tp + fp + 2),
+                )
+            })
+            .collect()
+    }
+
+    /// Suggests allow-list entries: recurring false-positive strings and directories.
+    fn suggestions(&self) -> Vec<(String, usize)> {
+        let mut strings: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut directories: BTreeMap<String, usize> = BTreeMap::new();
+        for record in self.records.values() {
+            if record.verdict != Verdict::FalsePositive {
+                continue;
+            }
+            if let Some(matched) = &record.matched {
+                *strings.entry(matched).or_default() += 1;
+            }
+            if let Some(parent) = record.path.as_deref().map(Path::new).and_then(Path::parent) {
+                *directories.entry(parent.display().to_string()).or_default() += 1;
+            }
+        }
+
+        let mut suggestions: Vec<(String, usize)> = directories
+            .into_iter()
+            .filter(|(dir, count): &(String, usize)| {
+                !dir.is_empty() && *count >= DIRECTORY_SUGGESTION_THRESHOLD
+            })
+            .map(|(dir, count): (String, usize)| (format!("path: {dir}/**"), count))
+            .collect();
+        suggestions.extend(
+            strings
+                .into_iter()
+                .map(|(s, count): (&str, usize)| (format!("string: {s}"), count)),
+        );
+        suggestions.sort_by_key(|(_, count): &(String, usize)| std::cmp::Reverse(*count));
+        suggestions
+    }
+}
+
+/// Runs a `gfr triage` subcommand.
+pub(crate) fn run_triage(action: TriageAction, styles: &Styles) -> Result<()> {
+    let mut store: TriageStore = TriageStore::load()?;
+    match action {
+        TriageAction::Mark {
+            verdict,
+            fingerprints,
+        } => {
+            let records: Vec<(String, TriageRecord)> = if fingerprints.is_empty() {
+                read_piped_findings(verdict)?
+            } else {
+                fingerprints
+                    .into_iter()
+                    .map(|fingerprint: String| record_for_fingerprint(fingerprint, verdict))
+                    .collect::<Result<_>>()?
+            };
+            let marked: usize = records.len();
+            store.records.extend(records);
+            store.save()?;
+            let label: &str = match verdict {
+                Verdict::TruePositive => "true positives",
+                Verdict::FalsePositive => "false positives",
+            };
+            println!(
+                "{} Marked {} findings as {label}.",
+                "✓".style(styles.success),
+                marked.to_string().style(styles.highlight),
+            );
+        }
+        TriageAction::Stats => {
+            println!("{}", "Triage history:".style(styles.title));
+            let confidence: HashMap<String, f64> = store.confidence();
+            for (pattern, (tp, fp)) in store.counts() {
+                println!(
+                    "  {} {} true / {} false positives, confidence {:.0}%",
+                    pattern.style(styles.highlight),
+                    tp,
+                    fp,
+                    confidence[pattern] * 100.0
+                );
+            }
+        }
+        TriageAction::Suggest => {
+            let suggestions: Vec<(String, usize)> = store.suggestions();
+            if suggestions.is_empty() {
+                println!("  {}", "No false positives recorded yet.".style(styles.dim));
+            }
+            for (entry, count) in suggestions {
+                println!(
+                    "  {}  {}",
+                    entry,
+                    format!("({count} false positives)").style(styles.dim)
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds a record from a bare fingerprint, whose prefix names the pattern.
+fn record_for_fingerprint(fingerprint: String, verdict: Verdict) -> Result<(String, TriageRecord)> {
+    let (pattern, _) = fingerprint
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Invalid fingerprint '{fingerprint}'. Expected 'pattern:hash'."))?;
+    let record: TriageRecord = TriageRecord {
+        pattern: pattern.to_string(),
+        verdict,
+        path: None,
+        matched: None,
+    };
+    Ok((fingerprint, record))
+}
+
+/// Reads `gfr search --json` output from stdin, one finding per line.
+fn read_piped_findings(verdict: Verdict) -> Result<Vec<(String, TriageRecord)>> {
+    if io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "Provide fingerprints or pipe `gfr search --json` output into `gfr triage mark`."
+        ));
+    }
+    let mut records: Vec<(String, TriageRecord)> = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line: String = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let finding: PipedFinding = serde_json::from_str(&line)
+            .with_context(|| format!("Expected a `gfr search --json` finding, got: {line}"))?;
+        records.push((
+            finding.fingerprint,
+            TriageRecord {
+                pattern: finding.pattern,
+                verdict,
+                path: finding.path,
+                matched: Some(finding.matched),
+            },
+        ));
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pattern: &str, verdict: Verdict, path: &str, matched: &str) -> TriageRecord {
+        TriageRecord {
+            pattern: pattern.to_string(),
+            verdict,
+            path: Some(path.to_string()),
+            matched: Some(matched.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_confidence_and_suggestions() {
+        let mut store: TriageStore = TriageStore::default();
+        let fixtures: [(&str, TriageRecord); 4] = [
+            (
+                "a",
+                record("aws", Verdict::TruePositive, "src/a.rs", "AKIA1"),
+            ),
+            (
+                "b",
+                record("aws", Verdict::FalsePositive, "tests/b.rs", "AKIAFAKE"),
+            ),
+            (
+                "c",
+                record("aws", Verdict::FalsePositive, "tests/c.rs", "AKIAFAKE"),
+            ),
+            (
+                "d",
+                record("aws", Verdict::FalsePositive, "tests/d.rs", "AKIAOTHER"),
+            ),
+        ];
+        for (fingerprint, r) in fixtures {
+            store.records.insert(fingerprint.to_string(), r);
+        }
+
+        let confidence: HashMap<String, f64> = store.confidence();
+        assert!((confidence["aws"] - 2.0 / 6.0).abs() < f64::EPSILON);
+
+        let suggestions: Vec<(String, usize)> = store.suggestions();
+        assert_eq!(suggestions[0], ("path: tests/**".to_string(), 3));
+        assert!(suggestions.contains(&("string: AKIAFAKE".to_string(), 2)));
+    }
+
+    #[test]
+    fn test_record_for_fingerprint_extracts_pattern() {
+        let (_, r) =
+            record_for_fingerprint("aws-keys:abc123".to_string(), Verdict::TruePositive).unwrap();
+        assert_eq!(r.pattern, "aws-keys");
+        assert!(record_for_fingerprint("nohash".to_string(), Verdict::TruePositive).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+//! Heuristics for `--tune auto`: picks walker parallelism and memory map usage
+//! from the machine and a quick sample of the search target.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use ignore::WalkBuilder;
+
+use gfr_core::winpath;
+
+/// How many files to look at when estimating the file size distribution.
+const SAMPLE_SIZE: usize = 256;
+/// Median file size above which memory maps beat buffered reads.
+const MMAP_MEDIAN_THRESHOLD: u64 = 1024 * 1024;
+/// Upper bound on walker threads, even for I/O-bound network scans.
+const MAX_THREADS: usize = 64;
+/// Filesystem types whose latency makes searches I/O bound.
+const NETWORK_FILESYSTEMS: [&str; 12] = [
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "fuse.sshfs",
+    "9p",
+    "afs",
+    "ceph",
+    "glusterfs",
+    "lustre",
+    "fuse.rclone",
+];
+
+/// Search settings chosen for the current machine and target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Tuning {
+    /// Number of walker threads. `0` lets the walker pick its own default.
+    pub(crate) threads: usize,
+    /// Whether the searcher may memory map files.
+    pub(crate) mmap: bool,
+}
+
+impl Tuning {
+    /// Settings used with `--tune off`: the walker and searcher defaults.
+    pub(crate) fn manual() -> Self {
+        Self {
+            threads: 0,
+            mmap: false,
+        }
+    }
+
+    /// Derives settings from the CPU count, file sizes under `path` and its filesystem.
+    pub(crate) fn auto(path: &Path) -> Self {
+        let cpus: usize = thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+        let network: bool = is_network_filesystem(path);
+        let median_size: u64 = sample_median_file_size(path);
+        Self::from_measurements(cpus, network, median_size)
+    }
+
+    fn from_measurements(cpus: usize, network: bool, median_size: u64) -> Self {
+        if network {
+            // Remote reads spend most of their time waiting, so oversubscribe the CPUs.
+            // Memory maps over network mounts can fault if the file changes underneath us.
+            Self {
+                threads: (cpus * 4).min(MAX_THREADS),
+                mmap: false,
+            }
+        } else {
+            Self {
+                threads: cpus.min(MAX_THREADS),
+                mmap: median_size >= MMAP_MEDIAN_THRESHOLD,
+            }
+        }
+    }
+}
+
+/// Returns the median size of the first few files found under `path`.
+fn sample_median_file_size(path: &Path) -> u64 {
+    let mut sizes: Vec<u64> = WalkBuilder::new(path)
+        .build()
+        .filter_map(Result::ok)
+        .filter(|e: &ignore::DirEntry| e.file_type().is_some_and(|ft: fs::FileType| ft.is_file()))
+        .take(SAMPLE_SIZE)
+        .filter_map(|e: ignore::DirEntry| e.metadata().ok())
+        .map(|m: fs::Metadata| m.len())
+        .collect();
+    if sizes.is_empty() {
+        return 0;
+    }
+    sizes.sort_unstable();
+    sizes[sizes.len() / 2]
+}
+
+/// Checks whether `path` lives on a network filesystem: a UNC share on Windows,
+/// otherwise by consulting `/proc/mounts`. False where neither applies.
+fn is_network_filesystem(path: &Path) -> bool {
+    if cfg!(windows) {
+        return path
+            .canonicalize()
+            .is_ok_and(|p: PathBuf| p.to_str().is_some_and(winpath::is_unc));
+    }
+    let Ok(mounts) = fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    mount_fstype(&mounts, &path).is_some_and(|fstype: &str| NETWORK_FILESYSTEMS.contains(&fstype))
+}
+
+/// Finds the filesystem type of the most specific mount point containing `path`.
+fn mount_fstype<'a>(mounts: &'a str, path: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line: &str| {
+            let mut fields = line.split_whitespace();
+            let mount_point: PathBuf = PathBuf::from(fields.nth(1)?.replace("\\040", " "));
+            let fstype: &str = fields.next()?;
+            path.starts_with(&mount_point)
+                .then_some((mount_point, fstype))
+        })
+        .max_by_key(|(mount_point, _): &(PathBuf, &str)| mount_point.components().count())
+        .map(|(_, fstype): (PathBuf, &str)| fstype)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_fstype_picks_most_specific_mount() {
+        let mounts: &str = "/dev/sda1 / ext4 rw 0 0\n\
+                            server:/export /mnt/share nfs4 rw 0 0\n\
+                            tmpfs /mnt/share/tmp tmpfs rw 0 0\n";
+        assert_eq!(mount_fstype(mounts, Path::new("/home/user")), Some("ext4"));
+        assert_eq!(
+            mount_fstype(mounts, Path::new("/mnt/share/src")),
+            Some("nfs4")
+        );
+        assert_eq!(
+            mount_fstype(mounts, Path::new("/mnt/share/tmp/x")),
+            Some("tmpfs")
+        );
+    }
+
+    #[test]
+    fn test_tuning_from_measurements() {
+        let network: Tuning = Tuning::from_measurements(8, true, 10 * 1024 * 1024);
+        assert_eq!(network.threads, 32);
+        assert!(!network.mmap);
+
+        let local_large: Tuning = Tuning::from_measurements(128, false, 4 * 1024 * 1024);
+        assert_eq!(local_large.threads, MAX_THREADS);
+        assert!(local_large.mmap);
+
+        let local_small: Tuning = Tuning::from_measurements(4, false, 2048);
+        assert_eq!(local_small.threads, 4);
+        assert!(!local_small.mmap);
+    }
+}
@@ -0,0 +1,388 @@
+//! `gfr validate`: checks pattern files against the published schema and makes
+//! sure their regexes compile, reporting where each problem is.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use grep_matcher::Matcher;
+use owo_colors::OwoColorize;
+use semver::Version;
+use serde_json::Value;
+
+use crate::http::{HttpClient, NetworkOptions};
+use crate::migrate;
+use crate::{
+    get_pattern_dir, list_pattern_names, pattern_file, resolve_pattern_name, Pattern,
+    PatternFormat, Styles, DEFAULT_PATTERN_SCHEMA_URL,
+};
+use gfr_core::engine::PatternMatcher;
+use gfr_core::jsonc;
+
+#[derive(Args, Debug)]
+pub(crate) struct ValidateArgs {
+    /// Name of a local pattern, or path to a pattern file.
+    #[arg(required_unless_present = "all", conflicts_with = "all")]
+    target: Option<String>,
+    /// Validate every local pattern.
+    #[arg(long)]
+    all: bool,
+    /// Skip fetching the published schema; only check structure and regexes.
+    #[arg(long)]
+    no_schema: bool,
+}
+
+/// A single problem found in a pattern file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Issue {
+    /// Where the problem is: a line/column, a JSON pointer, or a field name.
+    location: String,
+    message: String,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+impl Issue {
+    fn new(location: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            location: location.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Checks the contents of one pattern file. An empty result means it is valid.
+/// Comments and trailing commas are allowed, and files of older schema versions
+/// are checked as they are after upgrading.
+pub(crate) fn check_source(source: &str, schema: Option<&jsonschema::Validator>) -> Vec<Issue> {
+    let source: &str = &jsonc::strip(source);
+    let mut value: Value = match serde_json::from_str(source) {
+        Ok(value) => value,
+        Err(e) => return vec![syntax_issue(&e)],
+    };
+    let written: u64 = match migrate::upgrade(&mut value) {
+        Ok(written) => written,
+        Err(e) => return vec![Issue::new("schema_version", format!("{e:#}"))],
+    };
+
+    let mut issues: Vec<Issue> = Vec::new();
+    if let Some(schema) = schema {
+        for error in schema.iter_errors(&value) {
+            let pointer: String = error.instance_path.to_string();
+            let location: String = if pointer.is_empty() {
+                "/".to_string()
+            } else {
+                pointer
+            };
+            issues.push(Issue::new(location, error.to_string()));
+        }
+    }
+
+    // Upgraded files no longer match the source, so errors can't point into it.
+    let parsed: serde_json::Result<Pattern> = if written == migrate::SCHEMA_VERSION {
+        serde_json::from_str(source)
+    } else {
+        serde_json::from_value(value)
+    };
+    let pattern: Pattern = match parsed {
+        Ok(pattern) => pattern,
+        Err(e) if e.line() == 0 => {
+            issues.push(Issue::new("/", e.to_string()));
+            return issues;
+        }
+        Err(e) => {
+            issues.push(syntax_issue(&e));
+            return issues;
+        }
+    };
+
+    if let Err(e) = Version::parse(&pattern.version) {
+        issues.push(Issue::new(
+            "version",
+            format!("not a semantic version: {e}"),
+        ));
+    }
+
+    check_matching(&pattern, &mut issues);
+
+    if let Some(group) = &pattern.entropy_group {
+        if pattern.min_entropy.is_none() {
+            issues.push(Issue::new(
+                "entropy_group",
+                "has no effect without 'min_entropy'",
+            ));
+        }
+        if let Ok(matcher) = pattern.build_matcher() {
+            if matcher.capture_index(group).is_none() {
+                issues.push(Issue::new(
+                    "entropy_group",
+                    format!("'{group}' is not a named capture group of the pattern"),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+/// Checks the fields a pattern matches with: its regexes or strings, and keywords.
+fn check_matching(pattern: &Pattern, issues: &mut Vec<Issue>) {
+    match (&pattern.regex, &pattern.regex_list, &pattern.strings) {
+        (None, None, None) => issues.push(Issue::new(
+            "pattern",
+            "missing 'pattern', 'patterns' or 'strings'",
+        )),
+        (Some(regex), None, None) => {
+            if let Err(e) = compile(pattern, regex) {
+                issues.push(Issue::new("pattern", e));
+            }
+        }
+        (None, Some(list), None) if list.is_empty() => {
+            issues.push(Issue::new("patterns", "must not be empty"));
+        }
+        (None, Some(list), None) => {
+            for (i, regex) in list.iter().enumerate() {
+                if let Err(e) = compile(pattern, regex) {
+                    issues.push(Issue::new(format!("patterns[{i}]"), e));
+                }
+            }
+        }
+        (None, None, Some(strings)) if strings.is_empty() => {
+            issues.push(Issue::new("strings", "must not be empty"));
+        }
+        (None, None, Some(strings)) => {
+            if let Err(e) = PatternMatcher::literal(strings, pattern.ignore_case) {
+                issues.push(Issue::new("strings", e.to_string()));
+            }
+        }
+        _ => issues.push(Issue::new(
+            "pattern",
+            "use only one of 'pattern', 'patterns' and 'strings'",
+        )),
+    }
+
+    for (i, keyword) in pattern.keywords.iter().flatten().enumerate() {
+        if keyword.is_empty() || keyword.contains('\n') {
+            issues.push(Issue::new(
+                format!("keywords[{i}]"),
+                "must be non-empty and within one line",
+            ));
+        }
+    }
+}
+
+/// Checks the contents of the pattern file at `path`. YAML files are checked as
+/// the JSON document they describe.
+pub(crate) fn check_file(
+    path: &Path,
+    source: &str,
+    schema: Option<&jsonschema::Validator>,
+) -> Vec<Issue> {
+    if PatternFormat::of(path) == PatternFormat::Json {
+        return check_source(source, schema);
+    }
+    match serde_yaml::from_str::<Value>(source) {
+        Ok(value) => check_source(&value.to_string(), schema),
+        Err(e) => {
+            let location: String = e.location().map_or_else(
+                || "/".to_string(),
+                |l: serde_yaml::Location| format!("line {}, column {}", l.line(), l.column()),
+            );
+            vec![Issue::new(location, e.to_string())]
+        }
+    }
+}
+
+/// Compiles `regex` with the pattern's flags, as a search would.
+fn compile(pattern: &Pattern, regex: &str) -> std::result::Result<(), String> {
+    let single: Pattern = Pattern {
+        regex: Some(regex.to_string()),
+        regex_list: None,
+        strings: None,
+        keywords: None,
+        ..pattern.clone()
+    };
+    single
+        .build_matcher()
+        .map(|_: PatternMatcher| ())
+        .map_err(|e: anyhow::Error| e.to_string())
+}
+
+fn syntax_issue(error: &serde_json::Error) -> Issue {
+    // serde_json appends the position to its messages; it is reported separately here.
+    let message: String = error.to_string();
+    let message: &str = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(m, _): (&str, &str)| m);
+    Issue::new(
+        format!("line {}, column {}", error.line(), error.column()),
+        message,
+    )
+}
+
+/// Resolves the files to validate from the command-line target.
+fn resolve_targets(args: &ValidateArgs) -> Result<Vec<PathBuf>> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    if args.all {
+        let mut names: Vec<String> = if pattern_dir.exists() {
+            list_pattern_names(&pattern_dir)?
+        } else {
+            Vec::new()
+        };
+        names.sort();
+        return Ok(names
+            .into_iter()
+            .filter_map(|name: String| pattern_file(&pattern_dir, &name))
+            .collect());
+    }
+
+    let target: &str = args
+        .target
+        .as_deref()
+        .ok_or_else(|| anyhow!("Give a pattern name, a path, or --all."))?;
+    Ok(vec![resolve_target(target)?])
+}
+
+/// The file of a local pattern, or `target` itself if it is a path to a file.
+pub(crate) fn resolve_target(target: &str) -> Result<PathBuf> {
+    let as_path: &Path = Path::new(target);
+    if as_path.is_file() {
+        return Ok(as_path.to_path_buf());
+    }
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    if let Some(path) = resolve_pattern_name(&pattern_dir, target)?
+        .and_then(|name: String| pattern_file(&pattern_dir, &name))
+    {
+        Ok(path)
+    } else {
+        Err(anyhow!(
+            "No pattern named '{target}' and no file at that path."
+        ))
+    }
+}
+
+/// Downloads the published pattern schema, or offline takes the last
+/// downloaded copy, and compiles it.
+async fn fetch_schema(network: &NetworkOptions) -> Result<jsonschema::Validator> {
+    let body: Vec<u8> = HttpClient::new(network)?
+        .get(DEFAULT_PATTERN_SCHEMA_URL)
+        .await?;
+    let schema: Value =
+        serde_json::from_slice(&body).context("Failed to parse the pattern schema")?;
+    jsonschema::validator_for(&schema).map_err(|e| anyhow!("Invalid pattern schema: {e}"))
+}
+
+/// Validates the selected pattern files, failing if any of them has problems.
+pub(crate) async fn run_validate(
+    args: ValidateArgs,
+    network: &NetworkOptions,
+    styles: &Styles,
+) -> Result<()> {
+    let targets: Vec<PathBuf> = resolve_targets(&args)?;
+    if targets.is_empty() {
+        println!("{}", "No patterns to validate.".style(styles.dim));
+        return Ok(());
+    }
+
+    let schema: Option<jsonschema::Validator> = if args.no_schema {
+        None
+    } else {
+        match fetch_schema(network).await {
+            Ok(schema) => Some(schema),
+            Err(e) => {
+                eprintln!(
+                    "{} Could not load the pattern schema ({e:#}); only checking structure and regexes.",
+                    "!".style(styles.error)
+                );
+                None
+            }
+        }
+    };
+
+    let mut invalid: usize = 0;
+    for path in &targets {
+        let source: String = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let issues: Vec<Issue> = check_file(path, &source, schema.as_ref());
+        if issues.is_empty() {
+            println!("{} {}", "✓".style(styles.success), path.display());
+            continue;
+        }
+        invalid += 1;
+        println!("{} {}", "✗".style(styles.error), path.display());
+        for issue in &issues {
+            println!(
+                "    {}: {}",
+                issue.location.style(styles.highlight),
+                issue.message
+            );
+        }
+    }
+
+    if invalid > 0 {
+        return Err(anyhow!(
+            "{invalid} of {} pattern files are invalid.",
+            targets.len()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_source_reports_locations() {
+        assert!(check_source(r#"{"version": "1.0.0", "pattern": "a+"}"#, None).is_empty());
+
+        let syntax: Vec<Issue> =
+            check_source("{\n  \"pattern\": \"a\"\n  \"version\": \"1.0.0\"\n}", None);
+        assert_eq!(syntax.len(), 1);
+        assert_eq!(syntax[0].location, "line 3, column 3");
+        assert!(check_source("{\n  // one a\n  \"pattern\": \"a\",\n}", None).is_empty());
+
+        let issues: Vec<Issue> = check_source(
+            r#"{"version": "one", "patterns": ["ok", "(unclosed"]}"#,
+            None,
+        );
+        let locations: Vec<&str> = issues.iter().map(|i: &Issue| i.location.as_str()).collect();
+        assert_eq!(locations, vec!["version", "patterns[1]"]);
+
+        let entropy: Vec<Issue> = check_source(
+            r#"{"pattern": "key=(?P<value>\\w+)", "min_entropy": 3.5, "entropy_group": "val"}"#,
+            None,
+        );
+        assert_eq!(entropy.len(), 1);
+        assert_eq!(entropy[0].location, "entropy_group");
+
+        assert!(check_source(r#"{"strings": ["a.b", "c"]}"#, None).is_empty());
+        let strings: Vec<Issue> = check_source(r#"{"strings": ["line\nbreak"]}"#, None);
+        assert_eq!(strings.len(), 1);
+        assert_eq!(strings[0].location, "strings");
+        let both: Vec<Issue> = check_source(r#"{"pattern": "a", "strings": ["b"]}"#, None);
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0].location, "pattern");
+
+        let keywords: Vec<Issue> =
+            check_source(r#"{"pattern": "a", "keywords": ["key", ""]}"#, None);
+        assert_eq!(keywords.len(), 1);
+        assert_eq!(keywords[0].location, "keywords[1]");
+    }
+
+    #[test]
+    fn test_check_file_reads_yaml() {
+        let yaml: &str = "version: 1.0.0\npattern: |-\n  (?x)\n  key= # the prefix\n  \\w+\n";
+        assert!(check_file(Path::new("key.yaml"), yaml, None).is_empty());
+        assert!(!check_file(Path::new("key.json"), yaml, None).is_empty());
+
+        let issues: Vec<Issue> = check_file(Path::new("bad.yml"), "pattern: [\n", None);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].location.starts_with("line "));
+    }
+}
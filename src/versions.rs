@@ -0,0 +1,155 @@
+//! Earlier versions of installed patterns. Whenever `gfr install` or `gfr update`
+//! replaces a pattern file, the old file and its manifest entry are kept in the
+//! cache directory, so `gfr rollback` can put them back after a bad update.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+use owo_colors::OwoColorize;
+
+use crate::{
+    file_sha256, get_pattern_dir, load_manifest, resolve_pattern_name, save_manifest,
+    InstalledManifest, InstalledPattern, Styles, CONFIG_DIR,
+};
+
+/// Directory in gfr's cache directory that replaced versions are kept in.
+const VERSIONS_DIR: &str = "versions";
+/// Manifest entries of the kept versions of a pattern, oldest first.
+const HISTORY_FILE: &str = "history.json";
+
+#[derive(Args, Debug)]
+pub(crate) struct RollbackArgs {
+    /// The installed pattern to roll back.
+    name: String,
+    /// Roll back even if the pattern was edited locally since it was installed.
+    #[arg(long)]
+    force: bool,
+}
+
+/// Where the replaced versions of `name` are kept.
+fn history_dir(name: &str) -> Result<PathBuf> {
+    dirs::cache_dir()
+        .ok_or_else(|| anyhow!("Could not determine user's cache directory."))
+        .map(|dir: PathBuf| dir.join(CONFIG_DIR).join(VERSIONS_DIR).join(name))
+}
+
+fn load_history(dir: &Path) -> Result<Vec<InstalledPattern>> {
+    let path: PathBuf = dir.join(HISTORY_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file: File = File::open(&path)?;
+    serde_json::from_reader(file)
+        .with_context(|| format!("Failed to parse version history from: {}", path.display()))
+}
+
+fn save_history(dir: &Path, history: &[InstalledPattern]) -> Result<()> {
+    let file: File = File::create(dir.join(HISTORY_FILE))?;
+    serde_json::to_writer_pretty(file, history)?;
+    Ok(())
+}
+
+/// Keeps `body`, the file of `entry`, before an install replaces it.
+pub(crate) fn remember(name: &str, entry: &InstalledPattern, body: &[u8]) -> Result<()> {
+    remember_in(&history_dir(name)?, entry, body)
+}
+
+fn remember_in(dir: &Path, entry: &InstalledPattern, body: &[u8]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join(format!("{}.json", entry.version)), body)?;
+    let mut history: Vec<InstalledPattern> = load_history(dir)?;
+    // Reinstalling a version moves it to the end instead of listing it twice.
+    history.retain(|kept: &InstalledPattern| kept.version != entry.version);
+    history.push(entry.clone());
+    save_history(dir, &history)
+}
+
+/// Takes the most recently replaced version out of the history, with its file.
+fn take_previous(dir: &Path) -> Result<Option<(InstalledPattern, Vec<u8>)>> {
+    let mut history: Vec<InstalledPattern> = load_history(dir)?;
+    let Some(entry) = history.pop() else {
+        return Ok(None);
+    };
+    let file: PathBuf = dir.join(format!("{}.json", entry.version));
+    let body: Vec<u8> =
+        fs::read(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+    fs::remove_file(&file)?;
+    save_history(dir, &history)?;
+    Ok(Some((entry, body)))
+}
+
+/// Runs `gfr rollback`.
+pub(crate) fn run_rollback(args: &RollbackArgs, styles: &Styles) -> Result<()> {
+    let pattern_dir: PathBuf = get_pattern_dir()?;
+    let name: String =
+        resolve_pattern_name(&pattern_dir, &args.name)?.unwrap_or_else(|| args.name.clone());
+    let mut manifest: InstalledManifest = load_manifest()?;
+    let current: &InstalledPattern = manifest
+        .get(&name)
+        .ok_or_else(|| anyhow!("'{name}' wasn't installed from an index."))?;
+    let path: PathBuf = pattern_dir.join(format!("{name}.json"));
+    if !args.force && current.is_modified(&path) {
+        return Err(anyhow!(
+            "'{name}' was edited locally since it was installed; pass --force to roll back anyway."
+        ));
+    }
+    let current_version: String = current.version.clone();
+    let Some((mut previous, body)) = take_previous(&history_dir(&name)?)? else {
+        return Err(anyhow!(
+            "No earlier version of '{name}' is kept. Versions are kept once an update replaces them."
+        ));
+    };
+
+    fs::write(&path, &body).with_context(|| format!("Failed to write {}", path.display()))?;
+    previous.local_sha256 = file_sha256(&path);
+    previous.pinned = true;
+    println!(
+        "{} Rolled '{}' back from v{current_version} to v{}. It stays at this version until you `gfr install {name}`.",
+        "✓".style(styles.success),
+        name.style(styles.highlight),
+        previous.version.style(styles.highlight)
+    );
+    manifest.insert(name, previous);
+    save_manifest(&manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_keeps_replaced_versions() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let entry = |version: &str| InstalledPattern {
+            version: version.to_string(),
+            sha256: None,
+            source: None,
+            registry: None,
+            url: None,
+            local_sha256: None,
+            pinned: false,
+            signed: false,
+            signature: None,
+        };
+        assert!(take_previous(dir.path()).unwrap().is_none());
+        remember_in(dir.path(), &entry("1.0.0"), b"one").unwrap();
+        remember_in(dir.path(), &entry("1.1.0"), b"two").unwrap();
+        remember_in(dir.path(), &entry("1.0.0"), b"one").unwrap();
+
+        let (previous, body): (InstalledPattern, Vec<u8>) =
+            take_previous(dir.path()).unwrap().unwrap();
+        assert_eq!(
+            (previous.version.as_str(), body.as_slice()),
+            ("1.0.0", &b"one"[..])
+        );
+        let (previous, body): (InstalledPattern, Vec<u8>) =
+            take_previous(dir.path()).unwrap().unwrap();
+        assert_eq!(
+            (previous.version.as_str(), body.as_slice()),
+            ("1.1.0", &b"two"[..])
+        );
+        assert!(take_previous(dir.path()).unwrap().is_none());
+    }
+}
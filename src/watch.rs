@@ -0,0 +1,104 @@
+//! `gfr search --watch`: rescans files as they change, using the platform's file
+//! notification backend (inotify on Linux, its native equivalents elsewhere).
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use owo_colors::OwoColorize;
+
+use crate::Styles;
+
+/// Editors save in bursts (temp file, rename, metadata), so changes are gathered
+/// until none arrived for this long.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Watches `root` until the process is stopped, calling `rescan` with the paths
+/// that changed, relative to `root`. Removed files have nothing left to scan and
+/// are not reported.
+pub(crate) fn watch(
+    root: &Path,
+    styles: &Styles,
+    mut rescan: impl FnMut(&HashSet<PathBuf>),
+) -> Result<()> {
+    let root: PathBuf =
+        fs::canonicalize(root).with_context(|| format!("Failed to watch {}", root.display()))?;
+    let (tx, rx): (
+        mpsc::Sender<notify::Result<Event>>,
+        Receiver<notify::Result<Event>>,
+    ) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = notify::recommended_watcher(tx)?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", root.display()))?;
+
+    // The watcher sends until it is dropped, so this only ends with the process.
+    while let Ok(event) = rx.recv() {
+        let mut changed: HashSet<PathBuf> = HashSet::new();
+        collect(event, &root, &mut changed, styles);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect(event, &root, &mut changed, styles);
+        }
+        if !changed.is_empty() {
+            rescan(&changed);
+        }
+    }
+    Ok(())
+}
+
+/// Adds the files an event created or modified to `changed`.
+fn collect(
+    event: notify::Result<Event>,
+    root: &Path,
+    changed: &mut HashSet<PathBuf>,
+    styles: &Styles,
+) {
+    let event: Event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            eprintln!("{} {}", "Error:".style(styles.error), e);
+            return;
+        }
+    };
+    if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+        return;
+    }
+    changed.extend(
+        event
+            .paths
+            .iter()
+            .filter(|path: &&PathBuf| path.is_file())
+            .filter_map(|path: &PathBuf| path.strip_prefix(root).ok())
+            .map(Path::to_path_buf),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, RemoveKind};
+
+    #[test]
+    fn test_collect_keeps_changed_files_relative_to_root() {
+        let dir: tempfile::TempDir = tempfile::tempdir().unwrap();
+        let root: PathBuf = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/app.env"), "KEY=1").unwrap();
+
+        let mut changed: HashSet<PathBuf> = HashSet::new();
+        let created: Event = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(root.join("src/app.env"))
+            .add_path(root.join("src"));
+        let styles: Styles = Styles::new();
+        collect(Ok(created), &root, &mut changed, &styles);
+        let removed: Event =
+            Event::new(EventKind::Remove(RemoveKind::File)).add_path(root.join("gone.env"));
+        collect(Ok(removed), &root, &mut changed, &styles);
+
+        assert_eq!(changed, HashSet::from([PathBuf::from("src/app.env")]));
+    }
+}